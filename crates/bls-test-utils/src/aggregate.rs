@@ -0,0 +1,283 @@
+//! Aggregated PoPs over a set of validators on a single chain.
+
+use crate::{
+    fixture::{legacy_schema_version, BlsTestData, SCHEMA_VERSION},
+    generate::{generate_case, Domain, GenOptions, GenerateError},
+    words::{
+        g1_from_words, g1_to_words, g2_from_words_solidity, g2_to_words_solidity, words_from_hex,
+        words_to_hex,
+    },
+};
+use alloy::primitives::{Address, U256};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sylow::{glued_pairing, pairing, G1Affine, G1Projective, G2Affine, G2Projective, GroupTrait};
+
+/// Sums over every validator's valid PoP, in the same limb order as [`BlsTestData`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateData {
+    /// G1 sum of the StakeManager PoP signatures.
+    pub signature_stake_manager: [String; 2],
+    /// G1 sum of the ValidatorManager PoP signatures.
+    pub signature_validator_manager: [String; 2],
+    /// G2 sum of the validators' public keys.
+    pub public_key: [String; 4],
+}
+
+/// Top-level output of aggregate mode, written instead of the `BlsTestData` list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateTestData {
+    /// [`SCHEMA_VERSION`](crate::SCHEMA_VERSION) when written; files from before the field
+    /// existed read as 1.
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    pub chain_id: String,
+    pub domain_staking_manager: String,
+    pub domain_validator_manager: String,
+    pub validators: Vec<BlsTestData>,
+    pub aggregate: AggregateData,
+}
+
+/// The G2 sum of a fixture's honest public keys, written as the top-level
+/// `aggregate_public_key` when there is more than one, for APK-style verification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregatePublicKey {
+    /// `Σ pk` in Solidity limb order.
+    pub public_key: [String; 4],
+    /// How many keys are in the sum.
+    pub count: u64,
+}
+
+/// Running sum of public keys, for fixtures that are written one case at a time. Attack entries
+/// and cases without a valid PoP are left out.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PublicKeySum {
+    sum: G2Projective,
+    count: u64,
+}
+
+impl PublicKeySum {
+    /// Add the key of `case`, a case as generated, unless it is excluded.
+    pub fn add(&mut self, case: &BlsTestData) {
+        if case.attack.is_some() || !case.proof.iter().any(|proof| proof.valid) {
+            return
+        }
+        let words = words_from_hex(&case.public_key).expect("generated key");
+        self.sum =
+            self.sum + G2Projective::from(g2_from_words_solidity(words).expect("generated key"));
+        self.count += 1;
+    }
+
+    /// The sum so far, if it covers more than one key.
+    pub fn finish(&self) -> Option<AggregatePublicKey> {
+        (self.count > 1).then(|| AggregatePublicKey {
+            public_key: words_to_hex(g2_to_words_solidity(&G2Affine::from(self.sum))),
+            count: self.count,
+        })
+    }
+}
+
+/// The [`AggregatePublicKey`] of `cases`, see [`PublicKeySum`].
+pub fn aggregate_public_key(cases: &[BlsTestData]) -> Option<AggregatePublicKey> {
+    let mut sum = PublicKeySum::default();
+    cases.iter().for_each(|case| sum.add(case));
+    sum.finish()
+}
+
+/// Check `e(Σsig, G2) == Π e(H(m_i), pk_i)`.
+///
+/// When every validator signed the same bytes this collapses to `e(Σsig, G2) == e(H(m), Σpk)`,
+/// which is what is checked in that case. PoP messages embed the signer's key and address, so
+/// they normally differ and the product of pairings is needed.
+pub fn aggregate_pairing_check(
+    signature: &G1Affine,
+    message_hashes: &[G1Affine],
+    public_keys: &[G2Affine],
+) -> bool {
+    let lhs = pairing(&G1Projective::from(*signature), &G2Projective::generator());
+    let rhs = match message_hashes.split_first() {
+        Some((first, rest)) if rest.iter().all(|hash| hash == first) => {
+            let public_key = public_keys
+                .iter()
+                .map(G2Projective::from)
+                .fold(G2Projective::zero(), |sum, pk| sum + pk);
+            pairing(&G1Projective::from(*first), &public_key)
+        }
+        _ => glued_pairing(
+            &message_hashes.iter().map(G1Projective::from).collect::<Vec<_>>(),
+            &public_keys.iter().map(G2Projective::from).collect::<Vec<_>>(),
+        ),
+    };
+    lhs == rhs
+}
+
+/// The valid PoP points of one validator for one domain.
+fn valid_pop(case: &BlsTestData, domain: Domain) -> (G1Affine, G1Affine) {
+    let proof = case.proof.iter().find(|proof| proof.valid).expect("every case has a valid PoP");
+    let (signature, hash) = match domain {
+        Domain::StakeManager => {
+            (&proof.proof_of_possession_stake_manager, &proof.message_hash_stake_manager)
+        }
+        Domain::ValidatorManager => {
+            (&proof.proof_of_possession_validator_manager, &proof.message_hash_validator_manager)
+        }
+    };
+    let point = |words| {
+        g1_from_words(words_from_hex(words).expect("generated point")).expect("generated point")
+    };
+    (point(signature), point(hash))
+}
+
+/// Generate a case per wallet on `chain_id` and aggregate their PoPs in both domains.
+pub fn generate_aggregate(
+    wallets: &[Address],
+    chain_id: U256,
+    opts: &GenOptions,
+) -> Result<AggregateTestData, GenerateError> {
+    let validators = wallets
+        .par_iter()
+        .enumerate()
+        .map(|(index, wallet)| generate_case(*wallet, &[chain_id], &opts.nth_registration(index)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let public_keys: Vec<G2Affine> = validators
+        .iter()
+        .map(|case| {
+            g2_from_words_solidity(words_from_hex(&case.public_key).expect("generated key"))
+                .expect("generated key")
+        })
+        .collect();
+    let public_key =
+        public_keys.iter().map(G2Projective::from).fold(G2Projective::zero(), |sum, pk| sum + pk);
+
+    let mut signatures = [[U256::ZERO; 2]; 2];
+    for (domain, words) in
+        [Domain::StakeManager, Domain::ValidatorManager].into_iter().zip(&mut signatures)
+    {
+        let (signatures, hashes): (Vec<G1Affine>, Vec<G1Affine>) =
+            validators.iter().map(|case| valid_pop(case, domain)).unzip();
+        let signature = G1Affine::from(
+            signatures
+                .iter()
+                .map(G1Projective::from)
+                .fold(G1Projective::zero(), |sum, sig| sum + sig),
+        );
+        if opts.verify && !aggregate_pairing_check(&signature, &hashes, &public_keys) {
+            return Err(GenerateError::AggregateCheckFailed { chain_id, domain })
+        }
+        *words = g1_to_words(&signature);
+    }
+    let [signature_stake_manager, signature_validator_manager] = signatures;
+
+    Ok(AggregateTestData {
+        schema_version: SCHEMA_VERSION,
+        chain_id: chain_id.to_string(),
+        domain_staking_manager: opts.dst_stake_manager.clone(),
+        domain_validator_manager: opts.dst_validator_manager.clone(),
+        validators,
+        aggregate: AggregateData {
+            signature_stake_manager: words_to_hex(signature_stake_manager),
+            signature_validator_manager: words_to_hex(signature_validator_manager),
+            public_key: words_to_hex(g2_to_words_solidity(&G2Affine::from(public_key))),
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn wallets() -> Vec<Address> {
+        (1..=3).map(Address::repeat_byte).collect()
+    }
+
+    fn seeded() -> GenOptions {
+        GenOptions { seed: Some("5".parse().unwrap()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_aggregate_is_sum_of_validators() {
+        let data = generate_aggregate(&wallets(), U256::from(1), &seeded()).unwrap();
+        assert_eq!(data.chain_id, "1");
+        assert_eq!(data.validators.len(), 3);
+
+        let signatures: Vec<G1Affine> =
+            data.validators.iter().map(|case| valid_pop(case, Domain::StakeManager).0).collect();
+        let sum = signatures
+            .iter()
+            .map(G1Projective::from)
+            .fold(G1Projective::zero(), |sum, sig| sum + sig);
+        assert_eq!(
+            data.aggregate.signature_stake_manager,
+            words_to_hex(g1_to_words(&G1Affine::from(sum)))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_pairing_check() {
+        let data = generate_aggregate(&wallets(), U256::from(1), &seeded()).unwrap();
+        let public_keys: Vec<G2Affine> = data
+            .validators
+            .iter()
+            .map(|case| g2_from_words_solidity(words_from_hex(&case.public_key).unwrap()).unwrap())
+            .collect();
+        let (signatures, hashes): (Vec<G1Affine>, Vec<G1Affine>) =
+            data.validators.iter().map(|case| valid_pop(case, Domain::ValidatorManager)).unzip();
+        let aggregate =
+            g1_from_words(words_from_hex(&data.aggregate.signature_validator_manager).unwrap())
+                .unwrap();
+        assert!(aggregate_pairing_check(&aggregate, &hashes, &public_keys));
+        assert!(!aggregate_pairing_check(&signatures[0], &hashes, &public_keys));
+
+        let mut swapped = hashes.clone();
+        swapped.swap(0, 1);
+        assert!(!aggregate_pairing_check(&aggregate, &swapped, &public_keys));
+    }
+
+    #[test]
+    fn test_aggregate_public_key_excludes_attacks() {
+        let opts = GenOptions { include_invalid: true, include_attacks: true, ..seeded() };
+        let cases = crate::generate_cases(&wallets(), &[U256::from(1)], &opts).unwrap();
+        assert_eq!(cases.len(), 3 * 4);
+        let aggregate = aggregate_public_key(&cases).unwrap();
+        assert_eq!(aggregate.count, 3);
+
+        let data = generate_aggregate(&wallets(), U256::from(1), &seeded()).unwrap();
+        assert_eq!(aggregate.public_key, data.aggregate.public_key);
+        assert_eq!(aggregate_public_key(&cases[..4]), None, "one honest key is not an aggregate");
+    }
+
+    #[test]
+    fn test_aggregate_public_key_is_order_independent() {
+        let wallets: Vec<Address> = (1..=6).map(Address::repeat_byte).collect();
+        let cases = crate::generate_cases(&wallets, &[U256::from(1)], &seeded()).unwrap();
+        let aggregate = aggregate_public_key(&cases).unwrap();
+
+        let mut reordered = cases.clone();
+        reordered.reverse();
+        reordered.swap(1, 4);
+        assert_eq!(aggregate_public_key(&reordered).unwrap(), aggregate);
+
+        // (pk0 + pk1 + pk2) + (pk3 + pk4 + pk5), grouped differently from the running sum.
+        let key = |case: &BlsTestData| {
+            G2Projective::from(
+                g2_from_words_solidity(words_from_hex(&case.public_key).unwrap()).unwrap(),
+            )
+        };
+        let half = |cases: &[BlsTestData]| {
+            cases.iter().rev().map(key).fold(G2Projective::zero(), |sum, pk| pk + sum)
+        };
+        let grouped = G2Affine::from(half(&cases[3..]) + half(&cases[..3]));
+        assert_eq!(aggregate.public_key, words_to_hex(g2_to_words_solidity(&grouped)));
+    }
+
+    #[test]
+    fn test_aggregate_shared_message() {
+        // Two signers with sk = 1: each signature is the message hash itself.
+        let keys = [G2Affine::generator(); 2];
+        let hash = G1Affine::from(G1Projective::generator() * sylow::Fp::from(7u64));
+        let signature = G1Affine::from(G1Projective::from(hash) + G1Projective::from(hash));
+        assert!(aggregate_pairing_check(&signature, &[hash, hash], &keys));
+        assert!(!aggregate_pairing_check(&hash, &[hash, hash], &keys));
+    }
+}
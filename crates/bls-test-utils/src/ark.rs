@@ -0,0 +1,302 @@
+//! A second opinion from arkworks on every generated PoP, behind the `ark-cross-check` feature.
+//!
+//! Without the feature the fixtures rest on sylow alone. With it, every message hash and
+//! signature [`generate_case`](crate::generate_case) produces is re-derived from its `uint256`
+//! words with `ark-bn254`: `H(m)` is recomputed from the message and DST, and
+//! `e(sig, G2) == e(H(m), pk)` is checked again with arkworks' pairing. Any divergence fails
+//! generation instead of shipping a vector only one implementation agrees with.
+//!
+//! `expand_message_xmd` is written out here rather than taken from `ark-ff`: its
+//! `DefaultFieldHasher` pads with one field element's worth of zeros (48 bytes) where RFC 9380
+//! asks for the hash's block size (136 bytes for Keccak256, 64 for SHA-256), so it computes a
+//! different `H(m)`.
+
+use crate::{
+    expander::{HashFunction, MessageHasher, Sha256},
+    words::PointError,
+};
+use alloy::primitives::U256;
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G1Projective, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{AdditiveGroup, BigInt, BigInteger, Field, PrimeField};
+use sha3::{digest::crypto_common::BlockSizeUser, Digest, Keccak256};
+
+/// Where arkworks disagrees with what sylow produced.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CrossCheckError {
+    #[error("arkworks rejects a point sylow produced: {0}")]
+    Point(#[from] PointError),
+    #[error("arkworks hashes the message to {0:?}, not sylow's message hash")]
+    HashToCurveMismatch([U256; 2]),
+    #[error("arkworks' pairing check rejects the signature")]
+    PairingCheckFailed,
+}
+
+/// A word as a base field element, or `None` if it is not below the modulus.
+pub fn fq_from_word(word: U256) -> Option<Fq> {
+    Fq::from_bigint(BigInt::new(word.into_limbs()))
+}
+
+pub fn fq_to_word(x: Fq) -> U256 {
+    U256::from_limbs(x.into_bigint().0)
+}
+
+fn fq_from_words<const N: usize>(words: [U256; N]) -> Result<[Fq; N], PointError> {
+    let mut limbs = [Fq::ZERO; N];
+    for (limb, (x, word)) in limbs.iter_mut().zip(words).enumerate() {
+        *x = fq_from_word(word).ok_or(PointError::NotInField(limb))?;
+    }
+    Ok(limbs)
+}
+
+/// The arkworks point behind [`g1_to_words`](crate::g1_to_words) output, checking every limb is
+/// a field element and the point is on the curve, as [`g1_from_words`](crate::g1_from_words)
+/// does.
+pub fn g1_to_ark(words: [U256; 2]) -> Result<G1Affine, PointError> {
+    let [x, y] = fq_from_words(words)?;
+    let point = G1Affine::new_unchecked(x, y);
+    if !point.is_on_curve() {
+        return Err(PointError::NotOnCurve)
+    }
+    Ok(point)
+}
+
+/// `[x, y]`, with the point at infinity as the all-zero words the contracts use.
+pub fn g1_from_ark(point: &G1Affine) -> [U256; 2] {
+    point.xy().map_or([U256::ZERO; 2], |(x, y)| [fq_to_word(x), fq_to_word(y)])
+}
+
+/// The arkworks point behind Solidity-ordered `[x_re, x_im, y_re, y_im]` words, with the same
+/// field, twist and subgroup checks as
+/// [`g2_from_words_solidity`](crate::g2_from_words_solidity).
+pub fn g2_to_ark(words: [U256; 4]) -> Result<G2Affine, PointError> {
+    let [x_re, x_im, y_re, y_im] = fq_from_words(words)?;
+    let point = G2Affine::new_unchecked(Fq2::new(x_re, x_im), Fq2::new(y_re, y_im));
+    if !point.is_on_curve() {
+        return Err(PointError::NotOnCurve)
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(PointError::NotInSubgroup)
+    }
+    Ok(point)
+}
+
+/// Solidity-ordered `[x_re, x_im, y_re, y_im]`; arkworks keeps the real part in `c0`.
+pub fn g2_from_ark(point: &G2Affine) -> [U256; 4] {
+    point.xy().map_or([U256::ZERO; 4], |(x, y)| [x.c0, x.c1, y.c0, y.c1].map(fq_to_word))
+}
+
+/// `expand_message_xmd` (RFC 9380 section 5.3.1) with a 32-byte hash `D`, to `len` bytes. DSTs
+/// are at most 255 bytes, which the CLI enforces.
+fn expand_message_xmd<D: Digest + BlockSizeUser>(
+    dst: &[u8],
+    message: &[u8],
+    len: usize,
+) -> Vec<u8> {
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b_0 = D::new()
+        .chain_update(vec![0; D::block_size()])
+        .chain_update(message)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = Vec::with_capacity(len);
+    let mut b_i = vec![0; b_0.len()];
+    for i in 1..=len.div_ceil(b_0.len()) {
+        let xored: Vec<u8> = b_0.iter().zip(&b_i).map(|(a, b)| a ^ b).collect();
+        b_i = D::new()
+            .chain_update(xored)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize()
+            .to_vec();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len);
+    uniform_bytes
+}
+
+fn expand_message(hasher: &MessageHasher, dst: &str, message: &[u8]) -> Vec<u8> {
+    match hasher.hash_function {
+        HashFunction::Keccak256 => {
+            expand_message_xmd::<Keccak256>(dst.as_bytes(), message, hasher.expand_len)
+        }
+        HashFunction::Sha256 => {
+            expand_message_xmd::<Sha256>(dst.as_bytes(), message, hasher.expand_len)
+        }
+    }
+}
+
+/// `g(x) = x³ + 3`.
+fn curve(x: Fq) -> Fq {
+    x.square() * x + Fq::from(3)
+}
+
+fn sgn0(x: Fq) -> bool {
+    x.into_bigint().is_odd()
+}
+
+/// The Shallue–van de Woestijne map with `Z = 1`, as in [`crate::hash_to_curve`] but over
+/// arkworks' field.
+fn map_to_curve(u: Fq) -> G1Affine {
+    let z = Fq::ONE;
+    let c1 = curve(z);
+    let c2 = -z / Fq::from(2);
+    let mut c3 = (-curve(z) * Fq::from(3) * z.square()).sqrt().expect("-g(Z)·3Z² is a square");
+    if sgn0(c3) {
+        c3 = -c3;
+    }
+    let c4 = -(Fq::from(4) * curve(z)) / (Fq::from(3) * z.square());
+
+    let tv1 = u.square() * c1;
+    let tv2 = Fq::ONE + tv1;
+    let tv1 = Fq::ONE - tv1;
+    let tv3 = (tv1 * tv2).inverse().unwrap_or(Fq::ZERO);
+    let tv4 = u * tv1 * tv3 * c3;
+    let x1 = c2 - tv4;
+    let x2 = c2 + tv4;
+    let x3 = (tv2.square() * tv3).square() * c4 + z;
+    let (x, mut y) = [x1, x2, x3]
+        .into_iter()
+        .find_map(|x| curve(x).sqrt().map(|y| (x, y)))
+        .expect("one of x1, x2, x3 is on the curve");
+    if sgn0(u) != sgn0(y) {
+        y = -y;
+    }
+    G1Affine::new(x, y)
+}
+
+/// `H(message)` under `dst` with `hasher`, computed with arkworks alone.
+pub fn hash_to_curve_ark(hasher: &MessageHasher, dst: &str, message: &[u8]) -> G1Affine {
+    let uniform_bytes = expand_message(hasher, dst, message);
+    let (u0, u1) = uniform_bytes.split_at(hasher.expand_len / 2);
+    let [q0, q1] =
+        [u0, u1].map(|half| G1Projective::from(map_to_curve(Fq::from_be_bytes_mod_order(half))));
+    (q0 + q1).into_affine()
+}
+
+/// Recompute `H(message)` under `dst` with `hasher` and check it equals `message_hash`, then
+/// check `e(signature, G2) == e(H(m), public_key)`, all from the words written to the fixture.
+pub fn cross_check_pop(
+    hasher: &MessageHasher,
+    dst: &str,
+    message: &[u8],
+    message_hash: [U256; 2],
+    signature: [U256; 2],
+    public_key: [U256; 4],
+) -> Result<(), CrossCheckError> {
+    let expected = g1_from_ark(&hash_to_curve_ark(hasher, dst, message));
+    if expected != message_hash {
+        return Err(CrossCheckError::HashToCurveMismatch(expected))
+    }
+    let lhs = Bn254::pairing(g1_to_ark(signature)?, G2Affine::generator());
+    let rhs = Bn254::pairing(g1_to_ark(message_hash)?, g2_to_ark(public_key)?);
+    if lhs != rhs {
+        return Err(CrossCheckError::PairingCheckFailed)
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        hash_to_curve::hash_to_curve_intermediates,
+        keys::{keypair_from_secret, random_secret_key, wallet_rng},
+        words::{g1_to_words, g2_to_words_solidity, words_from_hex},
+    };
+    use alloy::primitives::Address;
+    use sylow::{
+        G1Affine as SylowG1, G1Projective as SylowG1Projective, G2Affine as SylowG2, GroupTrait,
+    };
+
+    #[test]
+    fn test_generators_agree() {
+        let g1 = g1_to_words(&SylowG1::from(SylowG1Projective::generator()));
+        assert_eq!(g1, [U256::from(1), U256::from(2)]);
+        assert_eq!(g1_to_ark(g1), Ok(G1Affine::generator()));
+        assert_eq!(g1_from_ark(&G1Affine::generator()), g1);
+
+        let g2 = g2_to_words_solidity(&SylowG2::from(sylow::G2Projective::generator()));
+        assert_eq!(g2_to_ark(g2), Ok(G2Affine::generator()));
+        assert_eq!(g2_from_ark(&G2Affine::generator()), g2);
+    }
+
+    #[test]
+    fn test_random_points_agree() {
+        let mut rng = wallet_rng(Some(&"35".parse().unwrap()), Address::ZERO, b"ark");
+        for _ in 0..8 {
+            let secret_key = random_secret_key(&mut rng);
+            let scalar = U256::from_be_bytes(secret_key.to_be_bytes()).into_limbs();
+            let kp = keypair_from_secret(secret_key);
+
+            let g1 = g1_to_words(&SylowG1::from(SylowG1Projective::generator() * secret_key));
+            let expected = G1Affine::generator().mul_bigint(scalar).into_affine();
+            assert_eq!(g1_to_ark(g1), Ok(expected));
+            assert_eq!(g1_from_ark(&expected), g1);
+
+            let g2 = g2_to_words_solidity(&SylowG2::from(kp.public_key));
+            let expected = G2Affine::generator().mul_bigint(scalar).into_affine();
+            assert_eq!(g2_to_ark(g2), Ok(expected));
+            assert_eq!(g2_from_ark(&expected), g2);
+        }
+    }
+
+    #[test]
+    fn test_conversions_reject_what_sylow_rejects() {
+        let [x, y] = g1_from_ark(&G1Affine::generator());
+        let p = Fq::MODULUS.0;
+        assert_eq!(g1_to_ark([U256::from_limbs(p), y]), Err(PointError::NotInField(0)));
+        assert_eq!(g1_to_ark([x, y + U256::from(1)]), Err(PointError::NotOnCurve));
+        assert_eq!(g1_to_ark([U256::ZERO; 2]), Err(PointError::NotOnCurve));
+        assert_eq!(g2_to_ark([U256::ZERO; 4]), Err(PointError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_hash_to_curve_agrees_with_sylow() {
+        for (dst, message) in [
+            ("StakeManager:BN254:PoP:v1:", &b""[..]),
+            ("ValidatorManager:BN254:PoP:v1:", b"abc"),
+            ("Bridge:BN254:v1:", &[0xff; 200]),
+        ] {
+            for hash_function in [HashFunction::Keccak256, HashFunction::Sha256] {
+                let hasher = MessageHasher { hash_function, ..Default::default() };
+                let stages = hash_to_curve_intermediates(&hasher, dst, message);
+                assert_eq!(
+                    hex::encode(expand_message(&hasher, dst, message)),
+                    stages.uniform_bytes[2..]
+                );
+                let point = hash_to_curve_ark(&hasher, dst, message);
+                assert_eq!(
+                    g1_from_ark(&point),
+                    words_from_hex(&stages.point).unwrap(),
+                    "{dst} {hash_function}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cross_check_pop() {
+        let dst = "StakeManager:BN254:PoP:v1:";
+        let signed = crate::sign_message(sylow::Fp::from(42), b"pop", dst).unwrap();
+        let message_hash = words_from_hex(&signed.message_hash).unwrap();
+        let signature = words_from_hex(&signed.signature).unwrap();
+        let public_key = words_from_hex(&signed.public_key).unwrap();
+        let hasher = MessageHasher::default();
+        assert_eq!(
+            cross_check_pop(&hasher, dst, b"pop", message_hash, signature, public_key),
+            Ok(())
+        );
+        assert!(matches!(
+            cross_check_pop(&hasher, dst, b"other", message_hash, signature, public_key),
+            Err(CrossCheckError::HashToCurveMismatch(_))
+        ));
+        let other = g1_from_ark(&G1Affine::generator());
+        assert_eq!(
+            cross_check_pop(&hasher, dst, b"pop", message_hash, other, public_key),
+            Err(CrossCheckError::PairingCheckFailed)
+        );
+    }
+}
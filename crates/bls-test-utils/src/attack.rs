@@ -0,0 +1,384 @@
+//! Malicious public keys, and PoPs replayed on another chain: entries the contracts must refuse
+//! to register.
+//!
+//! Every key is rejected by sylow itself before it is written, so a contract that accepts one
+//! is checking less than the reference implementation does.
+
+use crate::{
+    fixture::{BlsTestData, ProofData, PublicKeyAttack, GENERATOR_VERSION},
+    generate::{
+        calldata_hex, case_keypair, eth_private_key_hex, private_key_hex, Domain, GenOptions,
+        GenerateError,
+    },
+    keys::{SecretKeyPair, GROUP_ORDER},
+    words::{g1_to_words, g2_from_words_solidity, g2_to_words_solidity, words_to_hex},
+};
+use alloy::primitives::{keccak256, Address, U256};
+use sylow::{FieldExtensionTrait, Fp, Fp2, G2Affine, G2Projective, GroupError};
+
+/// `attack` of an entry whose PoP was signed for one chain id and is labelled with another, see
+/// [`chain_replays`].
+pub const CHAIN_REPLAY: &str = "chain_replay";
+
+fn fp(word: U256) -> Fp {
+    Option::from(Fp::from_be_bytes(&word.to_be_bytes::<32>())).expect("limb is below p")
+}
+
+fn fp_word(x: Fp) -> U256 {
+    U256::from_be_bytes(x.to_be_bytes())
+}
+
+fn is_on_twist(x: Fp2, y: Fp2) -> bool {
+    y.square() == x.square() * x + <Fp2 as FieldExtensionTrait<2, 2>>::curve_constant()
+}
+
+/// `valid` with `y_re + 1`: `y^2` changes while `x^3 + b'` does not, so the point leaves the
+/// twist.
+fn off_curve(valid: [U256; 4]) -> [U256; 4] {
+    let [x_re, x_im, y_re, y_im] = valid;
+    [x_re, x_im, fp_word(fp(y_re) + Fp::ONE), y_im]
+}
+
+/// An affine point on the twist `y^2 = x^3 + b'` over Fp2, `None` for the identity.
+///
+/// sylow only constructs G2 points that pass its subgroup check, so points outside G2 need their
+/// own (textbook, affine) arithmetic.
+type TwistPoint = Option<(Fp2, Fp2)>;
+
+fn twist_double(point: TwistPoint) -> TwistPoint {
+    let (x, y) = point?;
+    if y == Fp2::from(0) {
+        return None
+    }
+    let lambda = Fp2::from(3) * x.square() / (y + y);
+    let x3 = lambda.square() - x - x;
+    Some((x3, lambda * (x - x3) - y))
+}
+
+fn twist_add(p: TwistPoint, q: TwistPoint) -> TwistPoint {
+    let ((x1, y1), (x2, y2)) = match (p, q) {
+        (None, other) | (other, None) => return other,
+        (Some(p), Some(q)) => (p, q),
+    };
+    if x1 == x2 {
+        return if y1 + y2 == Fp2::from(0) { None } else { twist_double(p) }
+    }
+    let lambda = (y2 - y1) / (x2 - x1);
+    let x3 = lambda.square() - x1 - x2;
+    Some((x3, lambda * (x1 - x3) - y1))
+}
+
+/// `[scalar]point` by double-and-add over the bits of `scalar`, most significant first.
+fn twist_mul(point: TwistPoint, scalar: U256) -> TwistPoint {
+    (0..scalar.bit_len()).rev().fold(None, |acc, bit| {
+        let acc = twist_double(acc);
+        if scalar.bit(bit) {
+            twist_add(acc, point)
+        } else {
+            acc
+        }
+    })
+}
+
+fn twist_words((x, y): (Fp2, Fp2)) -> [U256; 4] {
+    // `Fp2::to_be_bytes` writes the imaginary half first.
+    let (x, y) = (x.to_be_bytes(), y.to_be_bytes());
+    [&x[32..], &x[..32], &y[32..], &y[..32]].map(U256::from_be_slice)
+}
+
+/// `[r]P` for the first point `P = (k + u, y)` on the twist.
+///
+/// The twist has `h * r` points with `gcd(h, r) = 1`, so `[r]P` has order dividing the cofactor
+/// `h`. It is on the curve and, unless it is the identity, outside the r-torsion subgroup that G2
+/// is: this is what multiplying by the wrong factor when clearing the cofactor produces.
+fn wrong_subgroup() -> [U256; 4] {
+    (1u64..)
+        .find_map(|k| {
+            let x = Fp2::new(&[Fp::from(k), Fp::ONE]);
+            let rhs = x.square() * x + <Fp2 as FieldExtensionTrait<2, 2>>::curve_constant();
+            let y = Option::<Fp2>::from(rhs.sqrt())?;
+            twist_mul(Some((x, y)), GROUP_ORDER).map(twist_words)
+        })
+        .expect("the twist has points outside G2")
+}
+
+/// The limbs of `attack`, starting from the valid key `valid`.
+pub fn attack_public_key(attack: PublicKeyAttack, valid: [U256; 4]) -> [U256; 4] {
+    match attack {
+        PublicKeyAttack::OffCurve => off_curve(valid),
+        PublicKeyAttack::WrongSubgroup => wrong_subgroup(),
+        PublicKeyAttack::Infinity => [U256::ZERO; 4],
+    }
+}
+
+/// Whether sylow rejects `words` for the reason `attack` names: off the twist, on it but outside
+/// G2, or the all-zero encoding (which is not on the twist either, since `b' != 0`). In every
+/// case [`g2_from_words_solidity`] must refuse the key as well.
+pub fn rejected_by_sylow(attack: PublicKeyAttack, words: [U256; 4]) -> bool {
+    let [x_re, x_im, y_re, y_im] = words;
+    let x = Fp2::new(&[fp(x_re), fp(x_im)]);
+    let y = Fp2::new(&[fp(y_re), fp(y_im)]);
+    let rejected = match attack {
+        PublicKeyAttack::OffCurve | PublicKeyAttack::Infinity => !is_on_twist(x, y),
+        PublicKeyAttack::WrongSubgroup => {
+            is_on_twist(x, y) &&
+                matches!(
+                    G2Projective::new([x, y, Fp2::new(&[Fp::ONE, Fp::ZERO])]),
+                    Err(GroupError::NotInSubgroup)
+                )
+        }
+    };
+    rejected && g2_from_words_solidity(words).is_err()
+}
+
+/// One entry per [`PublicKeyAttack`] for `sender`, signed with its real key over the PoP message
+/// of the malicious limbs. The infinity entry instead carries the all-zero signature, the forgery
+/// an unchecked identity key admits since `e(0, G2) == e(H(m), 0)`.
+pub fn generate_attacks(
+    sender: Address,
+    chain_ids: &[U256],
+    opts: &GenOptions,
+) -> Result<Vec<BlsTestData>, GenerateError> {
+    let kp = case_keypair(sender, opts);
+    let valid = g2_to_words_solidity(&G2Affine::from(kp.public_key));
+    PublicKeyAttack::ALL
+        .iter()
+        .map(|attack| {
+            let pk_words = attack_public_key(*attack, valid);
+            if !rejected_by_sylow(*attack, pk_words) {
+                return Err(GenerateError::AttackKeyAccepted { wallet: sender, attack: *attack })
+            }
+            let proof = opts
+                .pop_chain_ids(chain_ids)
+                .iter()
+                .map(|chain_id| attack_proof(*attack, &kp, *chain_id, &pk_words, sender, opts))
+                .collect();
+            Ok(BlsTestData {
+                private_key: private_key_hex(opts, &kp),
+                public_key: words_to_hex(pk_words),
+                public_key_compressed: None,
+                public_key_padded: None,
+                wallet_address: sender.to_string(),
+                eth_private_key: eth_private_key_hex(opts, sender),
+                message_encoding: opts.encoding,
+                chain_id_width: opts.chain_id_width,
+                pop_style: opts.pop_style,
+                preimage: opts.preimage,
+                prehash: opts.prehash,
+                hash_function: opts.hasher.hash_function,
+                expand_len: opts.hasher.expand_len,
+                domain_staking_manager: opts.dst_stake_manager.clone(),
+                domain_validator_manager: opts.dst_validator_manager.clone(),
+                proof,
+                attack: Some(attack.as_str().to_string()),
+                generated_at: opts.generated_at,
+                generator_version: Some(GENERATOR_VERSION.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// One entry per ordered pair of distinct chain ids `(a, b)` among the valid PoPs of `case`, a case
+/// as generated: the PoP signed for `a`, with its message hashes and `message_bytes`, labelled
+/// with `chain_id` `b`. The original chain id is kept in `signed_chain_id`.
+pub fn chain_replays(case: &BlsTestData) -> Vec<BlsTestData> {
+    let signed: Vec<&ProofData> = case.proof.iter().filter(|proof| proof.valid).collect();
+    signed
+        .iter()
+        .flat_map(|original| {
+            signed.iter().filter(move |other| other.chain_id != original.chain_id).map(|other| {
+                BlsTestData {
+                    proof: vec![ProofData {
+                        chain_id: other.chain_id.clone(),
+                        signed_chain_id: Some(original.chain_id.clone()),
+                        valid: false,
+                        invalid_reason: Some(CHAIN_REPLAY.to_string()),
+                        ..(*original).clone()
+                    }],
+                    attack: Some(CHAIN_REPLAY.to_string()),
+                    ..case.clone()
+                }
+            })
+        })
+        .collect()
+}
+
+fn attack_proof(
+    attack: PublicKeyAttack,
+    kp: &SecretKeyPair,
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+    opts: &GenOptions,
+) -> ProofData {
+    let message =
+        opts.signed_preimage(opts.pop_style, chain_id, pk_words, sender, opts.nonce_start);
+    let hashed = opts.prehash.apply(&message);
+    let domain = |dst: &str| {
+        let hash = opts.hasher.hash_to_curve(dst, &hashed);
+        let signature = match attack {
+            PublicKeyAttack::Infinity => [U256::ZERO; 2],
+            _ => g1_to_words(&opts.hasher.sign(dst, &hashed, kp.secret_key.expose())),
+        };
+        (g1_to_words(&hash), signature)
+    };
+    let (message_hash_stake_manager, proof_of_possession_stake_manager) =
+        domain(&opts.dst(Domain::StakeManager, chain_id));
+    let (message_hash_validator_manager, proof_of_possession_validator_manager) =
+        domain(&opts.dst(Domain::ValidatorManager, chain_id));
+    ProofData {
+        message_hash_stake_manager: words_to_hex(message_hash_stake_manager),
+        message_hash_validator_manager: words_to_hex(message_hash_validator_manager),
+        proof_of_possession_stake_manager: words_to_hex(proof_of_possession_stake_manager),
+        proof_of_possession_validator_manager: words_to_hex(proof_of_possession_validator_manager),
+        chain_id: chain_id.to_string(),
+        signed_chain_id: None,
+        nonce: opts.nonce_start.map(|nonce| nonce.to_string()),
+        domain_staking_manager: opts.proof_dst(Domain::StakeManager, chain_id),
+        domain_validator_manager: opts.proof_dst(Domain::ValidatorManager, chain_id),
+        message_bytes: Some(format!("0x{}", hex::encode(&message))),
+        message_keccak: Some(keccak256(&message).to_string()),
+        eip191_digest: opts.prehash.digest_hex(&message),
+        struct_preimage: opts.struct_preimage(chain_id, pk_words, sender),
+        proof_of_possession_stake_manager_compressed: None,
+        proof_of_possession_validator_manager_compressed: None,
+        pairing_input_stake_manager: None,
+        pairing_input_validator_manager: None,
+        message_hash_combined: None,
+        proof_of_possession_combined: None,
+        hash_to_curve_stake_manager: None,
+        hash_to_curve_validator_manager: None,
+        xmd_expansion_stake_manager: None,
+        xmd_expansion_validator_manager: None,
+        calldata: calldata_hex(opts, *pk_words, proof_of_possession_stake_manager),
+        ecdsa_binding: None,
+        expected_naive_result: None,
+        valid: false,
+        invalid_reason: Some(attack.as_str().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generate::{generate_case, pairing_check, pop_message},
+        words::{g1_from_words, words_from_hex},
+    };
+    use sylow::GroupTrait;
+
+    fn valid_key() -> [U256; 4] {
+        let opts = GenOptions { seed: Some("8".parse().unwrap()), ..Default::default() };
+        g2_to_words_solidity(&G2Affine::from(
+            case_keypair(Address::repeat_byte(3), &opts).public_key,
+        ))
+    }
+
+    #[test]
+    fn test_valid_key_is_not_rejected() {
+        let valid = valid_key();
+        assert!(g2_from_words_solidity(valid).is_ok());
+        for attack in PublicKeyAttack::ALL {
+            assert!(!rejected_by_sylow(attack, valid), "{attack}");
+        }
+    }
+
+    #[test]
+    fn test_attack_keys_are_rejected_for_their_reason() {
+        let valid = valid_key();
+        for attack in PublicKeyAttack::ALL {
+            assert!(rejected_by_sylow(attack, attack_public_key(attack, valid)), "{attack}");
+        }
+        // The off-curve key would pass a subgroup-only check if it were on the curve; the
+        // wrong-subgroup key passes the curve equation but not the torsion check.
+        assert!(!rejected_by_sylow(
+            PublicKeyAttack::WrongSubgroup,
+            attack_public_key(PublicKeyAttack::OffCurve, valid)
+        ));
+        assert!(!rejected_by_sylow(
+            PublicKeyAttack::OffCurve,
+            attack_public_key(PublicKeyAttack::WrongSubgroup, valid)
+        ));
+    }
+
+    #[test]
+    fn test_twist_arithmetic_matches_sylow() {
+        let generator = G2Affine::generator();
+        let [x_re, x_im, y_re, y_im] = g2_to_words_solidity(&generator);
+        let point = Some((Fp2::new(&[fp(x_re), fp(x_im)]), Fp2::new(&[fp(y_re), fp(y_im)])));
+        let expected = G2Affine::from(G2Projective::from(generator) * Fp::from(5));
+        assert_eq!(
+            twist_mul(point, U256::from(5)).map(twist_words),
+            Some(g2_to_words_solidity(&expected))
+        );
+        assert_eq!(twist_mul(point, GROUP_ORDER), None, "the generator has order r");
+    }
+
+    #[test]
+    fn test_wrong_subgroup_point_is_not_r_torsion() {
+        let [x_re, x_im, y_re, y_im] = wrong_subgroup();
+        let point = (Fp2::new(&[fp(x_re), fp(x_im)]), Fp2::new(&[fp(y_re), fp(y_im)]));
+        assert!(is_on_twist(point.0, point.1));
+        // [r]Q would be the identity for a point in G2.
+        assert!(twist_mul(Some(point), GROUP_ORDER).is_some());
+    }
+
+    #[test]
+    fn test_generate_attacks() {
+        let opts = GenOptions { seed: Some("8".parse().unwrap()), ..Default::default() };
+        let cases = generate_attacks(Address::repeat_byte(3), &[U256::from(1)], &opts).unwrap();
+        let attacks: Vec<_> = cases.iter().map(|case| case.attack.as_deref().unwrap()).collect();
+        assert_eq!(attacks, ["off_curve", "wrong_subgroup", "infinity"]);
+        for case in &cases {
+            assert!(case.proof.iter().all(|proof| !proof.valid));
+            assert!(g2_from_words_solidity(words_from_hex(&case.public_key).unwrap()).is_err());
+        }
+        assert_eq!(cases[2].public_key, words_to_hex([U256::ZERO; 4]));
+    }
+
+    #[test]
+    fn test_chain_replays_verify_only_on_the_signed_chain() {
+        let opts = GenOptions { seed: Some("40".parse().unwrap()), ..Default::default() };
+        let wallet = Address::repeat_byte(40);
+        let chain_ids = [U256::from(1), U256::from(8453), U256::from(31337)];
+        let case = generate_case(wallet, &chain_ids, &opts).unwrap();
+        let replays = chain_replays(&case);
+        let pairs: Vec<(&str, &str)> = replays
+            .iter()
+            .map(|entry| {
+                let proof = &entry.proof[0];
+                (proof.signed_chain_id.as_deref().unwrap(), proof.chain_id.as_str())
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("1", "8453"),
+                ("1", "31337"),
+                ("8453", "1"),
+                ("8453", "31337"),
+                ("31337", "1"),
+                ("31337", "8453")
+            ]
+        );
+
+        let pk_words = words_from_hex(&case.public_key).unwrap();
+        let public_key = g2_from_words_solidity(pk_words).unwrap();
+        let passes = |chain_id: &str, signature: &[String; 2]| {
+            let message = pop_message(opts.encoding, chain_id.parse().unwrap(), &pk_words, wallet);
+            let hash = opts.hasher.hash_to_curve(&opts.dst_stake_manager, &message);
+            let signature = g1_from_words(words_from_hex(signature).unwrap()).unwrap();
+            pairing_check(&signature, &hash, &public_key)
+        };
+        for entry in &replays {
+            let proof = &entry.proof[0];
+            assert_eq!(entry.attack.as_deref(), Some(CHAIN_REPLAY));
+            assert!(!proof.valid);
+            let signature = &proof.proof_of_possession_stake_manager;
+            assert!(!passes(&proof.chain_id, signature), "fails on the labelled chain");
+            assert!(passes(proof.signed_chain_id.as_ref().unwrap(), signature), "signed chain");
+        }
+        assert!(crate::verify_fixture(&replays).iter().all(crate::CheckResult::passed));
+        assert!(chain_replays(&generate_case(wallet, &chain_ids[..1], &opts).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! ECDSA bindings: the wallet's secp256k1 signature over the BLS public key it registers, so that
+//! neither key can be registered without the other.
+//!
+//! The wallet signs `keccak256(abi.encodePacked(pk_limbs, chain_id))` as a raw digest, without
+//! the EIP-191 prefix, and the signature is written as 65 bytes `r ‖ s ‖ v` with `v` 27 or 28,
+//! what `ecrecover` takes.
+
+use crate::hex_util::{decode_hex, decode_hex_array};
+use alloy::{
+    primitives::{keccak256, Address, Signature, SignatureError, B256, U256},
+    signers::{local::PrivateKeySigner, SignerSync},
+    sol_types::SolValue,
+};
+use serde::{Deserialize, Serialize};
+
+/// `abi.encodePacked(pk_limbs, chain_id)`, with the limbs in Solidity order.
+pub fn binding_preimage(pk_words: &[U256; 4], chain_id: U256) -> Vec<u8> {
+    (pk_words[0], pk_words[1], pk_words[2], pk_words[3], chain_id).abi_encode_packed()
+}
+
+/// keccak256 of the [`binding_preimage`], what the wallet signs.
+pub fn binding_digest(pk_words: &[U256; 4], chain_id: U256) -> B256 {
+    keccak256(binding_preimage(pk_words, chain_id))
+}
+
+/// Why an [`EcdsaBinding`] does not recover to an address.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingError {
+    #[error("`{field}` is not valid hex of the right length")]
+    InvalidHex { field: &'static str },
+    #[error(transparent)]
+    InvalidSignature(#[from] SignatureError),
+}
+
+/// The wallet's signature over one proof's public key and chain id, as recorded on a
+/// [`ProofData`](crate::ProofData).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EcdsaBinding {
+    /// [`binding_digest`], hex encoded.
+    pub digest: String,
+    /// The 65-byte `r ‖ s ‖ v` signature over `digest`, hex encoded.
+    pub signature: String,
+}
+
+impl EcdsaBinding {
+    /// Sign the binding of `pk_words` on `chain_id` with `signer`.
+    pub fn sign(signer: &PrivateKeySigner, pk_words: &[U256; 4], chain_id: U256) -> Self {
+        let digest = binding_digest(pk_words, chain_id);
+        let signature = signer.sign_hash_sync(&digest).expect("a local key signs any digest");
+        Self {
+            digest: digest.to_string(),
+            signature: format!("0x{}", hex::encode(signature.as_bytes())),
+        }
+    }
+
+    /// The stored digest.
+    pub fn digest(&self) -> Result<B256, BindingError> {
+        decode_hex_array(&self.digest)
+            .map(B256::from)
+            .map_err(|_| BindingError::InvalidHex { field: "digest" })
+    }
+
+    /// The address whose key produced `signature` over `digest`, as `ecrecover` computes it.
+    pub fn recover(&self) -> Result<Address, BindingError> {
+        let signature = decode_hex(&self.signature)
+            .map_err(|_| BindingError::InvalidHex { field: "signature" })?;
+        Ok(Signature::from_raw(&signature)?.recover_address_from_prehash(&self.digest()?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::primitives::b256;
+
+    #[test]
+    fn test_recovers_the_signer() {
+        let signer = PrivateKeySigner::from_bytes(&b256!(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        ))
+        .unwrap();
+        let pk_words = [1, 2, 3, 4].map(U256::from);
+        let binding = EcdsaBinding::sign(&signer, &pk_words, U256::from(8453));
+        assert_eq!(binding_preimage(&pk_words, U256::from(8453)).len(), 160);
+        assert_eq!(binding.digest().unwrap(), binding_digest(&pk_words, U256::from(8453)));
+        assert_eq!(decode_hex(&binding.signature).unwrap().len(), 65);
+        assert_eq!(binding.recover().unwrap(), signer.address());
+
+        let other_chain = EcdsaBinding {
+            digest: binding_digest(&pk_words, U256::from(1)).to_string(),
+            ..binding.clone()
+        };
+        assert_ne!(other_chain.recover().unwrap(), signer.address());
+        let truncated = EcdsaBinding { signature: binding.signature[..130].to_string(), ..binding };
+        assert!(matches!(truncated.recover(), Err(BindingError::InvalidSignature(_))));
+    }
+}
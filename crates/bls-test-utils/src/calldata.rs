@@ -0,0 +1,132 @@
+//! Ready-to-send `registerValidator(uint256[4],uint256[2])` calldata, for smoke tests against a
+//! deployed StakeManager (e.g. `cast send $STAKE_MANAGER $calldata` on anvil).
+
+use alloy::{
+    dyn_abi::{DynSolType, DynSolValue, JsonAbiExt, Specifier},
+    json_abi::Function,
+    primitives::{Selector, U256},
+};
+
+/// The StakeManager registration entry point.
+pub const DEFAULT_REGISTER_SIGNATURE: &str = "registerValidator(uint256[4],uint256[2])";
+
+/// Why a `--signature` cannot be used for registration calldata.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CalldataError {
+    #[error("`{signature}` is not a function signature: {reason}")]
+    InvalidSignature { signature: String, reason: String },
+    #[error("`{signature}` must take (uint256[4],uint256[2]), the public key and the PoP")]
+    WrongInputs { signature: String },
+}
+
+/// How registration calldata is built: the selector, followed by the ABI encoding of the public
+/// key limbs and the StakeManager PoP.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterCall {
+    function: Function,
+    selector: Selector,
+}
+
+impl Default for RegisterCall {
+    fn default() -> Self {
+        Self::from_signature(DEFAULT_REGISTER_SIGNATURE).expect("default signature parses")
+    }
+}
+
+impl RegisterCall {
+    /// Parse a Solidity signature such as [`DEFAULT_REGISTER_SIGNATURE`]; whatever its name, it
+    /// must take a `uint256[4]` and a `uint256[2]`.
+    pub fn from_signature(signature: &str) -> Result<Self, CalldataError> {
+        let function =
+            Function::parse(signature).map_err(|error| CalldataError::InvalidSignature {
+                signature: signature.to_string(),
+                reason: error.to_string(),
+            })?;
+        let expected = [
+            DynSolType::FixedArray(Box::new(DynSolType::Uint(256)), 4),
+            DynSolType::FixedArray(Box::new(DynSolType::Uint(256)), 2),
+        ];
+        let inputs: Result<Vec<DynSolType>, _> =
+            function.inputs.iter().map(Specifier::resolve).collect();
+        if inputs.ok().as_deref() != Some(&expected[..]) {
+            return Err(CalldataError::WrongInputs { signature: signature.to_string() })
+        }
+        let selector = function.selector();
+        Ok(Self { function, selector })
+    }
+
+    /// [`DEFAULT_REGISTER_SIGNATURE`]'s arguments behind another `selector`, for a contract
+    /// whose entry point is named differently.
+    pub fn from_selector(selector: Selector) -> Self {
+        Self { selector, ..Self::default() }
+    }
+
+    pub fn selector(&self) -> Selector {
+        self.selector
+    }
+
+    /// `selector ‖ abi.encode(public_key, signature)`.
+    pub fn encode(&self, public_key: [U256; 4], signature: [U256; 2]) -> Vec<u8> {
+        let words = |words: &[U256]| {
+            DynSolValue::FixedArray(
+                words.iter().map(|word| DynSolValue::Uint(*word, 256)).collect(),
+            )
+        };
+        let arguments = self
+            .function
+            .abi_encode_input_raw(&[words(&public_key), words(&signature)])
+            .expect("words match uint256[4] and uint256[2]");
+        [self.selector.as_slice(), &arguments].concat()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::sol_types::SolValue;
+
+    #[test]
+    fn test_default_selector() {
+        let call = RegisterCall::default();
+        assert_eq!(
+            call.selector(),
+            Selector::from_slice(&alloy::primitives::keccak256(DEFAULT_REGISTER_SIGNATURE)[..4])
+        );
+        let public_key = [1, 2, 3, 4].map(U256::from);
+        let signature = [5, 6].map(U256::from);
+        let calldata = call.encode(public_key, signature);
+        assert_eq!(calldata.len(), 4 + 6 * 32);
+        assert_eq!(calldata[4..], (public_key, signature).abi_encode_params());
+    }
+
+    #[test]
+    fn test_signature_and_selector_overrides() {
+        let named = RegisterCall::from_signature(
+            "function register(uint256[4] calldata pubkey, uint[2] calldata signature)",
+        )
+        .unwrap();
+        assert_eq!(
+            named.selector(),
+            Selector::from_slice(
+                &alloy::primitives::keccak256("register(uint256[4],uint256[2])")[..4]
+            )
+        );
+        assert_ne!(named.selector(), RegisterCall::default().selector());
+
+        let selector = Selector::new([0xde, 0xad, 0xbe, 0xef]);
+        let calldata =
+            RegisterCall::from_selector(selector).encode([U256::ZERO; 4], [U256::ZERO; 2]);
+        assert_eq!(calldata[..4], [0xde, 0xad, 0xbe, 0xef]);
+
+        for signature in ["registerValidator(uint256[2],uint256[4])", "registerValidator(bytes)"] {
+            assert_eq!(
+                RegisterCall::from_signature(signature),
+                Err(CalldataError::WrongInputs { signature: signature.to_string() })
+            );
+        }
+        assert!(matches!(
+            RegisterCall::from_signature("registerValidator("),
+            Err(CalldataError::InvalidSignature { .. })
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+//! Compressed encodings of the G1 and G2 words produced by [`crate::words`].
+//!
+//! The BN254 base field modulus is below 2^254, so the top two bits of the first byte of any
+//! encoded coordinate are free and carry the flags:
+//!
+//! * bit 7 (`0x80`) is set when y is the lexicographically larger square root, i.e. `y > (p-1)/2`
+//!   for G1, and for G2 `y_im > (p-1)/2`, or `y_im == 0` and `y_re > (p-1)/2`;
+//! * bit 6 (`0x40`) is reserved for the point at infinity. PoP fixtures never contain it, so it is
+//!   never set by the compressors and decompression rejects it.
+//!
+//! G1 compresses to the 32-byte big-endian x. G2 compresses to the 64 bytes `x_re || x_im`,
+//! the same limb order as [`g2_to_words_solidity`](crate::g2_to_words_solidity), with the flags
+//! in the first byte of `x_re`.
+
+use crate::words::{g1_from_words, g2_from_words_solidity};
+use alloy::primitives::{uint, U256};
+use sylow::{FieldExtensionTrait, Fp, Fp2};
+
+const FLAG_LARGER_Y: u8 = 0x80;
+const FLAG_INFINITY: u8 = 0x40;
+const FLAG_MASK: u8 = FLAG_LARGER_Y | FLAG_INFINITY;
+
+/// `(p - 1) / 2` for the BN254 base field.
+const HALF_MODULUS: U256 =
+    uint!(0x183227397098d014dc2822db40c0ac2ecbc0b548b438e5469e10460b6c3e7ea3_U256);
+
+fn is_larger(y: U256) -> bool {
+    y > HALF_MODULUS
+}
+
+fn fp_from_word(word: U256) -> Option<Fp> {
+    Option::from(Fp::from_be_bytes(&word.to_be_bytes::<32>()))
+}
+
+fn word_from_fp(value: Fp) -> U256 {
+    U256::from_be_bytes(value.to_be_bytes())
+}
+
+/// Split the flags off the first byte of an encoding, rejecting the infinity flag.
+fn split_flags<const N: usize>(bytes: &[u8; N]) -> Option<(bool, [u8; N])> {
+    let flags = bytes[0] & FLAG_MASK;
+    if flags & FLAG_INFINITY != 0 {
+        return None
+    }
+    let mut unflagged = *bytes;
+    unflagged[0] &= !FLAG_MASK;
+    Some((flags & FLAG_LARGER_Y != 0, unflagged))
+}
+
+/// Compress `[x, y]` to 32 bytes.
+pub fn compress_g1(words: [U256; 2]) -> [u8; 32] {
+    let [x, y] = words;
+    let mut bytes = x.to_be_bytes::<32>();
+    if is_larger(y) {
+        bytes[0] |= FLAG_LARGER_Y;
+    }
+    bytes
+}
+
+/// Recover `[x, y]` from [`compress_g1`]; `None` if x is not the abscissa of a curve point.
+pub fn decompress_g1(bytes: &[u8; 32]) -> Option<[U256; 2]> {
+    let (larger, x_bytes) = split_flags(bytes)?;
+    let x_word = U256::from_be_bytes(x_bytes);
+    let x = fp_from_word(x_word)?;
+    let y = Option::<Fp>::from(
+        (x.square() * x + <Fp as FieldExtensionTrait<1, 1>>::curve_constant()).sqrt(),
+    )?;
+    let y = if is_larger(word_from_fp(y)) == larger { y } else { -y };
+    let words = [x_word, word_from_fp(y)];
+    g1_from_words(words).ok().map(|_| words)
+}
+
+/// Compress Solidity-ordered `[x_re, x_im, y_re, y_im]` to 64 bytes.
+pub fn compress_g2(words: [U256; 4]) -> [u8; 64] {
+    let [x_re, x_im, y_re, y_im] = words;
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(&x_re.to_be_bytes::<32>());
+    bytes[32..].copy_from_slice(&x_im.to_be_bytes::<32>());
+    if is_larger(y_im) || (y_im.is_zero() && is_larger(y_re)) {
+        bytes[0] |= FLAG_LARGER_Y;
+    }
+    bytes
+}
+
+/// Recover `[x_re, x_im, y_re, y_im]` from [`compress_g2`]; `None` if x is not the abscissa of a
+/// point in the G2 subgroup.
+pub fn decompress_g2(bytes: &[u8; 64]) -> Option<[U256; 4]> {
+    let (larger, x_bytes) = split_flags(bytes)?;
+    let x_re = U256::from_be_slice(&x_bytes[..32]);
+    let x_im = U256::from_be_slice(&x_bytes[32..]);
+    let x = Fp2::new(&[fp_from_word(x_re)?, fp_from_word(x_im)?]);
+    let y2 = x.square() * x + <Fp2 as FieldExtensionTrait<2, 2>>::curve_constant();
+    let y = Option::<Fp2>::from(y2.sqrt())?;
+    // `Fp2::to_be_bytes` writes the imaginary part first.
+    let limbs = |y: Fp2| {
+        let bytes = y.to_be_bytes();
+        (U256::from_be_slice(&bytes[32..]), U256::from_be_slice(&bytes[..32]))
+    };
+    let (y_re, y_im) = limbs(y);
+    let is_larger_root = is_larger(y_im) || (y_im.is_zero() && is_larger(y_re));
+    let (y_re, y_im) = if is_larger_root == larger { (y_re, y_im) } else { limbs(-y) };
+    let words = [x_re, x_im, y_re, y_im];
+    g2_from_words_solidity(words).ok().map(|_| words)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::words::{g1_to_words, g2_to_words_solidity};
+    use sylow::{G1Affine, G1Projective, G2Affine, G2Projective, GroupTrait};
+
+    fn g1_cases() -> Vec<G1Affine> {
+        // (1, 2) has the smaller root, its negation (1, p - 2) the larger.
+        let generator = G1Projective::generator();
+        vec![
+            G1Affine::from(generator),
+            G1Affine::from(-generator),
+            G1Affine::from(generator * Fp::from(5u64)),
+        ]
+    }
+
+    fn g2_cases() -> Vec<G2Affine> {
+        let generator = G2Projective::generator();
+        vec![
+            G2Affine::from(generator),
+            G2Affine::from(-generator),
+            G2Affine::from(generator * Fp::from(5u64)),
+        ]
+    }
+
+    #[test]
+    fn test_g1_round_trip() {
+        let mut flags = Vec::new();
+        for point in g1_cases() {
+            let words = g1_to_words(&point);
+            let compressed = compress_g1(words);
+            flags.push(compressed[0] & FLAG_LARGER_Y != 0);
+            assert_eq!(decompress_g1(&compressed), Some(words));
+        }
+        assert_eq!(
+            flags[..2],
+            [false, true],
+            "generator has the smaller y, its negation the larger"
+        );
+    }
+
+    #[test]
+    fn test_g2_round_trip() {
+        let mut flags = Vec::new();
+        for point in g2_cases() {
+            let words = g2_to_words_solidity(&point);
+            let compressed = compress_g2(words);
+            flags.push(compressed[0] & FLAG_LARGER_Y != 0);
+            assert_eq!(decompress_g2(&compressed), Some(words));
+        }
+        assert_ne!(flags[0], flags[1], "a point and its negation take opposite roots");
+    }
+
+    #[test]
+    fn test_rejects_invalid_encodings() {
+        let mut infinity = compress_g1(g1_to_words(&G1Affine::generator()));
+        infinity[0] |= FLAG_INFINITY;
+        assert_eq!(decompress_g1(&infinity), None);
+
+        // x = 0 gives y^2 = 3, which is not a square.
+        assert_eq!(decompress_g1(&[0u8; 32]), None);
+        let mut over_modulus = [0u8; 32];
+        over_modulus[0] = 0x3f;
+        assert_eq!(decompress_g1(&over_modulus), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! What every producer and consumer of the vectors has to agree on byte for byte: the default
+//! DSTs, and the order of the four `uint256` limbs of a G2 point.
+//!
+//! sylow serializes each `Fp2` coordinate with its imaginary part (`c1`) first, while the
+//! contracts and every fixture take the real part first. Convert between the two with
+//! [`g2_sylow_to_solidity`] and [`g2_solidity_to_sylow`] rather than indexing limbs by hand.
+
+use alloy::primitives::U256;
+use std::fmt;
+
+/// Default DST of StakeManager PoPs.
+pub const DEFAULT_DST_STAKE_MANAGER: &str = "StakeManager:BN254:PoP:v1:";
+/// Default DST of ValidatorManager PoPs.
+pub const DEFAULT_DST_VALIDATOR_MANAGER: &str = "ValidatorManager:BN254:PoP:v1:";
+/// Default DST of StakeManager PoPs in [`PopStyle::Pubkey`](crate::PopStyle::Pubkey).
+pub const DEFAULT_DST_STAKE_MANAGER_PUBKEY: &str = "StakeManager:BN254:PoP:pubkey:v1:";
+/// Default DST of ValidatorManager PoPs in [`PopStyle::Pubkey`](crate::PopStyle::Pubkey).
+pub const DEFAULT_DST_VALIDATOR_MANAGER_PUBKEY: &str = "ValidatorManager:BN254:PoP:pubkey:v1:";
+/// Default DST of rotation signatures, see [`crate::rotate`].
+pub const DEFAULT_DST_ROTATE: &str = "ValidatorManager:BN254:Rotate:v1:";
+
+/// The order of the four limbs of a G2 point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimbOrder {
+    /// `[x_im, x_re, y_im, y_re]`, the 32-byte words of sylow's `to_be_bytes`.
+    Sylow,
+    /// `[x_re, x_im, y_re, y_im]`, what the contracts, the EIP-197 precompile input and the
+    /// fixtures use.
+    Solidity,
+}
+
+impl LimbOrder {
+    /// Reorder `words` from this order to `to`.
+    pub fn convert(self, words: [U256; 4], to: Self) -> [U256; 4] {
+        match (self, to) {
+            (Self::Sylow, Self::Solidity) => g2_sylow_to_solidity(words),
+            (Self::Solidity, Self::Sylow) => g2_solidity_to_sylow(words),
+            _ => words,
+        }
+    }
+}
+
+impl fmt::Display for LimbOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sylow => f.pad("sylow"),
+            Self::Solidity => f.pad("solidity"),
+        }
+    }
+}
+
+/// `[x_im, x_re, y_im, y_re]` to `[x_re, x_im, y_re, y_im]`: swap the halves of both coordinates.
+pub fn g2_sylow_to_solidity(words: [U256; 4]) -> [U256; 4] {
+    let [x_im, x_re, y_im, y_re] = words;
+    [x_re, x_im, y_re, y_im]
+}
+
+/// `[x_re, x_im, y_re, y_im]` to `[x_im, x_re, y_im, y_re]`, the inverse of
+/// [`g2_sylow_to_solidity`].
+pub fn g2_solidity_to_sylow(words: [U256; 4]) -> [U256; 4] {
+    let [x_re, x_im, y_re, y_im] = words;
+    [x_im, x_re, y_im, y_re]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::words::g2_to_words_solidity;
+    use alloy::primitives::uint;
+    use sylow::{G2Affine, G2Projective, GroupTrait};
+
+    /// The EIP-197 G2 generator in Solidity limb order.
+    const G2_GENERATOR_SOLIDITY: [U256; 4] = uint!([
+        0x1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed_U256,
+        0x198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2_U256,
+        0x12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa_U256,
+        0x090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b_U256,
+    ]);
+
+    #[test]
+    fn test_solidity_generator_converts_back_to_sylow() {
+        let sylow = LimbOrder::Solidity.convert(G2_GENERATOR_SOLIDITY, LimbOrder::Sylow);
+        assert_eq!(sylow, g2_solidity_to_sylow(G2_GENERATOR_SOLIDITY));
+        let bytes: Vec<u8> = sylow.iter().flat_map(U256::to_be_bytes::<32>).collect();
+        let point = G2Affine::from_be_bytes(&bytes.try_into().unwrap());
+        let point = G2Affine::from(Option::<G2Projective>::from(point).expect("a G2 point"));
+        assert_eq!(point, G2Affine::generator());
+
+        let bytes = G2Affine::generator().to_be_bytes();
+        let words: Vec<U256> = bytes.chunks(32).map(U256::from_be_slice).collect();
+        assert_eq!(words, sylow);
+        assert_eq!(g2_to_words_solidity(&G2Affine::generator()), G2_GENERATOR_SOLIDITY);
+    }
+
+    #[test]
+    fn test_conversions_are_inverse() {
+        let words = [U256::from(1), U256::from(2), U256::from(3), U256::from(4)];
+        assert_eq!(g2_sylow_to_solidity(words), [2, 1, 4, 3].map(U256::from));
+        assert_eq!(g2_solidity_to_sylow(g2_sylow_to_solidity(words)), words);
+        for from in [LimbOrder::Sylow, LimbOrder::Solidity] {
+            for to in [LimbOrder::Sylow, LimbOrder::Solidity] {
+                assert_eq!(to.convert(from.convert(words, to), from), words, "{from} -> {to}");
+            }
+            assert_eq!(from.convert(words, from), words);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! A fixture as CSV, for reviewing in a spreadsheet which validators are registered where.
+//!
+//! There is one row per proof, so one per wallet and chain id unless the fixture has negative
+//! vectors, under a header of [`CSV_COLUMNS`]. Points are split into one column per limb, in the
+//! fixture's own number format. Fields are quoted as RFC 4180 requires and rows end in CRLF. CSV
+//! is only written; [`load_fixture`](crate::load_fixture) does not read it back.
+
+use crate::fixture::BlsTestData;
+
+/// The header row.
+pub const CSV_COLUMNS: [&str; 19] = [
+    "wallet_address",
+    "chain_id",
+    "public_key_0",
+    "public_key_1",
+    "public_key_2",
+    "public_key_3",
+    "message_hash_stake_manager_x",
+    "message_hash_stake_manager_y",
+    "message_hash_validator_manager_x",
+    "message_hash_validator_manager_y",
+    "proof_of_possession_stake_manager_x",
+    "proof_of_possession_stake_manager_y",
+    "proof_of_possession_validator_manager_x",
+    "proof_of_possession_validator_manager_y",
+    "valid",
+    "invalid_reason",
+    "attack",
+    "domain_staking_manager",
+    "domain_validator_manager",
+];
+
+/// The header row, terminated.
+pub fn csv_header() -> String {
+    csv_record(&CSV_COLUMNS)
+}
+
+/// The rows of every proof of `case`, each terminated.
+pub fn csv_rows(case: &BlsTestData) -> String {
+    case.proof
+        .iter()
+        .map(|proof| {
+            let [pk_0, pk_1, pk_2, pk_3] = &case.public_key;
+            let [hash_stake_x, hash_stake_y] = &proof.message_hash_stake_manager;
+            let [hash_validator_x, hash_validator_y] = &proof.message_hash_validator_manager;
+            let [pop_stake_x, pop_stake_y] = &proof.proof_of_possession_stake_manager;
+            let [pop_validator_x, pop_validator_y] = &proof.proof_of_possession_validator_manager;
+            let fields: [&str; CSV_COLUMNS.len()] = [
+                &case.wallet_address,
+                &proof.chain_id,
+                pk_0,
+                pk_1,
+                pk_2,
+                pk_3,
+                hash_stake_x,
+                hash_stake_y,
+                hash_validator_x,
+                hash_validator_y,
+                pop_stake_x,
+                pop_stake_y,
+                pop_validator_x,
+                pop_validator_y,
+                if proof.valid { "true" } else { "false" },
+                proof.invalid_reason.as_deref().unwrap_or_default(),
+                case.attack.as_deref().unwrap_or_default(),
+                proof.domain_staking_manager.as_ref().unwrap_or(&case.domain_staking_manager),
+                proof.domain_validator_manager.as_ref().unwrap_or(&case.domain_validator_manager),
+            ];
+            csv_record(&fields)
+        })
+        .collect()
+}
+
+/// The header and the rows of every case.
+pub fn encode_csv(cases: &[BlsTestData]) -> String {
+    csv_header() + &cases.iter().map(csv_rows).collect::<String>()
+}
+
+/// One CRLF-terminated record, quoting the fields that hold a comma, a quote or a line break.
+fn csv_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    fields.join(",") + "\r\n"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(csv_record(&["0x1", "", "a,b"]), "0x1,,\"a,b\"\r\n");
+        assert_eq!(
+            csv_record(&["say \"hi\"", "two\nlines"]),
+            "\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+        assert_eq!(csv_header().matches(',').count(), CSV_COLUMNS.len() - 1);
+    }
+}
@@ -0,0 +1,308 @@
+//! Edge-case entries around the point at infinity and small offsets from a valid PoP, for
+//! checking that a verifier compares points strictly.
+//!
+//! A naive verifier runs `e(σ, G2) == e(H(m), pk)` on whatever points it is handed, reading the
+//! all-zero words as the identity the way the EIP-196/197 precompiles do. Every entry records in
+//! `expected_naive_result` whether that check passes, computed with sylow's group arithmetic and
+//! pairing; all of them must be rejected, so a verifier that accepts one recorded as `true` is
+//! only as strict as the naive check. G1 has cofactor one and no small-order points, so an
+//! offset of `k·G1` is as close to a valid signature as a point can get, and even the naive
+//! check refuses it.
+
+use crate::{
+    fixture::{BlsTestData, ProofData, GENERATOR_VERSION},
+    generate::{
+        calldata_hex, case_keypair, eth_private_key_hex, pairing_check, private_key_hex, Domain,
+        GenOptions, GenerateError,
+    },
+    keys::SecretKeyPair,
+    words::{
+        g1_from_words, g1_to_words, g2_from_words_solidity, g2_to_words_solidity, validate_g1,
+        validate_g2, words_to_hex,
+    },
+};
+use alloy::primitives::{keccak256, Address, U256};
+use std::fmt;
+use sylow::{pairing, Fp, G1Affine, G1Projective, G2Affine, G2Projective, GroupTrait};
+
+/// An adversarial entry written with `edge_cases`, tagged with its name in `attack`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeCase {
+    /// The G2 identity as public key, with the G1 identity as PoP: `e(0, G2) == e(H(m), 0)` for
+    /// every message, so the naive check passes.
+    IdentityPublicKey,
+    /// The wallet's real key with the G1 identity as PoP. `e(H(m), pk)` is not one, so the naive
+    /// check fails.
+    IdentitySignature,
+    /// The valid PoP plus `k·G1`, which a verifier that tolerates small-order components (by
+    /// clearing a cofactor G1 does not have, say) would accept. The naive check fails.
+    SignaturePlusGenerator(u64),
+}
+
+impl EdgeCase {
+    pub const ALL: [Self; 5] = [
+        Self::IdentityPublicKey,
+        Self::IdentitySignature,
+        Self::SignaturePlusGenerator(1),
+        Self::SignaturePlusGenerator(2),
+        Self::SignaturePlusGenerator(3),
+    ];
+
+    /// The entry's `attack`, e.g. `signature_plus_2g1`.
+    pub fn name(&self) -> String {
+        match self {
+            Self::IdentityPublicKey => "identity_public_key".to_string(),
+            Self::IdentitySignature => "identity_signature".to_string(),
+            Self::SignaturePlusGenerator(1) => "signature_plus_g1".to_string(),
+            Self::SignaturePlusGenerator(k) => format!("signature_plus_{k}g1"),
+        }
+    }
+
+    /// The edge case named `attack`, if it is one.
+    pub fn from_attack(attack: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|edge_case| edge_case.name() == attack)
+    }
+}
+
+impl fmt::Display for EdgeCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.name())
+    }
+}
+
+fn naive_g1(words: [U256; 2]) -> Option<G1Projective> {
+    if words == [U256::ZERO; 2] {
+        return Some(G1Projective::zero())
+    }
+    g1_from_words(words).ok().map(G1Projective::from)
+}
+
+fn naive_g2(words: [U256; 4]) -> Option<G2Projective> {
+    if words == [U256::ZERO; 4] {
+        return Some(G2Projective::zero())
+    }
+    g2_from_words_solidity(words).ok().map(G2Projective::from)
+}
+
+/// `e(signature, G2) == e(message_hash, public_key)` with the all-zero words taken as the
+/// identity instead of rejected, as a verifier without [`validate_g1`] and [`validate_g2`]
+/// computes it. Words that are not points at all fail.
+pub fn naive_pairing_check(
+    signature: [U256; 2],
+    message_hash: [U256; 2],
+    public_key: [U256; 4],
+) -> bool {
+    let (Some(signature), Some(message_hash), Some(public_key)) =
+        (naive_g1(signature), naive_g1(message_hash), naive_g2(public_key))
+    else {
+        return false
+    };
+    pairing(&signature, &G2Projective::generator()) == pairing(&message_hash, &public_key)
+}
+
+/// Whether the checks the contracts should run, point validation and the pairing, accept the
+/// PoP.
+fn strictly_accepted(signature: [U256; 2], message_hash: &G1Affine, public_key: [U256; 4]) -> bool {
+    match (validate_g1(signature), validate_g2(public_key)) {
+        (Ok(signature), Ok(public_key)) => pairing_check(&signature, message_hash, &public_key),
+        _ => false,
+    }
+}
+
+fn edge_case_proof(
+    edge_case: EdgeCase,
+    kp: &SecretKeyPair,
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+    opts: &GenOptions,
+) -> Result<ProofData, GenerateError> {
+    let message =
+        opts.signed_preimage(opts.pop_style, chain_id, pk_words, sender, opts.nonce_start);
+    let hashed = opts.prehash.apply(&message);
+    let domain = |domain: Domain| {
+        let dst = opts.dst(domain, chain_id);
+        let hash = opts.hasher.hash_to_curve(&dst, &hashed);
+        let signature = match edge_case {
+            EdgeCase::IdentityPublicKey | EdgeCase::IdentitySignature => [U256::ZERO; 2],
+            EdgeCase::SignaturePlusGenerator(k) => {
+                let signature =
+                    G1Projective::from(opts.hasher.sign(&dst, &hashed, kp.secret_key.expose()));
+                g1_to_words(&G1Affine::from(signature + G1Projective::generator() * Fp::from(k)))
+            }
+        };
+        if opts.verify && strictly_accepted(signature, &hash, *pk_words) {
+            return Err(GenerateError::EdgeCaseAccepted { wallet: sender, edge_case })
+        }
+        let hash = g1_to_words(&hash);
+        Ok((hash, signature, naive_pairing_check(signature, hash, *pk_words)))
+    };
+    let (message_hash_stake_manager, proof_of_possession_stake_manager, naive_stake_manager) =
+        domain(Domain::StakeManager)?;
+    let (message_hash_validator_manager, proof_of_possession_validator_manager, naive_validator) =
+        domain(Domain::ValidatorManager)?;
+    debug_assert_eq!(naive_stake_manager, naive_validator, "{edge_case} in both domains");
+
+    Ok(ProofData {
+        message_hash_stake_manager: words_to_hex(message_hash_stake_manager),
+        message_hash_validator_manager: words_to_hex(message_hash_validator_manager),
+        proof_of_possession_stake_manager: words_to_hex(proof_of_possession_stake_manager),
+        proof_of_possession_validator_manager: words_to_hex(proof_of_possession_validator_manager),
+        chain_id: chain_id.to_string(),
+        signed_chain_id: None,
+        nonce: opts.nonce_start.map(|nonce| nonce.to_string()),
+        domain_staking_manager: opts.proof_dst(Domain::StakeManager, chain_id),
+        domain_validator_manager: opts.proof_dst(Domain::ValidatorManager, chain_id),
+        message_bytes: Some(format!("0x{}", hex::encode(&message))),
+        message_keccak: Some(keccak256(&message).to_string()),
+        eip191_digest: opts.prehash.digest_hex(&message),
+        struct_preimage: opts.struct_preimage(chain_id, pk_words, sender),
+        proof_of_possession_stake_manager_compressed: None,
+        proof_of_possession_validator_manager_compressed: None,
+        pairing_input_stake_manager: None,
+        pairing_input_validator_manager: None,
+        message_hash_combined: None,
+        proof_of_possession_combined: None,
+        hash_to_curve_stake_manager: None,
+        hash_to_curve_validator_manager: None,
+        xmd_expansion_stake_manager: None,
+        xmd_expansion_validator_manager: None,
+        calldata: calldata_hex(opts, *pk_words, proof_of_possession_stake_manager),
+        ecdsa_binding: None,
+        expected_naive_result: Some(naive_stake_manager),
+        valid: false,
+        invalid_reason: Some(edge_case.name()),
+    })
+}
+
+/// One entry per [`EdgeCase`] for `sender`, signed with its real key. With `opts.verify`, each
+/// PoP is confirmed to fail point validation or the pairing check before it is written.
+pub fn generate_edge_cases(
+    sender: Address,
+    chain_ids: &[U256],
+    opts: &GenOptions,
+) -> Result<Vec<BlsTestData>, GenerateError> {
+    let kp = case_keypair(sender, opts);
+    let valid = g2_to_words_solidity(&G2Affine::from(kp.public_key));
+    EdgeCase::ALL
+        .iter()
+        .map(|edge_case| {
+            let pk_words = match edge_case {
+                EdgeCase::IdentityPublicKey => [U256::ZERO; 4],
+                _ => valid,
+            };
+            let proof = opts
+                .pop_chain_ids(chain_ids)
+                .iter()
+                .map(|chain_id| {
+                    edge_case_proof(*edge_case, &kp, *chain_id, &pk_words, sender, opts)
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(BlsTestData {
+                private_key: private_key_hex(opts, &kp),
+                public_key: words_to_hex(pk_words),
+                public_key_compressed: None,
+                public_key_padded: None,
+                wallet_address: sender.to_string(),
+                eth_private_key: eth_private_key_hex(opts, sender),
+                message_encoding: opts.encoding,
+                chain_id_width: opts.chain_id_width,
+                pop_style: opts.pop_style,
+                preimage: opts.preimage,
+                prehash: opts.prehash,
+                hash_function: opts.hasher.hash_function,
+                expand_len: opts.hasher.expand_len,
+                domain_staking_manager: opts.dst_stake_manager.clone(),
+                domain_validator_manager: opts.dst_validator_manager.clone(),
+                proof,
+                attack: Some(edge_case.name()),
+                generated_at: opts.generated_at,
+                generator_version: Some(GENERATOR_VERSION.to_string()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::words::words_from_hex;
+
+    fn seeded() -> GenOptions {
+        GenOptions { seed: Some("45".parse().unwrap()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_only_the_identity_key_passes_the_naive_check() {
+        let wallet = Address::repeat_byte(0x45);
+        let cases = generate_edge_cases(wallet, &[U256::from(1), U256::from(8453)], &seeded())
+            .expect("every edge case is rejected");
+        let names: Vec<_> = cases.iter().map(|case| case.attack.clone().unwrap()).collect();
+        assert_eq!(
+            names,
+            [
+                "identity_public_key",
+                "identity_signature",
+                "signature_plus_g1",
+                "signature_plus_2g1",
+                "signature_plus_3g1"
+            ]
+        );
+
+        for case in &cases {
+            let edge_case = EdgeCase::from_attack(case.attack.as_ref().unwrap()).unwrap();
+            let public_key = words_from_hex(&case.public_key).unwrap();
+            for proof in &case.proof {
+                assert!(!proof.valid);
+                let expected = edge_case == EdgeCase::IdentityPublicKey;
+                assert_eq!(proof.expected_naive_result, Some(expected), "{edge_case}");
+                for (signature, hash) in [
+                    (&proof.proof_of_possession_stake_manager, &proof.message_hash_stake_manager),
+                    (
+                        &proof.proof_of_possession_validator_manager,
+                        &proof.message_hash_validator_manager,
+                    ),
+                ] {
+                    let (signature, hash) =
+                        (words_from_hex(signature).unwrap(), words_from_hex(hash).unwrap());
+                    assert_eq!(naive_pairing_check(signature, hash, public_key), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_signatures_are_real_group_sums() {
+        let wallet = Address::repeat_byte(0x45);
+        let opts = seeded();
+        let kp = case_keypair(wallet, &opts);
+        let valid = g2_to_words_solidity(&G2Affine::from(kp.public_key));
+        let dst = opts.dst(Domain::StakeManager, U256::from(1));
+        let message = opts.signed_preimage(opts.pop_style, U256::from(1), &valid, wallet, None);
+        let signature =
+            G1Projective::from(opts.hasher.sign(&dst, &message, kp.secret_key.expose()));
+
+        let proof = edge_case_proof(
+            EdgeCase::SignaturePlusGenerator(2),
+            &kp,
+            U256::from(1),
+            &valid,
+            wallet,
+            &opts,
+        )
+        .unwrap();
+        let offset =
+            naive_g1(words_from_hex(&proof.proof_of_possession_stake_manager).unwrap()).unwrap();
+        assert_eq!(
+            G1Affine::from(offset - signature),
+            G1Affine::from(G1Projective::generator() * Fp::from(2))
+        );
+        assert!(naive_pairing_check([U256::ZERO; 2], [U256::ZERO; 2], valid));
+        assert!(!naive_pairing_check([U256::ZERO, U256::from(1)], [U256::ZERO; 2], valid));
+        assert_eq!(
+            EdgeCase::from_attack("signature_plus_2g1"),
+            Some(EdgeCase::SignaturePlusGenerator(2))
+        );
+        assert_eq!(EdgeCase::from_attack("infinity"), None);
+    }
+}
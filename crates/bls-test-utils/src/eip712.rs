@@ -0,0 +1,168 @@
+//! EIP-712-style struct-hash preimages, signed in [`Preimage::Eip712`](crate::Preimage::Eip712):
+//! instead of the packed message bytes, a PoP signs the 32-byte digest
+//! `keccak256(abi.encode(TYPEHASH, chainId, pubkey, sender, nonce, expiry))` of a
+//! [`PopRegistration`].
+//!
+//! Unlike full EIP-712 there is no domain separator (the DSTs already separate the contracts) and
+//! `pubkey` is ABI-encoded in place rather than hashed, matching what the contract computes.
+
+use crate::words::{words_to_hex, NumberFormat};
+use alloy::{
+    primitives::{keccak256, Address, B256, U256},
+    sol,
+    sol_types::{SolStruct, SolValue},
+};
+use serde::{Deserialize, Serialize};
+
+sol! {
+    /// What a PoP signs in [`Preimage::Eip712`](crate::Preimage::Eip712): the registration of
+    /// `pubkey` (Solidity limb order) by `sender` on `chainId`, valid with `nonce` until
+    /// `expiry`.
+    #[derive(Debug, PartialEq, Eq)]
+    struct PopRegistration {
+        uint256 chainId;
+        uint256[4] pubkey;
+        address sender;
+        uint256 nonce;
+        uint256 expiry;
+    }
+}
+
+impl PopRegistration {
+    /// `TYPEHASH`, keccak256 of the type string `PopRegistration(uint256 chainId,...)`.
+    pub fn typehash() -> B256 {
+        keccak256(Self::eip712_encode_type().as_bytes())
+    }
+
+    /// `keccak256(abi.encode(typehash, chainId, pubkey, sender, nonce, expiry))`, the preimage
+    /// that is hashed to the curve.
+    pub fn digest(&self) -> B256 {
+        let values =
+            (Self::typehash(), self.chainId, self.pubkey, self.sender, self.nonce, self.expiry);
+        keccak256(values.abi_encode_params())
+    }
+
+    /// The fields and digest as recorded on a [`ProofData`](crate::ProofData).
+    pub fn to_struct_preimage(&self) -> StructPreimage {
+        StructPreimage {
+            typehash: Self::typehash().to_string(),
+            chain_id: self.chainId.to_string(),
+            pubkey: words_to_hex(self.pubkey),
+            sender: self.sender.to_string(),
+            nonce: self.nonce.to_string(),
+            expiry: self.expiry.to_string(),
+            digest: self.digest().to_string(),
+        }
+    }
+}
+
+/// The [`PopRegistration`] a proof signed, with its typehash and digest. `chain_id`, `nonce` and
+/// `expiry` are decimal, like the proof's own; `pubkey` follows the file's number format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructPreimage {
+    pub typehash: String,
+    pub chain_id: String,
+    pub pubkey: [String; 4],
+    pub sender: String,
+    pub nonce: String,
+    pub expiry: String,
+    /// The signed digest, also the proof's `message_bytes`.
+    pub digest: String,
+}
+
+impl StructPreimage {
+    /// Write `pubkey` in `format`.
+    pub fn rewrite_numbers(&mut self, format: NumberFormat) {
+        format.rewrite(&mut self.pubkey);
+    }
+}
+
+/// The registration `sender` signs for `pk_words` on `chain_id`; without a nonce, nonce 0.
+pub fn pop_registration(
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+    nonce: Option<U256>,
+    expiry: U256,
+) -> PopRegistration {
+    PopRegistration {
+        chainId: chain_id,
+        pubkey: *pk_words,
+        sender,
+        nonce: nonce.unwrap_or_default(),
+        expiry,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::primitives::{address, b256, uint};
+
+    fn registration() -> PopRegistration {
+        pop_registration(
+            U256::from(8453),
+            &[U256::from(1), U256::from(2), U256::from(3), U256::from(4)],
+            address!("0x70997970C51812dc3A010C7d01b50e0d17dc79C8"),
+            Some(U256::from(7)),
+            uint!(1_700_000_000_U256),
+        )
+    }
+
+    #[test]
+    fn test_typehash_of_type_string() {
+        assert_eq!(
+            PopRegistration::eip712_encode_type(),
+            "PopRegistration(uint256 chainId,uint256[4] pubkey,address sender,uint256 nonce,\
+             uint256 expiry)"
+        );
+        assert_eq!(
+            PopRegistration::typehash(),
+            b256!("0x6aa3482c0a82eed0ad82723448c39b27b319ec7b260764145645da0bc0cd453e")
+        );
+    }
+
+    #[test]
+    fn test_digest_is_pinned() {
+        let registration = registration();
+        // `abi.encode` of the typehash and the fields, word by word.
+        let mut encoded = PopRegistration::typehash().to_vec();
+        for word in [U256::from(8453), U256::from(1), U256::from(2), U256::from(3), U256::from(4)] {
+            encoded.extend(word.to_be_bytes::<32>());
+        }
+        encoded.extend([0; 12]);
+        encoded.extend(registration.sender);
+        encoded.extend(U256::from(7).to_be_bytes::<32>());
+        encoded.extend(uint!(1_700_000_000_U256).to_be_bytes::<32>());
+        assert_eq!(
+            encoded,
+            (PopRegistration::typehash(), registration.clone()).abi_encode_params()
+        );
+        assert_eq!(registration.digest(), keccak256(&encoded));
+        assert_eq!(
+            registration.digest(),
+            b256!("0x56e4636ba5934ca929470ffd076a7e059510f468a40ae975e4d1f0eefe51db91")
+        );
+
+        let fields = registration.to_struct_preimage();
+        assert_eq!(fields.chain_id, "8453");
+        assert_eq!(fields.nonce, "7");
+        assert_eq!(fields.expiry, "1700000000");
+        assert_eq!(fields.digest, registration.digest().to_string());
+    }
+
+    #[test]
+    fn test_digest_binds_every_field() {
+        let base = registration();
+        let changed = [
+            PopRegistration { chainId: U256::from(1), ..base.clone() },
+            PopRegistration { pubkey: [U256::from(1); 4], ..base.clone() },
+            PopRegistration { sender: Address::ZERO, ..base.clone() },
+            PopRegistration { nonce: U256::from(8), ..base.clone() },
+            PopRegistration { expiry: U256::MAX, ..base.clone() },
+        ];
+        for registration in changed {
+            assert_ne!(registration.digest(), base.digest(), "{registration:?}");
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! The `expand_message_xmd` configuration behind every `H(m)`: Keccak256 to 96 bytes by default,
+//! or SHA-256 for contracts whose hash-to-curve is built on it.
+
+use crate::hash_to_curve::UNIFORM_BYTES_LEN;
+use serde::{Deserialize, Serialize};
+use sha3::{
+    digest::{
+        consts::{U32, U64},
+        crypto_common::BlockSizeUser,
+        FixedOutput, HashMarker, Output, OutputSizeUser, Update,
+    },
+    Keccak256,
+};
+use std::{fmt, str::FromStr};
+use sylow::{Expander, Fp, G1Affine, GroupTrait, XMDExpander};
+
+/// The `expand_message_xmd` output lengths sylow can hash to the curve from: its
+/// `hash_to_curve` always reduces two 48-byte halves.
+pub const SUPPORTED_EXPAND_LENS: [usize; 1] = [UNIFORM_BYTES_LEN];
+
+/// The hash `expand_message_xmd` is instantiated with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashFunction {
+    /// Ethereum's keccak256, what the contracts use today.
+    #[default]
+    Keccak256,
+    /// SHA-256, as in the RFC 9380 `BN254G1_XMD:SHA-256_SVDW_RO_` suites.
+    Sha256,
+}
+
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keccak256 => f.pad("keccak256"),
+            Self::Sha256 => f.pad("sha256"),
+        }
+    }
+}
+
+impl FromStr for HashFunction {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "keccak256" => Ok(Self::Keccak256),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(format!("`{input}` is not a hash function, expected `keccak256` or `sha256`")),
+        }
+    }
+}
+
+/// Check `expand_len` is one of [`SUPPORTED_EXPAND_LENS`].
+pub fn check_expand_len(expand_len: usize) -> Result<usize, String> {
+    if !SUPPORTED_EXPAND_LENS.contains(&expand_len) {
+        return Err(format!(
+            "sylow hashes to the curve from {SUPPORTED_EXPAND_LENS:?} bytes, not {expand_len}"
+        ))
+    }
+    Ok(expand_len)
+}
+
+/// sha2's SHA-256 behind the `digest` 0.11 traits sylow's [`XMDExpander`] is bounded by; sha2
+/// itself still implements 0.10's.
+#[derive(Clone, Default)]
+pub struct Sha256(sha2::Sha256);
+
+impl Update for Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(&mut self.0, data);
+    }
+}
+
+impl OutputSizeUser for Sha256 {
+    type OutputSize = U32;
+}
+
+impl BlockSizeUser for Sha256 {
+    type BlockSize = U64;
+}
+
+impl FixedOutput for Sha256 {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&sha2::Digest::finalize(self.0));
+    }
+}
+
+impl HashMarker for Sha256 {}
+
+/// Bind `$expander` to the [`XMDExpander`] for `$hasher` and `$dst` in `$body`. A macro rather
+/// than a method because the two expanders are different types.
+macro_rules! with_expander {
+    ($hasher:expr, $dst:expr, |$expander:ident| $body:expr) => {
+        match $hasher.hash_function {
+            HashFunction::Keccak256 => {
+                let $expander =
+                    XMDExpander::<Keccak256>::new($dst.as_bytes(), $hasher.expand_len as u64);
+                $body
+            }
+            HashFunction::Sha256 => {
+                let $expander =
+                    XMDExpander::<Sha256>::new($dst.as_bytes(), $hasher.expand_len as u64);
+                $body
+            }
+        }
+    };
+}
+
+/// How messages are hashed to G1, written to each entry's `hash_function` and `expand_len`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageHasher {
+    pub hash_function: HashFunction,
+    /// `expand_message_xmd` output length, one of [`SUPPORTED_EXPAND_LENS`].
+    pub expand_len: usize,
+}
+
+impl Default for MessageHasher {
+    fn default() -> Self {
+        Self { hash_function: HashFunction::Keccak256, expand_len: UNIFORM_BYTES_LEN }
+    }
+}
+
+impl MessageHasher {
+    /// `expand_message_xmd(message, dst, expand_len)`.
+    pub fn expand_message(&self, dst: &str, message: &[u8]) -> Vec<u8> {
+        self.prepare(dst).expand_message(message, self.expand_len)
+    }
+
+    /// The two halves of [`Self::expand_message`] reduced mod p.
+    pub fn hash_to_field(&self, dst: &str, message: &[u8]) -> [Fp; 2] {
+        with_expander!(self, dst, |expander| {
+            expander.hash_to_field(message, 2, self.expand_len / 2).expect("hash_to_field")
+        })
+    }
+
+    /// `H(message)` under `dst`.
+    pub fn hash_to_curve(&self, dst: &str, message: &[u8]) -> G1Affine {
+        self.prepare(dst).hash_to_curve(message)
+    }
+
+    /// The expander for `dst`, to hash many messages under the same DST.
+    pub fn prepare(&self, dst: &str) -> DstHasher {
+        match self.hash_function {
+            HashFunction::Keccak256 => {
+                DstHasher::Keccak256(XMDExpander::new(dst.as_bytes(), self.expand_len as u64))
+            }
+            HashFunction::Sha256 => {
+                DstHasher::Sha256(XMDExpander::new(dst.as_bytes(), self.expand_len as u64))
+            }
+        }
+    }
+
+    /// `secret_key · H(message)` under `dst`.
+    pub fn sign(&self, dst: &str, message: &[u8], secret_key: Fp) -> G1Affine {
+        with_expander!(self, dst, |expander| {
+            G1Affine::sign_message(&expander, message, secret_key).expect("sign")
+        })
+    }
+}
+
+/// A [`MessageHasher`] bound to one DST, see [`MessageHasher::prepare`].
+pub enum DstHasher {
+    Keccak256(XMDExpander<Keccak256>),
+    Sha256(XMDExpander<Sha256>),
+}
+
+impl DstHasher {
+    /// `expand_message_xmd(message, dst, len)` as the expander produces it, the uniform bytes
+    /// [`Self::hash_to_curve`] reduces when `len` is the hasher's `expand_len`.
+    pub fn expand_message(&self, message: &[u8], len: usize) -> Vec<u8> {
+        match self {
+            Self::Keccak256(expander) => expander.expand_message(message, len),
+            Self::Sha256(expander) => expander.expand_message(message, len),
+        }
+        .expect("expand_message_xmd")
+    }
+
+    /// `H(message)` under the prepared DST.
+    pub fn hash_to_curve(&self, message: &[u8]) -> G1Affine {
+        match self {
+            Self::Keccak256(expander) => G1Affine::hash_to_curve(expander, message),
+            Self::Sha256(expander) => G1Affine::hash_to_curve(expander, message),
+        }
+        .expect("hash to curve")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{conventions::DEFAULT_DST_STAKE_MANAGER, generate::pairing_check};
+    use sylow::{G2Affine, G2Projective};
+
+    const SHA256: MessageHasher =
+        MessageHasher { hash_function: HashFunction::Sha256, expand_len: UNIFORM_BYTES_LEN };
+
+    /// RFC 9380 appendix K.1, `expand_message_xmd(SHA-256)` with a 32-byte output.
+    #[test]
+    fn test_sha256_expander_matches_rfc_9380() {
+        let dst = "QUUX-V01-CS02-with-expander-SHA256-128";
+        for (message, expected) in [
+            (&b""[..], "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"),
+            (b"abc", "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"),
+        ] {
+            let expander = XMDExpander::<Sha256>::new(dst.as_bytes(), 128);
+            assert_eq!(hex::encode(expander.expand_message(message, 32).unwrap()), expected);
+            assert_eq!(hex::encode(SHA256.prepare(dst).expand_message(message, 32)), expected);
+        }
+    }
+
+    #[test]
+    fn test_hash_functions_give_different_points() {
+        let keccak = MessageHasher::default();
+        for message in [&b""[..], b"abc", &[0xff; 200]] {
+            assert_ne!(
+                keccak.hash_to_curve(DEFAULT_DST_STAKE_MANAGER, message),
+                SHA256.hash_to_curve(DEFAULT_DST_STAKE_MANAGER, message)
+            );
+        }
+
+        let secret_key = Fp::from(37);
+        let public_key = G2Affine::from(G2Projective::generator() * secret_key);
+        let hash = SHA256.hash_to_curve(DEFAULT_DST_STAKE_MANAGER, b"pop");
+        let signature = SHA256.sign(DEFAULT_DST_STAKE_MANAGER, b"pop", secret_key);
+        assert!(pairing_check(&signature, &hash, &public_key));
+    }
+
+    #[test]
+    fn test_expand_len_and_hash_parsing() {
+        assert_eq!(check_expand_len(96), Ok(96));
+        assert!(check_expand_len(128).unwrap_err().contains("[96]"));
+        assert_eq!("sha256".parse(), Ok(HashFunction::Sha256));
+        assert_eq!(HashFunction::Keccak256.to_string(), "keccak256");
+        assert!("sha3".parse::<HashFunction>().is_err());
+    }
+}
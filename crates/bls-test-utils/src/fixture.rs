@@ -0,0 +1,334 @@
+//! The serialized shape of `bls_test_data.json`.
+
+use crate::{
+    binding::EcdsaBinding,
+    eip712::StructPreimage,
+    expander::HashFunction,
+    generate::{ChainIdWidth, MessageEncoding, PopStyle, Prehash, Preimage},
+    hash_to_curve::{HashToCurveIntermediates, UNIFORM_BYTES_LEN},
+    words::NumberFormat,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the file layout, written as the top-level `schema_version`. Version 1 files, from
+/// before the field existed, are a bare list of [`BlsTestData`]; see
+/// [`load_fixture`](crate::load_fixture).
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The crate version recorded as every generated entry's `generator_version`.
+pub const GENERATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The version of files without a `schema_version`.
+pub(crate) fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// PoPs for one chain id, in both contract domains.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofData {
+    pub message_hash_stake_manager: [String; 2],
+    pub message_hash_validator_manager: [String; 2],
+    pub proof_of_possession_stake_manager: [String; 2],
+    pub proof_of_possession_validator_manager: [String; 2],
+    pub chain_id: String,
+    /// On `chain_replay` entries, the chain id the PoP was actually signed for; `chain_id` is
+    /// the one it is replayed on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_chain_id: Option<String>,
+    /// The registration nonce signed after the sender, in decimal, see
+    /// [`pop_message_with_nonce`](crate::pop_message_with_nonce). Only written with a nonce set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// The StakeManager DST used for this chain id when it embeds the chain id
+    /// (`dst_per_chain`), overriding the entry's `domain_staking_manager`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_staking_manager: Option<String>,
+    /// The ValidatorManager DST used for this chain id, as above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_validator_manager: Option<String>,
+    /// Hex of the exact PoP preimage that was hashed to the curve (in [`Prehash::Eip191`], whose
+    /// digest was), see [`pop_preimage`](crate::pop_preimage). Older files omit it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_bytes: Option<String>,
+    /// keccak256 of `message_bytes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_keccak: Option<String>,
+    /// The EIP-191 digest of `message_bytes` that was hashed to the curve instead of them. Only
+    /// written in [`Prehash::Eip191`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eip191_digest: Option<String>,
+    /// The [`PopRegistration`](crate::PopRegistration) whose digest is `message_bytes`. Only
+    /// written in [`Preimage::Eip712`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub struct_preimage: Option<StructPreimage>,
+    /// [`compress_g1`](crate::compress_g1) of the StakeManager PoP, hex encoded. Only written
+    /// with `compressed` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_of_possession_stake_manager_compressed: Option<String>,
+    /// [`compress_g1`](crate::compress_g1) of the ValidatorManager PoP, hex encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_of_possession_validator_manager_compressed: Option<String>,
+    /// [`pairing_input`](crate::pairing_input) of the StakeManager PoP, hex encoded: the 384
+    /// bytes to pass to the `0x08` precompile, which returns `1` exactly when `valid`. Only
+    /// written with `pairing_input` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pairing_input_stake_manager: Option<String>,
+    /// [`pairing_input`](crate::pairing_input) of the ValidatorManager PoP, hex encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pairing_input_validator_manager: Option<String>,
+    /// `message_hash_stake_manager + message_hash_validator_manager` in G1, the hash side of
+    /// [`combined_pairing_check`](crate::combined_pairing_check). Only written with `combined`
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_hash_combined: Option<[String; 2]>,
+    /// `proof_of_possession_stake_manager + proof_of_possession_validator_manager` in G1, which
+    /// verifies against `message_hash_combined` exactly when `valid`. Absent from negative
+    /// vectors whose signatures are not curve points.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_of_possession_combined: Option<[String; 2]>,
+    /// Every stage of `message_hash_stake_manager`, see [`crate::hash_to_curve`]. Only written
+    /// with `include_intermediates` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_to_curve_stake_manager: Option<HashToCurveIntermediates>,
+    /// Every stage of `message_hash_validator_manager`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_to_curve_validator_manager: Option<HashToCurveIntermediates>,
+    /// `expand_message_xmd` of the hashed message under the StakeManager DST, hex encoded
+    /// exactly as the expander produced it (`expand_len` bytes). Only written with
+    /// `include_intermediates` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xmd_expansion_stake_manager: Option<String>,
+    /// `expand_message_xmd` under the ValidatorManager DST, as above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xmd_expansion_validator_manager: Option<String>,
+    /// StakeManager `registerValidator(public_key, proof_of_possession_stake_manager)` calldata,
+    /// hex encoded, see [`RegisterCall`](crate::RegisterCall). Only written with `calldata` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calldata: Option<String>,
+    /// The wallet's ECDSA signature over `public_key` and `chain_id`, see [`crate::binding`].
+    /// Only written for wallets whose key is known, generated or derived from a mnemonic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecdsa_binding: Option<EcdsaBinding>,
+    /// Whether a verifier that runs the pairing check without validating its points (reading the
+    /// all-zero words as the identity) accepts this PoP, in both domains. Only written on
+    /// [`EdgeCase`](crate::EdgeCase) entries, which a strict verifier always rejects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_naive_result: Option<bool>,
+    /// `false` for negative vectors; omitted (and defaulted) for valid ones so older files and
+    /// consumers are unaffected.
+    #[serde(default = "default_valid", skip_serializing_if = "is_valid")]
+    pub valid: bool,
+    /// Why a negative vector must be rejected, one of [`InvalidReason::as_str`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<String>,
+}
+
+fn default_expand_len() -> usize {
+    UNIFORM_BYTES_LEN
+}
+
+fn default_valid() -> bool {
+    true
+}
+
+fn is_valid(valid: &bool) -> bool {
+    *valid
+}
+
+/// How a negative vector's signatures are corrupted. The message hashes always stay those of the
+/// labelled chain id, so only the signature is wrong.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidReason {
+    /// Correct message signed by an unrelated key.
+    WrongKey,
+    /// Signature over the message for `chain_id + 1`.
+    WrongChainId,
+    /// Valid signature with its x and y coordinates swapped.
+    SwappedCoordinates,
+    /// A random G1 point: on the curve, but not a signature over anything.
+    RandomPoint,
+    /// Signature over the message with `nonce + 1`, while the proof records `nonce`.
+    WrongNonce,
+}
+
+impl InvalidReason {
+    pub const ALL: [Self; 5] = [
+        Self::WrongKey,
+        Self::WrongChainId,
+        Self::SwappedCoordinates,
+        Self::RandomPoint,
+        Self::WrongNonce,
+    ];
+
+    /// The reasons that apply to PoPs in `style`, with or without a nonce: a
+    /// [`PopStyle::Pubkey`] PoP does not depend on the chain id, so `WrongChainId` would still
+    /// verify, and `WrongNonce` needs a nonce to get wrong.
+    pub fn applicable(style: PopStyle, with_nonce: bool) -> impl Iterator<Item = Self> {
+        Self::ALL.into_iter().filter(move |reason| match reason {
+            Self::WrongChainId => style == PopStyle::Message,
+            Self::WrongNonce => with_nonce,
+            _ => true,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WrongKey => "wrong_key",
+            Self::WrongChainId => "wrong_chain_id",
+            Self::SwappedCoordinates => "swapped_coordinates",
+            Self::RandomPoint => "random_point",
+            Self::WrongNonce => "wrong_nonce",
+        }
+    }
+}
+
+/// How a malicious `public_key` is built, see [`generate_attacks`](crate::generate_attacks). The
+/// contracts must refuse to register any of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicKeyAttack {
+    /// A valid key with `y_re` incremented, so the coordinates miss the twist equation.
+    OffCurve,
+    /// A point on the twist whose order divides the cofactor, as if the cofactor had been
+    /// cleared by multiplying with r instead of h.
+    WrongSubgroup,
+    /// All four limbs zero, the EIP-197 encoding of the point at infinity.
+    Infinity,
+}
+
+impl PublicKeyAttack {
+    pub const ALL: [Self; 3] = [Self::OffCurve, Self::WrongSubgroup, Self::Infinity];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OffCurve => "off_curve",
+            Self::WrongSubgroup => "wrong_subgroup",
+            Self::Infinity => "infinity",
+        }
+    }
+}
+
+impl fmt::Display for PublicKeyAttack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// One wallet's key material and PoPs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlsTestData {
+    /// The BLS secret key. Only written with `include_secrets` set, see [`Self::strip_secrets`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+    pub public_key: [String; 4],
+    /// [`compress_g2`](crate::compress_g2) of `public_key`, hex encoded. Only written with
+    /// `compressed` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_compressed: Option<String>,
+    /// [`pad_g2`](crate::pad_g2) of `public_key`, hex encoded. Only written with `padded` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_padded: Option<String>,
+    pub wallet_address: String,
+    /// secp256k1 key of `wallet_address`, for wallets generated with `generate_wallets`. Only
+    /// written with `include_secrets` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_private_key: Option<String>,
+    /// How the PoP preimage was encoded; files written before this field existed are packed.
+    #[serde(default)]
+    pub message_encoding: MessageEncoding,
+    /// Width of the chain id in packed preimages; only written for [`ChainIdWidth::U64`].
+    #[serde(default, skip_serializing_if = "ChainIdWidth::is_u256")]
+    pub chain_id_width: ChainIdWidth,
+    /// What the PoPs sign; files written before this field existed are [`PopStyle::Message`].
+    #[serde(default)]
+    pub pop_style: PopStyle,
+    /// Whether the PoPs sign the message bytes or their struct hash; only written in
+    /// [`Preimage::Eip712`].
+    #[serde(default, skip_serializing_if = "Preimage::is_bytes")]
+    pub preimage: Preimage,
+    /// Whether the PoPs hash `message_bytes` or their `eip191_digest` to the curve; only written
+    /// in [`Prehash::Eip191`].
+    #[serde(default, skip_serializing_if = "Prehash::is_raw")]
+    pub prehash: Prehash,
+    /// The `expand_message_xmd` hash of every message hash; files written before this field
+    /// existed use Keccak256.
+    #[serde(default)]
+    pub hash_function: HashFunction,
+    /// The `expand_message_xmd` output length; files written before this field existed use 96.
+    #[serde(default = "default_expand_len")]
+    pub expand_len: usize,
+    pub domain_staking_manager: String,
+    pub domain_validator_manager: String,
+    pub proof: Vec<ProofData>,
+    /// Set on entries whose `public_key` is malicious, one of [`PublicKeyAttack::as_str`], on
+    /// replayed PoPs, [`CHAIN_REPLAY`](crate::CHAIN_REPLAY), and on edge cases, one of
+    /// [`EdgeCase::name`](crate::EdgeCase::name). Their proofs are all `valid: false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attack: Option<String>,
+    /// Unix time the entry was generated at, see [`GenOptions::generated_at`](crate::GenOptions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<u64>,
+    /// `CARGO_PKG_VERSION` of the bls-test-utils that wrote the entry, see [`GENERATOR_VERSION`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_version: Option<String>,
+}
+
+impl BlsTestData {
+    /// Drop `private_key` and `eth_private_key`, for files written without `include_secrets`.
+    pub fn strip_secrets(&mut self) {
+        self.private_key = None;
+        self.eth_private_key = None;
+    }
+
+    /// Write every limb (the public key, message hashes, PoPs and hash-to-curve stages) in
+    /// `format`. Byte strings such as `message_bytes` and compressed points stay hex.
+    pub fn rewrite_numbers(&mut self, format: NumberFormat) {
+        format.rewrite(&mut self.public_key);
+        for proof in &mut self.proof {
+            format.rewrite(&mut proof.message_hash_stake_manager);
+            format.rewrite(&mut proof.message_hash_validator_manager);
+            format.rewrite(&mut proof.proof_of_possession_stake_manager);
+            format.rewrite(&mut proof.proof_of_possession_validator_manager);
+            for combined in
+                [&mut proof.message_hash_combined, &mut proof.proof_of_possession_combined]
+                    .into_iter()
+                    .flatten()
+            {
+                format.rewrite(combined);
+            }
+            if let Some(struct_preimage) = &mut proof.struct_preimage {
+                struct_preimage.rewrite_numbers(format);
+            }
+            for stages in
+                [&mut proof.hash_to_curve_stake_manager, &mut proof.hash_to_curve_validator_manager]
+                    .into_iter()
+                    .flatten()
+            {
+                stages.rewrite_numbers(format);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_flag_is_backward_compatible() {
+        let legacy = r#"{
+            "message_hash_stake_manager": ["0x1", "0x2"],
+            "message_hash_validator_manager": ["0x1", "0x2"],
+            "proof_of_possession_stake_manager": ["0x1", "0x2"],
+            "proof_of_possession_validator_manager": ["0x1", "0x2"],
+            "chain_id": "1"
+        }"#;
+        let proof: ProofData = serde_json::from_str(legacy).unwrap();
+        assert!(proof.valid);
+        assert!(proof.invalid_reason.is_none());
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(!json.contains("\"valid\""), "valid entries do not carry the new fields");
+        assert!(!json.contains("invalid_reason"));
+    }
+}
@@ -0,0 +1,850 @@
+//! JSON, YAML and TOML encodings of a fixture, and the CSV export of [`crate::csv`].
+//!
+//! All three carry the same field names and the same `0x` hex strings (or decimal limbs, see
+//! [`NumberFormat`]). YAML output quotes every string, since YAML 1.1 readers such as PyYAML (and
+//! so Ansible) would otherwise load `0x…` words as integers.
+//!
+//! A list of cases is written as a table of its [`SCHEMA_VERSION`] and the cases under `vectors`,
+//! which TOML writes as an array of tables (`[[vectors]]`, with each case's proofs as
+//! `[[vectors.proof]]`). Version 1 files are the bare list JSON and YAML wrote before the version
+//! existed (TOML always used `vectors`); they decode the same way, as do files of later versions,
+//! which may only add fields. A fixture of several honest keys also carries their
+//! [`AggregatePublicKey`] after the cases.
+
+use crate::{
+    aggregate::{aggregate_public_key, AggregatePublicKey},
+    csv::{csv_header, csv_rows},
+    fixture::{BlsTestData, SCHEMA_VERSION},
+    split::{load_split, SplitError, SplitWriter},
+    words::NumberFormat,
+};
+use alloy::primitives::Address;
+use serde::{
+    de::{self, value::SeqAccessDeserializer, DeserializeOwned, IgnoredAny, MapAccess, SeqAccess},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_yaml::Value;
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    slice,
+    str::FromStr,
+};
+
+/// File format of a fixture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+    /// Rows of a list of cases, see [`crate::csv`]. Not a serde format, so [`Self::encode`] and
+    /// [`Self::decode`] fail with [`FormatError::Csv`].
+    Csv,
+}
+
+/// Why a fixture could not be encoded or decoded.
+#[derive(Debug, thiserror::Error)]
+pub enum FormatError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error(transparent)]
+    TomlSerialize(#[from] toml::ser::Error),
+    #[error(transparent)]
+    TomlDeserialize(#[from] toml::de::Error),
+    #[error("CSV is only written as rows of a list of cases, and not read back")]
+    Csv,
+}
+
+/// Why [`load_fixture`] failed.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    #[error("reading {}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("parsing {}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: FormatError,
+    },
+    #[error("{} holds the entry of {wallet}", path.display())]
+    WrongWallet { path: PathBuf, wallet: String },
+    #[error("{} already has entries for {}", path.display(), wallets.join(", "))]
+    Conflict { path: PathBuf, wallets: Vec<String> },
+}
+
+/// Why [`ArrayWriter`] or [`FixtureWriter`] could not write a fixture.
+#[derive(Debug, thiserror::Error)]
+pub enum WriteError {
+    #[error("writing {name}")]
+    Write {
+        name: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{format} list does not start with its header: {list}")]
+    Header { format: OutputFormat, list: String },
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error(transparent)]
+    Split(#[from] SplitError),
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    schema_version: u32,
+    #[serde(skip_serializing_if = "NumberFormat::is_hex")]
+    number_format: NumberFormat,
+    vectors: &'a [T],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aggregate_public_key: Option<AggregatePublicKey>,
+}
+
+/// The cases of a list of any schema version. Nothing was removed or reinterpreted since
+/// version 1, so only the enclosing table differs and unknown keys are skipped.
+struct FixtureFile(Vec<BlsTestData>);
+
+impl<'de> Deserialize<'de> for FixtureFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FixtureFileVisitor)
+    }
+}
+
+struct FixtureFileVisitor;
+
+impl<'de> de::Visitor<'de> for FixtureFileVisitor {
+    type Value = FixtureFile;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of cases or a table with `vectors`")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        Deserialize::deserialize(SeqAccessDeserializer::new(seq)).map(FixtureFile)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut vectors = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "schema_version" => {
+                    map.next_value::<u32>()?;
+                }
+                "vectors" => vectors = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        vectors.map(FixtureFile).ok_or_else(|| de::Error::missing_field("vectors"))
+    }
+}
+
+/// Read the cases of a fixture written in any schema version, in the format its extension names
+/// (see [`OutputFormat::from_path`]), or of a split fixture if `path` is a directory.
+pub fn load_fixture(path: &Path) -> Result<Vec<BlsTestData>, LoadError> {
+    if path.is_dir() {
+        return load_split(path)
+    }
+    let raw = fs::read_to_string(path)
+        .map_err(|source| LoadError::Read { path: path.to_owned(), source })?;
+    OutputFormat::from_path(path)
+        .decode_cases(&raw)
+        .map_err(|source| LoadError::Parse { path: path.to_owned(), source })
+}
+
+/// The entries of the fixture at `path` an append of `wallets` keeps: all of them, or with
+/// `replace` those of wallets not in `wallets`. Without `replace`, entries of `wallets` fail with
+/// [`LoadError::Conflict`]. A missing or empty file has none.
+pub fn existing_cases(
+    path: &Path,
+    format: OutputFormat,
+    wallets: &[Address],
+    replace: bool,
+) -> Result<Vec<BlsTestData>, LoadError> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(LoadError::Read { path: path.to_owned(), source }),
+    };
+    if raw.trim().is_empty() {
+        return Ok(Vec::new())
+    }
+    let mut cases = format
+        .decode_cases(&raw)
+        .map_err(|source| LoadError::Parse { path: path.to_owned(), source })?;
+
+    let wallets: HashSet<Address> = wallets.iter().copied().collect();
+    let regenerated = |case: &BlsTestData| {
+        case.wallet_address.parse::<Address>().is_ok_and(|wallet| wallets.contains(&wallet))
+    };
+    let mut conflicts: Vec<String> = cases
+        .iter()
+        .filter(|case| regenerated(case))
+        .map(|case| case.wallet_address.clone())
+        .collect();
+    conflicts.sort();
+    conflicts.dedup();
+    if !conflicts.is_empty() && !replace {
+        return Err(LoadError::Conflict { path: path.to_owned(), wallets: conflicts })
+    }
+    cases.retain(|case| !regenerated(case));
+    Ok(cases)
+}
+
+impl OutputFormat {
+    /// The formats that encode any value and decode it again. [`Self::Csv`] is not one of them.
+    pub const ALL: [Self; 3] = [Self::Json, Self::Yaml, Self::Toml];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Csv => "csv",
+        }
+    }
+
+    /// The format a path's extension names (`.yaml`/`.yml`, `.toml`, `.csv`), JSON otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("toml") => Self::Toml,
+            Some("csv") => Self::Csv,
+            _ => Self::Json,
+        }
+    }
+
+    /// Encode a single value, such as an aggregate fixture, which must be a table in TOML.
+    /// `compact` selects single-line JSON and inline TOML arrays; YAML has one layout.
+    pub fn encode<T: Serialize>(self, value: &T, compact: bool) -> Result<String, FormatError> {
+        Ok(match (self, compact) {
+            (Self::Json, true) => serde_json::to_string(value)?,
+            (Self::Json, false) => serde_json::to_string_pretty(value)?,
+            (Self::Yaml, _) => yaml_lines(&serde_yaml::to_value(value)?)
+                .into_iter()
+                .map(|line| line + "\n")
+                .collect(),
+            (Self::Toml, true) => toml::to_string(value)?,
+            (Self::Toml, false) => toml::to_string_pretty(value)?,
+            (Self::Csv, _) => return Err(FormatError::Csv),
+        })
+    }
+
+    /// Decode a value written by [`Self::encode`].
+    pub fn decode<T: DeserializeOwned>(self, raw: &str) -> Result<T, FormatError> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(raw)?,
+            Self::Yaml => serde_yaml::from_str(raw)?,
+            Self::Toml => toml::from_str(raw)?,
+            Self::Csv => return Err(FormatError::Csv),
+        })
+    }
+
+    /// Encode a list of cases (or of [`FoundryFixture`](crate::FoundryFixture)s) under
+    /// `vectors`, next to the current `schema_version`.
+    pub fn encode_cases<T: Serialize>(
+        self,
+        cases: &[T],
+        compact: bool,
+    ) -> Result<String, FormatError> {
+        self.encode_cases_as(cases, NumberFormat::Hex, compact)
+    }
+
+    /// [`Self::encode_cases`], recording `number_format` as the top-level `number_format` unless
+    /// it is hex. The cases are written as they are, see [`BlsTestData::rewrite_numbers`].
+    pub fn encode_cases_as<T: Serialize>(
+        self,
+        cases: &[T],
+        number_format: NumberFormat,
+        compact: bool,
+    ) -> Result<String, FormatError> {
+        let versioned = Versioned {
+            schema_version: SCHEMA_VERSION,
+            number_format,
+            vectors: cases,
+            aggregate_public_key: None,
+        };
+        self.encode(&versioned, compact)
+    }
+
+    /// [`Self::encode_cases`], followed by the [`aggregate_public_key`] of the cases when there
+    /// is more than one honest key.
+    pub fn encode_fixture(
+        self,
+        cases: &[BlsTestData],
+        compact: bool,
+    ) -> Result<String, FormatError> {
+        self.encode_fixture_as(cases, NumberFormat::Hex, compact)
+    }
+
+    /// [`Self::encode_fixture`] with every limb in `number_format`, which is recorded as the
+    /// top-level `number_format` unless it is hex.
+    pub fn encode_fixture_as(
+        self,
+        cases: &[BlsTestData],
+        number_format: NumberFormat,
+        compact: bool,
+    ) -> Result<String, FormatError> {
+        let mut aggregate_public_key = aggregate_public_key(cases);
+        let mut cases = cases.to_vec();
+        cases.iter_mut().for_each(|case| case.rewrite_numbers(number_format));
+        if let Some(key) = &mut aggregate_public_key {
+            number_format.rewrite(&mut key.public_key);
+        }
+        let versioned = Versioned {
+            schema_version: SCHEMA_VERSION,
+            number_format,
+            vectors: &cases,
+            aggregate_public_key,
+        };
+        self.encode(&versioned, compact)
+    }
+
+    /// Decode a list of cases written by [`Self::encode_cases`], or by any earlier version.
+    pub fn decode_cases(self, raw: &str) -> Result<Vec<BlsTestData>, FormatError> {
+        Ok(self.decode::<FixtureFile>(raw)?.0)
+    }
+}
+
+/// What [`OutputFormat::encode_fixture`] writes after `vectors`.
+#[derive(Serialize)]
+struct Trailer<'a> {
+    aggregate_public_key: &'a AggregatePublicKey,
+}
+
+/// Where [`ArrayWriter::create`] writes `path` before renaming it into place: a hidden sibling,
+/// so the rename stays on one filesystem.
+pub fn partial_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map_or_else(Default::default, |name| name.to_string_lossy());
+    path.with_file_name(format!(".{name}.partial"))
+}
+
+/// A [`partial_path`] that is removed once dropped, unless it was renamed into place.
+#[derive(Debug)]
+struct PartialFile {
+    path: PathBuf,
+    partial: PathBuf,
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        // Already gone once renamed.
+        let _ = fs::remove_file(&self.partial);
+    }
+}
+
+/// Writes a list of cases one element at a time, producing the same bytes as
+/// [`OutputFormat::encode_cases_as`] (or [`OutputFormat::encode_fixture_as`], given the cases'
+/// [`AggregatePublicKey`]) on the whole `Vec`, so large fixtures are never held in memory.
+///
+/// A file is written next to its destination under [`partial_path`] and renamed over it by
+/// [`Self::finish`]. A writer dropped before that, after a failed run, removes it again and
+/// leaves any previous fixture in place.
+pub struct ArrayWriter {
+    name: String,
+    file: Option<PartialFile>,
+    writer: BufWriter<Box<dyn Write>>,
+    format: OutputFormat,
+    number_format: NumberFormat,
+    compact: bool,
+    len: usize,
+}
+
+impl fmt::Debug for ArrayWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArrayWriter")
+            .field("name", &self.name)
+            .field("format", &self.format)
+            .field("number_format", &self.number_format)
+            .field("compact", &self.compact)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArrayWriter {
+    /// Start a list to be renamed to `path` once finished. `number_format` is the one the cases
+    /// are pushed in, recorded in the header.
+    pub fn create(
+        path: &Path,
+        format: OutputFormat,
+        number_format: NumberFormat,
+        compact: bool,
+    ) -> Result<Self, WriteError> {
+        let name = path.display().to_string();
+        let partial = partial_path(path);
+        // Errors name the destination, not the partial file.
+        let file = File::create(&partial)
+            .map_err(|source| WriteError::Write { name: name.clone(), source })?;
+        let mut writer = Self::new(Box::new(file), name, format, number_format, compact);
+        writer.file = Some(PartialFile { path: path.to_owned(), partial });
+        Ok(writer)
+    }
+
+    /// Start a list written straight to `writer`, such as stdout, which errors call `name`.
+    pub fn new(
+        writer: Box<dyn Write>,
+        name: impl Into<String>,
+        format: OutputFormat,
+        number_format: NumberFormat,
+        compact: bool,
+    ) -> Self {
+        let writer = BufWriter::new(writer);
+        Self { name: name.into(), file: None, writer, format, number_format, compact, len: 0 }
+    }
+
+    /// The format the list is written in.
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    fn write_error(&self) -> impl FnOnce(io::Error) -> WriteError + '_ {
+        |source| WriteError::Write { name: self.name.clone(), source }
+    }
+
+    /// Everything before the first element: the `schema_version`, any `number_format` and the
+    /// opening of `vectors`.
+    fn header(&self) -> String {
+        let number_format = self.number_format;
+        match (self.format, self.compact, number_format.is_hex()) {
+            (OutputFormat::Json, true, true) => {
+                format!(r#"{{"schema_version":{SCHEMA_VERSION},"vectors":["#)
+            }
+            (OutputFormat::Json, true, false) => format!(
+                r#"{{"schema_version":{SCHEMA_VERSION},"number_format":"{number_format}","vectors":["#
+            ),
+            (OutputFormat::Json, false, true) => {
+                format!("{{\n  \"schema_version\": {SCHEMA_VERSION},\n  \"vectors\": [\n")
+            }
+            (OutputFormat::Json, false, false) => format!(
+                "{{\n  \"schema_version\": {SCHEMA_VERSION},\n  \"number_format\": \"{number_format}\",\n  \"vectors\": [\n"
+            ),
+            (OutputFormat::Yaml, _, true) => format!("schema_version: {SCHEMA_VERSION}\nvectors:\n"),
+            (OutputFormat::Yaml, _, false) => format!(
+                "schema_version: {SCHEMA_VERSION}\nnumber_format: '{number_format}'\nvectors:\n"
+            ),
+            (OutputFormat::Toml, _, true) => format!("schema_version = {SCHEMA_VERSION}\n\n"),
+            (OutputFormat::Toml, _, false) => format!(
+                "schema_version = {SCHEMA_VERSION}\nnumber_format = \"{number_format}\"\n\n"
+            ),
+            (OutputFormat::Csv, ..) => csv_header(),
+        }
+    }
+
+    /// Write the [`csv_rows`] of `case`, after the header if it is the first.
+    pub fn push_rows(&mut self, case: &BlsTestData) -> Result<(), WriteError> {
+        let header = if self.len == 0 { self.header() } else { String::new() };
+        self.len += 1;
+        write!(self.writer, "{header}{}", csv_rows(case)).map_err(self.write_error())
+    }
+
+    /// Write `case` as the next element of `vectors`.
+    pub fn push<T: Serialize>(&mut self, case: &T) -> Result<(), WriteError> {
+        let header = self.header();
+        let separator = match (self.format, self.len, self.compact) {
+            (_, 0, _) => header.as_str(),
+            (OutputFormat::Json, _, true) => ",",
+            (OutputFormat::Json, _, false) => ",\n",
+            // Tables of an array are separated by a blank line.
+            (OutputFormat::Toml, _, _) => "\n",
+            _ => "",
+        };
+        let element = match (self.format, self.compact) {
+            (OutputFormat::Json, true) => serde_json::to_string(case).map_err(FormatError::from)?,
+            // Elements sit two levels deep, in `vectors`.
+            (OutputFormat::Json, false) => serde_json::to_string_pretty(case)
+                .map_err(FormatError::from)?
+                .lines()
+                .map(|line| format!("    {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            // A one-element list is the header and the element's own block.
+            _ => {
+                let list = self.format.encode_cases_as(
+                    slice::from_ref(case),
+                    self.number_format,
+                    self.compact,
+                )?;
+                match list.strip_prefix(&header) {
+                    Some(element) => element.to_string(),
+                    None => return Err(WriteError::Header { format: self.format, list }),
+                }
+            }
+        };
+        self.len += 1;
+        write!(self.writer, "{separator}{element}").map_err(self.write_error())
+    }
+
+    /// Close the list, with `aggregate_public_key` (as generated, in hex) if there is one, and
+    /// rename it into place.
+    pub fn finish(
+        self,
+        mut aggregate_public_key: Option<AggregatePublicKey>,
+    ) -> Result<(), WriteError> {
+        if let Some(key) = &mut aggregate_public_key {
+            self.number_format.rewrite(&mut key.public_key);
+        }
+        let trailer = match (&aggregate_public_key, self.format, self.compact) {
+            (None, ..) => String::new(),
+            (Some(key), OutputFormat::Json, true) => format!(
+                r#","aggregate_public_key":{}"#,
+                serde_json::to_string(key).map_err(FormatError::from)?
+            ),
+            (Some(key), OutputFormat::Json, false) => format!(
+                ",\n  \"aggregate_public_key\": {}",
+                serde_json::to_string_pretty(key).map_err(FormatError::from)?.replace('\n', "\n  ")
+            ),
+            (Some(key), OutputFormat::Yaml, _) => {
+                self.format.encode(&Trailer { aggregate_public_key: key }, self.compact)?
+            }
+            (Some(key), OutputFormat::Toml, _) => format!(
+                "\n{}",
+                self.format.encode(&Trailer { aggregate_public_key: key }, self.compact)?
+            ),
+            // Rows have no place for it.
+            (Some(_), OutputFormat::Csv, _) => String::new(),
+        };
+        let end = match (self.format, self.len, self.compact) {
+            (OutputFormat::Csv, 0, _) => self.header(),
+            (_, 0, _) => {
+                self.format.encode_cases_as::<BlsTestData>(&[], self.number_format, self.compact)?
+            }
+            (OutputFormat::Json, _, true) => format!("]{trailer}}}"),
+            (OutputFormat::Json, _, false) => format!("\n  ]{trailer}\n}}"),
+            _ => trailer,
+        };
+        let Self { name, file, mut writer, .. } = self;
+        let write_error = |source| WriteError::Write { name: name.clone(), source };
+        write!(writer, "{end}").and_then(|()| writer.flush()).map_err(write_error)?;
+        drop(writer);
+        match &file {
+            Some(file) => fs::rename(&file.partial, &file.path).map_err(write_error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A fixture written one entry at a time, as one list or with one file per wallet.
+#[derive(Debug)]
+pub enum FixtureWriter {
+    Array(ArrayWriter),
+    Split(SplitWriter),
+}
+
+impl FixtureWriter {
+    /// Write `entry`, which is `case` in the layout being written. CSV lists get the rows of
+    /// `case`.
+    pub fn push<T: Serialize>(&mut self, case: &BlsTestData, entry: &T) -> Result<(), WriteError> {
+        match self {
+            Self::Array(output) if output.format == OutputFormat::Csv => output.push_rows(case),
+            Self::Array(output) => output.push(entry),
+            Self::Split(output) => Ok(output.push(case, entry)?),
+        }
+    }
+
+    /// Finish the fixture, with `aggregate_public_key` (as generated, in hex) if there is one.
+    pub fn finish(
+        self,
+        aggregate_public_key: Option<AggregatePublicKey>,
+    ) -> Result<(), WriteError> {
+        match self {
+            Self::Array(output) => output.finish(aggregate_public_key),
+            Self::Split(output) => Ok(output.finish(aggregate_public_key).map(drop)?),
+        }
+    }
+}
+
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Sequence(items) => !items.is_empty(),
+        Value::Mapping(fields) => !fields.is_empty(),
+        _ => false,
+    }
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(string) => format!("'{}'", string.replace('\'', "''")),
+        Value::Sequence(_) => "[]".to_string(),
+        Value::Mapping(_) => "{}".to_string(),
+        Value::Tagged(tagged) => format!("{} {}", tagged.tag, yaml_scalar(&tagged.value)),
+    }
+}
+
+/// Block-style YAML of `value`, one line per entry, with every string single-quoted.
+fn yaml_lines(value: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    match value {
+        Value::Mapping(fields) if is_block(value) => {
+            for (key, field) in fields {
+                let key = key.as_str().map_or_else(|| yaml_scalar(key), str::to_string);
+                if is_block(field) {
+                    lines.push(format!("{key}:"));
+                    lines.extend(yaml_lines(field).into_iter().map(|line| format!("  {line}")));
+                } else {
+                    lines.push(format!("{key}: {}", yaml_scalar(field)));
+                }
+            }
+        }
+        Value::Sequence(items) if is_block(value) => {
+            for item in items {
+                if !is_block(item) {
+                    lines.push(format!("- {}", yaml_scalar(item)));
+                    continue
+                }
+                for (i, line) in yaml_lines(item).into_iter().enumerate() {
+                    lines.push(if i == 0 { format!("- {line}") } else { format!("  {line}") });
+                }
+            }
+        }
+        scalar => lines.push(yaml_scalar(scalar)),
+    }
+    lines
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().chain([Self::Csv]).find(|format| format.as_str() == input).ok_or_else(
+            || format!("`{input}` is not a format, expected `json`, `yaml`, `toml` or `csv`"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        csv::encode_csv,
+        generate::{generate_case, GenOptions},
+    };
+    use alloy::primitives::{Address, U256};
+
+    fn cases() -> Vec<BlsTestData> {
+        let opts = GenOptions {
+            seed: Some("25".parse().unwrap()),
+            include_invalid: true,
+            compressed: true,
+            include_intermediates: true,
+            include_secrets: true,
+            ..Default::default()
+        };
+        [Address::repeat_byte(1), Address::repeat_byte(2)]
+            .map(|wallet| generate_case(wallet, &[U256::from(1), U256::from(8453)], &opts).unwrap())
+            .to_vec()
+    }
+
+    #[test]
+    fn test_round_trip_per_format() {
+        let cases = cases();
+        for format in OutputFormat::ALL {
+            for compact in [false, true] {
+                let raw = format.encode_cases(&cases, compact).unwrap();
+                assert_eq!(format.decode_cases(&raw).unwrap(), cases, "{format} {compact}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_decodes_version_1_lists() {
+        let cases = cases();
+        for format in [OutputFormat::Json, OutputFormat::Yaml] {
+            let bare = format.encode(&cases, false).unwrap();
+            assert_eq!(format.decode_cases(&bare).unwrap(), cases, "{format}");
+        }
+        let toml = toml::to_string(&toml::toml! { vectors = [] }).unwrap();
+        assert_eq!(OutputFormat::Toml.decode_cases(&toml).unwrap(), []);
+
+        let json = OutputFormat::Json.encode_cases(&cases, true).unwrap();
+        assert!(json.starts_with(r#"{"schema_version":2,"vectors":[{"#), "{json}");
+        let error = OutputFormat::Json.decode_cases(r#"{"schema_version":2}"#).unwrap_err();
+        assert!(error.to_string().contains("missing field `vectors`"), "{error}");
+        assert!(OutputFormat::Json.decode_cases(r#"{"schema_version":"2","vectors":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_field_names_and_hex_survive() {
+        let cases = cases();
+        let yaml = OutputFormat::Yaml.encode_cases(&cases, false).unwrap();
+        assert!(yaml.contains(&format!("wallet_address: '{}'", cases[0].wallet_address)), "{yaml}");
+        assert!(yaml.contains(&format!("- '{}'", cases[0].public_key[0])), "{yaml}");
+        assert!(!yaml.contains(": 0x"), "hex words are quoted: {yaml}");
+        assert!(yaml.starts_with("schema_version: 2\nvectors:\n  - private_key: '"), "{yaml}");
+        assert!(yaml.contains("\n        valid: false\n"), "{yaml}");
+
+        let toml = OutputFormat::Toml.encode_cases(&cases, false).unwrap();
+        assert!(toml.starts_with("schema_version = 2\n"), "{toml}");
+        assert_eq!(toml.matches("[[vectors]]").count(), cases.len(), "{toml}");
+        assert_eq!(
+            toml.matches("[[vectors.proof]]").count(),
+            cases.iter().map(|case| case.proof.len()).sum::<usize>()
+        );
+        assert!(
+            toml.contains(&format!("private_key = \"{}\"", cases[0].private_key.as_ref().unwrap())),
+            "{toml}"
+        );
+        assert!(toml.contains("invalid_reason = \"wrong_key\""), "{toml}");
+    }
+
+    #[test]
+    fn test_decimal_fixture_is_numerically_equal() {
+        let cases = cases();
+        for format in OutputFormat::ALL {
+            let raw = format.encode_fixture_as(&cases, NumberFormat::Dec, false).unwrap();
+            let mut decoded = format.decode_cases(&raw).unwrap();
+            assert_ne!(decoded, cases, "{format}");
+            assert!(!decoded[0].public_key[0].starts_with("0x"), "{format}");
+            decoded.iter_mut().for_each(|case| case.rewrite_numbers(NumberFormat::Hex));
+            assert_eq!(decoded, cases, "{format}");
+        }
+        let json = OutputFormat::Json.encode_fixture_as(&cases, NumberFormat::Dec, true).unwrap();
+        assert!(json.starts_with(r#"{"schema_version":2,"number_format":"dec","vectors":["#));
+        assert!(!OutputFormat::Json
+            .encode_fixture(&cases, true)
+            .unwrap()
+            .contains("number_format"));
+    }
+
+    #[test]
+    fn test_array_writer_matches_encode_cases() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = cases();
+        let key = aggregate_public_key(&cases);
+        assert!(key.is_some());
+        let stream = |path: &Path, format, number_format, cases: &[BlsTestData], key| {
+            let mut writer = ArrayWriter::create(path, format, number_format, false).unwrap();
+            if format == OutputFormat::Csv {
+                cases.iter().try_for_each(|case| writer.push_rows(case)).unwrap();
+            } else {
+                cases.iter().try_for_each(|case| writer.push(case)).unwrap();
+            }
+            writer.finish(key).unwrap();
+            assert!(!partial_path(path).exists());
+            fs::read_to_string(path).unwrap()
+        };
+        for format in OutputFormat::ALL {
+            for compact in [false, true] {
+                for number_format in [NumberFormat::Hex, NumberFormat::Dec] {
+                    let path = dir.path().join(format!("out.{format}"));
+                    let mut rewritten = cases.clone();
+                    rewritten.iter_mut().for_each(|case| case.rewrite_numbers(number_format));
+                    let write = |cases: &[BlsTestData], key| {
+                        let mut writer =
+                            ArrayWriter::create(&path, format, number_format, compact).unwrap();
+                        cases.iter().try_for_each(|case| writer.push(case)).unwrap();
+                        writer.finish(key).unwrap();
+                        assert!(!partial_path(&path).exists());
+                        fs::read_to_string(&path).unwrap()
+                    };
+                    let case = format!("{format} {number_format} {compact}");
+                    assert_eq!(
+                        write(&rewritten, None),
+                        format.encode_cases_as(&rewritten, number_format, compact).unwrap(),
+                        "{case}"
+                    );
+                    assert_eq!(
+                        write(&rewritten, key.clone()),
+                        format.encode_fixture_as(&cases, number_format, compact).unwrap(),
+                        "{case}"
+                    );
+                    assert_eq!(
+                        write(&[], None),
+                        format.encode_cases_as::<BlsTestData>(&[], number_format, compact).unwrap(),
+                        "{case}"
+                    );
+                }
+            }
+        }
+        let path = dir.path().join("out.csv");
+        assert_eq!(
+            stream(&path, OutputFormat::Csv, NumberFormat::Hex, &cases, key),
+            encode_csv(&cases)
+        );
+        assert_eq!(stream(&path, OutputFormat::Csv, NumberFormat::Hex, &[], None), csv_header());
+    }
+
+    #[test]
+    fn test_unfinished_writer_leaves_previous_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.json");
+        fs::write(&path, "previous").unwrap();
+        let mut writer =
+            ArrayWriter::create(&path, OutputFormat::Json, NumberFormat::Hex, false).unwrap();
+        writer.push(&cases()[0]).unwrap();
+        assert!(partial_path(&path).exists());
+        drop(writer);
+        assert!(!partial_path(&path).exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous");
+        assert_eq!(partial_path(&path), dir.path().join(".out.json.partial"));
+    }
+
+    #[test]
+    fn test_existing_cases_for_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.json");
+        let cases = cases();
+        let [first, second] = [1, 2].map(Address::repeat_byte);
+        let existing = |wallets: &[Address], replace| {
+            existing_cases(&path, OutputFormat::Json, wallets, replace)
+        };
+        assert!(existing(&[first], false).unwrap().is_empty(), "a missing file has none");
+        fs::write(&path, "\n").unwrap();
+        assert!(existing(&[first], false).unwrap().is_empty());
+
+        fs::write(&path, OutputFormat::Json.encode_cases(&cases, false).unwrap()).unwrap();
+        assert_eq!(existing(&[Address::repeat_byte(3)], false).unwrap(), cases);
+        let error = existing(&[second], false).unwrap_err();
+        assert!(
+            matches!(&error, LoadError::Conflict { wallets, .. } if *wallets == [cases[1].wallet_address.clone()]),
+            "{error}"
+        );
+        assert_eq!(existing(&[second], true).unwrap(), [cases[0].clone()]);
+        assert_eq!(existing(&[first, second], true).unwrap(), []);
+
+        let repeated = [&cases[..], &cases[..1]].concat();
+        fs::write(&path, OutputFormat::Json.encode_cases(&repeated, false).unwrap()).unwrap();
+        let error = existing(&[first, second], false).unwrap_err();
+        assert!(
+            matches!(&error, LoadError::Conflict { wallets, .. } if wallets.len() == 2),
+            "{error}"
+        );
+
+        fs::write(&path, "[").unwrap();
+        assert!(matches!(existing(&[first], false), Err(LoadError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_format_names() {
+        for format in OutputFormat::ALL {
+            assert_eq!(format.to_string().parse(), Ok(format));
+            let path = format!("out.{format}");
+            assert_eq!(OutputFormat::from_path(Path::new(&path)), format);
+        }
+        assert_eq!(OutputFormat::from_path(Path::new("out.yml")), OutputFormat::Yaml);
+        assert_eq!(OutputFormat::from_path(Path::new("out")), OutputFormat::Json);
+        assert!("xml".parse::<OutputFormat>().is_err());
+        assert_eq!("csv".parse(), Ok(OutputFormat::Csv));
+        assert_eq!(OutputFormat::from_path(Path::new("out.csv")), OutputFormat::Csv);
+        assert!(matches!(OutputFormat::Csv.decode_cases(""), Err(FormatError::Csv)));
+    }
+}
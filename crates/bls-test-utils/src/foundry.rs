@@ -0,0 +1,262 @@
+//! The `--layout foundry` shape of a fixture, decodable with
+//! `abi.decode(vm.parseJson(json, "$.vectors[i]"), (Fixture))`.
+//!
+//! `vm.parseJson` orders object keys alphabetically and decodes an object as a tuple of its
+//! values in that order, so every struct here declares its fields sorted and the Solidity structs
+//! in [`FOUNDRY_STRUCTS`] match them one to one. Points are objects rather than arrays, since a
+//! JSON array decodes as a dynamic array and could not fill a `uint256[2]`. Words are
+//! zero-padded 32-byte hex strings, which Foundry reads as `bytes32` and which ABI-decode as
+//! `uint256`; chain ids are JSON numbers. Every key is always present, as a struct needs all its
+//! fields: `attack` and `invalidReason` are empty strings when unset.
+
+use crate::{fixture::BlsTestData, generate::Domain, solidity::RenderError, words::u256_to_0x};
+use alloy::primitives::{Address, U256};
+use serde::{Serialize, Serializer};
+
+/// The Solidity structs a [`FoundryFixture`] decodes into.
+pub const FOUNDRY_STRUCTS: &str = r#"struct G1Point {
+    uint256 x;
+    uint256 y;
+}
+
+struct G2Point {
+    uint256 xIm;
+    uint256 xRe;
+    uint256 yIm;
+    uint256 yRe;
+}
+
+struct Proof {
+    uint256 chainId;
+    string domainStake;
+    string domainValidator;
+    string invalidReason;
+    G1Point messageHashStake;
+    G1Point messageHashValidator;
+    G1Point proofOfPossessionStake;
+    G1Point proofOfPossessionValidator;
+    bool valid;
+}
+
+struct Fixture {
+    string attack;
+    uint256 privateKey;
+    Proof[] proofs;
+    G2Point publicKey;
+    address walletAddress;
+}
+"#;
+
+/// A uint256 written as a JSON number when it fits in a `u64`, and as a 32-byte `0x` quantity
+/// otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quantity(pub U256);
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match u64::try_from(self.0) {
+            Ok(value) => serializer.serialize_u64(value),
+            Err(_) => serializer.serialize_str(&u256_to_0x(self.0)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct G1Point {
+    pub x: String,
+    pub y: String,
+}
+
+/// A G2 point with each coordinate split into its imaginary and real part.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct G2Point {
+    pub x_im: String,
+    pub x_re: String,
+    pub y_im: String,
+    pub y_re: String,
+}
+
+/// One [`ProofData`](crate::ProofData) with the DSTs it was hashed under.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoundryProof {
+    pub chain_id: Quantity,
+    pub domain_stake: String,
+    pub domain_validator: String,
+    pub invalid_reason: String,
+    pub message_hash_stake: G1Point,
+    pub message_hash_validator: G1Point,
+    pub proof_of_possession_stake: G1Point,
+    pub proof_of_possession_validator: G1Point,
+    pub valid: bool,
+}
+
+/// One wallet's entry in the Foundry layout.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoundryFixture {
+    pub attack: String,
+    /// Zero when the entry has no `private_key`.
+    pub private_key: String,
+    pub proofs: Vec<FoundryProof>,
+    pub public_key: G2Point,
+    /// EIP-55 checksummed, as in the default layout.
+    pub wallet_address: String,
+}
+
+/// Convert `case` to the Foundry layout, normalising every word to 32 bytes.
+pub fn foundry_fixture(case: &BlsTestData) -> Result<FoundryFixture, RenderError> {
+    let word = |field: &'static str, value: &str| {
+        value.parse::<U256>().map(u256_to_0x).map_err(|_| RenderError::InvalidWord {
+            wallet: case.wallet_address.clone(),
+            field,
+            value: value.to_string(),
+        })
+    };
+    let g1 = |field: &'static str, [x, y]: &[String; 2]| {
+        Ok::<_, RenderError>(G1Point { x: word(field, x)?, y: word(field, y)? })
+    };
+    let [x_re, x_im, y_re, y_im] = &case.public_key;
+    let public_key = G2Point {
+        x_im: word("public_key", x_im)?,
+        x_re: word("public_key", x_re)?,
+        y_im: word("public_key", y_im)?,
+        y_re: word("public_key", y_re)?,
+    };
+    let proofs = case
+        .proof
+        .iter()
+        .map(|proof| {
+            let chain_id = proof.chain_id.parse().map_err(|_| RenderError::InvalidWord {
+                wallet: case.wallet_address.clone(),
+                field: "chain_id",
+                value: proof.chain_id.clone(),
+            })?;
+            let dst = |domain| match domain {
+                Domain::StakeManager => proof
+                    .domain_staking_manager
+                    .clone()
+                    .unwrap_or_else(|| case.domain_staking_manager.clone()),
+                Domain::ValidatorManager => proof
+                    .domain_validator_manager
+                    .clone()
+                    .unwrap_or_else(|| case.domain_validator_manager.clone()),
+            };
+            Ok(FoundryProof {
+                chain_id: Quantity(chain_id),
+                domain_stake: dst(Domain::StakeManager),
+                domain_validator: dst(Domain::ValidatorManager),
+                invalid_reason: proof.invalid_reason.clone().unwrap_or_default(),
+                message_hash_stake: g1(
+                    "message_hash_stake_manager",
+                    &proof.message_hash_stake_manager,
+                )?,
+                message_hash_validator: g1(
+                    "message_hash_validator_manager",
+                    &proof.message_hash_validator_manager,
+                )?,
+                proof_of_possession_stake: g1(
+                    "proof_of_possession_stake_manager",
+                    &proof.proof_of_possession_stake_manager,
+                )?,
+                proof_of_possession_validator: g1(
+                    "proof_of_possession_validator_manager",
+                    &proof.proof_of_possession_validator_manager,
+                )?,
+                valid: proof.valid,
+            })
+        })
+        .collect::<Result<_, RenderError>>()?;
+    Ok(FoundryFixture {
+        attack: case.attack.clone().unwrap_or_default(),
+        private_key: word("private_key", case.private_key.as_deref().unwrap_or("0"))?,
+        proofs,
+        public_key,
+        wallet_address: case
+            .wallet_address
+            .parse::<Address>()
+            .map_err(|_| RenderError::InvalidWallet(case.wallet_address.clone()))?
+            .to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generate::{generate_case, GenOptions};
+
+    fn case() -> BlsTestData {
+        let opts = GenOptions {
+            seed: Some("26".parse().unwrap()),
+            include_invalid: true,
+            ..Default::default()
+        };
+        generate_case(Address::repeat_byte(26), &[U256::from(1)], &opts).unwrap()
+    }
+
+    /// Field names of `name` in [`FOUNDRY_STRUCTS`], in declaration order.
+    fn struct_fields(name: &str) -> Vec<String> {
+        let body = FOUNDRY_STRUCTS.split(&format!("struct {name} {{")).nth(1).unwrap();
+        body.split('}')
+            .next()
+            .unwrap()
+            .split(';')
+            .filter_map(|field| field.split_whitespace().nth(1).map(str::to_string))
+            .collect()
+    }
+
+    fn keys(value: &serde_yaml::Value) -> Vec<String> {
+        let fields = value.as_mapping().unwrap();
+        fields.keys().map(|key| key.as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_keys_are_sorted_and_match_the_structs() {
+        let fixture = serde_yaml::to_value(foundry_fixture(&case()).unwrap()).unwrap();
+        let proof = &fixture["proofs"][0];
+        for (value, name) in [
+            (&fixture, "Fixture"),
+            (proof, "Proof"),
+            (&fixture["publicKey"], "G2Point"),
+            (&proof["messageHashStake"], "G1Point"),
+        ] {
+            let keys = keys(value);
+            let mut sorted = keys.clone();
+            sorted.sort();
+            assert_eq!(keys, sorted, "{name} keys are in vm.parseJson order");
+            assert_eq!(keys, struct_fields(name), "{name} matches its Solidity struct");
+        }
+    }
+
+    #[test]
+    fn test_values() {
+        let case = case();
+        let fixture = foundry_fixture(&case).unwrap();
+        assert_eq!(fixture.public_key.x_re, case.public_key[0]);
+        assert_eq!(fixture.public_key.x_im, case.public_key[1]);
+        assert_eq!(fixture.attack, "");
+        assert_eq!(fixture.proofs[0].domain_stake, case.domain_staking_manager);
+        assert_eq!(fixture.proofs[1].invalid_reason, "wrong_key");
+        assert!(!fixture.proofs[1].valid);
+
+        let json = serde_json::to_string(&fixture).unwrap();
+        assert!(json.contains(r#""chainId":1,"#), "{json}");
+        assert!(json.contains(&format!(r#""walletAddress":"{}""#, case.wallet_address)), "{json}");
+        let large = serde_json::to_string(&Quantity(U256::MAX)).unwrap();
+        assert_eq!(large, format!("\"0x{}\"", "f".repeat(64)));
+    }
+
+    #[test]
+    fn test_rejects_bad_values() {
+        let mut bad_word = case();
+        bad_word.proof[0].proof_of_possession_validator_manager[0] = "0xzz".into();
+        assert!(matches!(
+            foundry_fixture(&bad_word),
+            Err(RenderError::InvalidWord { field: "proof_of_possession_validator_manager", .. })
+        ));
+        let mut bad_wallet = case();
+        bad_wallet.wallet_address = "nope".into();
+        assert_eq!(foundry_fixture(&bad_wallet), Err(RenderError::InvalidWallet("nope".into())));
+    }
+}
@@ -0,0 +1,1765 @@
+//! Proof-of-possession generation for the StakeManager and ValidatorManager contracts.
+
+use crate::{
+    binding::EcdsaBinding,
+    calldata::RegisterCall,
+    compress::{compress_g1, compress_g2},
+    conventions::{
+        DEFAULT_DST_ROTATE, DEFAULT_DST_STAKE_MANAGER, DEFAULT_DST_STAKE_MANAGER_PUBKEY,
+        DEFAULT_DST_VALIDATOR_MANAGER, DEFAULT_DST_VALIDATOR_MANAGER_PUBKEY,
+    },
+    edge_case::EdgeCase,
+    eip712::{pop_registration, StructPreimage},
+    expander::{DstHasher, MessageHasher},
+    fixture::{BlsTestData, InvalidReason, ProofData, PublicKeyAttack, GENERATOR_VERSION},
+    hash_to_curve::{hash_to_curve_intermediates, HashToCurveIntermediates},
+    keys::{
+        generate_keypair, keypair_from_secret, random_secret_key, wallet_rng, SecretKey,
+        SecretKeyPair, Seed,
+    },
+    padded::pad_g2,
+    precompile::pairing_input,
+    words::{fp_to_hex, g1_from_words, g1_to_words, g2_to_words_solidity, words_to_hex},
+};
+use alloy::{
+    primitives::{eip191_hash_message, keccak256, Address, U256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolValue,
+};
+use rand_chacha::ChaCha20Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap, fmt, str::FromStr, sync::OnceLock};
+use sylow::{
+    glued_miller_loop, pairing, Fp, G1Affine, G1Projective, G2Affine, G2PreComputed, G2Projective,
+    GroupTrait, Gt,
+};
+
+/// The contract a PoP is produced for, each with its own DST.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Domain {
+    StakeManager,
+    ValidatorManager,
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StakeManager => f.pad("StakeManager"),
+            Self::ValidatorManager => f.pad("ValidatorManager"),
+        }
+    }
+}
+
+/// Errors returned by [`generate_case`], [`generate_aggregate`](crate::generate_aggregate),
+/// [`generate_rotations`](crate::generate_rotations) and [`sign_message`](crate::sign_message).
+#[derive(Debug, thiserror::Error)]
+pub enum GenerateError {
+    #[error("pairing check failed for wallet {wallet} on chain {chain_id} ({domain} domain)")]
+    PairingCheckFailed { wallet: Address, chain_id: U256, domain: Domain },
+    #[error(
+        "combined pairing check of both domains failed for wallet {wallet} on chain {chain_id}"
+    )]
+    CombinedCheckFailed { wallet: Address, chain_id: U256 },
+    #[error("pairing check failed for the signature under DST `{dst}`")]
+    SignatureCheckFailed { dst: String },
+    #[error("aggregate pairing check failed on chain {chain_id} ({domain} domain)")]
+    AggregateCheckFailed { chain_id: U256, domain: Domain },
+    #[error("rotation by wallet {wallet} on chain {chain_id} fails the pairing check")]
+    RotationCheckFailed { wallet: Address, chain_id: U256 },
+    #[error("rotation by wallet {wallet} on chain {chain_id} signed by the new key verifies")]
+    NewKeyRotationAccepted { wallet: Address, chain_id: U256 },
+    #[error("{edge_case} edge case for wallet {wallet} passes point validation and pairing")]
+    EdgeCaseAccepted { wallet: Address, edge_case: EdgeCase },
+    #[error("{attack} public key for wallet {wallet} passes sylow's curve and subgroup checks")]
+    AttackKeyAccepted { wallet: Address, attack: PublicKeyAttack },
+    #[error("chain id {chain_id} does not fit in a uint{width}")]
+    ChainIdTooWide { chain_id: U256, width: ChainIdWidth },
+    #[cfg(feature = "ark-cross-check")]
+    #[error("arkworks cross-check failed for {wallet} on chain {chain_id} ({domain}): {source}")]
+    CrossCheckFailed {
+        wallet: Address,
+        chain_id: U256,
+        domain: Domain,
+        source: Box<crate::ark::CrossCheckError>,
+    },
+    #[cfg(feature = "ark-cross-check")]
+    #[error("arkworks cross-check failed for the signature under DST `{dst}`: {source}")]
+    SignatureCrossCheckFailed { dst: String, source: Box<crate::ark::CrossCheckError> },
+}
+
+/// Options for [`generate_case`].
+#[derive(Clone, Debug)]
+pub struct GenOptions {
+    /// Derive keys deterministically from this seed instead of the OS RNG.
+    pub seed: Option<Seed>,
+    /// Run the local pairing check on every generated PoP.
+    pub verify: bool,
+    /// Also emit negative vectors (`valid: false`) for every chain id, see [`InvalidReason`].
+    pub include_invalid: bool,
+    /// Also emit entries with malicious public keys after every case, see [`PublicKeyAttack`].
+    pub include_attacks: bool,
+    /// Also emit identity and offset-signature entries after every case, see [`EdgeCase`].
+    pub edge_cases: bool,
+    /// Also emit every case's PoPs relabelled with each other chain id, see
+    /// [`chain_replays`](crate::chain_replays).
+    pub include_chain_replays: bool,
+    /// Also emit compressed encodings of public keys and signatures, see [`crate::compress`].
+    pub compressed: bool,
+    /// Also emit the EIP-2537-style padded encoding of public keys, see [`crate::padded`].
+    pub padded: bool,
+    /// Also emit the pairing precompile input of every PoP, see [`crate::precompile`].
+    pub pairing_input: bool,
+    /// Also emit the G1 sums of both domains' message hashes and PoPs, see
+    /// [`combined_pairing_check`].
+    pub combined: bool,
+    /// Also emit every stage of hash-to-curve for each message hash, see
+    /// [`crate::hash_to_curve`].
+    pub include_intermediates: bool,
+    /// Also emit StakeManager registration calldata for every PoP, see [`crate::calldata`].
+    pub calldata: Option<RegisterCall>,
+    /// Existing secret keys by wallet, used instead of generating one for those wallets.
+    pub keys: HashMap<Address, SecretKey>,
+    /// Write every entry's `private_key` and `eth_private_key`. Off by default, so that fixtures
+    /// can be shared without exposing keys.
+    pub include_secrets: bool,
+    /// secp256k1 signers of generated wallets; with `include_secrets`, their keys are written to
+    /// `eth_private_key`.
+    pub eth_signers: HashMap<Address, PrivateKeySigner>,
+    /// Encoding of the PoP preimage, written to `message_encoding`.
+    pub encoding: MessageEncoding,
+    /// Width of the chain id in [`MessageEncoding::Packed`] preimages, written to
+    /// `chain_id_width`.
+    pub chain_id_width: ChainIdWidth,
+    /// How PoP messages are hashed to the curve in both domains, written to `hash_function` and
+    /// `expand_len`.
+    pub hasher: MessageHasher,
+    /// What the PoP signs, written to `pop_style`.
+    pub pop_style: PopStyle,
+    /// Whether [`PopStyle::Message`] PoPs sign the message bytes or their struct hash, written to
+    /// `preimage`.
+    pub preimage: Preimage,
+    /// Whether the preimage is hashed to the curve as is or as its EIP-191 digest, written to
+    /// `prehash`.
+    pub prehash: Prehash,
+    /// The `expiry` of every [`PopRegistration`](crate::PopRegistration) signed in
+    /// [`Preimage::Eip712`]. Defaults to `type(uint256).max`, never.
+    pub expiry: U256,
+    /// DST for StakeManager PoPs, written to `domain_staking_manager`.
+    pub dst_stake_manager: String,
+    /// DST for ValidatorManager PoPs, written to `domain_validator_manager`.
+    pub dst_validator_manager: String,
+    /// DST of key rotation signatures, written to `domain_rotate`, see [`crate::rotate`].
+    pub dst_rotate: String,
+    /// Append the decimal chain id and a colon to both DSTs, see [`GenOptions::dst`].
+    pub dst_per_chain: bool,
+    /// Sign a registration nonce into every PoP preimage, see [`pop_message_with_nonce`]. This is
+    /// the nonce of [`generate_case`]; [`generate_cases`] counts up from it, one per wallet.
+    pub nonce_start: Option<U256>,
+    /// Unix timestamp written to every entry's `generated_at`. Unset by default, so that seeded
+    /// output stays byte-identical across runs.
+    pub generated_at: Option<u64>,
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            verify: true,
+            include_invalid: false,
+            include_attacks: false,
+            edge_cases: false,
+            include_chain_replays: false,
+            compressed: false,
+            padded: false,
+            pairing_input: false,
+            combined: false,
+            include_intermediates: false,
+            calldata: None,
+            keys: HashMap::new(),
+            include_secrets: false,
+            eth_signers: HashMap::new(),
+            encoding: MessageEncoding::Packed,
+            chain_id_width: ChainIdWidth::U256,
+            hasher: MessageHasher::default(),
+            pop_style: PopStyle::Message,
+            preimage: Preimage::Bytes,
+            prehash: Prehash::Raw,
+            expiry: U256::MAX,
+            dst_stake_manager: DEFAULT_DST_STAKE_MANAGER.to_string(),
+            dst_validator_manager: DEFAULT_DST_VALIDATOR_MANAGER.to_string(),
+            dst_rotate: DEFAULT_DST_ROTATE.to_string(),
+            dst_per_chain: false,
+            nonce_start: None,
+            generated_at: None,
+        }
+    }
+}
+
+impl GenOptions {
+    /// The DST `domain` hashes with on `chain_id`: the configured one or, with `dst_per_chain`,
+    /// that followed by `{chain_id}:` (e.g. `StakeManager:BN254:PoP:v1:8453:`).
+    pub fn dst(&self, domain: Domain, chain_id: U256) -> String {
+        let dst = match domain {
+            Domain::StakeManager => &self.dst_stake_manager,
+            Domain::ValidatorManager => &self.dst_validator_manager,
+        };
+        if self.dst_per_chain {
+            return format!("{dst}{chain_id}:")
+        }
+        dst.clone()
+    }
+
+    /// The options of the `index`-th wallet of a run: with a nonce set, ones whose registrations
+    /// sign `nonce_start + index`; otherwise these.
+    pub fn nth_registration(&self, index: usize) -> Cow<'_, Self> {
+        match self.nonce_start {
+            Some(start) if index > 0 => {
+                Cow::Owned(Self { nonce_start: Some(start + U256::from(index)), ..self.clone() })
+            }
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// [`Self::dst`] as recorded on a [`ProofData`]: only set when it differs per chain.
+    pub(crate) fn proof_dst(&self, domain: Domain, chain_id: U256) -> Option<String> {
+        self.dst_per_chain.then(|| self.dst(domain, chain_id))
+    }
+
+    /// The chain ids PoPs are signed for: `chain_ids`, or the single chain id 0 in
+    /// [`PopStyle::Pubkey`], whose PoPs are not bound to a chain.
+    pub(crate) fn pop_chain_ids(&self, chain_ids: &[U256]) -> Vec<U256> {
+        match self.pop_style {
+            PopStyle::Message => chain_ids.to_vec(),
+            PopStyle::Pubkey => vec![U256::ZERO],
+        }
+    }
+
+    /// What `sender`'s PoP of `pk_words` in `style` signs on `chain_id` with `nonce`: the
+    /// [`pop_preimage`] or, for [`PopStyle::Message`] in [`Preimage::Eip712`], the digest of its
+    /// [`PopRegistration`](crate::PopRegistration).
+    pub(crate) fn signed_preimage(
+        &self,
+        style: PopStyle,
+        chain_id: U256,
+        pk_words: &[U256; 4],
+        sender: Address,
+        nonce: Option<U256>,
+    ) -> Vec<u8> {
+        if self.preimage == Preimage::Eip712 && style == PopStyle::Message {
+            return pop_registration(chain_id, pk_words, sender, nonce, self.expiry)
+                .digest()
+                .to_vec()
+        }
+        pop_preimage(style, self.encoding, self.chain_id_width, chain_id, pk_words, sender, nonce)
+    }
+
+    /// The struct a [`Self::signed_preimage`] with `nonce_start` hashes, as recorded on a
+    /// [`ProofData`], in [`Preimage::Eip712`].
+    pub(crate) fn struct_preimage(
+        &self,
+        chain_id: U256,
+        pk_words: &[U256; 4],
+        sender: Address,
+    ) -> Option<StructPreimage> {
+        (self.preimage == Preimage::Eip712 && self.pop_style == PopStyle::Message).then(|| {
+            pop_registration(chain_id, pk_words, sender, self.nonce_start, self.expiry)
+                .to_struct_preimage()
+        })
+    }
+
+    /// `secret_key · H(message)` in `domain` on `chain_id`, with `message` [`Prehash::apply`]d.
+    fn sign(&self, domain: Domain, chain_id: U256, message: &[u8], secret_key: Fp) -> G1Affine {
+        self.hasher.sign(&self.dst(domain, chain_id), &self.prehash.apply(message), secret_key)
+    }
+}
+
+/// Check `e(sig, G2) == e(H(m), pk)`, the relation the contracts verify on-chain.
+pub fn pairing_check(signature: &G1Affine, message_hash: &G1Affine, public_key: &G2Affine) -> bool {
+    PreparedKey::new(public_key).check(signature, message_hash)
+}
+
+/// `-G2` with its Miller loop lines precomputed, shared by every [`PreparedKey`].
+fn neg_generator_lines() -> &'static G2PreComputed {
+    static LINES: OnceLock<G2PreComputed> = OnceLock::new();
+    LINES.get_or_init(|| (-G2Affine::generator()).precompute())
+}
+
+/// A public key with the Miller loop lines of `-G2` and of the key precomputed, so that checking
+/// many PoPs of it costs one Miller loop and one final exponentiation each.
+pub(crate) struct PreparedKey {
+    public_key: G2Affine,
+    /// `[-G2, pk]`, or `None` for the identity key.
+    lines: Option<[G2PreComputed; 2]>,
+}
+
+impl PreparedKey {
+    pub(crate) fn new(public_key: &G2Affine) -> Self {
+        let lines = (!G2Projective::from(*public_key).is_zero())
+            .then(|| [*neg_generator_lines(), public_key.precompute()]);
+        Self { public_key: *public_key, lines }
+    }
+
+    /// [`pairing_check`] as `e(sig, -G2) · e(H(m), pk) == 1`. The precomputed lines only hold
+    /// for points other than the identity, whose pairings [`pairing`] takes to be 1.
+    pub(crate) fn check(&self, signature: &G1Affine, message_hash: &G1Affine) -> bool {
+        let is_zero = |point: &G1Affine| G1Projective::from(*point).is_zero();
+        match &self.lines {
+            Some(lines) if !is_zero(signature) && !is_zero(message_hash) => {
+                glued_miller_loop(lines, &[*signature, *message_hash]).final_exponentiation() ==
+                    Gt::identity()
+            }
+            _ => {
+                pairing(&G1Projective::from(*signature), &G2Projective::generator()) ==
+                    pairing(
+                        &G1Projective::from(*message_hash),
+                        &G2Projective::from(self.public_key),
+                    )
+            }
+        }
+    }
+}
+
+/// `a + b` in G1.
+pub fn g1_add(a: &G1Affine, b: &G1Affine) -> G1Affine {
+    G1Affine::from(G1Projective::from(*a) + G1Projective::from(*b))
+}
+
+/// Check the StakeManager and ValidatorManager PoPs of one key with a single pairing,
+/// `e(σ_stake + σ_validator, G2) == e(H_stake(m) + H_validator(m), pk)`, which halves the
+/// pairing cost on-chain. By bilinearity it holds whenever both [`pairing_check`]s do. It does
+/// not bind the domains separately: moving a point from one signature to the other leaves both
+/// sums unchanged, so a contract that relies on it cannot tell which domain was signed.
+pub fn combined_pairing_check(
+    signatures: [&G1Affine; 2],
+    message_hashes: [&G1Affine; 2],
+    public_key: &G2Affine,
+) -> bool {
+    let [stake_manager, validator_manager] = signatures;
+    let signature = g1_add(stake_manager, validator_manager);
+    let [stake_manager, validator_manager] = message_hashes;
+    pairing_check(&signature, &g1_add(stake_manager, validator_manager), public_key)
+}
+
+/// Run [`pairing_check`] for one PoP, naming the wallet, chain and domain on failure.
+fn verify_pop(
+    wallet: Address,
+    chain_id: U256,
+    domain: Domain,
+    signature: &G1Affine,
+    message_hash: &G1Affine,
+    public_key: &PreparedKey,
+) -> Result<(), GenerateError> {
+    if !public_key.check(signature, message_hash) {
+        return Err(GenerateError::PairingCheckFailed { wallet, chain_id, domain })
+    }
+    Ok(())
+}
+
+/// How the PoP preimage is ABI-encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageEncoding {
+    /// `abi.encodePacked`, what the contracts use today.
+    #[default]
+    Packed,
+    /// `abi.encode`: every value padded to a 32-byte word.
+    Standard,
+}
+
+impl fmt::Display for MessageEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Packed => f.pad("packed"),
+            Self::Standard => f.pad("standard"),
+        }
+    }
+}
+
+impl FromStr for MessageEncoding {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "packed" => Ok(Self::Packed),
+            "standard" => Ok(Self::Standard),
+            _ => {
+                Err(format!("`{input}` is not a message encoding, expected `packed` or `standard`"))
+            }
+        }
+    }
+}
+
+/// The Solidity type of the chain id in a packed PoP preimage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum ChainIdWidth {
+    /// `uint64`, 8 bytes, as on L2 deployments that store the chain id in a `uint64`.
+    U64,
+    /// `uint256`, a full word, what the mainnet contracts use.
+    #[default]
+    U256,
+}
+
+impl ChainIdWidth {
+    /// The width in bits.
+    pub fn bits(&self) -> u16 {
+        match self {
+            Self::U64 => 64,
+            Self::U256 => 256,
+        }
+    }
+
+    /// Check that `chain_id` fits.
+    pub fn check(&self, chain_id: U256) -> Result<(), GenerateError> {
+        if chain_id.bit_len() > usize::from(self.bits()) {
+            return Err(GenerateError::ChainIdTooWide { chain_id, width: *self })
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_u256(&self) -> bool {
+        *self == Self::U256
+    }
+}
+
+impl From<ChainIdWidth> for u16 {
+    fn from(width: ChainIdWidth) -> Self {
+        width.bits()
+    }
+}
+
+impl TryFrom<u16> for ChainIdWidth {
+    type Error = String;
+
+    fn try_from(bits: u16) -> Result<Self, Self::Error> {
+        match bits {
+            64 => Ok(Self::U64),
+            256 => Ok(Self::U256),
+            _ => Err(format!("`{bits}` is not a chain id width, expected `64` or `256`")),
+        }
+    }
+}
+
+impl fmt::Display for ChainIdWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.bits().to_string())
+    }
+}
+
+impl FromStr for ChainIdWidth {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "64" => Ok(Self::U64),
+            "256" => Ok(Self::U256),
+            _ => Err(format!("`{input}` is not a chain id width, expected `64` or `256`")),
+        }
+    }
+}
+
+/// What a PoP signs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PopStyle {
+    /// The chain id, public key and sender, see [`pop_message`]. One PoP per chain id.
+    #[default]
+    Message,
+    /// Only the 128-byte public key, the conventional PoP, see [`pubkey_pop_message`]. One PoP
+    /// per key, written with chain id 0.
+    Pubkey,
+}
+
+impl PopStyle {
+    /// The DST `domain` hashes with unless one is configured.
+    pub fn default_dst(&self, domain: Domain) -> &'static str {
+        match (self, domain) {
+            (Self::Message, Domain::StakeManager) => DEFAULT_DST_STAKE_MANAGER,
+            (Self::Message, Domain::ValidatorManager) => DEFAULT_DST_VALIDATOR_MANAGER,
+            (Self::Pubkey, Domain::StakeManager) => DEFAULT_DST_STAKE_MANAGER_PUBKEY,
+            (Self::Pubkey, Domain::ValidatorManager) => DEFAULT_DST_VALIDATOR_MANAGER_PUBKEY,
+        }
+    }
+}
+
+impl fmt::Display for PopStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Message => f.pad("message"),
+            Self::Pubkey => f.pad("pubkey"),
+        }
+    }
+}
+
+impl FromStr for PopStyle {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "message" => Ok(Self::Message),
+            "pubkey" => Ok(Self::Pubkey),
+            _ => Err(format!("`{input}` is not a PoP style, expected `message` or `pubkey`")),
+        }
+    }
+}
+
+/// What a [`PopStyle::Message`] PoP hashes to the curve.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preimage {
+    /// The message bytes themselves, see [`pop_preimage`].
+    #[default]
+    Bytes,
+    /// The 32-byte digest of a [`PopRegistration`](crate::PopRegistration) of the same chain id,
+    /// public key, sender and nonce, see [`crate::eip712`]. `message_encoding` does not apply.
+    Eip712,
+}
+
+impl Preimage {
+    pub(crate) fn is_bytes(&self) -> bool {
+        *self == Self::Bytes
+    }
+}
+
+impl fmt::Display for Preimage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes => f.pad("bytes"),
+            Self::Eip712 => f.pad("eip712"),
+        }
+    }
+}
+
+impl FromStr for Preimage {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "bytes" => Ok(Self::Bytes),
+            "eip712" => Ok(Self::Eip712),
+            _ => Err(format!("`{input}` is not a preimage, expected `bytes` or `eip712`")),
+        }
+    }
+}
+
+/// What is hashed to the curve in place of the signed preimage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prehash {
+    /// The preimage itself.
+    #[default]
+    Raw,
+    /// The EIP-191 personal message digest of the preimage,
+    /// `keccak256("\x19Ethereum Signed Message:\n" || len || preimage)` with `len` in decimal,
+    /// as `personal_sign` and `eth_sign` compute it.
+    Eip191,
+}
+
+impl Prehash {
+    /// The bytes hashed to the curve for `preimage`.
+    pub fn apply<'a>(&self, preimage: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            Self::Raw => Cow::Borrowed(preimage),
+            Self::Eip191 => Cow::Owned(eip191_hash_message(preimage).to_vec()),
+        }
+    }
+
+    /// The digest of `preimage` as recorded on a [`ProofData`], in [`Prehash::Eip191`].
+    pub(crate) fn digest_hex(&self, preimage: &[u8]) -> Option<String> {
+        (*self == Self::Eip191).then(|| eip191_hash_message(preimage).to_string())
+    }
+
+    pub(crate) fn is_raw(&self) -> bool {
+        *self == Self::Raw
+    }
+}
+
+impl fmt::Display for Prehash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Raw => f.pad("raw"),
+            Self::Eip191 => f.pad("eip191"),
+        }
+    }
+}
+
+impl FromStr for Prehash {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "raw" => Ok(Self::Raw),
+            "eip191" => Ok(Self::Eip191),
+            _ => Err(format!("`{input}` is not a prehash, expected `raw` or `eip191`")),
+        }
+    }
+}
+
+/// `abi.encodePacked(chain_id, pk_limbs, sender)` (or `abi.encode` of the same values), the
+/// preimage both contracts hash.
+pub fn pop_message(
+    encoding: MessageEncoding,
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+) -> Vec<u8> {
+    let values = (chain_id, pk_words[0], pk_words[1], pk_words[2], pk_words[3], sender);
+    match encoding {
+        MessageEncoding::Packed => values.abi_encode_packed(),
+        MessageEncoding::Standard => values.abi_encode_params(),
+    }
+}
+
+/// [`pop_message`] followed by the registration `nonce`, so that a PoP cannot be replayed once its
+/// registration is gone: `abi.encodePacked(chain_id, pk_limbs, sender, nonce)`.
+pub fn pop_message_with_nonce(
+    encoding: MessageEncoding,
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+    nonce: U256,
+) -> Vec<u8> {
+    let values = (chain_id, pk_words[0], pk_words[1], pk_words[2], pk_words[3], sender, nonce);
+    match encoding {
+        MessageEncoding::Packed => values.abi_encode_packed(),
+        MessageEncoding::Standard => values.abi_encode_params(),
+    }
+}
+
+/// [`pop_message`] or [`pop_message_with_nonce`] with the chain id packed as a `uint64` when
+/// `width` is [`ChainIdWidth::U64`]: `abi.encodePacked(uint64(chain_id), pk_limbs, sender)`,
+/// 24 bytes shorter. `abi.encode` pads every value to a word, so there the width changes
+/// nothing. Only the low 64 bits of `chain_id` are packed; [`ChainIdWidth::check`] it first.
+pub fn pop_message_with_width(
+    encoding: MessageEncoding,
+    width: ChainIdWidth,
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+    nonce: Option<U256>,
+) -> Vec<u8> {
+    if encoding == MessageEncoding::Packed && width == ChainIdWidth::U64 {
+        let chain_id = chain_id.wrapping_to::<u64>();
+        let limbs = (pk_words[0], pk_words[1], pk_words[2], pk_words[3]);
+        return match nonce {
+            None => (chain_id, limbs, sender).abi_encode_packed(),
+            Some(nonce) => (chain_id, limbs, sender, nonce).abi_encode_packed(),
+        }
+    }
+    match nonce {
+        None => pop_message(encoding, chain_id, pk_words, sender),
+        Some(nonce) => pop_message_with_nonce(encoding, chain_id, pk_words, sender, nonce),
+    }
+}
+
+/// The 128-byte uncompressed public key in Solidity limb order, the preimage in
+/// [`PopStyle::Pubkey`].
+pub fn pubkey_pop_message(pk_words: &[U256; 4]) -> Vec<u8> {
+    pk_words.abi_encode_packed()
+}
+
+/// The preimage a PoP in `style` signs; `width`, `chain_id`, `sender` and `nonce` only matter in
+/// [`PopStyle::Message`].
+pub fn pop_preimage(
+    style: PopStyle,
+    encoding: MessageEncoding,
+    width: ChainIdWidth,
+    chain_id: U256,
+    pk_words: &[U256; 4],
+    sender: Address,
+    nonce: Option<U256>,
+) -> Vec<u8> {
+    match style {
+        PopStyle::Message => {
+            pop_message_with_width(encoding, width, chain_id, pk_words, sender, nonce)
+        }
+        PopStyle::Pubkey => pubkey_pop_message(pk_words),
+    }
+}
+
+/// The hex secret key of `kp`, when secrets are written.
+pub(crate) fn private_key_hex(opts: &GenOptions, kp: &SecretKeyPair) -> Option<String> {
+    opts.include_secrets.then(|| fp_to_hex(kp.secret_key.expose()))
+}
+
+/// The `0x` hex secp256k1 key of `sender` if it is a generated wallet and secrets are written.
+pub(crate) fn eth_private_key_hex(opts: &GenOptions, sender: Address) -> Option<String> {
+    let signer = opts.eth_signers.get(&sender).filter(|_| opts.include_secrets)?;
+    Some(format!("0x{}", hex::encode(signer.to_bytes())))
+}
+
+/// `0x`-prefixed hex of a compressed point, when compressed output is requested.
+fn compressed_hex(opts: &GenOptions, compress: impl FnOnce() -> Vec<u8>) -> Option<String> {
+    opts.compressed.then(|| format!("0x{}", hex::encode(compress())))
+}
+
+/// `0x`-prefixed hex of the [`pairing_input`] of one PoP, when requested.
+fn pairing_input_hex(
+    opts: &GenOptions,
+    signature: [U256; 2],
+    message_hash: [U256; 2],
+    public_key: [U256; 4],
+) -> Option<String> {
+    opts.pairing_input
+        .then(|| format!("0x{}", hex::encode(pairing_input(signature, message_hash, public_key))))
+}
+
+/// Words of the [`g1_add`] sum of a StakeManager and a ValidatorManager point, when combined
+/// output is requested and both are curve points.
+fn combined_words(opts: &GenOptions, a: [U256; 2], b: [U256; 2]) -> Option<[String; 2]> {
+    if !opts.combined {
+        return None
+    }
+    let sum = g1_add(&g1_from_words(a).ok()?, &g1_from_words(b).ok()?);
+    Some(words_to_hex(g1_to_words(&sum)))
+}
+
+/// `0x`-prefixed hex of the registration calldata of one StakeManager PoP, when requested.
+pub(crate) fn calldata_hex(
+    opts: &GenOptions,
+    public_key: [U256; 4],
+    signature: [U256; 2],
+) -> Option<String> {
+    opts.calldata
+        .as_ref()
+        .map(|call| format!("0x{}", hex::encode(call.encode(public_key, signature))))
+}
+
+/// The [`hash_to_curve_intermediates`] of `message` in `domain`, when requested.
+fn intermediates(
+    opts: &GenOptions,
+    domain: Domain,
+    chain_id: U256,
+    message: &[u8],
+) -> Option<HashToCurveIntermediates> {
+    opts.include_intermediates
+        .then(|| hash_to_curve_intermediates(&opts.hasher, &opts.dst(domain, chain_id), message))
+}
+
+/// `0x`-prefixed hex of the `expand_message_xmd` output `hasher` hashes `message` from, when
+/// intermediates are requested.
+fn xmd_expansion(opts: &GenOptions, hasher: &DstHasher, message: &[u8]) -> Option<String> {
+    opts.include_intermediates.then(|| {
+        format!("0x{}", hex::encode(hasher.expand_message(message, opts.hasher.expand_len)))
+    })
+}
+
+/// Signature words for a negative vector in one domain, see [`InvalidReason`].
+#[allow(clippy::too_many_arguments)]
+fn invalid_signature(
+    reason: InvalidReason,
+    opts: &GenOptions,
+    domain: Domain,
+    chain_id: U256,
+    valid_signature: [U256; 2],
+    message: &[u8],
+    wrong_message: &[u8],
+    kp: &SecretKeyPair,
+    rng: &mut ChaCha20Rng,
+) -> [U256; 2] {
+    let [x, y] = valid_signature;
+    match reason {
+        InvalidReason::WrongKey => {
+            let other_key = random_secret_key(rng);
+            g1_to_words(&opts.sign(domain, chain_id, message, other_key))
+        }
+        InvalidReason::WrongChainId | InvalidReason::WrongNonce => {
+            g1_to_words(&opts.sign(domain, chain_id, wrong_message, kp.secret_key.expose()))
+        }
+        InvalidReason::SwappedCoordinates => [y, x],
+        InvalidReason::RandomPoint => g1_to_words(&G1Affine::rand(rng)),
+    }
+}
+
+/// The imported key of `sender`, or one generated for it.
+pub(crate) fn case_keypair(sender: Address, opts: &GenOptions) -> SecretKeyPair {
+    match opts.keys.get(&sender) {
+        Some(secret_key) => keypair_from_secret(secret_key.expose()).into(),
+        None => generate_keypair(opts.seed.as_ref(), sender).into(),
+    }
+}
+
+/// [`DstHasher`]s of both domains, prepared once per case unless the DSTs embed the chain id.
+struct DomainHashers {
+    stake_manager: DstHasher,
+    validator_manager: DstHasher,
+}
+
+impl DomainHashers {
+    fn new(opts: &GenOptions, chain_id: U256) -> Self {
+        Self {
+            stake_manager: opts.hasher.prepare(&opts.dst(Domain::StakeManager, chain_id)),
+            validator_manager: opts.hasher.prepare(&opts.dst(Domain::ValidatorManager, chain_id)),
+        }
+    }
+}
+
+/// `secret_key · message_hash`, what signing the message hashed to `message_hash` gives.
+pub(crate) fn sign_hash(message_hash: &G1Affine, secret_key: Fp) -> G1Affine {
+    G1Affine::from(G1Projective::from(*message_hash) * secret_key)
+}
+
+/// The valid PoPs of one chain id, with what the negative vectors are derived from.
+struct SignedChain {
+    proof: ProofData,
+    message: Vec<u8>,
+    message_hash_stake_manager: [U256; 2],
+    message_hash_validator_manager: [U256; 2],
+    signature_stake_manager: [U256; 2],
+    signature_validator_manager: [U256; 2],
+}
+
+/// Hash, sign and (given `public_key`, prepared when `opts.verify` is set) pairing-check the PoP
+/// message of `chain_id` in both domains, with `hashers` if the DSTs are the same on every chain.
+#[allow(clippy::too_many_arguments)]
+fn sign_chain(
+    sender: Address,
+    chain_id: U256,
+    kp: &SecretKeyPair,
+    public_key: Option<&PreparedKey>,
+    pk_words: &[U256; 4],
+    hashers: Option<&DomainHashers>,
+    opts: &GenOptions,
+) -> Result<SignedChain, GenerateError> {
+    let message_bytes =
+        opts.signed_preimage(opts.pop_style, chain_id, pk_words, sender, opts.nonce_start);
+    let message_hex = format!("0x{}", hex::encode(&message_bytes));
+    let message_keccak = keccak256(&message_bytes).to_string();
+    let hashed = opts.prehash.apply(&message_bytes);
+
+    // H2C and PoP signature. Signing multiplies the hash, rather than hashing the message again.
+    let per_chain;
+    let hashers = match hashers {
+        Some(hashers) => hashers,
+        None => {
+            per_chain = DomainHashers::new(opts, chain_id);
+            &per_chain
+        }
+    };
+    let curve_stake_manager = hashers.stake_manager.hash_to_curve(&hashed);
+    let curve_validator_manager = hashers.validator_manager.hash_to_curve(&hashed);
+    let msg_xy_stake_manager = g1_to_words(&curve_stake_manager);
+    let msg_xy_validator_manager = g1_to_words(&curve_validator_manager);
+
+    let secret_key = kp.secret_key.expose();
+    let signature_stake_manager = sign_hash(&curve_stake_manager, secret_key);
+    let signature_validator_manager = sign_hash(&curve_validator_manager, secret_key);
+
+    if let Some(public_key) = public_key {
+        verify_pop(
+            sender,
+            chain_id,
+            Domain::StakeManager,
+            &signature_stake_manager,
+            &curve_stake_manager,
+            public_key,
+        )?;
+        verify_pop(
+            sender,
+            chain_id,
+            Domain::ValidatorManager,
+            &signature_validator_manager,
+            &curve_validator_manager,
+            public_key,
+        )?;
+        // `combined_pairing_check`, with the key's lines already computed.
+        if opts.combined &&
+            !public_key.check(
+                &g1_add(&signature_stake_manager, &signature_validator_manager),
+                &g1_add(&curve_stake_manager, &curve_validator_manager),
+            )
+        {
+            return Err(GenerateError::CombinedCheckFailed { wallet: sender, chain_id })
+        }
+    }
+
+    let sig_xy_stake_manager = g1_to_words(&signature_stake_manager);
+    let sig_xy_validator_manager = g1_to_words(&signature_validator_manager);
+    #[cfg(feature = "ark-cross-check")]
+    for (domain, message_hash, signature) in [
+        (Domain::StakeManager, msg_xy_stake_manager, sig_xy_stake_manager),
+        (Domain::ValidatorManager, msg_xy_validator_manager, sig_xy_validator_manager),
+    ] {
+        crate::ark::cross_check_pop(
+            &opts.hasher,
+            &opts.dst(domain, chain_id),
+            &hashed,
+            message_hash,
+            signature,
+            *pk_words,
+        )
+        .map_err(|source| GenerateError::CrossCheckFailed {
+            wallet: sender,
+            chain_id,
+            domain,
+            source: Box::new(source),
+        })?;
+    }
+    let proof = ProofData {
+        chain_id: chain_id.to_string(),
+        signed_chain_id: None,
+        nonce: opts.nonce_start.map(|nonce| nonce.to_string()),
+        domain_staking_manager: opts.proof_dst(Domain::StakeManager, chain_id),
+        domain_validator_manager: opts.proof_dst(Domain::ValidatorManager, chain_id),
+        message_bytes: Some(message_hex),
+        message_keccak: Some(message_keccak),
+        eip191_digest: opts.prehash.digest_hex(&message_bytes),
+        struct_preimage: opts.struct_preimage(chain_id, pk_words, sender),
+        proof_of_possession_stake_manager: words_to_hex(sig_xy_stake_manager),
+        proof_of_possession_validator_manager: words_to_hex(sig_xy_validator_manager),
+        proof_of_possession_stake_manager_compressed: compressed_hex(opts, || {
+            compress_g1(sig_xy_stake_manager).to_vec()
+        }),
+        proof_of_possession_validator_manager_compressed: compressed_hex(opts, || {
+            compress_g1(sig_xy_validator_manager).to_vec()
+        }),
+        pairing_input_stake_manager: pairing_input_hex(
+            opts,
+            sig_xy_stake_manager,
+            msg_xy_stake_manager,
+            *pk_words,
+        ),
+        pairing_input_validator_manager: pairing_input_hex(
+            opts,
+            sig_xy_validator_manager,
+            msg_xy_validator_manager,
+            *pk_words,
+        ),
+        message_hash_combined: combined_words(opts, msg_xy_stake_manager, msg_xy_validator_manager),
+        proof_of_possession_combined: combined_words(
+            opts,
+            sig_xy_stake_manager,
+            sig_xy_validator_manager,
+        ),
+        hash_to_curve_stake_manager: intermediates(opts, Domain::StakeManager, chain_id, &hashed),
+        hash_to_curve_validator_manager: intermediates(
+            opts,
+            Domain::ValidatorManager,
+            chain_id,
+            &hashed,
+        ),
+        xmd_expansion_stake_manager: xmd_expansion(opts, &hashers.stake_manager, &hashed),
+        xmd_expansion_validator_manager: xmd_expansion(opts, &hashers.validator_manager, &hashed),
+        calldata: calldata_hex(opts, *pk_words, sig_xy_stake_manager),
+        ecdsa_binding: opts
+            .eth_signers
+            .get(&sender)
+            .map(|signer| EcdsaBinding::sign(signer, pk_words, chain_id)),
+        expected_naive_result: None,
+        message_hash_stake_manager: words_to_hex(msg_xy_stake_manager),
+        message_hash_validator_manager: words_to_hex(msg_xy_validator_manager),
+        valid: true,
+        invalid_reason: None,
+    };
+    Ok(SignedChain {
+        proof,
+        message: message_bytes,
+        message_hash_stake_manager: msg_xy_stake_manager,
+        message_hash_validator_manager: msg_xy_validator_manager,
+        signature_stake_manager: sig_xy_stake_manager,
+        signature_validator_manager: sig_xy_validator_manager,
+    })
+}
+
+/// Generate a key for `sender` and a PoP in both domains for every chain id (or a single one in
+/// [`PopStyle::Pubkey`]). Fails without signing if a chain id does not fit in
+/// `opts.chain_id_width`.
+pub fn generate_case(
+    sender: Address,
+    chain_ids: &[U256],
+    opts: &GenOptions,
+) -> Result<BlsTestData, GenerateError> {
+    let seed = opts.seed.as_ref();
+    let chain_ids = &opts.pop_chain_ids(chain_ids)[..];
+    for chain_id in chain_ids {
+        opts.chain_id_width.check(*chain_id)?;
+    }
+    let kp = case_keypair(sender, opts);
+
+    let pk_affine: G2Affine = G2Affine::from(kp.public_key);
+    let pk_words = g2_to_words_solidity(&pk_affine);
+
+    // Hash-to-curve, signing and the pairing checks dominate, so run those per chain in
+    // parallel. The negative vectors draw from one RNG per wallet and stay sequential, in chain
+    // order, so seeded output does not depend on scheduling. What does not depend on the chain,
+    // the key's pairing lines and (unless they embed the chain id) the DSTs' expanders, is
+    // prepared once.
+    let public_key = opts.verify.then(|| PreparedKey::new(&pk_affine));
+    let hashers = (!opts.dst_per_chain).then(|| DomainHashers::new(opts, U256::ZERO));
+    let signed: Vec<SignedChain> = chain_ids
+        .par_iter()
+        .map(|chain_id| {
+            let hashers = hashers.as_ref();
+            sign_chain(sender, *chain_id, &kp, public_key.as_ref(), &pk_words, hashers, opts)
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut proof_data: Vec<ProofData> = Vec::new();
+    let mut invalid_rng = wallet_rng(seed, sender, b"invalid");
+    for (chain_id, signed) in chain_ids.iter().zip(signed) {
+        proof_data.push(signed.proof.clone());
+
+        if opts.include_invalid {
+            let wrong_message = |chain_id: U256, nonce: Option<U256>| {
+                opts.signed_preimage(PopStyle::Message, chain_id, &pk_words, sender, nonce)
+            };
+            let wrong_chain_message = wrong_message(chain_id + U256::from(1), opts.nonce_start);
+            let wrong_nonce_message =
+                wrong_message(*chain_id, opts.nonce_start.map(|nonce| nonce + U256::from(1)));
+            for reason in InvalidReason::applicable(opts.pop_style, opts.nonce_start.is_some()) {
+                let wrong_message = match reason {
+                    InvalidReason::WrongNonce => &wrong_nonce_message,
+                    _ => &wrong_chain_message,
+                };
+                let stake_manager = invalid_signature(
+                    reason,
+                    opts,
+                    Domain::StakeManager,
+                    *chain_id,
+                    signed.signature_stake_manager,
+                    &signed.message,
+                    wrong_message,
+                    &kp,
+                    &mut invalid_rng,
+                );
+                let validator_manager = invalid_signature(
+                    reason,
+                    opts,
+                    Domain::ValidatorManager,
+                    *chain_id,
+                    signed.signature_validator_manager,
+                    &signed.message,
+                    wrong_message,
+                    &kp,
+                    &mut invalid_rng,
+                );
+                proof_data.push(ProofData {
+                    proof_of_possession_stake_manager: words_to_hex(stake_manager),
+                    proof_of_possession_validator_manager: words_to_hex(validator_manager),
+                    proof_of_possession_stake_manager_compressed: compressed_hex(opts, || {
+                        compress_g1(stake_manager).to_vec()
+                    }),
+                    proof_of_possession_validator_manager_compressed: compressed_hex(opts, || {
+                        compress_g1(validator_manager).to_vec()
+                    }),
+                    pairing_input_stake_manager: pairing_input_hex(
+                        opts,
+                        stake_manager,
+                        signed.message_hash_stake_manager,
+                        pk_words,
+                    ),
+                    pairing_input_validator_manager: pairing_input_hex(
+                        opts,
+                        validator_manager,
+                        signed.message_hash_validator_manager,
+                        pk_words,
+                    ),
+                    proof_of_possession_combined: combined_words(
+                        opts,
+                        stake_manager,
+                        validator_manager,
+                    ),
+                    calldata: calldata_hex(opts, pk_words, stake_manager),
+                    valid: false,
+                    invalid_reason: Some(reason.as_str().to_string()),
+                    ..signed.proof.clone()
+                });
+            }
+        }
+    }
+
+    Ok(BlsTestData {
+        private_key: private_key_hex(opts, &kp),
+        public_key: words_to_hex(pk_words),
+        public_key_compressed: compressed_hex(opts, || compress_g2(pk_words).to_vec()),
+        public_key_padded: opts.padded.then(|| format!("0x{}", hex::encode(pad_g2(pk_words)))),
+        proof: proof_data,
+        wallet_address: sender.to_string(),
+        eth_private_key: eth_private_key_hex(opts, sender),
+        message_encoding: opts.encoding,
+        chain_id_width: opts.chain_id_width,
+        pop_style: opts.pop_style,
+        preimage: opts.preimage,
+        prehash: opts.prehash,
+        hash_function: opts.hasher.hash_function,
+        expand_len: opts.hasher.expand_len,
+        domain_staking_manager: opts.dst_stake_manager.clone(),
+        domain_validator_manager: opts.dst_validator_manager.clone(),
+        attack: None,
+        generated_at: opts.generated_at,
+        generator_version: Some(GENERATOR_VERSION.to_string()),
+    })
+}
+
+/// [`generate_case`] for every wallet in parallel, each followed by its
+/// [`generate_attacks`](crate::generate_attacks) entries with `opts.include_attacks`, its
+/// [`generate_edge_cases`](crate::generate_edge_cases) entries with `opts.edge_cases` and its
+/// [`chain_replays`](crate::chain_replays) with `opts.include_chain_replays`. The output
+/// is in wallet order and identical to generating the wallets one after another; run it inside a
+/// [`rayon::ThreadPool`] to bound the number of threads.
+pub fn generate_cases(
+    wallets: &[Address],
+    chain_ids: &[U256],
+    opts: &GenOptions,
+) -> Result<Vec<BlsTestData>, GenerateError> {
+    let cases: Vec<Vec<BlsTestData>> = wallets
+        .par_iter()
+        .enumerate()
+        .map(|(index, wallet)| {
+            let opts = &opts.nth_registration(index);
+            let mut cases = vec![generate_case(*wallet, chain_ids, opts)?];
+            if opts.include_attacks {
+                cases.extend(crate::attack::generate_attacks(*wallet, chain_ids, opts)?);
+            }
+            if opts.edge_cases {
+                cases.extend(crate::edge_case::generate_edge_cases(*wallet, chain_ids, opts)?);
+            }
+            if opts.include_chain_replays {
+                cases.extend(crate::attack::chain_replays(&cases[0]));
+            }
+            Ok(cases)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(cases.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        compress::{decompress_g1, decompress_g2},
+        expander::HashFunction,
+        hash_to_curve::UNIFORM_BYTES_LEN,
+        padded::unpad_g2,
+        words::{g1_from_words, g2_from_words_solidity, words_from_hex},
+    };
+    use alloy::primitives::b256;
+    use sha3::Keccak256;
+    use sylow::{Expander, XMDExpander};
+
+    /// Public key for seed 42 and the first default wallet.
+    const PINNED_PUBLIC_KEY: [&str; 4] = [
+        "0x14bba7a5abfe800ba0aa2df639ac414e75533ba1cbeb39827135aa8ca7873e66",
+        "0x0b3dd4b22243c2e37f3848bcb4f10eaa9fb4cca0b99905c36e2f1e19436d5306",
+        "0x0de6b774e8e25564fb6e68b75e4703d7e4fd76c4c1a8b6a8ae347b895f340662",
+        "0x0b3c9e6ad752d2f16d4281671a038b0271adb79736302f445049464c2329fe76",
+    ];
+
+    fn wallet() -> Address {
+        Address::from_str("0x328809Bc894f92807417D2dAD6b7C998c1aFdac6").unwrap()
+    }
+
+    fn seeded(seed: &str) -> GenOptions {
+        GenOptions { seed: Some(seed.parse().unwrap()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let wallets: Vec<Address> =
+            (0..200u64).map(|i| Address::left_padding_from(&i.to_be_bytes())).collect();
+        let chain_ids = [U256::from(1)];
+        let opts = seeded("19");
+        // The pairing check does not change the output, so only the parallel run pays for it.
+        let unverified = GenOptions { verify: false, ..opts.clone() };
+        let sequential: Vec<BlsTestData> = wallets
+            .iter()
+            .map(|wallet| generate_case(*wallet, &chain_ids, &unverified))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let parallel = pool.install(|| generate_cases(&wallets, &chain_ids, &opts)).unwrap();
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn test_dst_per_chain() {
+        let chain_ids = [U256::from(1), U256::from(8453)];
+        let plain = generate_case(wallet(), &chain_ids, &seeded("21")).unwrap();
+        let opts = GenOptions { dst_per_chain: true, ..seeded("21") };
+        let per_chain = generate_case(wallet(), &chain_ids, &opts).unwrap();
+        assert_eq!(per_chain.public_key, plain.public_key);
+        assert!(plain.proof.iter().all(|proof| proof.domain_staking_manager.is_none()));
+        assert_eq!(
+            per_chain.proof[1].domain_staking_manager.as_deref(),
+            Some("StakeManager:BN254:PoP:v1:8453:")
+        );
+        assert_eq!(
+            per_chain.proof[0].domain_validator_manager.as_deref(),
+            Some("ValidatorManager:BN254:PoP:v1:1:")
+        );
+        for (plain, per_chain) in plain.proof.iter().zip(&per_chain.proof) {
+            assert_eq!(per_chain.message_bytes, plain.message_bytes);
+            assert_ne!(per_chain.message_hash_stake_manager, plain.message_hash_stake_manager);
+        }
+        assert!(crate::verify_fixture(&[per_chain]).iter().all(crate::CheckResult::passed));
+
+        // The same bytes land on different points under the chain 1 and chain 8453 DSTs.
+        let message = hex::decode(&plain.proof[0].message_bytes.as_ref().unwrap()[2..]).unwrap();
+        let [chain_1, chain_8453] = chain_ids.map(|chain_id| {
+            g1_to_words(
+                &opts.hasher.hash_to_curve(&opts.dst(Domain::StakeManager, chain_id), &message),
+            )
+        });
+        assert_ne!(chain_1, chain_8453);
+    }
+
+    #[test]
+    fn test_eip191_digest_is_pinned() {
+        let digest = b256!("0xd9eba16ed0ecae432b71fe008c98cc872bb4cc214d3220a36f365326cf807d68");
+        assert_eq!(Prehash::Eip191.apply(b"hello world"), &digest[..]);
+        assert_eq!(keccak256(b"\x19Ethereum Signed Message:\n11hello world"), digest);
+        assert_eq!(Prehash::Raw.apply(b"hello world"), &b"hello world"[..]);
+    }
+
+    #[test]
+    fn test_eip191_prehash() {
+        let chain_ids = [U256::from(1), U256::from(8453)];
+        let raw = generate_case(wallet(), &chain_ids, &seeded("57")).unwrap();
+        let opts = GenOptions { prehash: Prehash::Eip191, include_invalid: true, ..seeded("57") };
+        let prehashed = generate_case(wallet(), &chain_ids, &opts).unwrap();
+        assert_eq!(prehashed.public_key, raw.public_key);
+        assert_eq!(prehashed.prehash, Prehash::Eip191);
+        for (raw, prehashed) in raw.proof.iter().zip(prehashed.proof.iter().filter(|p| p.valid)) {
+            assert_eq!(prehashed.message_bytes, raw.message_bytes);
+            assert!(raw.eip191_digest.is_none());
+            let message = hex::decode(&raw.message_bytes.as_ref().unwrap()[2..]).unwrap();
+            let digest = eip191_hash_message(&message);
+            assert_eq!(prehashed.eip191_digest, Some(digest.to_string()));
+            assert_eq!(
+                prehashed.message_hash_stake_manager,
+                words_to_hex(g1_to_words(
+                    &opts.hasher.hash_to_curve(DEFAULT_DST_STAKE_MANAGER, digest.as_slice())
+                ))
+            );
+            assert_ne!(prehashed.message_hash_stake_manager, raw.message_hash_stake_manager);
+            assert_ne!(
+                prehashed.proof_of_possession_validator_manager,
+                raw.proof_of_possession_validator_manager
+            );
+        }
+        assert!(crate::verify_fixture(std::slice::from_ref(&prehashed))
+            .iter()
+            .all(crate::CheckResult::passed));
+
+        // Relabelling either way breaks the entry.
+        let relabelled = BlsTestData { prehash: Prehash::Raw, ..prehashed };
+        assert!(crate::verify_fixture(&[relabelled]).iter().all(|result| !result.passed()));
+        let relabelled = BlsTestData { prehash: Prehash::Eip191, ..raw };
+        assert!(crate::verify_fixture(&[relabelled]).iter().all(|result| !result.passed()));
+    }
+
+    #[test]
+    fn test_xmd_expansion() {
+        let hasher = MessageHasher::default().prepare(DEFAULT_DST_STAKE_MANAGER);
+        assert_eq!(xmd_expansion(&GenOptions::default(), &hasher, b"abc"), None);
+        let opts = GenOptions { include_intermediates: true, ..Default::default() };
+        let expansion = xmd_expansion(&opts, &hasher, b"abc").unwrap();
+        assert_eq!(expansion.len(), 2 + 2 * UNIFORM_BYTES_LEN);
+        assert_eq!(
+            expansion,
+            hash_to_curve_intermediates(&opts.hasher, DEFAULT_DST_STAKE_MANAGER, b"abc")
+                .uniform_bytes
+        );
+        let expander = XMDExpander::<Keccak256>::new(DEFAULT_DST_STAKE_MANAGER.as_bytes(), 96);
+        assert_eq!(
+            expansion[2..],
+            hex::encode(expander.expand_message(b"abc", UNIFORM_BYTES_LEN).unwrap())
+        );
+
+        let case = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        let proof = &case.proof[0];
+        let stages = proof.hash_to_curve_validator_manager.as_ref().unwrap();
+        assert_eq!(proof.xmd_expansion_validator_manager.as_ref(), Some(&stages.uniform_bytes));
+        assert_ne!(proof.xmd_expansion_validator_manager, proof.xmd_expansion_stake_manager);
+    }
+
+    #[test]
+    fn test_seed_pins_public_key() {
+        let case = generate_case(wallet(), &[U256::from(1)], &seeded("42")).unwrap();
+        assert_eq!(case.public_key, PINNED_PUBLIC_KEY);
+    }
+
+    #[test]
+    fn test_imported_key_matches_generated() {
+        let opts = GenOptions { include_secrets: true, ..seeded("42") };
+        let generated = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        let secret_key = crate::parse_secret_key(generated.private_key.as_ref().unwrap()).unwrap();
+        let opts = GenOptions {
+            keys: HashMap::from([(wallet(), secret_key.into())]),
+            include_secrets: true,
+            ..Default::default()
+        };
+        let imported = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        assert_eq!(imported.public_key, PINNED_PUBLIC_KEY);
+        assert_eq!(imported, generated, "an imported key yields the same PoPs");
+    }
+
+    #[test]
+    fn test_dst_changes_message_hash() {
+        let v1 = generate_case(wallet(), &[U256::from(1)], &seeded("3")).unwrap();
+        let opts =
+            GenOptions { dst_stake_manager: "StakeManager:BN254:PoP:v2:".into(), ..seeded("3") };
+        let v2 = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        assert_eq!(v2.domain_staking_manager, "StakeManager:BN254:PoP:v2:");
+        assert_eq!(v2.public_key, v1.public_key, "same key, only the DST differs");
+        assert_ne!(v2.proof[0].message_hash_stake_manager, v1.proof[0].message_hash_stake_manager);
+        assert_eq!(
+            v2.proof[0].message_hash_validator_manager,
+            v1.proof[0].message_hash_validator_manager
+        );
+    }
+
+    #[test]
+    fn test_eth_private_key_derives_wallet() {
+        let signer = crate::generate_eth_wallets(Some(&"6".parse().unwrap()), 1).remove(0);
+        let sender = signer.address();
+        let opts = GenOptions { eth_signers: HashMap::from([(sender, signer)]), ..seeded("6") };
+        let hidden = generate_case(sender, &[U256::from(1)], &opts).unwrap();
+        assert_eq!((hidden.private_key, hidden.eth_private_key), (None, None));
+        let opts = GenOptions { include_secrets: true, ..opts };
+        let case = generate_case(sender, &[U256::from(1)], &opts).unwrap();
+
+        let key = case.eth_private_key.as_deref().expect("generated wallet carries its key");
+        let derived: PrivateKeySigner = key.trim_start_matches("0x").parse().unwrap();
+        assert_eq!(derived.address().to_string(), case.wallet_address);
+        assert!(crate::verify_fixture(&[case]).iter().all(crate::CheckResult::passed));
+
+        let external = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        assert!(external.eth_private_key.is_none());
+    }
+
+    #[test]
+    fn test_nonce_is_signed_after_sender() {
+        let pk_words = [U256::from(1), U256::from(2), U256::from(3), U256::from(4)];
+        let plain = pop_message(MessageEncoding::Packed, U256::from(1), &pk_words, wallet());
+        let with_nonce = pop_message_with_nonce(
+            MessageEncoding::Packed,
+            U256::from(1),
+            &pk_words,
+            wallet(),
+            U256::from(7),
+        );
+        assert_eq!(with_nonce[..plain.len()], plain[..]);
+        assert_eq!(with_nonce[plain.len()..], U256::from(7).to_be_bytes::<32>());
+
+        let wallets: Vec<Address> = (1..=3).map(Address::repeat_byte).collect();
+        let opts =
+            GenOptions { nonce_start: Some(U256::from(5)), include_invalid: true, ..seeded("39") };
+        let cases = generate_cases(&wallets, &[U256::from(1), U256::from(8453)], &opts).unwrap();
+        for (case, nonce) in cases.iter().zip(["5", "6", "7"]) {
+            assert!(case.proof.iter().all(|proof| proof.nonce.as_deref() == Some(nonce)));
+            assert_eq!(
+                case.proof.iter().filter(|proof| proof.invalid_reason.is_some()).count(),
+                2 * InvalidReason::ALL.len()
+            );
+        }
+        assert!(crate::verify_fixture(&cases).iter().all(crate::CheckResult::passed));
+
+        let plain = generate_case(wallets[0], &[U256::from(1)], &seeded("39")).unwrap();
+        assert_eq!(plain.proof[0].nonce, None);
+        assert_eq!(plain.public_key, cases[0].public_key);
+        assert_ne!(
+            plain.proof[0].message_hash_stake_manager,
+            cases[0].proof[0].message_hash_stake_manager
+        );
+    }
+
+    #[test]
+    fn test_eip712_preimage_signs_struct_digest() {
+        let expiry = U256::from(1_700_000_000u64);
+        let opts = GenOptions {
+            preimage: Preimage::Eip712,
+            expiry,
+            nonce_start: Some(U256::from(5)),
+            include_invalid: true,
+            include_attacks: true,
+            edge_cases: true,
+            include_chain_replays: true,
+            ..seeded("47")
+        };
+        let wallets = [wallet(), Address::repeat_byte(0x47)];
+        let cases = generate_cases(&wallets, &[U256::from(1), U256::from(8453)], &opts).unwrap();
+        assert_eq!(cases[0].preimage, Preimage::Eip712);
+        assert!(crate::verify_fixture(&cases).iter().all(crate::CheckResult::passed));
+
+        let pk_words = words_from_hex(&cases[0].public_key).unwrap();
+        for (proof, chain_id) in cases[0].proof.iter().filter(|proof| proof.valid).zip([1u64, 8453])
+        {
+            let registration = pop_registration(
+                U256::from(chain_id),
+                &pk_words,
+                wallet(),
+                Some(U256::from(5)),
+                expiry,
+            );
+            let digest = registration.digest().to_string();
+            assert_eq!(proof.message_bytes.as_ref(), Some(&digest));
+            assert_eq!(proof.struct_preimage, Some(registration.to_struct_preimage()));
+        }
+        let wrong_nonce = cases[0]
+            .proof
+            .iter()
+            .find(|proof| proof.invalid_reason.as_deref() == Some("wrong_nonce"))
+            .unwrap();
+        assert_eq!(wrong_nonce.struct_preimage.as_ref().unwrap().nonce, "5");
+
+        let bytes = generate_case(wallet(), &[U256::from(1)], &seeded("47")).unwrap();
+        assert_eq!(bytes.preimage, Preimage::Bytes);
+        assert_eq!(bytes.proof[0].struct_preimage, None);
+        assert!(!serde_json::to_string(&bytes).unwrap().contains("preimage"));
+    }
+
+    #[test]
+    fn test_encodings_give_different_hashes() {
+        let pk_words = [U256::from(1), U256::from(2), U256::from(3), U256::from(4)];
+        let packed = pop_message(MessageEncoding::Packed, U256::from(1), &pk_words, wallet());
+        let standard = pop_message(MessageEncoding::Standard, U256::from(1), &pk_words, wallet());
+        assert_eq!(packed.len(), 5 * 32 + 20);
+        assert_eq!(standard.len(), 6 * 32);
+        assert_eq!(standard[..160], packed[..160], "only the address is padded differently");
+
+        let packed = generate_case(wallet(), &[U256::from(1)], &seeded("3")).unwrap();
+        let opts = GenOptions { encoding: MessageEncoding::Standard, ..seeded("3") };
+        let standard = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        assert_eq!(packed.message_encoding, MessageEncoding::Packed);
+        assert_eq!(standard.message_encoding, MessageEncoding::Standard);
+        assert_eq!(standard.public_key, packed.public_key);
+        assert_ne!(
+            standard.proof[0].message_hash_stake_manager,
+            packed.proof[0].message_hash_stake_manager
+        );
+        assert_ne!(
+            standard.proof[0].message_hash_validator_manager,
+            packed.proof[0].message_hash_validator_manager
+        );
+    }
+
+    #[test]
+    fn test_chain_id_widths_give_different_hashes() {
+        let pk_words = [U256::from(1), U256::from(2), U256::from(3), U256::from(4)];
+        let chain_id = U256::from(8453);
+        let message = |encoding, width, nonce| {
+            pop_message_with_width(encoding, width, chain_id, &pk_words, wallet(), nonce)
+        };
+        let wide = message(MessageEncoding::Packed, ChainIdWidth::U256, None);
+        let narrow = message(MessageEncoding::Packed, ChainIdWidth::U64, None);
+        assert_eq!(wide, pop_message(MessageEncoding::Packed, chain_id, &pk_words, wallet()));
+        assert_eq!(narrow.len(), wide.len() - 24);
+        assert_eq!(narrow[..8], 8453u64.to_be_bytes());
+        assert_eq!(narrow[8..], wide[32..], "only the chain id is narrower");
+        let with_nonce = message(MessageEncoding::Packed, ChainIdWidth::U64, Some(U256::from(5)));
+        assert_eq!(with_nonce[..narrow.len()], narrow[..]);
+        assert_eq!(
+            message(MessageEncoding::Standard, ChainIdWidth::U64, None),
+            message(MessageEncoding::Standard, ChainIdWidth::U256, None),
+            "abi.encode pads a uint64 to a word too"
+        );
+
+        let wide = generate_case(wallet(), &[chain_id], &seeded("50")).unwrap();
+        let opts = GenOptions { chain_id_width: ChainIdWidth::U64, ..seeded("50") };
+        let narrow = generate_case(wallet(), &[chain_id], &opts).unwrap();
+        assert_eq!(narrow.chain_id_width, ChainIdWidth::U64);
+        assert_eq!(narrow.public_key, wide.public_key);
+        assert_ne!(narrow.proof[0].message_bytes, wide.proof[0].message_bytes);
+        assert_ne!(
+            narrow.proof[0].message_hash_stake_manager,
+            wide.proof[0].message_hash_stake_manager
+        );
+        assert_ne!(
+            narrow.proof[0].message_hash_validator_manager,
+            wide.proof[0].message_hash_validator_manager
+        );
+    }
+
+    #[test]
+    fn test_chain_id_too_wide() {
+        let opts = GenOptions { chain_id_width: ChainIdWidth::U64, ..seeded("50") };
+        let max = U256::from(u64::MAX);
+        assert!(generate_case(wallet(), &[max], &opts).is_ok());
+        let too_wide = max + U256::from(1);
+        let error = generate_case(wallet(), &[U256::from(1), too_wide], &opts).unwrap_err();
+        assert!(
+            matches!(error, GenerateError::ChainIdTooWide { chain_id, width: ChainIdWidth::U64 } if chain_id == too_wide),
+            "{error}"
+        );
+        assert_eq!(error.to_string(), "chain id 18446744073709551616 does not fit in a uint64");
+        assert!(ChainIdWidth::U256.check(U256::MAX).is_ok());
+
+        assert_eq!(serde_json::to_string(&ChainIdWidth::U64).unwrap(), "64");
+        assert_eq!(serde_json::from_str::<ChainIdWidth>("256").unwrap(), ChainIdWidth::U256);
+        assert!(serde_json::from_str::<ChainIdWidth>("128").is_err());
+        assert_eq!("64".parse(), Ok(ChainIdWidth::U64));
+    }
+
+    #[test]
+    fn test_pubkey_pop_style() {
+        let chain_ids = [U256::from(8453), U256::from(1)];
+        let message = generate_case(wallet(), &chain_ids, &seeded("32")).unwrap();
+        let opts = GenOptions {
+            pop_style: PopStyle::Pubkey,
+            include_invalid: true,
+            include_attacks: true,
+            ..seeded("32")
+        };
+        let cases = generate_cases(&[wallet()], &chain_ids, &opts).unwrap();
+        let pubkey = &cases[0];
+        assert_eq!(pubkey.pop_style, PopStyle::Pubkey);
+        assert_eq!(pubkey.public_key, message.public_key, "the style does not change the key");
+
+        let reasons: Vec<Option<&str>> =
+            pubkey.proof.iter().map(|proof| proof.invalid_reason.as_deref()).collect();
+        assert_eq!(
+            reasons,
+            [None, Some("wrong_key"), Some("swapped_coordinates"), Some("random_point")],
+            "one PoP per key, and no chain id to get wrong"
+        );
+        assert!(pubkey.proof.iter().all(|proof| proof.chain_id == "0"));
+        let pk_words = words_from_hex(&pubkey.public_key).unwrap();
+        let preimage = format!("0x{}", hex::encode(pubkey_pop_message(&pk_words)));
+        assert_eq!(preimage.len(), 2 + 2 * 128);
+        assert_eq!(pubkey.proof[0].message_bytes.as_ref(), Some(&preimage));
+        assert_eq!(cases[1].proof.len(), 1, "attack entries skip the chain ids too");
+
+        assert_ne!(
+            pubkey.proof[0].proof_of_possession_stake_manager,
+            message.proof[1].proof_of_possession_stake_manager
+        );
+        assert_ne!(
+            pubkey.proof[0].proof_of_possession_validator_manager,
+            message.proof[1].proof_of_possession_validator_manager
+        );
+        let mut both = cases.clone();
+        both.push(message);
+        assert!(crate::verify_fixture(&both).iter().all(crate::CheckResult::passed));
+    }
+
+    #[test]
+    fn test_skip_verify_produces_same_vectors() {
+        let verified = generate_case(wallet(), &[U256::from(1)], &seeded("7")).unwrap();
+        let opts = GenOptions { verify: false, ..seeded("7") };
+        assert_eq!(generate_case(wallet(), &[U256::from(1)], &opts).unwrap(), verified);
+    }
+
+    #[test]
+    fn test_compressed_fields_round_trip() {
+        let plain = generate_case(wallet(), &[U256::from(1)], &seeded("8")).unwrap();
+        assert!(plain.public_key_compressed.is_none());
+        assert!(plain.proof[0].proof_of_possession_stake_manager_compressed.is_none());
+
+        let opts = GenOptions { compressed: true, ..seeded("8") };
+        let case = generate_case(wallet(), &[U256::from(1), U256::from(8453)], &opts).unwrap();
+        let bytes = |hex: &Option<String>| {
+            hex::decode(hex.as_ref().unwrap().trim_start_matches("0x")).unwrap()
+        };
+
+        let public_key = decompress_g2(&bytes(&case.public_key_compressed).try_into().unwrap());
+        assert_eq!(public_key, words_from_hex(&case.public_key));
+        assert!(case.public_key_padded.is_none(), "padding is a separate option");
+        for proof in &case.proof {
+            for (compressed, words) in [
+                (
+                    &proof.proof_of_possession_stake_manager_compressed,
+                    &proof.proof_of_possession_stake_manager,
+                ),
+                (
+                    &proof.proof_of_possession_validator_manager_compressed,
+                    &proof.proof_of_possession_validator_manager,
+                ),
+            ] {
+                assert_eq!(
+                    decompress_g1(&bytes(compressed).try_into().unwrap()),
+                    words_from_hex(words)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_padded_public_key_round_trip() {
+        let opts = GenOptions { padded: true, ..seeded("8") };
+        let case = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        let padded = hex::decode(case.public_key_padded.unwrap().trim_start_matches("0x")).unwrap();
+        assert_eq!(padded.len(), 256);
+        assert_eq!(unpad_g2(&padded.try_into().unwrap()), words_from_hex(&case.public_key));
+        assert!(case.public_key_compressed.is_none());
+    }
+
+    #[test]
+    fn test_sign_hash_matches_signing_the_message() {
+        let secret_key = generate_keypair(Some(&"56".parse().unwrap()), wallet()).secret_key;
+        for hash_function in [HashFunction::Keccak256, HashFunction::Sha256] {
+            let hasher = MessageHasher { hash_function, ..Default::default() };
+            let prepared = hasher.prepare(DEFAULT_DST_STAKE_MANAGER);
+            for message in [&b""[..], b"message", &[0xff; 300]] {
+                let hash = prepared.hash_to_curve(message);
+                assert_eq!(hash, hasher.sign(DEFAULT_DST_STAKE_MANAGER, message, Fp::from(1u64)));
+                assert_eq!(
+                    sign_hash(&hash, secret_key),
+                    hasher.sign(DEFAULT_DST_STAKE_MANAGER, message, secret_key)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_prepared_key_matches_two_pairings() {
+        let kp = generate_keypair(Some(&"56".parse().unwrap()), wallet());
+        let hash = MessageHasher::default().hash_to_curve(DEFAULT_DST_STAKE_MANAGER, b"message");
+        let signature = sign_hash(&hash, kp.secret_key);
+        let g1_zero = G1Affine::from(G1Projective::zero());
+        let g2_zero = G2Affine::from(G2Projective::zero());
+        for public_key in [G2Affine::from(kp.public_key), g2_zero] {
+            let prepared = PreparedKey::new(&public_key);
+            for signature in [signature, g1_add(&signature, &hash), g1_zero] {
+                for message_hash in [hash, g1_zero] {
+                    let expected = pairing(&signature.into(), &G2Projective::generator()) ==
+                        pairing(&message_hash.into(), &public_key.into());
+                    assert_eq!(prepared.check(&signature, &message_hash), expected);
+                }
+            }
+        }
+        assert!(PreparedKey::new(&G2Affine::from(kp.public_key)).check(&signature, &hash));
+    }
+
+    #[test]
+    fn test_verify_pop_rejects_corrupted_signature() {
+        let seed: Seed = "1".parse().unwrap();
+        let sender = wallet();
+        let kp = generate_keypair(Some(&seed), sender);
+        let public_key = PreparedKey::new(&G2Affine::from(kp.public_key));
+        let expander = XMDExpander::<Keccak256>::new(DEFAULT_DST_STAKE_MANAGER.as_bytes(), 96);
+        let message_hash = G1Affine::hash_to_curve(&expander, b"message").unwrap();
+        let signature = G1Affine::sign_message(&expander, b"message", kp.secret_key).unwrap();
+        let chain_id = U256::from(8453);
+
+        verify_pop(sender, chain_id, Domain::StakeManager, &signature, &message_hash, &public_key)
+            .expect("valid signature passes");
+
+        let corrupted = G1Affine::from(G1Projective::from(signature) + G1Projective::generator());
+        let error = verify_pop(
+            sender,
+            chain_id,
+            Domain::StakeManager,
+            &corrupted,
+            &message_hash,
+            &public_key,
+        )
+        .expect_err("corrupted signature is rejected");
+        let message = error.to_string();
+        assert!(message.contains(&sender.to_string()), "{message}");
+        assert!(message.contains("8453"), "{message}");
+        assert!(message.contains("StakeManager"), "{message}");
+    }
+
+    /// Re-run the pairing check from the serialized limbs, as a consumer of the file would.
+    fn entry_passes(public_key: &[String; 4], signature: &[String; 2], hash: &[String; 2]) -> bool {
+        let public_key = g2_from_words_solidity(words_from_hex(public_key).unwrap()).unwrap();
+        let g1 = |words| g1_from_words(words_from_hex(words).unwrap());
+        match (g1(signature), g1(hash)) {
+            (Ok(signature), Ok(hash)) => pairing_check(&signature, &hash, &public_key),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_include_invalid_entries_fail_pairing() {
+        let opts = GenOptions { include_invalid: true, ..seeded("9") };
+        let entry = generate_case(wallet(), &[U256::from(1)], &opts).unwrap();
+        assert_eq!(entry.proof.len(), 1 + InvalidReason::ALL.len() - 1, "no wrong_nonce entry");
+
+        let reasons: Vec<Option<&str>> =
+            entry.proof.iter().map(|proof| proof.invalid_reason.as_deref()).collect();
+        let mut expected = vec![None];
+        expected.extend(
+            InvalidReason::applicable(PopStyle::Message, false).map(|reason| Some(reason.as_str())),
+        );
+        assert_eq!(reasons, expected);
+
+        for proof in &entry.proof {
+            assert_eq!(proof.chain_id, "1");
+            for (signature, hash) in [
+                (&proof.proof_of_possession_stake_manager, &proof.message_hash_stake_manager),
+                (
+                    &proof.proof_of_possession_validator_manager,
+                    &proof.message_hash_validator_manager,
+                ),
+            ] {
+                assert_eq!(
+                    entry_passes(&entry.public_key, signature, hash),
+                    proof.valid,
+                    "{:?}",
+                    proof.invalid_reason
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_combined_check_passes_exactly_when_both_domains_pass() {
+        let opts = GenOptions { include_invalid: true, combined: true, ..seeded("44") };
+        let entry = generate_case(wallet(), &[U256::from(1), U256::from(8453)], &opts).unwrap();
+        let public_key =
+            g2_from_words_solidity(words_from_hex(&entry.public_key).unwrap()).unwrap();
+        let g1 = |words: &[String; 2]| g1_from_words(words_from_hex(words).unwrap());
+
+        // Pair every StakeManager signature with every ValidatorManager one of the same chain id,
+        // so that one domain passes while the other fails as well.
+        for stake_manager in &entry.proof {
+            for validator_manager in
+                entry.proof.iter().filter(|proof| proof.chain_id == stake_manager.chain_id)
+            {
+                let both = entry_passes(
+                    &entry.public_key,
+                    &stake_manager.proof_of_possession_stake_manager,
+                    &stake_manager.message_hash_stake_manager,
+                ) && entry_passes(
+                    &entry.public_key,
+                    &validator_manager.proof_of_possession_validator_manager,
+                    &validator_manager.message_hash_validator_manager,
+                );
+                let combined = match (
+                    g1(&stake_manager.proof_of_possession_stake_manager),
+                    g1(&validator_manager.proof_of_possession_validator_manager),
+                ) {
+                    (Ok(stake_signature), Ok(validator_signature)) => combined_pairing_check(
+                        [&stake_signature, &validator_signature],
+                        [
+                            &g1(&stake_manager.message_hash_stake_manager).unwrap(),
+                            &g1(&validator_manager.message_hash_validator_manager).unwrap(),
+                        ],
+                        &public_key,
+                    ),
+                    _ => false,
+                };
+                assert_eq!(
+                    combined, both,
+                    "{:?} and {:?}",
+                    stake_manager.invalid_reason, validator_manager.invalid_reason
+                );
+            }
+        }
+
+        for proof in &entry.proof {
+            let hashes =
+                [&proof.message_hash_stake_manager, &proof.message_hash_validator_manager].map(g1);
+            let hash = g1_add(hashes[0].as_ref().unwrap(), hashes[1].as_ref().unwrap());
+            assert_eq!(proof.message_hash_combined, Some(words_to_hex(g1_to_words(&hash))));
+            let signatures = [
+                &proof.proof_of_possession_stake_manager,
+                &proof.proof_of_possession_validator_manager,
+            ]
+            .map(g1);
+            let signature = match signatures {
+                [Ok(stake_manager), Ok(validator_manager)] => {
+                    Some(words_to_hex(g1_to_words(&g1_add(&stake_manager, &validator_manager))))
+                }
+                _ => None,
+            };
+            assert_eq!(proof.proof_of_possession_combined, signature, "{:?}", proof.invalid_reason);
+        }
+        assert!(generate_case(wallet(), &[U256::from(1)], &seeded("44")).unwrap().proof[0]
+            .message_hash_combined
+            .is_none());
+    }
+}
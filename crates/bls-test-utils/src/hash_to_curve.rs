@@ -0,0 +1,185 @@
+//! The stages of hash-to-curve (RFC 9380) as sylow runs them, for testing a Solidity
+//! `hashToPoint` one step at a time.
+//!
+//! `H(m)` is `expand_message_xmd` with Keccak256 (or SHA-256, see [`crate::expander`]) to 96
+//! uniform bytes, each 48-byte half reduced
+//! mod p to a field element `u`, each `u` mapped to the curve with the Shallue–van de Woestijne
+//! map (section 6.6.1) and the two points added. BN254 G1 has cofactor one, so there is no
+//! clearing step. sylow does not export its map, so it is repeated here for `y² = x³ + 3` with
+//! the same `Z = 1`; the tests check the sum against sylow's `hash_to_curve`.
+
+use crate::{
+    expander::MessageHasher,
+    words::{fp_to_hex, g1_to_words, words_to_hex, NumberFormat},
+};
+use serde::{Deserialize, Serialize};
+use sylow::{Fp, G1Affine, G1Projective};
+
+/// Bytes `expand_message_xmd` produces: two field elements of 48 bytes.
+pub const UNIFORM_BYTES_LEN: usize = 96;
+
+/// Every intermediate of `H(m)` under one DST, hex encoded like the rest of the fixture.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashToCurveIntermediates {
+    /// `expand_message_xmd(message, DST, 96)`.
+    pub uniform_bytes: String,
+    /// `[u0, u1]`, the halves of `uniform_bytes` reduced mod p.
+    pub u: [String; 2],
+    /// `map_to_curve(u0)` as `[x, y]`.
+    pub q0: [String; 2],
+    /// `map_to_curve(u1)` as `[x, y]`.
+    pub q1: [String; 2],
+    /// `Q0 + Q1`, the message hash.
+    pub point: [String; 2],
+}
+
+impl HashToCurveIntermediates {
+    /// Write the field elements and points in `format`; `uniform_bytes` stays hex.
+    pub fn rewrite_numbers(&mut self, format: NumberFormat) {
+        format.rewrite(&mut self.u);
+        format.rewrite(&mut self.q0);
+        format.rewrite(&mut self.q1);
+        format.rewrite(&mut self.point);
+    }
+}
+
+/// Run hash-to-curve on `message` under `dst` with `hasher` and keep every stage.
+pub fn hash_to_curve_intermediates(
+    hasher: &MessageHasher,
+    dst: &str,
+    message: &[u8],
+) -> HashToCurveIntermediates {
+    let uniform_bytes = hasher.expand_message(dst, message);
+    let u = hasher.hash_to_field(dst, message);
+    let [q0, q1] = u.map(map_to_curve);
+    let point = G1Affine::from(G1Projective::from(q0) + G1Projective::from(q1));
+    HashToCurveIntermediates {
+        uniform_bytes: format!("0x{}", hex::encode(uniform_bytes)),
+        u: u.map(fp_to_hex),
+        q0: words_to_hex(g1_to_words(&q0)),
+        q1: words_to_hex(g1_to_words(&q1)),
+        point: words_to_hex(g1_to_words(&point)),
+    }
+}
+
+/// `g(x) = x³ + 3`.
+fn curve(x: Fp) -> Fp {
+    x.square() * x + Fp::THREE
+}
+
+/// The Shallue–van de Woestijne map of RFC 9380 section 6.6.1 with `A = 0`, `B = 3`, `Z = 1`.
+/// Not constant time, which does not matter for test vectors.
+fn map_to_curve(u: Fp) -> G1Affine {
+    let z = Fp::ONE;
+    let c1 = curve(z);
+    let c2 = -z / Fp::TWO;
+    let mut c3: Fp =
+        Option::from((-curve(z) * Fp::THREE * z.square()).sqrt()).expect("-g(Z)·3Z² is a square");
+    if bool::from(c3.sgn0()) {
+        c3 = -c3;
+    }
+    let c4 = -(Fp::FOUR * curve(z)) / (Fp::THREE * z.square());
+
+    let tv1 = u.square() * c1;
+    let tv2 = Fp::ONE + tv1;
+    let tv1 = Fp::ONE - tv1;
+    // `Fp` division returns zero for a zero denominator, the `inv0` the RFC asks for.
+    let tv3 = Fp::ONE / (tv1 * tv2);
+    let tv4 = u * tv1 * tv3 * c3;
+    let x1 = c2 - tv4;
+    let x2 = c2 + tv4;
+    let x3 = (tv2.square() * tv3).square() * c4 + z;
+    let x = [x1, x2].into_iter().find(|x| bool::from(curve(*x).is_square())).unwrap_or(x3);
+    let mut y: Fp = Option::from(curve(x).sqrt()).expect("one of x1, x2, x3 is on the curve");
+    if u.sgn0().unwrap_u8() != y.sgn0().unwrap_u8() {
+        y = -y;
+    }
+    G1Affine::new([x, y]).expect("the map lands on the curve")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        expander::{HashFunction, Sha256},
+        generate::{generate_case, GenOptions},
+    };
+    use alloy::primitives::{Address, U256, U512};
+    use sha3::Keccak256;
+    use sylow::{GroupTrait, XMDExpander};
+
+    #[test]
+    fn test_stages_match_sylow() {
+        for (dst, message) in [
+            ("StakeManager:BN254:PoP:v1:", &b""[..]),
+            ("ValidatorManager:BN254:PoP:v1:", b"abc"),
+            ("Bridge:BN254:v1:", &[0xff; 200]),
+        ] {
+            let keccak = XMDExpander::<Keccak256>::new(dst.as_bytes(), 96);
+            let sha256 = XMDExpander::<Sha256>::new(dst.as_bytes(), 96);
+            for (hash_function, expected) in [
+                (HashFunction::Keccak256, G1Affine::hash_to_curve(&keccak, message).unwrap()),
+                (HashFunction::Sha256, G1Affine::hash_to_curve(&sha256, message).unwrap()),
+            ] {
+                let hasher = MessageHasher { hash_function, ..Default::default() };
+                let stages = hash_to_curve_intermediates(&hasher, dst, message);
+                assert_eq!(stages.point, words_to_hex(g1_to_words(&expected)), "{dst}");
+                assert_eq!(stages.uniform_bytes.len(), 2 + 2 * UNIFORM_BYTES_LEN);
+                assert_ne!(stages.q0, stages.q1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_u_are_the_reduced_halves() {
+        let stages =
+            hash_to_curve_intermediates(&MessageHasher::default(), "Bridge:BN254:v1:", b"abc");
+        let bytes = hex::decode(&stages.uniform_bytes[2..]).unwrap();
+        let p: U512 =
+            "0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47".parse().unwrap();
+        for (half, u) in bytes.chunks(48).zip(&stages.u) {
+            assert_eq!(u.parse::<U512>().unwrap(), U512::from_be_slice(half) % p);
+        }
+    }
+
+    /// The StakeManager stages of a seeded PoP. A change to any of them changes what the
+    /// contracts must compute, so it has to be deliberate.
+    #[test]
+    fn test_pinned_intermediates() {
+        let opts = GenOptions {
+            seed: Some("29".parse().unwrap()),
+            include_intermediates: true,
+            ..Default::default()
+        };
+        let case = generate_case(Address::repeat_byte(29), &[U256::from(1)], &opts).unwrap();
+        let stages = case.proof[0].hash_to_curve_stake_manager.clone().unwrap();
+        assert_eq!(
+            stages,
+            HashToCurveIntermediates {
+                uniform_bytes: concat!(
+                    "0x5b637c6e0a3f8cd4cfeba4d5be8611b40c02cb2137240df8fd692061c5c6b1c7",
+                    "34d810eb374198187f555d2eb6832d8b72a115fcb9c740e150c3365a0e807631",
+                    "a924411896701427a2d4c5f701f324945a9dba4ffd8329b72cfd3affab2a3717",
+                )
+                .into(),
+                u: [
+                    "0x1168e9490de0e5ccf272bab6e6b880d80d1ecf3e0495adad9f24a74b74d4ee8f".into(),
+                    "0x069e65baca928df76bcb1548bcceb4a2b7ab89e4b575d698221e5e9979ff1a44".into(),
+                ],
+                q0: [
+                    "0x15fbb6586979246dc145880ae4b0116b729ceca71f0f20e39a10b63fbd5d5872".into(),
+                    "0x149f3f01fa0fa0b8a13f70e69740fcc2c5a811b2200fea890b6ae1fac7748f89".into(),
+                ],
+                q1: [
+                    "0x2d449fef1e2587a52b0fb7e06b08702b17ec8b3bba69fbad6b3ede62eb17f4d8".into(),
+                    "0x09ff73255957596141a8e6ab51603f55a5fc1074ec3d249e6b923da072e56180".into(),
+                ],
+                point: [
+                    "0x2c06c95389b4033eae25b3de1f2e58c5d74c3afbe1575d87acebcc1c932bb279".into(),
+                    "0x07275341675c0a059a078fcd7d3af6903a7dc16c666bf2d9a9a5367785ab597c".into(),
+                ],
+            }
+        );
+        assert_eq!(stages.point, case.proof[0].message_hash_stake_manager);
+    }
+}
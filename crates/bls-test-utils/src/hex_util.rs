@@ -0,0 +1,144 @@
+//! Hex parsing shared by every CLI flag and input file, lenient enough for hand-written fixtures:
+//! the `0x` prefix is optional (`0X` works too), digits may be in either case, and `_` or
+//! whitespace may separate them (`0xdead_beef`, `dead beef`).
+//!
+//! Byte strings need an even number of digits; numbers such as scalars and seeds do not.
+
+use alloy::primitives::U256;
+
+/// Why a string is not the hex it should be. Positions are 1-based and count the prefix.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum HexError {
+    #[error("`{character}` at position {position} is not a hex digit")]
+    NonHex { character: char, position: usize },
+    #[error("odd number of hex digits ({0}), bytes take two each")]
+    OddLength(usize),
+    #[error("no hex digits")]
+    Empty,
+    #[error("{digits} hex digits do not fit in {max_bytes} bytes")]
+    TooLong { digits: usize, max_bytes: usize },
+    #[error("expected {expected} bytes, got {actual}")]
+    Length { expected: usize, actual: usize },
+}
+
+/// The hex digits of `input`, without surrounding whitespace, the prefix and separators. Only
+/// fails with [`HexError::NonHex`].
+pub fn hex_digits(input: &str) -> Result<String, HexError> {
+    let trimmed = input.trim_start();
+    let digits =
+        trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")).unwrap_or(trimmed);
+    let prefix = input.len() - digits.len();
+    let mut out = String::with_capacity(digits.len());
+    for (offset, character) in digits.char_indices() {
+        match character {
+            c if c.is_ascii_hexdigit() => out.push(c),
+            '_' => {}
+            c if c.is_whitespace() => {}
+            character => {
+                let position = input[..prefix + offset].chars().count() + 1;
+                return Err(HexError::NonHex { character, position })
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Decode a byte string of any length, including the empty one (`0x`).
+pub fn decode_hex(input: &str) -> Result<Vec<u8>, HexError> {
+    let digits = hex_digits(input)?;
+    if digits.len() % 2 == 1 {
+        return Err(HexError::OddLength(digits.len()))
+    }
+    Ok(hex::decode(digits).expect("an even number of hex digits"))
+}
+
+/// Decode exactly `N` bytes, e.g. a 20-byte address.
+pub fn decode_hex_array<const N: usize>(input: &str) -> Result<[u8; N], HexError> {
+    let bytes = decode_hex(input)?;
+    let actual = bytes.len();
+    bytes.try_into().map_err(|_| HexError::Length { expected: N, actual })
+}
+
+/// Parse a big-endian number of at most 64 digits, a 32-byte field. Its digits need not be an
+/// even count, so `0x1` is one.
+pub fn parse_hex_u256(input: &str) -> Result<U256, HexError> {
+    let digits = hex_digits(input)?;
+    if digits.is_empty() {
+        return Err(HexError::Empty)
+    }
+    if digits.len() > 64 {
+        return Err(HexError::TooLong { digits: digits.len(), max_bytes: 32 })
+    }
+    Ok(U256::from_str_radix(&digits, 16).expect("at most 64 hex digits"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prefix_case_and_separators() {
+        let deadbeef = Ok(vec![0xde, 0xad, 0xbe, 0xef]);
+        for input in [
+            "0xdeadbeef",
+            "deadbeef",
+            "0XDEADBEEF",
+            "0xDeAdBeEf",
+            "0xdead_beef",
+            "de_ad_be_ef",
+            "0xdead beef",
+            "  0xdeadbeef\n",
+            "0x_dead\tbeef_",
+        ] {
+            assert_eq!(decode_hex(input), deadbeef, "{input:?}");
+        }
+        assert_eq!(hex_digits("0xAb_cD"), Ok("AbcD".to_string()));
+    }
+
+    #[test]
+    fn test_byte_strings() {
+        assert_eq!(decode_hex(""), Ok(vec![]));
+        assert_eq!(decode_hex("0x"), Ok(vec![]));
+        assert_eq!(decode_hex("0x_"), Ok(vec![]));
+        assert_eq!(decode_hex("0x0"), Err(HexError::OddLength(1)));
+        assert_eq!(decode_hex("0xabc"), Err(HexError::OddLength(3)));
+        assert_eq!(decode_hex("ab c"), Err(HexError::OddLength(3)));
+        assert_eq!(decode_hex("0xzz"), Err(HexError::NonHex { character: 'z', position: 3 }));
+        assert_eq!(decode_hex("12g4"), Err(HexError::NonHex { character: 'g', position: 3 }));
+        assert_eq!(decode_hex(" 0x1-"), Err(HexError::NonHex { character: '-', position: 5 }));
+        assert_eq!(decode_hex("0x0x12"), Err(HexError::NonHex { character: 'x', position: 4 }));
+        assert_eq!(decode_hex("0xé1"), Err(HexError::NonHex { character: 'é', position: 3 }));
+
+        assert_eq!(decode_hex_array::<2>("0x_12_34"), Ok([0x12, 0x34]));
+        assert_eq!(decode_hex_array::<2>("0x12"), Err(HexError::Length { expected: 2, actual: 1 }));
+        assert_eq!(
+            decode_hex_array::<2>("0x123456"),
+            Err(HexError::Length { expected: 2, actual: 3 })
+        );
+        assert_eq!(decode_hex_array::<2>("0x123"), Err(HexError::OddLength(3)));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse_hex_u256("0x1"), Ok(U256::from(1)));
+        assert_eq!(parse_hex_u256("2A"), Ok(U256::from(42)));
+        assert_eq!(parse_hex_u256("0x0"), Ok(U256::ZERO));
+        assert_eq!(parse_hex_u256("0x1_0000"), Ok(U256::from(0x10000)));
+        assert_eq!(parse_hex_u256(&format!("0x{}", "F".repeat(64))), Ok(U256::MAX));
+        assert_eq!(parse_hex_u256(&format!("0x{}1", "0".repeat(63))), Ok(U256::from(1)));
+
+        assert_eq!(parse_hex_u256(""), Err(HexError::Empty));
+        assert_eq!(parse_hex_u256("0x"), Err(HexError::Empty));
+        assert_eq!(parse_hex_u256("0x_ _"), Err(HexError::Empty));
+        assert_eq!(
+            parse_hex_u256(&format!("0x1{}", "0".repeat(64))),
+            Err(HexError::TooLong { digits: 65, max_bytes: 32 })
+        );
+        assert_eq!(
+            parse_hex_u256(&format!("0x{}1", "0".repeat(64))),
+            Err(HexError::TooLong { digits: 65, max_bytes: 32 }),
+            "leading zeros count towards the field width"
+        );
+        assert_eq!(parse_hex_u256("-1"), Err(HexError::NonHex { character: '-', position: 1 }));
+    }
+}
@@ -0,0 +1,374 @@
+//! BLS key generation, optionally seeded for reproducible fixtures.
+
+use crate::{
+    hex_util::{hex_digits, parse_hex_u256, HexError},
+    wallets::{parse_address, AddressError},
+};
+use alloy::{
+    primitives::{keccak256, uint, Address, U256},
+    signers::local::PrivateKeySigner,
+};
+use crypto_bigint::rand_core::{OsRng, RngCore};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use std::{collections::HashMap, fmt, str::FromStr};
+use sylow::{FieldExtensionTrait, Fp, Fr, G2Projective, GroupTrait, KeyPair};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// The BN254 group order r; secret keys must lie in `[1, r)`.
+pub const GROUP_ORDER: U256 =
+    uint!(0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001_U256);
+
+/// Why a secret key was rejected.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum KeyError {
+    #[error("`{input}` is not a hex scalar of at most 32 bytes: {reason}")]
+    InvalidHex { input: String, reason: HexError },
+    #[error("private key must be nonzero")]
+    Zero,
+    #[error("private key `{0}` is not below the BN254 group order r")]
+    NotBelowOrder(String),
+}
+
+/// Why a keys file could not be read.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum KeysFileError {
+    #[error("line {line}: expected `<wallet> <private key>`, got `{input}`")]
+    Malformed { line: usize, input: String },
+    #[error("line {line}: `{input}` is not a valid address: {reason}")]
+    InvalidWallet { line: usize, input: String, reason: AddressError },
+    #[error("line {line}: {source}")]
+    InvalidKey { line: usize, source: KeyError },
+    #[error("line {line}: wallet {wallet} was already given a different key")]
+    ConflictingKey { line: usize, wallet: Address },
+}
+
+/// Parse a secret key given as hex (with or without `0x`), rejecting zero and anything not
+/// below r.
+pub fn parse_secret_key(input: &str) -> Result<Fp, KeyError> {
+    let value = parse_hex_u256(input)
+        .map_err(|reason| KeyError::InvalidHex { input: input.to_string(), reason })?;
+    if value.is_zero() {
+        return Err(KeyError::Zero)
+    }
+    if value >= GROUP_ORDER {
+        return Err(KeyError::NotBelowOrder(input.to_string()))
+    }
+    Ok(Option::from(Fp::from_be_bytes(&value.to_be_bytes::<32>())).expect("r is below p"))
+}
+
+/// Parse a keys file: one `<wallet> <private key>` pair per line, separated by whitespace or a
+/// comma, with blank lines and `#` comments ignored. Line numbers in errors are 1-based.
+pub fn parse_keys_file(contents: &str) -> Result<Vec<(Address, Fp)>, KeysFileError> {
+    let mut keys: Vec<(Address, Fp)> = Vec::new();
+    let mut index: HashMap<Address, usize> = HashMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let input = line.split('#').next().unwrap_or_default().trim();
+        if input.is_empty() {
+            continue
+        }
+        let fields: Vec<&str> = input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .collect();
+        let [wallet, key] = fields[..] else {
+            return Err(KeysFileError::Malformed { line: line_number, input: input.to_string() })
+        };
+        let wallet = parse_address(wallet).map_err(|reason| KeysFileError::InvalidWallet {
+            line: line_number,
+            input: wallet.to_string(),
+            reason,
+        })?;
+        let key = parse_secret_key(key)
+            .map_err(|source| KeysFileError::InvalidKey { line: line_number, source })?;
+        match index.get(&wallet) {
+            Some(&existing) if keys[existing].1 != key => {
+                return Err(KeysFileError::ConflictingKey { line: line_number, wallet })
+            }
+            Some(_) => {}
+            None => {
+                index.insert(wallet, keys.len());
+                keys.push((wallet, key));
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// 32-byte master seed for deterministic key generation.
+///
+/// Decimal input is read as a `u64` and hex input as a big-endian number, both left-padded to 32
+/// bytes, so `42` and `0x2a` select the same keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Seed([u8; 32]);
+
+impl Seed {
+    /// ChaCha20 RNG for one wallet: the sub-seed is `keccak256(seed || wallet || purpose)` so
+    /// every wallet gets a distinct key that does not depend on its position in the wallet list.
+    /// The BLS key itself uses an empty `purpose`.
+    pub fn rng_for(&self, wallet: Address, purpose: &[u8]) -> ChaCha20Rng {
+        let sub_seed = keccak256([self.0.as_slice(), wallet.as_slice(), purpose].concat());
+        ChaCha20Rng::from_seed(sub_seed.0)
+    }
+}
+
+impl Seed {
+    /// The `index`-th secp256k1 wallet for this seed, from `keccak256(seed || "eth-wallet" ||
+    /// index || counter)` with the counter bumped in the (negligible) case the hash is not a
+    /// valid secp256k1 scalar.
+    fn eth_signer(&self, index: u64) -> PrivateKeySigner {
+        (0u64..)
+            .find_map(|counter| {
+                let preimage =
+                    [&self.0[..], b"eth-wallet", &index.to_be_bytes(), &counter.to_be_bytes()];
+                PrivateKeySigner::from_bytes(&keccak256(preimage.concat())).ok()
+            })
+            .expect("some counter yields a valid secp256k1 key")
+    }
+
+    /// The `index`-th synthetic wallet for this seed, the low 20 bytes of
+    /// `keccak256(seed || "synthetic-wallet" || index)`.
+    fn synthetic_wallet(&self, index: u64) -> Address {
+        let preimage = [&self.0[..], b"synthetic-wallet", &index.to_be_bytes()];
+        Address::from_word(keccak256(preimage.concat()))
+    }
+}
+
+impl FromStr for Seed {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut seed = [0u8; 32];
+        if input.starts_with("0x") || input.starts_with("0X") {
+            let digits = hex_digits(input)
+                .map_err(|error| format!("`{input}` is not a valid hex seed: {error}"))?;
+            let digits = if digits.len() % 2 == 1 { format!("0{digits}") } else { digits };
+            let bytes = hex::decode(&digits).expect("an even number of hex digits");
+            if bytes.is_empty() || bytes.len() > 32 {
+                return Err(format!("`{input}` must encode between 1 and 32 bytes"))
+            }
+            seed[32 - bytes.len()..].copy_from_slice(&bytes);
+        } else {
+            let value: u64 = input
+                .parse()
+                .map_err(|error| format!("`{input}` is not a valid u64 seed: {error}"))?;
+            seed[24..].copy_from_slice(&value.to_be_bytes());
+        }
+        Ok(Self(seed))
+    }
+}
+
+/// `count` fresh secp256k1 wallets whose addresses can sign the registration transaction,
+/// deterministic when a seed is given.
+pub fn generate_eth_wallets(seed: Option<&Seed>, count: u64) -> Vec<PrivateKeySigner> {
+    (0..count)
+        .map(|index| match seed {
+            Some(seed) => seed.eth_signer(index),
+            None => PrivateKeySigner::random(),
+        })
+        .collect()
+}
+
+/// `count` wallet addresses nobody holds a key for, for bulk vectors where only distinct senders
+/// matter; deterministic when a seed is given.
+pub fn synthetic_wallets(seed: Option<&Seed>, count: u64) -> Vec<Address> {
+    (0..count)
+        .map(|index| match seed {
+            Some(seed) => seed.synthetic_wallet(index),
+            None => {
+                let mut address = Address::ZERO;
+                OsRng.fill_bytes(address.as_mut_slice());
+                address
+            }
+        })
+        .collect()
+}
+
+/// RNG for auxiliary randomness of one wallet, seeded from `seed` when given.
+pub fn wallet_rng(seed: Option<&Seed>, wallet: Address, purpose: &[u8]) -> ChaCha20Rng {
+    match seed {
+        Some(seed) => seed.rng_for(wallet, purpose),
+        None => ChaCha20Rng::from_rng(OsRng).expect("OS RNG is available"),
+    }
+}
+
+/// Same derivation as `KeyPair::generate`: a uniform scalar mod r, lifted into Fp.
+pub fn random_secret_key(rng: &mut ChaCha20Rng) -> Fp {
+    Fp::new(Fr::rand(rng).value())
+}
+
+/// The key pair for an existing secret key.
+pub fn keypair_from_secret(secret_key: Fp) -> KeyPair {
+    KeyPair { secret_key, public_key: G2Projective::generator() * secret_key }
+}
+
+/// Generate a key pair, deterministically when a seed is given.
+pub fn generate_keypair(seed: Option<&Seed>, wallet: Address) -> KeyPair {
+    let Some(seed) = seed else { return KeyPair::generate() };
+    keypair_from_secret(random_secret_key(&mut seed.rng_for(wallet, b"")))
+}
+
+/// A secret key that is overwritten with zeros when dropped, so that keys which are generated but
+/// never exported do not linger in freed memory. `Fp` is `Copy`, so this covers the copies the
+/// library keeps (imported keys and each case's key pair), not the temporaries of one signature.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey([u8; 32]);
+
+impl SecretKey {
+    /// The scalar itself, for signing.
+    pub fn expose(&self) -> Fp {
+        Option::from(Fp::from_be_bytes(&self.0)).expect("a secret key is below r")
+    }
+}
+
+impl From<Fp> for SecretKey {
+    fn from(secret_key: Fp) -> Self {
+        Self(secret_key.to_be_bytes())
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// [`KeyPair`] with its secret in a [`SecretKey`].
+#[derive(Clone, Debug)]
+pub struct SecretKeyPair {
+    pub secret_key: SecretKey,
+    pub public_key: G2Projective,
+}
+
+impl From<KeyPair> for SecretKeyPair {
+    fn from(kp: KeyPair) -> Self {
+        Self { secret_key: kp.secret_key.into(), public_key: kp.public_key }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_seed_parsing() {
+        assert_eq!("0x2a".parse::<Seed>(), "42".parse::<Seed>());
+        assert_eq!("0x02a".parse::<Seed>(), "42".parse::<Seed>());
+        assert!("0x".parse::<Seed>().is_err());
+        assert!(format!("0x{}", "ff".repeat(33)).parse::<Seed>().is_err());
+        assert!("-1".parse::<Seed>().is_err());
+        assert!("0xzz".parse::<Seed>().is_err());
+    }
+
+    #[test]
+    fn test_parse_secret_key_bounds() {
+        const BASE_FIELD_MODULUS: U256 =
+            uint!(0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47_U256);
+        let one = U256::from(1);
+        let hex = |value: U256| format!("{value:#x}");
+        let accepted = [
+            ("1".to_string(), one),
+            ("0x01".to_string(), one),
+            ("0x2".to_string(), U256::from(2)),
+            (hex(GROUP_ORDER >> 1), GROUP_ORDER >> 1),
+            (hex(GROUP_ORDER - one), GROUP_ORDER - one),
+            (format!("0X{:X}", GROUP_ORDER - one), GROUP_ORDER - one),
+            ("0x_2a".to_string(), U256::from(42)),
+            (" 0x00_2A ".to_string(), U256::from(42)),
+        ];
+        for (input, value) in accepted {
+            let key = parse_secret_key(&input).unwrap_or_else(|error| panic!("{input}: {error}"));
+            assert_eq!(U256::from_be_bytes(key.to_be_bytes()), value, "{input}");
+
+            // The key signs, and the signature verifies under its public key.
+            let keypair = keypair_from_secret(key);
+            let hasher = crate::MessageHasher::default();
+            let signature = hasher.sign(crate::DEFAULT_DST_STAKE_MANAGER, b"boundary", key);
+            let hash = hasher.hash_to_curve(crate::DEFAULT_DST_STAKE_MANAGER, b"boundary");
+            let public_key = sylow::G2Affine::from(keypair.public_key);
+            assert!(crate::pairing_check(&signature, &hash, &public_key), "{input}");
+        }
+
+        let invalid =
+            |input: &str, reason| KeyError::InvalidHex { input: input.to_string(), reason };
+        let too_long = HexError::TooLong { digits: 65, max_bytes: 32 };
+        let rejected = [
+            ("0x0".to_string(), KeyError::Zero),
+            ("0".to_string(), KeyError::Zero),
+            (format!("0x{}", "0".repeat(64)), KeyError::Zero),
+            (hex(GROUP_ORDER), KeyError::NotBelowOrder(hex(GROUP_ORDER))),
+            (hex(GROUP_ORDER + one), KeyError::NotBelowOrder(hex(GROUP_ORDER + one))),
+            (hex(BASE_FIELD_MODULUS), KeyError::NotBelowOrder(hex(BASE_FIELD_MODULUS))),
+            (hex(U256::MAX), KeyError::NotBelowOrder(hex(U256::MAX))),
+            (format!("0x{}", "f".repeat(65)), invalid(&format!("0x{}", "f".repeat(65)), too_long)),
+            ("0x".to_string(), invalid("0x", HexError::Empty)),
+            ("".to_string(), invalid("", HexError::Empty)),
+            ("0xzz".to_string(), invalid("0xzz", HexError::NonHex { character: 'z', position: 3 })),
+            ("-1".to_string(), invalid("-1", HexError::NonHex { character: '-', position: 1 })),
+        ];
+        for (input, error) in rejected {
+            assert_eq!(parse_secret_key(&input), Err(error), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_keys_file() {
+        let wallet = Address::repeat_byte(1);
+        let contents = format!("# validators\n{wallet} 0x02\n\n{wallet},0x2  # repeat\n");
+        assert_eq!(parse_keys_file(&contents), Ok(vec![(wallet, Fp::from(2u64))]));
+
+        let error = parse_keys_file(&format!("{wallet} 0x02\n{wallet} 0x03")).unwrap_err();
+        assert_eq!(error, KeysFileError::ConflictingKey { line: 2, wallet });
+        let error = parse_keys_file(&format!("\n{wallet} 0x0")).unwrap_err();
+        assert_eq!(error, KeysFileError::InvalidKey { line: 2, source: KeyError::Zero });
+        let error = parse_keys_file(&format!("{wallet}")).unwrap_err();
+        assert!(matches!(error, KeysFileError::Malformed { line: 1, .. }), "{error}");
+        let error = parse_keys_file("0x1234 0x02").unwrap_err();
+        assert!(matches!(error, KeysFileError::InvalidWallet { line: 1, .. }), "{error}");
+    }
+
+    #[test]
+    fn test_synthetic_wallets() {
+        let seed: Seed = "42".parse().unwrap();
+        let wallets = synthetic_wallets(Some(&seed), 1000);
+        assert_eq!(synthetic_wallets(Some(&seed), 10), wallets[..10]);
+        assert_eq!(crate::dedup_wallets(wallets.clone()), wallets, "every address is distinct");
+        assert_ne!(synthetic_wallets(Some(&"43".parse().unwrap()), 1), wallets[..1]);
+        assert_ne!(synthetic_wallets(None, 1), synthetic_wallets(None, 1));
+    }
+
+    #[test]
+    fn test_eth_wallets() {
+        let seed: Seed = "42".parse().unwrap();
+        let wallets = generate_eth_wallets(Some(&seed), 3);
+        let again = generate_eth_wallets(Some(&seed), 2);
+        assert_eq!(
+            wallets[..2].iter().map(|w| w.address()).collect::<Vec<_>>(),
+            again.iter().map(|w| w.address()).collect::<Vec<_>>()
+        );
+        assert_ne!(wallets[0].address(), wallets[1].address());
+        assert_ne!(
+            generate_eth_wallets(None, 1)[0].address(),
+            generate_eth_wallets(None, 1)[0].address()
+        );
+    }
+
+    #[test]
+    fn test_secret_key_zeroizes() {
+        let mut key = SecretKey::from(Fp::from(42u64));
+        assert_eq!(key.expose(), Fp::from(42u64));
+        assert_eq!(format!("{key:?}"), "SecretKey(..)", "Debug does not print the key");
+        key.zeroize();
+        assert_eq!(key.0, [0; 32]);
+    }
+
+    #[test]
+    fn test_seeded_keys_are_per_wallet() {
+        let seed: Seed = "42".parse().unwrap();
+        let first = generate_keypair(Some(&seed), Address::repeat_byte(1));
+        let again = generate_keypair(Some(&seed), Address::repeat_byte(1));
+        let other = generate_keypair(Some(&seed), Address::repeat_byte(2));
+        assert_eq!(first.secret_key, again.secret_key);
+        assert_ne!(first.secret_key, other.secret_key);
+    }
+}
@@ -0,0 +1,294 @@
+//! EIP-2335-style encrypted keystores for BLS secret keys.
+//!
+//! The layout, KDFs (scrypt or PBKDF2-HMAC-SHA256), checksum (`sha256(dk[16..32] ||
+//! ciphertext)`) and cipher (AES-128-CTR keyed with `dk[0..16]`) follow EIP-2335. Two things
+//! differ because the keys are BN254 rather than BLS12-381: `pubkey` holds the 64-byte
+//! [`compress_g2`](crate::compress_g2) encoding, and an extra `wallet_address` field records
+//! which sender the key signs for so vectors can be regenerated from the keystore alone.
+
+use crate::{
+    compress::compress_g2,
+    hex_util::decode_hex,
+    keys::{keypair_from_secret, parse_secret_key, KeyError},
+    words::g2_to_words_solidity,
+};
+use aes::{
+    cipher::{KeyIvInit, StreamCipher},
+    Aes128,
+};
+use alloy::primitives::Address;
+use crypto_bigint::rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sylow::{Fp, G2Affine};
+use unicode_normalization::UnicodeNormalization;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+const DKLEN: usize = 32;
+
+/// Key derivation function and its cost parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kdf {
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    Pbkdf2 { c: u32 },
+}
+
+impl Kdf {
+    /// The scrypt parameters from the EIP-2335 test vectors (`n = 2^18, r = 8, p = 1`).
+    pub const SCRYPT: Self = Self::Scrypt { log_n: 18, r: 8, p: 1 };
+    /// The PBKDF2 parameters from the EIP-2335 test vectors (`c = 2^18`).
+    pub const PBKDF2: Self = Self::Pbkdf2 { c: 1 << 18 };
+}
+
+/// Why a keystore could not be decrypted.
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("checksum mismatch: wrong password or corrupted keystore")]
+    WrongPassword,
+    #[error("unsupported {kind} `{function}`")]
+    Unsupported { kind: &'static str, function: String },
+    #[error("invalid keystore field `{0}`")]
+    InvalidField(&'static str),
+    #[error(transparent)]
+    InvalidKey(#[from] KeyError),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeystoreModule {
+    pub function: String,
+    pub params: Value,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub kdf: KeystoreModule,
+    pub checksum: KeystoreModule,
+    pub cipher: KeystoreModule,
+}
+
+/// One encrypted key, serialized as the keystore JSON file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub crypto: KeystoreCrypto,
+    #[serde(default)]
+    pub description: String,
+    pub pubkey: String,
+    #[serde(default)]
+    pub path: String,
+    pub uuid: String,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_address: Option<String>,
+}
+
+/// EIP-2335 password processing: NFKD normalisation, then C0, C1 and delete control codes are
+/// stripped.
+fn process_password(password: &str) -> Vec<u8> {
+    password.nfkd().filter(|c| !c.is_control()).collect::<String>().into_bytes()
+}
+
+fn derive_key(kdf: &Kdf, password: &[u8], salt: &[u8]) -> Result<[u8; DKLEN], KeystoreError> {
+    let mut key = [0u8; DKLEN];
+    match *kdf {
+        Kdf::Scrypt { log_n, r, p } => {
+            let params = scrypt::Params::new(log_n, r, p)
+                .map_err(|_| KeystoreError::InvalidField("crypto.kdf.params"))?;
+            scrypt::scrypt(password, salt, &params, &mut key)
+                .map_err(|_| KeystoreError::InvalidField("crypto.kdf.params.dklen"))?;
+        }
+        Kdf::Pbkdf2 { c } => pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, c, &mut key),
+    }
+    Ok(key)
+}
+
+fn checksum(key: &[u8; DKLEN], ciphertext: &[u8]) -> [u8; 32] {
+    Sha256::new().chain_update(&key[16..]).chain_update(ciphertext).finalize().into()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// A random RFC 4122 version 4 UUID.
+fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = random_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Encrypt `secret_key` under `password` with a fresh salt and IV.
+pub fn encrypt_keystore(
+    secret_key: Fp,
+    wallet: Option<Address>,
+    password: &str,
+    kdf: &Kdf,
+) -> Result<Keystore, KeystoreError> {
+    let salt: [u8; 32] = random_bytes();
+    let iv: [u8; 16] = random_bytes();
+    let key = derive_key(kdf, &process_password(password), &salt)?;
+
+    let mut ciphertext = secret_key.to_be_bytes().to_vec();
+    Aes128Ctr::new(key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+
+    let kdf = match *kdf {
+        Kdf::Scrypt { log_n, r, p } => KeystoreModule {
+            function: "scrypt".into(),
+            params: json!({"dklen": DKLEN, "n": 1u64 << log_n, "r": r, "p": p, "salt": hex::encode(salt)}),
+            message: String::new(),
+        },
+        Kdf::Pbkdf2 { c } => KeystoreModule {
+            function: "pbkdf2".into(),
+            params: json!({"dklen": DKLEN, "c": c, "prf": "hmac-sha256", "salt": hex::encode(salt)}),
+            message: String::new(),
+        },
+    };
+    let public_key = G2Affine::from(keypair_from_secret(secret_key).public_key);
+    Ok(Keystore {
+        crypto: KeystoreCrypto {
+            kdf,
+            checksum: KeystoreModule {
+                function: "sha256".into(),
+                params: json!({}),
+                message: hex::encode(checksum(&key, &ciphertext)),
+            },
+            cipher: KeystoreModule {
+                function: "aes-128-ctr".into(),
+                params: json!({"iv": hex::encode(iv)}),
+                message: hex::encode(ciphertext),
+            },
+        },
+        description: "BN254 BLS key generated by bls-test-utils".into(),
+        pubkey: hex::encode(compress_g2(g2_to_words_solidity(&public_key))),
+        path: String::new(),
+        uuid: uuid_v4(),
+        version: 4,
+        wallet_address: wallet.map(|wallet| wallet.to_string()),
+    })
+}
+
+fn hex_param(
+    module: &KeystoreModule,
+    name: &str,
+    field: &'static str,
+) -> Result<Vec<u8>, KeystoreError> {
+    module
+        .params
+        .get(name)
+        .and_then(Value::as_str)
+        .and_then(|value| decode_hex(value).ok())
+        .ok_or(KeystoreError::InvalidField(field))
+}
+
+fn u32_param(
+    module: &KeystoreModule,
+    name: &str,
+    field: &'static str,
+) -> Result<u32, KeystoreError> {
+    module
+        .params
+        .get(name)
+        .and_then(Value::as_u64)
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or(KeystoreError::InvalidField(field))
+}
+
+/// Decrypt a keystore, checking the password against its checksum first.
+pub fn decrypt_keystore(keystore: &Keystore, password: &str) -> Result<Fp, KeystoreError> {
+    let crypto = &keystore.crypto;
+    let kdf = match crypto.kdf.function.as_str() {
+        "scrypt" => {
+            let n = u32_param(&crypto.kdf, "n", "crypto.kdf.params.n")?;
+            if !n.is_power_of_two() {
+                return Err(KeystoreError::InvalidField("crypto.kdf.params.n"))
+            }
+            Kdf::Scrypt {
+                log_n: n.trailing_zeros() as u8,
+                r: u32_param(&crypto.kdf, "r", "crypto.kdf.params.r")?,
+                p: u32_param(&crypto.kdf, "p", "crypto.kdf.params.p")?,
+            }
+        }
+        "pbkdf2" => Kdf::Pbkdf2 { c: u32_param(&crypto.kdf, "c", "crypto.kdf.params.c")? },
+        function => {
+            return Err(KeystoreError::Unsupported { kind: "kdf", function: function.into() })
+        }
+    };
+    if crypto.cipher.function != "aes-128-ctr" {
+        return Err(KeystoreError::Unsupported {
+            kind: "cipher",
+            function: crypto.cipher.function.clone(),
+        })
+    }
+    if crypto.checksum.function != "sha256" {
+        return Err(KeystoreError::Unsupported {
+            kind: "checksum",
+            function: crypto.checksum.function.clone(),
+        })
+    }
+
+    let salt = hex_param(&crypto.kdf, "salt", "crypto.kdf.params.salt")?;
+    let iv: [u8; 16] = hex_param(&crypto.cipher, "iv", "crypto.cipher.params.iv")?
+        .try_into()
+        .map_err(|_| KeystoreError::InvalidField("crypto.cipher.params.iv"))?;
+    let ciphertext = decode_hex(&crypto.cipher.message)
+        .map_err(|_| KeystoreError::InvalidField("crypto.cipher.message"))?;
+    let expected = decode_hex(&crypto.checksum.message)
+        .map_err(|_| KeystoreError::InvalidField("crypto.checksum.message"))?;
+
+    let key = derive_key(&kdf, &process_password(password), &salt)?;
+    if checksum(&key, &ciphertext)[..] != expected[..] {
+        return Err(KeystoreError::WrongPassword)
+    }
+    let mut secret = ciphertext;
+    Aes128Ctr::new(key[..16].into(), &iv.into()).apply_keystream(&mut secret);
+    Ok(parse_secret_key(&format!("0x{}", hex::encode(secret)))?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Cheap parameters so the tests do not spend seconds in the KDF.
+    const FAST_SCRYPT: Kdf = Kdf::Scrypt { log_n: 4, r: 8, p: 1 };
+    const FAST_PBKDF2: Kdf = Kdf::Pbkdf2 { c: 16 };
+
+    #[test]
+    fn test_round_trip() {
+        let secret_key = parse_secret_key("0x1234").unwrap();
+        for kdf in [FAST_SCRYPT, FAST_PBKDF2] {
+            let keystore =
+                encrypt_keystore(secret_key, Some(Address::repeat_byte(1)), "𝔱𝔢𝔰𝔱", &kdf).unwrap();
+            let json = serde_json::to_string(&keystore).unwrap();
+            let keystore: Keystore = serde_json::from_str(&json).unwrap();
+            assert_eq!(decrypt_keystore(&keystore, "𝔱𝔢𝔰𝔱").unwrap(), secret_key);
+            // NFKD folds the fraktur letters to ASCII, as EIP-2335 requires.
+            assert_eq!(decrypt_keystore(&keystore, "test").unwrap(), secret_key);
+            assert_eq!(keystore.wallet_address, Some(Address::repeat_byte(1).to_string()));
+        }
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let keystore = encrypt_keystore(Fp::ONE, None, "correct", &FAST_PBKDF2).unwrap();
+        assert!(matches!(
+            decrypt_keystore(&keystore, "incorrect"),
+            Err(KeystoreError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_modules() {
+        let mut keystore = encrypt_keystore(Fp::ONE, None, "pw", &FAST_SCRYPT).unwrap();
+        keystore.crypto.cipher.function = "aes-256-gcm".into();
+        assert!(matches!(
+            decrypt_keystore(&keystore, "pw"),
+            Err(KeystoreError::Unsupported { kind: "cipher", .. })
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+//! BN254 BLS proof-of-possession test vectors for the StakeManager and ValidatorManager
+//! contracts.
+//!
+//! The `bls-test-utils` binary is a thin CLI over [`generate_case`]; other crates can depend on
+//! this library to build the same fixtures in-process.
+
+pub mod aggregate;
+#[cfg(feature = "ark-cross-check")]
+pub mod ark;
+pub mod attack;
+pub mod binding;
+pub mod calldata;
+pub mod compress;
+pub mod conventions;
+pub mod csv;
+pub mod edge_case;
+pub mod eip712;
+pub mod expander;
+pub mod fixture;
+pub mod format;
+pub mod foundry;
+pub mod generate;
+pub mod hash_to_curve;
+pub mod hex_util;
+pub mod keys;
+pub mod keystore;
+pub mod mnemonic;
+pub mod padded;
+pub mod precompile;
+pub mod rotate;
+pub mod sign;
+pub mod solidity;
+pub mod split;
+pub mod threshold;
+pub mod verify;
+pub mod wallets;
+pub mod words;
+
+pub use aggregate::*;
+#[cfg(feature = "ark-cross-check")]
+pub use ark::*;
+pub use attack::*;
+pub use binding::*;
+pub use calldata::*;
+pub use compress::*;
+pub use conventions::*;
+pub use csv::*;
+pub use edge_case::*;
+pub use eip712::*;
+pub use expander::*;
+pub use fixture::*;
+pub use format::*;
+pub use foundry::*;
+pub use generate::*;
+pub use hash_to_curve::*;
+pub use hex_util::*;
+pub use keys::*;
+pub use keystore::*;
+pub use mnemonic::*;
+pub use padded::*;
+pub use precompile::*;
+pub use rotate::*;
+pub use sign::*;
+pub use solidity::*;
+pub use split::*;
+pub use threshold::*;
+pub use verify::*;
+pub use wallets::*;
+pub use words::*;
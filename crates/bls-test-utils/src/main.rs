@@ -1,150 +1,2239 @@
 use alloy::{
-    primitives::{Address, U256},
-    sol_types::SolValue,
+    primitives::{Address, Selector, U256},
+    signers::local::PrivateKeySigner,
 };
-use serde::{Deserialize, Serialize};
-use sha3::Keccak256;
-use std::{fs, str::FromStr};
-use sylow::{Fp, G1Affine, G2Affine, GroupTrait, KeyPair, XMDExpander};
-
-const DST: &str = "StakeManager:BN254:PoP:v1:";
-const DST_VALIDATOR_MANAGER: &str = "ValidatorManager:BN254:PoP:v1:";
-
-#[derive(Serialize, Deserialize)]
-struct ProofData {
-    message_hash_stake_manager: [String; 2],
-    message_hash_validator_manager: [String; 2],
-    proof_of_possession_stake_manager: [String; 2],
-    proof_of_possession_validator_manager: [String; 2],
-    chain_id: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct BlsTestData {
-    private_key: String,
-    public_key: [String; 4],
-    wallet_address: String,
-    domain_staking_manager: String,
-    domain_validator_manager: String,
-    proof: Vec<ProofData>,
-}
-
-fn u256_to_0x(x: U256) -> String {
-    format!("0x{}", hex::encode(x.to_be_bytes::<32>()))
-}
-
-fn fp_to_hex(x: Fp) -> String {
-    format!("0x{}", hex::encode(x.to_be_bytes()))
-}
-
-fn g1_to_words(p: &G1Affine) -> [U256; 2] {
-    let b = p.to_be_bytes();
-    [U256::from_be_slice(&b[0..32]), U256::from_be_slice(&b[32..64])]
-}
-
-/// Return limbs in Solidity order: [x_re, x_im, y_re, y_im].
-/// Sylow bytes are [x.c0, x.c1, y.c0, y.c1] with c0=imag, c1=real.
-fn g2_to_words_solidity(p: &G2Affine) -> [U256; 4] {
-    let b = p.to_be_bytes();
-    let x_im = U256::from_be_slice(&b[0..32]);
-    let x_re = U256::from_be_slice(&b[32..64]);
-    let y_im = U256::from_be_slice(&b[64..96]);
-    let y_re = U256::from_be_slice(&b[96..128]);
-    [x_re, x_im, y_re, y_im]
-}
-
-fn generate_single_case(wallet_address: &str, chain_ids: &[U256]) -> BlsTestData {
-    let kp: KeyPair = KeyPair::generate();
-
-    let pk_affine: G2Affine = G2Affine::from(kp.public_key);
-    let pk_words = g2_to_words_solidity(&pk_affine);
-
-    let sender = Address::from_str(wallet_address).expect("address");
-    let mut proof_data: Vec<ProofData> = Vec::new();
-
-    for chain_id in chain_ids {
-        let message_bytes = (chain_id, pk_words[0], pk_words[1], pk_words[2], pk_words[3], sender)
-            .abi_encode_packed();
-
-        let expander_stake_manager = XMDExpander::<Keccak256>::new(DST.as_bytes(), 96);
-        let expander_validator_manager =
-            XMDExpander::<Keccak256>::new(DST_VALIDATOR_MANAGER.as_bytes(), 96);
-
-        // H2C and PoP signature
-        let curve_stake_manager: G1Affine =
-            G1Affine::hash_to_curve(&expander_stake_manager, &message_bytes)
-                .expect("Unable to create has from curve");
-        let curve_validator_manager: G1Affine =
-            G1Affine::hash_to_curve(&expander_validator_manager, &message_bytes)
-                .expect("Unable to create has from curve");
-        let msg_xy_stake_manager = g1_to_words(&curve_stake_manager);
-        let msg_xy_validator_manager = g1_to_words(&curve_validator_manager);
-
-        let signature_stake_manager: G1Affine =
-            G1Affine::sign_message(&expander_stake_manager, &message_bytes, kp.secret_key.clone())
-                .expect("Unable to sign message");
-        let signature_validator_manager: G1Affine =
-            G1Affine::sign_message(&expander_validator_manager, &message_bytes, kp.secret_key)
-                .expect("Unable to sign message");
-
-        let sig_xy_stake_manager = g1_to_words(&signature_stake_manager);
-        let sig_xy_validator_manager = g1_to_words(&signature_validator_manager);
-        proof_data.push(ProofData {
-            chain_id: (*chain_id).to_string(),
-            proof_of_possession_stake_manager: [
-                u256_to_0x(sig_xy_stake_manager[0]),
-                u256_to_0x(sig_xy_stake_manager[1]),
-            ],
-            proof_of_possession_validator_manager: [
-                u256_to_0x(sig_xy_validator_manager[0]),
-                u256_to_0x(sig_xy_validator_manager[1]),
-            ],
-            message_hash_stake_manager: [
-                u256_to_0x(msg_xy_stake_manager[0]),
-                u256_to_0x(msg_xy_stake_manager[1]),
-            ],
-            message_hash_validator_manager: [
-                u256_to_0x(msg_xy_validator_manager[0]),
-                u256_to_0x(msg_xy_validator_manager[1]),
-            ],
-        });
-    }
-
-    /*  // Local pairing check
-    let lhs = pairing(&G1Projective::from(sig), &G2Projective::from(G2Affine::generator()));
-    let rhs = pairing(&G1Projective::from(h), &G2Projective::from(pk_affine));
-    if lhs != rhs {
-        eprintln!("WARNING: pairing check failed for {}", wallet_address);
-    } */
-
-    BlsTestData {
-        private_key: fp_to_hex(kp.secret_key),
-        public_key: [
-            u256_to_0x(pk_words[0]),
-            u256_to_0x(pk_words[1]),
-            u256_to_0x(pk_words[2]),
-            u256_to_0x(pk_words[3]),
+use bls_test_utils::{
+    bls_secret_from_eth_key, check_expand_len, decode_hex, decrypt_keystore, dedup_wallets,
+    derive_eth_wallets, encrypt_keystore, existing_cases, foundry_fixture, generate_aggregate,
+    generate_cases, generate_eth_wallets, generate_keypair, generate_rotations, generate_threshold,
+    load_fixture, parse_address, parse_keys_file, parse_secret_key, parse_wallets, render_solidity,
+    sign_message, synthetic_wallets, verify_fixture, ArrayWriter, BlsTestData, ChainIdWidth,
+    Domain, FixtureWriter, GenOptions, HashFunction, Kdf, Keystore, LoadError, MessageEncoding,
+    MessageHasher, NumberFormat, OutputFormat, PopStyle, Prehash, Preimage, PublicKeySum,
+    RegisterCall, SecretKey, Seed, SplitWriter, DEFAULT_DERIVATION, DEFAULT_DST_ROTATE,
+    UNIFORM_BYTES_LEN,
+};
+use clap::{Parser, Subcommand};
+use eyre::{eyre, WrapErr};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use sylow::Fp;
+
+/// Wallets used when no `--wallet` is passed, kept so existing fixtures can be regenerated.
+const DEFAULT_WALLETS: [&str; 5] = [
+    "0x328809Bc894f92807417D2dAD6b7C998c1aFdac6",
+    "0x1D96F2f6BeF1202E4Ce1Ff6Dad0c2CB002861d3e",
+    "0xcDFdF57D10EA95520a2CF09119Db2d2afa6F6bf7",
+    "0x52d4630789F63F9C715a2D30fCe65727D009f8d9",
+    "0x5898751917a8482c6FEb4D20b6e6C7442716Fd96",
+];
+/// Chain ids used when no `--chain-id` is passed (Base, Ethereum mainnet).
+const DEFAULT_CHAIN_IDS: [u64; 2] = [8453, 1];
+const DEFAULT_OUT: &str = "bls_test_data.json";
+const DEFAULT_AGGREGATE_OUT: &str = "bls_aggregate_test_data.json";
+const DEFAULT_ROTATE_OUT: &str = "bls_rotation_test_data.json";
+const DEFAULT_SIGN_OUT: &str = "bls_signature.json";
+const DEFAULT_THRESHOLD_OUT: &str = "bls_threshold_test_data.json";
+/// `--out` value that writes to stdout instead of a file.
+const STDOUT: &str = "-";
+/// Wallets generated (in parallel) before their entries are written out, which bounds memory
+/// for large `--count` runs.
+const CHUNK_SIZE: usize = 256;
+
+/// Generate BN254 BLS proof-of-possession test vectors for the StakeManager and
+/// ValidatorManager contracts.
+#[derive(Parser, Debug)]
+#[command(name = "bls-test-utils", version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Wallet address used as the PoP sender. Repeat for multiple wallets.
+    #[arg(long = "wallet", value_name = "ADDRESS", value_parser = parse_wallet)]
+    wallets: Vec<Address>,
+    /// File of wallet addresses, one per line or as a JSON array. Combined with `--wallet`;
+    /// duplicates are dropped.
+    #[arg(long, value_name = "PATH")]
+    wallets_file: Option<PathBuf>,
+    /// Existing BLS secret key (hex, nonzero and below r) for the `--wallet` at the same
+    /// position. If given, there must be exactly one per `--wallet`.
+    #[arg(long = "private-key", value_name = "HEX", value_parser = parse_private_key)]
+    private_keys: Vec<Fp>,
+    /// File of `<wallet> <private key>` pairs, one per line. Those wallets are signed for with the
+    /// given keys instead of generated ones.
+    #[arg(long, value_name = "PATH")]
+    keys_file: Option<PathBuf>,
+    /// Encrypted keystore written by `--export-keystore`. Its wallet is signed for with the
+    /// decrypted key. Repeat for multiple keystores.
+    #[arg(long = "import-keystore", value_name = "PATH")]
+    import_keystores: Vec<PathBuf>,
+    /// Also write every wallet's BLS key to `<DIR>/<wallet>.json` as an encrypted keystore.
+    #[arg(long, value_name = "DIR", conflicts_with = "rotate")]
+    export_keystore: Option<PathBuf>,
+    /// Also write every entry's BLS `private_key`, and the `eth_private_key` of generated and
+    /// derived wallets. Without it the output holds no secrets.
+    #[arg(long)]
+    include_secrets: bool,
+    /// Keystore password for `--export-keystore` and `--import-keystore`. Prompted for on stdin
+    /// when not given.
+    #[arg(long, value_name = "PASSWORD")]
+    password: Option<String>,
+    /// Key derivation function for exported keystores: `scrypt` or `pbkdf2`.
+    #[arg(long, value_name = "KDF", default_value = "scrypt", value_parser = parse_kdf)]
+    kdf: Kdf,
+    /// Domain separation tag for StakeManager PoPs. Defaults to `StakeManager:BN254:PoP:v1:`, or
+    /// `StakeManager:BN254:PoP:pubkey:v1:` with `--pop-style pubkey`.
+    #[arg(long, value_name = "DST", value_parser = parse_dst)]
+    dst_stake_manager: Option<String>,
+    /// Domain separation tag for ValidatorManager PoPs. Defaults to
+    /// `ValidatorManager:BN254:PoP:v1:`, or `ValidatorManager:BN254:PoP:pubkey:v1:` with
+    /// `--pop-style pubkey`.
+    #[arg(long, value_name = "DST", value_parser = parse_dst)]
+    dst_validator_manager: Option<String>,
+    /// Append the decimal chain id and a colon to both DSTs (e.g.
+    /// `StakeManager:BN254:PoP:v1:8453:`) and record the DSTs used on every proof.
+    #[arg(long)]
+    dst_per_chain: bool,
+    /// Also generate this many secp256k1 wallets and sign for their addresses, writing each
+    /// wallet's key to `eth_private_key` so tests can send the registration from it.
+    #[arg(long, value_name = "COUNT", default_value_t = 0)]
+    generate_wallets: u64,
+    /// Sign for the first `--count` accounts of this English BIP-39 mnemonic instead of synthetic
+    /// wallets, writing each account's key to `eth_private_key`. Their BLS keys are derived from
+    /// the account keys, so the same mnemonic always yields the same fixture.
+    #[arg(long, value_name = "WORDS")]
+    mnemonic: Option<String>,
+    /// With `--mnemonic`, the BIP-32 path the accounts are numbered under: account `i` is
+    /// `<PATH>/i`.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_DERIVATION, requires = "mnemonic")]
+    derivation: String,
+    /// Generate this many synthetic validators instead of using supplied wallets: fresh BLS keys
+    /// for derived addresses that nobody holds a key for. Entries are written as they are
+    /// generated. With `--mnemonic`, the number of accounts derived from it instead.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        conflicts_with_all = [
+            "wallets", "wallets_file", "private_keys", "keys_file", "import_keystores",
+            "generate_wallets", "aggregate", "emit_solidity",
+        ],
+    )]
+    count: u64,
+    /// ABI encoding of the PoP preimage: `packed` (`abi.encodePacked`) or `standard`
+    /// (`abi.encode`).
+    #[arg(long, value_name = "ENCODING", default_value_t = MessageEncoding::Packed)]
+    encoding: MessageEncoding,
+    /// Solidity type of the chain id in packed preimages: `256` (`uint256`, 32 bytes) or `64`
+    /// (`uint64`, 8 bytes). Every `--chain-id` must fit.
+    #[arg(
+        long,
+        value_name = "BITS",
+        default_value_t = ChainIdWidth::U256,
+        conflicts_with = "rotate"
+    )]
+    chain_id_width: ChainIdWidth,
+    /// What each PoP signs: `message` (chain id, public key and sender, once per `--chain-id`)
+    /// or `pubkey` (only the 128-byte public key, once per key).
+    #[arg(long, value_name = "STYLE", default_value_t = PopStyle::Message)]
+    pop_style: PopStyle,
+    /// Hash `expand_message_xmd` is built on in both domains: `keccak256` or `sha256`.
+    #[arg(long = "hash", value_name = "HASH", default_value_t = HashFunction::Keccak256)]
+    hash_function: HashFunction,
+    /// `expand_message_xmd` output length in bytes; sylow only supports 96.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = UNIFORM_BYTES_LEN,
+        value_parser = parse_expand_len
+    )]
+    expand_len: usize,
+    /// Append a registration nonce to every PoP preimage and record it on each proof. Wallets
+    /// take consecutive nonces, the same on every chain id.
+    #[arg(long, conflicts_with = "rotate")]
+    with_nonce: bool,
+    /// With `--with-nonce`, the nonce of the first wallet. Defaults to 0.
+    #[arg(long, value_name = "NONCE", requires = "with_nonce")]
+    nonce_start: Option<U256>,
+    /// What each `message` PoP hashes to the curve: `bytes` (the encoded message) or `eip712`
+    /// (the digest of a `PopRegistration` of the chain id, public key, sender, nonce and
+    /// `--expiry`). Wallets take consecutive nonces as with `--with-nonce`.
+    #[arg(long, value_name = "PREIMAGE", default_value_t = Preimage::Bytes, conflicts_with = "rotate")]
+    preimage: Preimage,
+    /// With `--preimage eip712`, the expiry signed into every registration. Defaults to
+    /// `type(uint256).max`.
+    #[arg(long, value_name = "TIMESTAMP")]
+    expiry: Option<U256>,
+    /// What is hashed to the curve in place of the preimage: `raw` (the preimage itself) or
+    /// `eip191` (`keccak256("\x19Ethereum Signed Message:\n" || len || preimage)`, recorded as
+    /// each proof's `eip191_digest`).
+    #[arg(long, value_name = "PREHASH", default_value_t = Prehash::Raw, conflicts_with = "rotate")]
+    prehash: Prehash,
+    /// Chain id to sign a PoP for. Repeat for multiple chains.
+    #[arg(long = "chain-id", value_name = "CHAIN_ID")]
+    chain_ids: Vec<U256>,
+    /// Path the generated vectors are written to, or `-` for stdout. Defaults to
+    /// `bls_test_data.json`, or `bls_aggregate_test_data.json` with `--aggregate`.
+    #[arg(long, value_name = "PATH")]
+    out: Option<PathBuf>,
+    /// Write each wallet's entry to `<DIR>/<wallet>.json` and an `index.json` of the wallets
+    /// and chain ids instead of one file. Each wallet must have a single entry.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = [
+            "out", "format", "append", "aggregate", "rotate", "include_attacks", "edge_cases",
+            "include_chain_replays",
         ],
-        proof: proof_data,
-        wallet_address: wallet_address.to_string(),
-        domain_staking_manager: DST.to_string(),
-        domain_validator_manager: DST_VALIDATOR_MANAGER.to_string(),
-    }
-}
-
-fn main() {
-    let wallets = [
-        "0x328809Bc894f92807417D2dAD6b7C998c1aFdac6",
-        "0x1D96F2f6BeF1202E4Ce1Ff6Dad0c2CB002861d3e",
-        "0xcDFdF57D10EA95520a2CF09119Db2d2afa6F6bf7",
-        "0x52d4630789F63F9C715a2D30fCe65727D009f8d9",
-        "0x5898751917a8482c6FEb4D20b6e6C7442716Fd96",
-    ];
-    let chain_ids = &[U256::from(8453), U256::from(1)];
-    let mut out = Vec::with_capacity(wallets.len());
-    for wallet in wallets {
-        out.push(generate_single_case(wallet, chain_ids));
-    }
-    fs::write(format!("bls_test_data.json"), serde_json::to_string_pretty(&out).unwrap())
-        .expect("write");
+    )]
+    split_output: Option<PathBuf>,
+    /// Output format: `json`, `yaml`, `toml`, or `csv` for one row per PoP, which is not read
+    /// back. The default path's extension follows it.
+    #[arg(long, value_name = "FORMAT", default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+    /// Shape of the JSON entries: `default`, or `foundry` for a sorted, flattened variant that
+    /// `abi.decode(vm.parseJson(...), (Fixture))` accepts.
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        default_value = "default",
+        value_parser = parse_layout,
+        conflicts_with = "aggregate"
+    )]
+    layout: Layout,
+    /// Pretty-print the JSON output (default).
+    #[arg(long, overrides_with = "compact")]
+    pretty: bool,
+    /// Write the JSON output on a single line.
+    #[arg(long, overrides_with = "pretty")]
+    compact: bool,
+    /// Write limbs (public keys, message hashes and PoPs) as `0x` hex or as decimal strings,
+    /// recorded as the top-level `number_format`.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value_t = NumberFormat::Hex,
+        conflicts_with_all = ["aggregate", "rotate", "layout"]
+    )]
+    number_format: NumberFormat,
+    /// Merge the new entries into the existing `--out` fixture (of any schema version) instead
+    /// of overwriting it. Fails if a wallet already has entries, unless `--replace-existing`.
+    #[arg(long, conflicts_with_all = ["aggregate", "rotate", "layout"])]
+    append: bool,
+    /// With `--append`, drop the existing entries of regenerated wallets instead of failing.
+    #[arg(long, requires = "append")]
+    replace_existing: bool,
+    /// Derive BLS keys deterministically from this seed (decimal u64 or 0x-prefixed hex of up
+    /// to 32 bytes). Without it keys are drawn from the OS RNG.
+    #[arg(long, value_name = "SEED")]
+    seed: Option<Seed>,
+    /// Skip the local pairing check on every generated PoP.
+    #[arg(long)]
+    skip_verify: bool,
+    /// Also emit negative vectors (`valid: false`) for every chain id, see `InvalidReason`.
+    #[arg(long)]
+    include_invalid: bool,
+    /// Also emit, per wallet, entries with an off-curve, wrong-subgroup and infinity public key,
+    /// tagged with `attack`. Each is confirmed to fail sylow's checks before it is written.
+    #[arg(long, conflicts_with = "aggregate")]
+    include_attacks: bool,
+    /// Also emit, per wallet, entries with an identity public key, an identity signature and
+    /// signatures offset by small multiples of the G1 generator, tagged with `attack` and their
+    /// `expected_naive_result`.
+    #[arg(long, conflicts_with = "aggregate")]
+    edge_cases: bool,
+    /// Also emit, per wallet and ordered pair of chain ids `(a, b)`, the PoP signed for `a`
+    /// labelled with chain id `b`, tagged with `attack: "chain_replay"`.
+    #[arg(long, conflicts_with_all = ["aggregate", "dst_per_chain"])]
+    include_chain_replays: bool,
+    /// Also write compressed public keys (64 bytes) and signatures (32 bytes) next to the limbs.
+    #[arg(long)]
+    compressed: bool,
+    /// Also write public keys in the EIP-2537-style padded encoding: 256 bytes, each limb
+    /// left-padded to 64.
+    #[arg(long)]
+    padded: bool,
+    /// Also write each PoP's 384-byte input to the BN254 pairing precompile (`0x08`).
+    #[arg(long)]
+    pairing_input: bool,
+    /// Also write the G1 sums of both domains' message hashes and PoPs, checked with one pairing.
+    #[arg(long)]
+    combined: bool,
+    /// Also write every hash-to-curve stage of each message hash: the expanded bytes, u0/u1,
+    /// Q0/Q1 and their sum.
+    #[arg(long)]
+    include_intermediates: bool,
+    /// Also write, per PoP, the StakeManager `registerValidator(uint256[4],uint256[2])` calldata
+    /// for the entry's public key and StakeManager PoP.
+    #[arg(long)]
+    calldata: bool,
+    /// With `--calldata`, use this 4-byte function selector instead.
+    #[arg(long, value_name = "HEX", requires = "calldata", conflicts_with = "signature")]
+    selector: Option<Selector>,
+    /// With `--calldata`, take the selector from this Solidity signature, e.g.
+    /// `register(uint256[4],uint256[2])`. It must take a `uint256[4]` and a `uint256[2]`.
+    #[arg(long, value_name = "SIGNATURE", requires = "calldata", value_parser = parse_register_signature)]
+    signature: Option<RegisterCall>,
+    /// Also write the vectors as a `BlsTestVectors` Solidity library to this path.
+    #[arg(long, value_name = "PATH")]
+    emit_solidity: Option<PathBuf>,
+    /// Number of threads generating vectors; 0 uses one per CPU. The output does not depend on
+    /// it.
+    #[arg(long, short = 'j', value_name = "N", default_value_t = 0)]
+    jobs: usize,
+    /// Aggregate the PoPs of all wallets on a single `--chain-id` into one signature and public
+    /// key per domain.
+    #[arg(long)]
+    aggregate: bool,
+    /// Give every wallet a second key and have its current key sign the rotation to it on
+    /// every chain id, writing rotation vectors instead of PoPs.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "aggregate",
+            "include_attacks",
+            "edge_cases",
+            "include_chain_replays",
+            "dst_per_chain",
+            "emit_solidity",
+            "layout",
+        ]
+    )]
+    rotate: bool,
+    /// Domain separation tag for rotation signatures. Defaults to
+    /// `ValidatorManager:BN254:Rotate:v1:`.
+    #[arg(long, value_name = "DST", value_parser = parse_dst, requires = "rotate")]
+    dst_rotate: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Re-check an existing fixture: re-derive every PoP message and hash-to-curve result and run
+    /// both pairing checks. Exits non-zero if any entry does not check out.
+    Verify {
+        /// Fixture written by this tool, e.g. `bls_test_data.json`, or a `--split-output`
+        /// directory.
+        path: PathBuf,
+    },
+    /// Sign an arbitrary message and write it with its hash-to-curve point, the signature and
+    /// the public key. The pairing check runs before anything is written.
+    Sign(SignArgs),
+    /// Split a master key into `--shares` Shamir shares with a trusted dealer, sign a message
+    /// with every share and combine `--threshold` of the partial signatures into the master
+    /// signature. The combined signature is checked against the group key before writing.
+    Threshold(ThresholdArgs),
+}
+
+/// Arguments of the `sign` subcommand. The key is `--private-key`, the key of
+/// `--import-keystore`, the one `--seed` derives, or else a random one.
+#[derive(clap::Args, Debug)]
+struct SignArgs {
+    /// Bytes to sign: `0x`-prefixed hex, or the path of a file whose contents are signed.
+    #[arg(long, value_name = "HEX|PATH")]
+    message: String,
+    /// Domain separation tag the message is hashed under.
+    #[arg(long, value_name = "DST", value_parser = parse_dst)]
+    dst: String,
+    /// Existing BLS secret key (hex, nonzero and below r).
+    #[arg(
+        long,
+        value_name = "HEX",
+        value_parser = parse_private_key,
+        conflicts_with_all = ["import_keystore", "seed"],
+    )]
+    private_key: Option<Fp>,
+    /// Encrypted keystore to sign with, e.g. one written by `--export-keystore`.
+    #[arg(long, value_name = "PATH", conflicts_with = "seed")]
+    import_keystore: Option<PathBuf>,
+    /// Keystore password for `--import-keystore`. Prompted for on stdin when not given.
+    #[arg(long, value_name = "PASSWORD", requires = "import_keystore")]
+    password: Option<String>,
+    /// Derive the key from this seed, as the generator does.
+    #[arg(long, value_name = "SEED")]
+    seed: Option<Seed>,
+    /// With `--seed`, sign with the key this wallet's fixture entry gets. Defaults to the zero
+    /// address.
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_wallet, requires = "seed")]
+    wallet: Option<Address>,
+    /// Path the signature is written to, or `-` for stdout.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_SIGN_OUT)]
+    out: PathBuf,
+    /// Write the JSON output on a single line.
+    #[arg(long)]
+    compact: bool,
+}
+
+/// Arguments of the `threshold` subcommand.
+#[derive(clap::Args, Debug)]
+struct ThresholdArgs {
+    /// Partial signatures needed to reconstruct the signature (`t`).
+    #[arg(long, value_name = "T")]
+    threshold: u64,
+    /// Shares the master key is split into (`n`).
+    #[arg(long, value_name = "N")]
+    shares: u64,
+    /// Comma-separated 1-based share indices whose partial signatures are combined, at least
+    /// `--threshold` of them. Defaults to the first `--threshold` shares.
+    #[arg(long, value_name = "INDEX,...", value_delimiter = ',')]
+    signers: Vec<u64>,
+    /// Bytes to sign: `0x`-prefixed hex, or the path of a file whose contents are signed.
+    #[arg(long, value_name = "HEX|PATH")]
+    message: String,
+    /// Domain separation tag the message is hashed under.
+    #[arg(long, value_name = "DST", value_parser = parse_dst)]
+    dst: String,
+    /// Seed for the dealer's polynomial. Random when not given.
+    #[arg(long, value_name = "SEED")]
+    seed: Option<Seed>,
+    /// Path the vectors are written to, or `-` for stdout.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_THRESHOLD_OUT)]
+    out: PathBuf,
+    /// Write the JSON output on a single line.
+    #[arg(long)]
+    compact: bool,
+}
+
+impl SignArgs {
+    fn secret_key(&self) -> eyre::Result<Fp> {
+        if let Some(secret_key) = self.private_key {
+            return Ok(secret_key)
+        }
+        if let Some(path) = &self.import_keystore {
+            let password = match &self.password {
+                Some(password) => password.clone(),
+                None => prompt_password()?,
+            };
+            return decrypt_keystore(&parse_keystore(path)?, &password)
+                .wrap_err_with(|| format!("decrypting {}", path.display()))
+        }
+        Ok(generate_keypair(self.seed.as_ref(), self.wallet.unwrap_or_default()).secret_key)
+    }
+}
+
+impl Cli {
+    /// Imported keys from `--private-key` (paired with `--wallet`), `--keys-file` and
+    /// `--import-keystore`, and those of the `derived` `--mnemonic` accounts.
+    fn keys(
+        &self,
+        password: Option<&str>,
+        derived: &[PrivateKeySigner],
+    ) -> eyre::Result<HashMap<Address, SecretKey>> {
+        let mut pairs = Vec::new();
+        if !self.private_keys.is_empty() {
+            if self.private_keys.len() != self.wallets.len() {
+                return Err(eyre!(
+                    "--private-key is paired with --wallet by position: got {} keys for {} wallets",
+                    self.private_keys.len(),
+                    self.wallets.len()
+                ))
+            }
+            pairs.extend(self.wallets.iter().copied().zip(self.private_keys.iter().copied()));
+        }
+        if let Some(path) = &self.keys_file {
+            let contents =
+                fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+            pairs.extend(
+                parse_keys_file(&contents)
+                    .wrap_err_with(|| format!("parsing {}", path.display()))?,
+            );
+        }
+        for path in &self.import_keystores {
+            let (wallet, keystore) = read_keystore(path)?;
+            let password = password.expect("password is read when keystores are imported");
+            let key = decrypt_keystore(&keystore, password)
+                .wrap_err_with(|| format!("decrypting {}", path.display()))?;
+            pairs.push((wallet, key));
+        }
+        pairs.extend(
+            derived.iter().map(|signer| (signer.address(), bls_secret_from_eth_key(signer))),
+        );
+
+        let mut keys = HashMap::new();
+        for (wallet, key) in pairs {
+            match keys.insert(wallet, key) {
+                Some(existing) if existing != key => {
+                    return Err(eyre!("wallet {wallet} was given two different private keys"))
+                }
+                _ => {}
+            }
+        }
+        Ok(keys.into_iter().map(|(wallet, key)| (wallet, key.into())).collect())
+    }
+
+    /// Senders: `--wallet`, `--wallets-file`, `--keys-file`, `--import-keystore`, the
+    /// `generated` wallets and then the `--count` synthetic ones, or
+    /// the default wallets when none of them is given.
+    fn wallets(&self, generated: &[Address]) -> eyre::Result<Vec<Address>> {
+        let mut wallets = self.wallets.clone();
+        if let Some(path) = &self.wallets_file {
+            let contents =
+                fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+            wallets.extend(
+                parse_wallets(&contents).wrap_err_with(|| format!("parsing {}", path.display()))?,
+            );
+        }
+        if let Some(path) = &self.keys_file {
+            let contents =
+                fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+            wallets.extend(
+                parse_keys_file(&contents)
+                    .wrap_err_with(|| format!("parsing {}", path.display()))?
+                    .into_iter()
+                    .map(|(wallet, _)| wallet),
+            );
+        }
+        for path in &self.import_keystores {
+            wallets.push(read_keystore(path)?.0);
+        }
+        wallets.extend_from_slice(generated);
+        if self.mnemonic.is_none() {
+            wallets.extend(synthetic_wallets(self.seed.as_ref(), self.count));
+        }
+        if wallets.is_empty() &&
+            self.wallets_file.is_none() &&
+            self.keys_file.is_none() &&
+            self.import_keystores.is_empty() &&
+            self.generate_wallets == 0 &&
+            self.count == 0
+        {
+            return Ok(DEFAULT_WALLETS
+                .iter()
+                .map(|wallet| parse_address(wallet).expect("default wallet is valid"))
+                .collect())
+        }
+        Ok(dedup_wallets(wallets))
+    }
+
+    /// The `--count` accounts of `--mnemonic`, if given.
+    fn mnemonic_signers(&self) -> eyre::Result<Vec<PrivateKeySigner>> {
+        let Some(mnemonic) = &self.mnemonic else { return Ok(Vec::new()) };
+        if self.count == 0 {
+            return Err(eyre!("--mnemonic derives --count accounts, pass --count 1 or more"))
+        }
+        let count = u32::try_from(self.count)
+            .map_err(|_| eyre!("--mnemonic derives at most 2^32 - 1 accounts"))?;
+        Ok(derive_eth_wallets(mnemonic, &self.derivation, count)?)
+    }
+
+    /// `--password`, or a line read from stdin if a keystore is imported or exported without
+    /// one.
+    fn password(&self) -> eyre::Result<Option<String>> {
+        if self.import_keystores.is_empty() && self.export_keystore.is_none() {
+            return Ok(None)
+        }
+        if let Some(password) = &self.password {
+            return Ok(Some(password.clone()))
+        }
+        prompt_password().map(Some)
+    }
+
+    fn out(&self) -> PathBuf {
+        let default = match (self.aggregate, self.rotate) {
+            (true, _) => DEFAULT_AGGREGATE_OUT,
+            (_, true) => DEFAULT_ROTATE_OUT,
+            _ => DEFAULT_OUT,
+        };
+        self.out
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(default).with_extension(self.format.as_str()))
+    }
+
+    /// `--dst-stake-manager` or `--dst-validator-manager`, or the `--pop-style` default.
+    fn dst(&self, domain: Domain) -> String {
+        let dst = match domain {
+            Domain::StakeManager => &self.dst_stake_manager,
+            Domain::ValidatorManager => &self.dst_validator_manager,
+        };
+        dst.clone().unwrap_or_else(|| self.pop_style.default_dst(domain).to_string())
+    }
+
+    /// Every entry's `generated_at`: `SOURCE_DATE_EPOCH` when set, else the current time, except
+    /// that `--seed` and `--mnemonic` runs without it record none so their output stays
+    /// byte-identical.
+    fn generated_at(&self) -> eyre::Result<Option<u64>> {
+        if let Ok(epoch) = env::var("SOURCE_DATE_EPOCH") {
+            return epoch
+                .parse()
+                .map(Some)
+                .map_err(|_| eyre!("SOURCE_DATE_EPOCH `{epoch}` is not a unix timestamp"))
+        }
+        if self.seed.is_some() || self.mnemonic.is_some() {
+            return Ok(None)
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).wrap_err("reading the clock")?;
+        Ok(Some(now.as_secs()))
+    }
+
+    fn chain_ids(&self) -> Vec<U256> {
+        if self.chain_ids.is_empty() {
+            return DEFAULT_CHAIN_IDS.iter().map(|chain_id| U256::from(*chain_id)).collect()
+        }
+        self.chain_ids.clone()
+    }
+}
+
+/// A keystore password read from stdin.
+fn prompt_password() -> eyre::Result<String> {
+    eprint!("keystore password: ");
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).wrap_err("reading password from stdin")?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `expand_message_xmd` requires a DST of 1 to 255 bytes.
+fn parse_dst(input: &str) -> Result<String, String> {
+    if input.is_empty() || input.len() > 255 {
+        return Err(format!("DST must be between 1 and 255 bytes, got {}", input.len()))
+    }
+    Ok(input.to_string())
+}
+
+fn parse_kdf(input: &str) -> Result<Kdf, String> {
+    match input {
+        "scrypt" => Ok(Kdf::SCRYPT),
+        "pbkdf2" => Ok(Kdf::PBKDF2),
+        _ => Err(format!("unknown KDF `{input}`, expected `scrypt` or `pbkdf2`")),
+    }
+}
+
+/// Shape of the entries in the output file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Layout {
+    /// [`BlsTestData`], as the library serializes it.
+    Default,
+    /// [`FoundryFixture`](bls_test_utils::FoundryFixture), see [`bls_test_utils::foundry`].
+    Foundry,
+}
+
+fn parse_layout(input: &str) -> Result<Layout, String> {
+    match input {
+        "default" => Ok(Layout::Default),
+        "foundry" => Ok(Layout::Foundry),
+        _ => Err(format!("unknown layout `{input}`, expected `default` or `foundry`")),
+    }
+}
+
+fn parse_expand_len(input: &str) -> Result<usize, String> {
+    check_expand_len(input.parse().map_err(|_| format!("`{input}` is not a byte count"))?)
+}
+
+fn parse_private_key(input: &str) -> Result<Fp, String> {
+    parse_secret_key(input).map_err(|error| error.to_string())
+}
+
+fn parse_register_signature(input: &str) -> Result<RegisterCall, String> {
+    RegisterCall::from_signature(input).map_err(|error| error.to_string())
+}
+
+fn parse_wallet(input: &str) -> Result<Address, String> {
+    parse_address(input).map_err(|error| format!("`{input}` is not a valid address: {error}"))
+}
+
+fn run(cli: &Cli) -> eyre::Result<()> {
+    match &cli.command {
+        Some(Command::Verify { path }) => return verify(path),
+        Some(Command::Sign(args)) => return sign(args),
+        Some(Command::Threshold(args)) => return threshold(args),
+        None => {}
+    }
+    if cli.pop_style == PopStyle::Pubkey && cli.dst_per_chain {
+        return Err(eyre!(
+            "--dst-per-chain does not apply to --pop-style pubkey, which signs no chain id"
+        ))
+    }
+    if cli.pop_style == PopStyle::Pubkey && cli.with_nonce {
+        return Err(eyre!("--with-nonce does not apply to --pop-style pubkey, which signs no nonce"))
+    }
+    if cli.pop_style == PopStyle::Pubkey && cli.preimage == Preimage::Eip712 {
+        return Err(eyre!("--preimage eip712 signs the chain id and sender, not --pop-style pubkey"))
+    }
+    if cli.expiry.is_some() && cli.preimage != Preimage::Eip712 {
+        return Err(eyre!("--expiry only applies to --preimage eip712"))
+    }
+    if cli.chain_id_width != ChainIdWidth::U256 && cli.preimage == Preimage::Eip712 {
+        return Err(eyre!("--preimage eip712 signs the chain id as a uint256, not --chain-id-width"))
+    }
+    if cli.prehash == Prehash::Eip191 && cli.preimage == Preimage::Eip712 {
+        return Err(eyre!("--prehash eip191 applies to the message bytes, not an eip712 digest"))
+    }
+    let chain_ids = cli.chain_ids();
+    let derived = cli.mnemonic_signers()?;
+    let mut signers = generate_eth_wallets(cli.seed.as_ref(), cli.generate_wallets);
+    signers.extend(derived.iter().cloned());
+    let generated: Vec<Address> = signers.iter().map(|signer| signer.address()).collect();
+    let password = cli.password()?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs)
+        .build()
+        .wrap_err("starting the generation thread pool")?;
+    let opts = GenOptions {
+        seed: cli.seed,
+        verify: !cli.skip_verify,
+        include_invalid: cli.include_invalid,
+        include_attacks: cli.include_attacks,
+        edge_cases: cli.edge_cases,
+        include_chain_replays: cli.include_chain_replays,
+        compressed: cli.compressed,
+        padded: cli.padded,
+        pairing_input: cli.pairing_input,
+        combined: cli.combined,
+        include_intermediates: cli.include_intermediates,
+        calldata: cli.calldata.then(|| match (&cli.signature, cli.selector) {
+            (Some(call), _) => call.clone(),
+            (None, Some(selector)) => RegisterCall::from_selector(selector),
+            (None, None) => RegisterCall::default(),
+        }),
+        keys: cli.keys(password.as_deref(), &derived)?,
+        dst_stake_manager: cli.dst(Domain::StakeManager),
+        dst_validator_manager: cli.dst(Domain::ValidatorManager),
+        dst_rotate: cli.dst_rotate.clone().unwrap_or_else(|| DEFAULT_DST_ROTATE.to_string()),
+        dst_per_chain: cli.dst_per_chain,
+        nonce_start: (cli.with_nonce || cli.preimage == Preimage::Eip712)
+            .then(|| cli.nonce_start.unwrap_or_default()),
+        encoding: cli.encoding,
+        chain_id_width: cli.chain_id_width,
+        hasher: MessageHasher { hash_function: cli.hash_function, expand_len: cli.expand_len },
+        pop_style: cli.pop_style,
+        preimage: cli.preimage,
+        prehash: cli.prehash,
+        expiry: cli.expiry.unwrap_or(U256::MAX),
+        eth_signers: generated.iter().copied().zip(signers).collect(),
+        // Keystores are encrypted from the written key, which is stripped again below.
+        include_secrets: cli.include_secrets || cli.export_keystore.is_some(),
+        generated_at: cli.generated_at()?,
+    };
+    for chain_id in &chain_ids {
+        opts.chain_id_width.check(*chain_id)?;
+        for domain in [Domain::StakeManager, Domain::ValidatorManager] {
+            parse_dst(&opts.dst(domain, *chain_id))
+                .map_err(|error| eyre!("{domain} DST: {error}"))?;
+        }
+    }
+    if cli.layout == Layout::Foundry && cli.format != OutputFormat::Json {
+        return Err(eyre!("--layout foundry is only written as JSON"))
+    }
+    if cli.format == OutputFormat::Csv && (cli.aggregate || cli.rotate || cli.append) {
+        return Err(eyre!("--format csv writes rows of PoPs, not --aggregate, --rotate or --append"))
+    }
+    if cli.aggregate {
+        let [chain_id] = chain_ids[..] else {
+            return Err(eyre!("--aggregate takes exactly one --chain-id, got {}", chain_ids.len()))
+        };
+        let wallets = cli.wallets(&generated)?;
+        let mut data = pool.install(|| generate_aggregate(&wallets, chain_id, &opts))?;
+        export_keystores(cli, password.as_deref(), &data.validators)?;
+        if !cli.include_secrets {
+            data.validators.iter_mut().for_each(BlsTestData::strip_secrets);
+        }
+        write_solidity(cli, &data.validators)?;
+        return write_output(cli, &data)
+    }
+    if cli.rotate {
+        if cli.pop_style == PopStyle::Pubkey {
+            return Err(eyre!("--rotate signs the chain id and sender, not --pop-style pubkey"))
+        }
+        let wallets = cli.wallets(&generated)?;
+        return write_output(cli, &pool.install(|| generate_rotations(&wallets, &chain_ids, &opts))?)
+    }
+    let wallets = cli.wallets(&generated)?;
+    let out = cli.out();
+    let existing = if cli.append { append_target(cli, &out, &wallets)? } else { Vec::new() };
+    let mut output = match &cli.split_output {
+        Some(dir) => {
+            FixtureWriter::Split(SplitWriter::create(dir, cli.number_format, cli.compact)?)
+        }
+        None if out == Path::new(STDOUT) => FixtureWriter::Array(ArrayWriter::new(
+            open_output(&out)?,
+            output_name(&out),
+            cli.format,
+            cli.number_format,
+            cli.compact,
+        )),
+        None => FixtureWriter::Array(ArrayWriter::create(
+            &out,
+            cli.format,
+            cli.number_format,
+            cli.compact,
+        )?),
+    };
+    let mut public_keys = PublicKeySum::default();
+    let mut solidity = Vec::new();
+    // A failure drops `output`, which leaves any previous fixture in place.
+    for mut case in existing {
+        if !cli.include_secrets {
+            case.strip_secrets();
+        }
+        public_keys.add(&case);
+        case.rewrite_numbers(cli.number_format);
+        output.push(&case, &case)?;
+    }
+    for (index, chunk) in wallets.chunks(CHUNK_SIZE).enumerate() {
+        let opts = opts.nth_registration(index * CHUNK_SIZE);
+        let mut cases = pool.install(|| generate_cases(chunk, &chain_ids, &opts))?;
+        export_keystores(cli, password.as_deref(), &cases)?;
+        for case in &mut cases {
+            if !cli.include_secrets {
+                case.strip_secrets();
+            }
+            case.rewrite_numbers(cli.number_format);
+        }
+        for case in &cases {
+            match cli.layout {
+                Layout::Default => {
+                    public_keys.add(case);
+                    output.push(case, case)?
+                }
+                Layout::Foundry => output.push(case, &foundry_fixture(case)?)?,
+            }
+        }
+        if cli.emit_solidity.is_some() {
+            solidity.extend(cases);
+        }
+    }
+    output.finish(public_keys.finish())?;
+    write_solidity(cli, &solidity)
+}
+
+/// The entries of the `--append` target that are kept, see [`existing_cases`].
+fn append_target(cli: &Cli, out: &Path, wallets: &[Address]) -> eyre::Result<Vec<BlsTestData>> {
+    if out == Path::new(STDOUT) {
+        return Err(eyre!("--append merges into a file, not stdout"))
+    }
+    existing_cases(out, cli.format, wallets, cli.replace_existing).map_err(|error| match error {
+        LoadError::Conflict { .. } => {
+            eyre!("{error}; pass --replace-existing to regenerate them")
+        }
+        error => error.into(),
+    })
+}
+
+fn parse_keystore(path: &Path) -> eyre::Result<Keystore> {
+    let raw = fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&raw).wrap_err_with(|| format!("parsing {}", path.display()))
+}
+
+/// A keystore and the wallet it was exported for.
+fn read_keystore(path: &Path) -> eyre::Result<(Address, Keystore)> {
+    let keystore = parse_keystore(path)?;
+    let wallet = keystore
+        .wallet_address
+        .as_deref()
+        .ok_or_else(|| eyre!("{} has no wallet_address", path.display()))?;
+    let wallet = parse_wallet(wallet)
+        .map_err(|error| eyre!(error))
+        .wrap_err_with(|| format!("parsing {}", path.display()))?;
+    Ok((wallet, keystore))
+}
+
+fn export_keystores(cli: &Cli, password: Option<&str>, cases: &[BlsTestData]) -> eyre::Result<()> {
+    let Some(dir) = &cli.export_keystore else { return Ok(()) };
+    let password = password.expect("password is read when keystores are exported");
+    fs::create_dir_all(dir).wrap_err_with(|| format!("creating {}", dir.display()))?;
+    for case in cases {
+        let wallet = parse_wallet(&case.wallet_address).map_err(|error| eyre!(error))?;
+        let secret_key = parse_secret_key(
+            case.private_key.as_deref().expect("keys are generated when keystores are exported"),
+        )?;
+        let keystore = encrypt_keystore(secret_key, Some(wallet), password, &cli.kdf)?;
+        let path = dir.join(format!("{wallet}.json"));
+        fs::write(&path, serde_json::to_string_pretty(&keystore)?)
+            .wrap_err_with(|| format!("writing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn write_solidity(cli: &Cli, cases: &[BlsTestData]) -> eyre::Result<()> {
+    let Some(path) = &cli.emit_solidity else { return Ok(()) };
+    fs::write(path, render_solidity(cases)?).wrap_err_with(|| format!("writing {}", path.display()))
+}
+
+fn verify(path: &Path) -> eyre::Result<()> {
+    let cases = load_fixture(path)?;
+    let results = verify_fixture(&cases);
+
+    println!("{:<42} {:>10} {:<16} {:<7} result", "wallet", "chain_id", "domain", "expect");
+    for result in &results {
+        println!("{result}");
+    }
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    println!("{} of {} PoPs checked out", results.len() - failed, results.len());
+    if failed > 0 {
+        return Err(eyre!("{failed} PoP(s) in {} failed verification", path.display()))
+    }
+    Ok(())
+}
+
+/// `--message`: `0x`-prefixed hex, or the contents of the file it names.
+fn read_message(input: &str) -> eyre::Result<Vec<u8>> {
+    if input.starts_with("0x") || input.starts_with("0X") {
+        return decode_hex(input).wrap_err_with(|| format!("`{input}` is not valid hex"))
+    }
+    fs::read(input).wrap_err_with(|| format!("reading {input}"))
+}
+
+fn sign(args: &SignArgs) -> eyre::Result<()> {
+    let message = read_message(&args.message)?;
+    let signed = sign_message(args.secret_key()?, &message, &args.dst)?;
+    write_to(&args.out, &OutputFormat::Json.encode(&signed, args.compact)?)
+}
+
+fn threshold(args: &ThresholdArgs) -> eyre::Result<()> {
+    let message = read_message(&args.message)?;
+    let data = generate_threshold(
+        args.seed.as_ref(),
+        args.threshold,
+        args.shares,
+        &args.signers,
+        &message,
+        &args.dst,
+    )?;
+    write_to(&args.out, &OutputFormat::Json.encode(&data, args.compact)?)
+}
+
+fn write_output<T: Serialize>(cli: &Cli, value: &T) -> eyre::Result<()> {
+    write_to(&cli.out(), &cli.format.encode(value, cli.compact)?)
+}
+
+/// How errors name an `--out` destination.
+fn output_name(path: &Path) -> String {
+    if path == Path::new(STDOUT) {
+        return "stdout".to_string()
+    }
+    path.display().to_string()
+}
+
+/// The `--out` destination: the file at `path`, created or truncated, or stdout for `-`.
+fn open_output(path: &Path) -> eyre::Result<Box<dyn Write>> {
+    if path == Path::new(STDOUT) {
+        return Ok(Box::new(io::stdout().lock()))
+    }
+    let file = File::create(path).wrap_err_with(|| format!("writing {}", path.display()))?;
+    Ok(Box::new(file))
+}
+
+fn write_to(path: &Path, contents: &str) -> eyre::Result<()> {
+    let mut output = open_output(path)?;
+    output
+        .write_all(contents.as_bytes())
+        .and_then(|()| output.flush())
+        .wrap_err_with(|| format!("writing {}", output_name(path)))
+}
+
+fn main() -> eyre::Result<()> {
+    run(&Cli::parse())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls_test_utils::{
+        binding_digest, encode_csv, partial_path, words_from_hex, AggregateTestData, CheckResult,
+        RotationTestData, SignedMessage, SplitIndex, ThresholdTestData, VerifyFailure, CSV_COLUMNS,
+        DEFAULT_DST_STAKE_MANAGER, DEFAULT_DST_VALIDATOR_MANAGER, GROUP_ORDER, SCHEMA_VERSION,
+    };
+    use std::collections::HashSet;
+
+    /// The first data row of `--seed 59 --chain-id 1 --chain-id 8453 --format csv`.
+    const PINNED_CSV_ROW: &str = concat!(
+        "0x328809Bc894f92807417D2dAD6b7C998c1aFdac6,",
+        "1,",
+        "0x23e479f380c7c99f91a6838a1681b7c01319e062aab71e8b89124b466dcb37e6,",
+        "0x0e19dda10341370e43d34d7ca4ab62c5d9a972e2e68a50d9ff16a826d3d18a82,",
+        "0x0ffe88521ad4aef58e9f25e8839cea16276810b866280fdca43e2a45e517d237,",
+        "0x08078c5ecf81315f2a4d999cf661a90352383a4885ac8a103c9f6d342683e1a7,",
+        "0x0327271ed4465d745c913a5c44bd34f185869f062d6642f880192bd23d8f6ead,",
+        "0x20e486c8e2c01e9f9acb7ec1b60e2492faa236d8bac7cb767639ea2ec856807b,",
+        "0x0762cd695a95021a89dc97d9f9af263987d61544f89ce69019cc6db279604022,",
+        "0x2e5abc90de1e318b8facf73961a13ffe14292d816f15e98d66ba7b9b67df8e7b,",
+        "0x055145750badfe49a06ea6de26f136a62c8b08b64c0005a69ff3f6ad4c1726b2,",
+        "0x118ed815cb4fdc76f19bad59f4798f423df36a86162e3fd63f0da292ddc54e9a,",
+        "0x12a49bd95c56dbc5d3f41ba7244ff84491fa7af65e07aa8eab3a58dddf93368d,",
+        "0x266e978c837b689cc3cfa14d7e57779280f5f403f0f08ab2c343868c79dd1253,",
+        // valid, no invalid_reason, no attack
+        "true,,,",
+        "StakeManager:BN254:PoP:v1:,",
+        "ValidatorManager:BN254:PoP:v1:",
+    );
+
+    fn run_raw(args: &[&str]) -> eyre::Result<String> {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("out.json");
+        let mut argv = vec!["bls-test-utils", "--out", out.to_str().unwrap()];
+        argv.extend_from_slice(args);
+        run(&Cli::try_parse_from(argv).expect("valid args"))?;
+        Ok(fs::read_to_string(&out).expect("read output"))
+    }
+
+    fn run_with(args: &[&str]) -> (Vec<BlsTestData>, String) {
+        let raw = run_raw(args).expect("run");
+        (OutputFormat::Json.decode_cases(&raw).expect("output parses as BlsTestData"), raw)
+    }
+
+    #[test]
+    fn test_defaults_match_legacy_wallets_and_chains() {
+        let (data, raw) = run_with(&[]);
+        let wallets: Vec<&str> = data.iter().map(|entry| entry.wallet_address.as_str()).collect();
+        assert_eq!(wallets, DEFAULT_WALLETS);
+        for entry in &data {
+            let chain_ids: Vec<&str> = entry.proof.iter().map(|p| p.chain_id.as_str()).collect();
+            assert_eq!(chain_ids, ["8453", "1"]);
+            assert_eq!(entry.domain_staking_manager, DEFAULT_DST_STAKE_MANAGER);
+            assert_eq!(entry.domain_validator_manager, DEFAULT_DST_VALIDATOR_MANAGER);
+        }
+        assert!(raw.contains('\n'), "pretty output is the default");
+    }
+
+    #[test]
+    fn test_wallet_and_chain_id_flags() {
+        let (data, _) = run_with(&[
+            "--wallet",
+            "0x328809Bc894f92807417D2dAD6b7C998c1aFdac6",
+            "--chain-id",
+            "10",
+            "--chain-id",
+            "42161",
+            "--chain-id",
+            "31337",
+        ]);
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].wallet_address, "0x328809Bc894f92807417D2dAD6b7C998c1aFdac6");
+        let chain_ids: Vec<&str> = data[0].proof.iter().map(|p| p.chain_id.as_str()).collect();
+        assert_eq!(chain_ids, ["10", "42161", "31337"]);
+    }
+
+    #[test]
+    fn test_compact_output() {
+        let (data, raw) = run_with(&["--compact", "--chain-id", "1"]);
+        assert_eq!(data.len(), DEFAULT_WALLETS.len());
+        assert!(!raw.contains('\n'));
+
+        let (_, raw) = run_with(&["--compact", "--pretty", "--chain-id", "1"]);
+        assert!(raw.contains('\n'), "the last of --pretty/--compact wins");
+    }
+
+    #[test]
+    fn test_seed_is_deterministic() {
+        let (_, first) = run_with(&["--seed", "42"]);
+        let (_, second) = run_with(&["--seed", "0x2a"]);
+        assert_eq!(first, second, "same seed must give byte-identical output");
+
+        let (data, _) = run_with(&["--seed", "42"]);
+        let keys: HashSet<&[String; 4]> = data.iter().map(|entry| &entry.public_key).collect();
+        assert_eq!(keys.len(), data.len(), "every wallet gets its own key");
+        assert_eq!(data.len(), DEFAULT_WALLETS.len());
+
+        let (_, other) = run_with(&["--seed", "43"]);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_no_seed_is_random() {
+        let (first, _) = run_with(&["--chain-id", "1"]);
+        let (second, _) = run_with(&["--chain-id", "1"]);
+        assert_ne!(first[0].public_key, second[0].public_key);
+    }
+
+    /// `cases` without their `generated_at`, for comparing runs made at different times.
+    fn undated(mut cases: Vec<BlsTestData>) -> Vec<BlsTestData> {
+        cases.iter_mut().for_each(|case| case.generated_at = None);
+        cases
+    }
+
+    #[test]
+    fn test_generation_metadata() {
+        let (cases, _) = run_with(&["--chain-id", "1"]);
+        let generated_at = cases[0].generated_at.expect("unseeded runs are timestamped");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(generated_at <= now && now - generated_at < 600);
+        assert_eq!(cases[0].generator_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+
+        let (cases, raw) = run_with(&["--seed", "36", "--chain-id", "1"]);
+        assert_eq!(cases[0].generated_at, None);
+        assert!(!raw.contains("generated_at"), "seeded output does not depend on the clock");
+        assert!(cases.iter().all(|case| case.generator_version.is_some()));
+    }
+
+    #[test]
+    fn test_skip_verify_produces_same_vectors() {
+        let (_, verified) = run_with(&["--seed", "7", "--chain-id", "1"]);
+        let (_, skipped) = run_with(&["--seed", "7", "--chain-id", "1", "--skip-verify"]);
+        assert_eq!(verified, skipped);
+    }
+
+    #[test]
+    fn test_valid_entries_omit_invalid_fields() {
+        let (_, raw) = run_with(&["--seed", "9", "--chain-id", "1"]);
+        assert!(!raw.contains("\"valid\""), "valid entries do not carry the new fields");
+        assert!(!raw.contains("invalid_reason"));
+
+        let (data, _) = run_with(&["--seed", "9", "--chain-id", "1", "--include-invalid"]);
+        assert!(data.iter().all(|entry| entry.proof.iter().filter(|p| !p.valid).count() == 4));
+    }
+
+    #[test]
+    fn test_aggregate_mode() {
+        let raw = run_raw(&["--aggregate", "--seed", "3", "--chain-id", "8453"]).unwrap();
+        let data: AggregateTestData = serde_json::from_str(&raw).expect("aggregate output parses");
+        assert_eq!(data.schema_version, SCHEMA_VERSION);
+        assert_eq!(data.chain_id, "8453");
+        assert_eq!(data.validators.len(), DEFAULT_WALLETS.len());
+
+        let error = run_raw(&["--aggregate"]).expect_err("defaults have two chain ids");
+        assert!(error.to_string().contains("exactly one --chain-id"), "{error}");
+
+        let cli = Cli::try_parse_from(["bls-test-utils", "--aggregate"]).unwrap();
+        assert_eq!(cli.out(), PathBuf::from(DEFAULT_AGGREGATE_OUT));
+        let cli = Cli::try_parse_from(["bls-test-utils"]).unwrap();
+        assert_eq!(cli.out(), PathBuf::from(DEFAULT_OUT));
+    }
+
+    #[test]
+    fn test_rotate_mode() {
+        let raw = run_raw(&["--rotate", "--seed", "38", "--chain-id", "1"]).unwrap();
+        let data: RotationTestData = serde_json::from_str(&raw).expect("rotation output parses");
+        assert_eq!(data.domain_rotate, DEFAULT_DST_ROTATE);
+        assert_eq!(data.rotations.len(), DEFAULT_WALLETS.len());
+        let (cases, _) = run_with(&["--seed", "38", "--chain-id", "1"]);
+        for (rotation, case) in data.rotations.iter().zip(&cases) {
+            assert_eq!(rotation.old_public_key, case.public_key, "the registered key rotates");
+            let valid: Vec<bool> = rotation.proof.iter().map(|proof| proof.valid).collect();
+            assert_eq!(valid, [true, false]);
+        }
+        assert!(!raw.contains("private_key"), "secrets are opt-in");
+        let raw = run_raw(&["--rotate", "--seed", "38", "--chain-id", "1", "--include-secrets"]);
+        let secret: RotationTestData = serde_json::from_str(&raw.unwrap()).unwrap();
+        for (rotation, case) in secret.rotations.iter().zip(&data.rotations) {
+            assert!(rotation.old_private_key.is_some() && rotation.new_private_key.is_some());
+            assert_eq!(rotation.new_public_key, case.new_public_key);
+        }
+
+        let dst = "ValidatorManager:BN254:Rotate:v2:";
+        let raw = run_raw(&["--rotate", "--dst-rotate", dst, "--seed", "38", "--chain-id", "1"]);
+        let other: RotationTestData = serde_json::from_str(&raw.unwrap()).unwrap();
+        assert_eq!(other.domain_rotate, dst);
+        assert_ne!(
+            other.rotations[0].proof[0].message_hash,
+            data.rotations[0].proof[0].message_hash
+        );
+
+        let error = run_raw(&["--rotate", "--pop-style", "pubkey"]).expect_err("nothing to bind");
+        assert!(error.to_string().contains("--pop-style pubkey"), "{error}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--rotate", "--aggregate"]).is_err());
+        assert!(Cli::try_parse_from(["bls-test-utils", "--dst-rotate", dst]).is_err());
+        let cli = Cli::try_parse_from(["bls-test-utils", "--rotate"]).unwrap();
+        assert_eq!(cli.out(), PathBuf::from(DEFAULT_ROTATE_OUT));
+    }
+
+    #[test]
+    fn test_verify_subcommand() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("fixture.json");
+        let good = run_raw(&["--seed", "4", "--chain-id", "1", "--include-invalid"]).unwrap();
+        fs::write(&path, &good).unwrap();
+        let verify = || {
+            run(&Cli::try_parse_from(["bls-test-utils", "verify", path.to_str().unwrap()]).unwrap())
+        };
+        verify().expect("freshly generated fixture verifies");
+
+        let mut cases = OutputFormat::Json.decode_cases(&good).unwrap();
+        cases[2].proof[0].chain_id = "2".into();
+        // Written as a version 1 bare list, which verify still reads.
+        fs::write(&path, serde_json::to_string(&cases).unwrap()).unwrap();
+        let error = verify().expect_err("relabelled chain id is caught");
+        assert!(error.to_string().contains("2 PoP(s)"), "{error}");
+
+        fs::write(&path, "not json").unwrap();
+        assert!(verify().is_err());
+    }
+
+    #[test]
+    fn test_csv_format() {
+        let args = ["--seed", "59", "--chain-id", "1", "--chain-id", "8453", "--include-invalid"];
+        let csv = run_raw(&[&args[..], &["--format", "csv"]].concat()).unwrap();
+        let (cases, _) = run_with(&args);
+        assert_eq!(csv, encode_csv(&cases));
+        let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!(rows.len(), 1 + cases.iter().map(|case| case.proof.len()).sum::<usize>());
+        assert_eq!(rows[0], CSV_COLUMNS.join(","));
+        assert!(rows.iter().all(|row| row.split(',').count() == CSV_COLUMNS.len()), "{csv}");
+        assert_eq!(rows[1], PINNED_CSV_ROW);
+
+        let error = run_raw(&["--format", "csv", "--rotate"]).unwrap_err();
+        assert!(error.to_string().contains("--format csv"), "{error}");
+        let error = run_raw(&["--format", "csv", "--layout", "foundry"]).unwrap_err();
+        assert!(error.to_string().contains("only written as JSON"), "{error}");
+    }
+
+    #[test]
+    fn test_split_output() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let split = dir.path().join("split");
+        let split = split.to_str().unwrap();
+        let args = ["--seed", "4", "--chain-id", "1", "--chain-id", "8453", "--include-invalid"];
+        let (combined, _) = run_with(&args);
+        run(&Cli::try_parse_from(
+            [&["bls-test-utils", "--split-output", split][..], &args].concat(),
+        )
+        .unwrap())
+        .unwrap();
+
+        let index: SplitIndex =
+            serde_json::from_str(&fs::read_to_string(format!("{split}/index.json")).unwrap())
+                .unwrap();
+        assert_eq!(index.schema_version, SCHEMA_VERSION);
+        assert_eq!(index.wallets, DEFAULT_WALLETS);
+        assert_eq!(index.chain_ids, ["1", "8453"]);
+        assert!(index.aggregate_public_key.is_some());
+        for (wallet, case) in DEFAULT_WALLETS.iter().zip(&combined) {
+            let raw = fs::read_to_string(format!("{split}/{wallet}.json")).unwrap();
+            let entry: BlsTestData = serde_json::from_str(&raw).unwrap();
+            assert_eq!(undated(vec![entry]), undated(vec![case.clone()]));
+        }
+        let verify = || run(&Cli::try_parse_from(["bls-test-utils", "verify", split]).unwrap());
+        verify().expect("verify reads a split directory");
+
+        let path = format!("{split}/{}.json", DEFAULT_WALLETS[1]);
+        let mut case: BlsTestData =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        case.proof[0].chain_id = "2".into();
+        fs::write(&path, serde_json::to_string(&case).unwrap()).unwrap();
+        let error = verify().expect_err("relabelled chain id is caught");
+        assert!(error.to_string().contains("PoP(s)"), "{error}");
+
+        for conflict in ["--include-attacks", "--append", "--aggregate"] {
+            let args = ["bls-test-utils", "--split-output", split, conflict];
+            assert!(Cli::try_parse_from(args).is_err(), "{conflict}");
+        }
+    }
+
+    #[test]
+    fn test_sign_subcommand() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("signature.json");
+        let sign = |args: &[&str]| {
+            let mut argv = vec!["bls-test-utils", "sign", "--out", out.to_str().unwrap()];
+            argv.extend_from_slice(args);
+            run(&Cli::try_parse_from(argv)?)?;
+            let raw = fs::read_to_string(&out)?;
+            Ok::<SignedMessage, eyre::Report>(serde_json::from_str(&raw)?)
+        };
+
+        let signed =
+            sign(&["--message", "0xdeadbeef", "--dst", "Bridge:v1:", "--private-key", "0x2a"])
+                .unwrap();
+        assert_eq!(signed.message, "0xdeadbeef");
+        assert_eq!(
+            signed,
+            sign_message(
+                parse_secret_key("0x2a").unwrap(),
+                &[0xde, 0xad, 0xbe, 0xef],
+                "Bridge:v1:"
+            )
+            .unwrap()
+        );
+
+        let file = dir.path().join("payload.bin");
+        fs::write(&file, [0xde, 0xad, 0xbe, 0xef]).unwrap();
+        let from_file = sign(&[
+            "--message",
+            file.to_str().unwrap(),
+            "--dst",
+            "Bridge:v1:",
+            "--private-key",
+            "0x2a",
+        ])
+        .unwrap();
+        assert_eq!(from_file, signed, "a file is signed by its contents");
+
+        let (cases, _) = run_with(&["--seed", "4", "--chain-id", "1", "--include-secrets"]);
+        let seeded = sign(&[
+            "--message",
+            "0x",
+            "--dst",
+            "Bridge:v1:",
+            "--seed",
+            "4",
+            "--wallet",
+            &cases[1].wallet_address,
+        ])
+        .unwrap();
+        assert_eq!(
+            Some(seeded.private_key),
+            cases[1].private_key,
+            "--seed derives the fixture's key"
+        );
+
+        let random = || sign(&["--message", "0x01", "--dst", "Bridge:v1:"]).unwrap().private_key;
+        assert_ne!(random(), random());
+        assert!(sign(&["--message", "0xzz", "--dst", "Bridge:v1:"]).is_err());
+        let error = sign(&["--message", "0xabc", "--dst", "Bridge:v1:"]).unwrap_err();
+        assert!(format!("{error:#}").contains("odd number of hex digits (3)"), "{error:#}");
+        let grouped =
+            sign(&["--message", "0xDEAD_beef", "--dst", "Bridge:v1:", "--private-key", "0x_2a"]);
+        assert_eq!(grouped.unwrap(), signed, "separators and case do not change the bytes");
+        assert!(sign(&["--message", "0x01", "--dst", "D", "--seed", "1", "--private-key", "0x2a"])
+            .is_err());
+        assert!(sign(&["--message", "0x01", "--dst", ""]).is_err());
+    }
+
+    #[test]
+    fn test_threshold_subcommand() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("threshold.json");
+        let threshold = |args: &[&str]| {
+            let mut argv = vec!["bls-test-utils", "threshold", "--out", out.to_str().unwrap()];
+            argv.extend_from_slice(args);
+            run(&Cli::try_parse_from(argv)?)?;
+            let raw = fs::read_to_string(&out)?;
+            Ok::<ThresholdTestData, eyre::Report>(serde_json::from_str(&raw)?)
+        };
+        let base = ["--message", "0xc0ffee", "--dst", "Bridge:v1:", "--seed", "31"];
+
+        let data =
+            threshold(&[&base[..], &["--threshold", "2", "--shares", "4"]].concat()).unwrap();
+        assert_eq!(data.signers, [1, 2]);
+        let subset = threshold(
+            &[&base[..], &["--threshold", "2", "--shares", "4", "--signers", "4,2"]].concat(),
+        )
+        .unwrap();
+        assert_eq!(subset.signers, [4, 2]);
+        assert_eq!(subset.signature, data.signature);
+        let master = parse_secret_key(&data.private_key).unwrap();
+        assert_eq!(
+            data.signature,
+            sign_message(master, &[0xc0, 0xff, 0xee], "Bridge:v1:").unwrap().signature
+        );
+
+        for bad in [
+            &["--threshold", "5", "--shares", "4"][..],
+            &["--threshold", "2", "--shares", "4", "--signers", "3"],
+        ] {
+            assert!(threshold(&[&base[..], bad].concat()).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn test_include_attacks() {
+        let (data, _) = run_with(&["--seed", "4", "--chain-id", "1", "--include-attacks"]);
+        assert_eq!(data.len(), DEFAULT_WALLETS.len() * 4);
+        let attacks: Vec<Option<&str>> = data[..4].iter().map(|e| e.attack.as_deref()).collect();
+        assert_eq!(attacks, [None, Some("off_curve"), Some("wrong_subgroup"), Some("infinity")]);
+        assert!(verify_fixture(&data).iter().all(bls_test_utils::CheckResult::passed));
+
+        let (plain, raw) = run_with(&["--seed", "4", "--chain-id", "1"]);
+        assert!(!raw.contains("attack"), "attack entries are opt-in");
+        assert_eq!(data[0], plain[0]);
+    }
+
+    #[test]
+    fn test_include_chain_replays() {
+        let args = ["--seed", "40", "--chain-id", "1", "--chain-id", "8453"];
+        let (data, _) = run_with(&[&args[..], &["--include-chain-replays"]].concat());
+        assert_eq!(data.len(), DEFAULT_WALLETS.len() * 3);
+        let replays: Vec<(Option<&str>, &str)> = data[..3]
+            .iter()
+            .map(|entry| (entry.attack.as_deref(), entry.proof.last().unwrap().chain_id.as_str()))
+            .collect();
+        assert_eq!(
+            replays,
+            [(None, "8453"), (Some("chain_replay"), "8453"), (Some("chain_replay"), "1")]
+        );
+        assert!(verify_fixture(&data).iter().all(bls_test_utils::CheckResult::passed));
+
+        let (plain, _) = run_with(&args);
+        assert_eq!(data[0], plain[0]);
+        assert!(Cli::try_parse_from([
+            "bls-test-utils",
+            "--include-chain-replays",
+            "--dst-per-chain"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_jobs_flag() {
+        let (_, single) = run_with(&["--seed", "8", "--include-invalid", "--jobs", "1"]);
+        let (_, parallel) = run_with(&["--seed", "8", "--include-invalid", "--jobs", "4"]);
+        assert_eq!(single, parallel);
+    }
+
+    #[test]
+    fn test_array_writer_matches_serde() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("array");
+        let (cases, _) = run_with(&["--seed", "1", "--chain-id", "1", "--include-invalid"]);
+        for (format, number_format) in OutputFormat::ALL
+            .into_iter()
+            .flat_map(|format| [(format, NumberFormat::Hex), (format, NumberFormat::Dec)])
+        {
+            for compact in [false, true] {
+                for len in [0, 1, cases.len()] {
+                    let mut output =
+                        ArrayWriter::create(&path, format, number_format, compact).unwrap();
+                    let mut public_keys = PublicKeySum::default();
+                    for case in &cases[..len] {
+                        public_keys.add(case);
+                        let mut case = case.clone();
+                        case.rewrite_numbers(number_format);
+                        output.push(&case).unwrap();
+                    }
+                    output.finish(public_keys.finish()).unwrap();
+                    let expected =
+                        format.encode_fixture_as(&cases[..len], number_format, compact).unwrap();
+                    assert_eq!(
+                        fs::read_to_string(&path).unwrap(),
+                        expected,
+                        "{format} {number_format} {compact} {len}"
+                    );
+                }
+            }
+        }
+        let raw = OutputFormat::Json.encode_cases(&cases, false).unwrap();
+        assert!(raw.starts_with("{\n  \"schema_version\": 2,\n  \"vectors\": [\n    {"), "{raw}");
+    }
+
+    #[test]
+    fn test_aggregate_public_key_field() {
+        let raw = run_raw(&["--seed", "34", "--chain-id", "1", "--include-attacks"]).unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        let cases = OutputFormat::Json.decode_cases(&raw).unwrap();
+        assert_eq!(cases.len(), DEFAULT_WALLETS.len() * 4);
+        let expected = bls_test_utils::aggregate_public_key(&cases).unwrap();
+        assert_eq!(expected.count, DEFAULT_WALLETS.len() as u64, "attack keys are left out");
+        assert_eq!(fixture["aggregate_public_key"], serde_json::to_value(&expected).unwrap());
+
+        let raw = run_raw(&["--seed", "34", "--chain-id", "1", "--wallet", DEFAULT_WALLETS[0]]);
+        assert!(!raw.unwrap().contains("aggregate_public_key"), "a single key is not summed");
+        let raw = run_raw(&["--seed", "34", "--chain-id", "1", "--layout", "foundry"]);
+        assert!(!raw.unwrap().contains("aggregate_public_key"));
+    }
+
+    #[test]
+    fn test_format_flag() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let (json, _) = run_with(&["--seed", "3", "--chain-id", "1"]);
+        for format in [OutputFormat::Yaml, OutputFormat::Toml] {
+            let out = dir.path().join(format!("vectors.{format}"));
+            let args = ["--seed", "3", "--chain-id", "1", "--format", format.as_str(), "--out"];
+            let cli = Cli::try_parse_from(
+                ["bls-test-utils"].iter().chain(&args).chain(&[out.to_str().unwrap()]),
+            )
+            .unwrap();
+            run(&cli).expect("run");
+            let raw = fs::read_to_string(&out).unwrap();
+            assert_eq!(format.decode_cases(&raw).unwrap(), json, "{format}");
+            verify(&out).expect("verify reads the format from the extension");
+        }
+        let cli = Cli::try_parse_from(["bls-test-utils", "--format", "yaml"]).unwrap();
+        assert_eq!(cli.out(), PathBuf::from("bls_test_data.yaml"));
+        assert!(Cli::try_parse_from(["bls-test-utils", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_count_flag() {
+        let (data, _) =
+            run_with(&["--count", "300", "--seed", "20", "--chain-id", "1", "--skip-verify"]);
+        assert_eq!(data.len(), 300, "spans more than one chunk");
+        let wallets: HashSet<&str> = data.iter().map(|e| e.wallet_address.as_str()).collect();
+        assert_eq!(wallets.len(), 300);
+        assert!(data.iter().all(|entry| entry.eth_private_key.is_none()));
+        let sample: Vec<BlsTestData> = data.iter().step_by(50).cloned().collect();
+        assert!(verify_fixture(&sample).iter().all(bls_test_utils::CheckResult::passed));
+
+        let (again, _) = run_with(&["--count", "3", "--seed", "20", "--chain-id", "1"]);
+        assert_eq!(again, data[..3], "seeded runs are reproducible");
+        let conflict = ["bls-test-utils", "--count", "1", "--wallet", DEFAULT_WALLETS[0]];
+        assert!(Cli::try_parse_from(conflict).is_err());
+    }
+
+    #[test]
+    #[ignore = "generates 10,000 wallets; run with --release -- --ignored"]
+    fn test_count_flag_at_scale() {
+        let (data, _) =
+            run_with(&["--count", "10000", "--seed", "20", "--chain-id", "1", "--skip-verify"]);
+        assert_eq!(data.len(), 10_000);
+        let wallets: HashSet<&str> = data.iter().map(|e| e.wallet_address.as_str()).collect();
+        assert_eq!(wallets.len(), 10_000);
+        let (head, _) = run_with(&["--count", "300", "--seed", "20", "--chain-id", "1"]);
+        assert_eq!(head, data[..300], "streaming more chunks keeps the seeded order");
+        let sample: Vec<BlsTestData> = data.iter().step_by(997).cloned().collect();
+        assert!(verify_fixture(&sample).iter().all(bls_test_utils::CheckResult::passed));
+    }
+
+    #[test]
+    fn test_failed_generation_leaves_no_output() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("out.json");
+        let blocker = dir.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let cli = Cli::try_parse_from([
+            "bls-test-utils",
+            "--out",
+            out.to_str().unwrap(),
+            "--export-keystore",
+            blocker.join("keystores").to_str().unwrap(),
+            "--password",
+            "pw",
+        ])
+        .unwrap();
+        assert!(run(&cli).is_err());
+        assert!(!out.exists());
+        assert!(!partial_path(&out).exists());
+    }
+
+    #[test]
+    fn test_append_merges_into_existing_fixture() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("out.json");
+        let append = |args: &[&str]| {
+            let mut argv =
+                vec!["bls-test-utils", "--out", out.to_str().unwrap(), "--seed", "42", "--append"];
+            argv.extend_from_slice(args);
+            run(&Cli::try_parse_from(argv).expect("valid args"))
+        };
+
+        fs::write(&out, "").unwrap();
+        append(&["--wallet", DEFAULT_WALLETS[0]]).expect("merge into an empty file");
+        let first = load_fixture(&out).unwrap();
+        assert_eq!(first.len(), 1);
+
+        // A version 1 fixture is the bare list; it is rewritten as the current version.
+        fs::write(&out, serde_json::to_string(&first).unwrap()).unwrap();
+        append(&["--wallet", DEFAULT_WALLETS[1]]).expect("merge into a v1 file");
+        let merged = load_fixture(&out).unwrap();
+        let wallets: Vec<&str> = merged.iter().map(|case| case.wallet_address.as_str()).collect();
+        assert_eq!(wallets, DEFAULT_WALLETS[..2]);
+        assert_eq!(merged[0], first[0]);
+        assert!(fs::read_to_string(&out).unwrap().starts_with("{\n  \"schema_version\": 2,"));
+
+        let error = append(&["--wallet", DEFAULT_WALLETS[1]]).expect_err("conflict");
+        assert!(error.to_string().contains(DEFAULT_WALLETS[1]), "{error}");
+        assert!(error.to_string().contains("--replace-existing"), "{error}");
+        assert_eq!(load_fixture(&out).unwrap(), merged, "a conflict leaves the file alone");
+
+        append(&["--wallet", DEFAULT_WALLETS[1], "--include-invalid", "--replace-existing"])
+            .expect("replace");
+        let replaced = load_fixture(&out).unwrap();
+        assert_eq!(replaced.len(), 2);
+        assert_eq!(replaced[0], merged[0]);
+        assert!(replaced[1].proof.len() > merged[1].proof.len());
+        assert!(verify_fixture(&replaced).iter().all(bls_test_utils::CheckResult::passed));
+        assert!(!partial_path(&out).exists());
+
+        assert!(Cli::try_parse_from(["bls-test-utils", "--replace-existing"]).is_err());
+        assert!(Cli::try_parse_from(["bls-test-utils", "--append", "--rotate"]).is_err());
+        let stdout = Cli::try_parse_from(["bls-test-utils", "--out", "-", "--append"]).unwrap();
+        let error = run(&stdout).expect_err("stdout");
+        assert!(error.to_string().contains("--append"), "{error}");
+    }
+
+    #[test]
+    fn test_combined_flag() {
+        let args = ["--seed", "44", "--chain-id", "1", "--include-invalid"];
+        let (cases, _) = run_with(&[&args[..], &["--combined"]].concat());
+        assert!(cases
+            .iter()
+            .flat_map(|case| &case.proof)
+            .all(|proof| proof.message_hash_combined.is_some()));
+        assert!(verify_fixture(&cases).iter().all(bls_test_utils::CheckResult::passed));
+        let (_, raw) = run_with(&args);
+        assert!(!raw.contains("combined"), "combined fields are opt-in");
+    }
+
+    #[test]
+    fn test_edge_cases_flag() {
+        let (cases, _) = run_with(&["--seed", "45", "--chain-id", "1", "--edge-cases"]);
+        assert_eq!(cases.len(), DEFAULT_WALLETS.len() * (1 + bls_test_utils::EdgeCase::ALL.len()));
+        let naive: Vec<(&str, Option<bool>)> = cases[1..6]
+            .iter()
+            .map(|case| (case.attack.as_deref().unwrap(), case.proof[0].expected_naive_result))
+            .collect();
+        assert_eq!(
+            naive,
+            [
+                ("identity_public_key", Some(true)),
+                ("identity_signature", Some(false)),
+                ("signature_plus_g1", Some(false)),
+                ("signature_plus_2g1", Some(false)),
+                ("signature_plus_3g1", Some(false)),
+            ]
+        );
+        assert!(verify_fixture(&cases).iter().all(bls_test_utils::CheckResult::passed));
+        assert!(Cli::try_parse_from(["bls-test-utils", "--edge-cases", "--rotate"]).is_err());
+    }
+
+    #[test]
+    fn test_compressed_flag() {
+        let (data, _) = run_with(&["--seed", "2", "--chain-id", "1", "--compressed"]);
+        assert!(data.iter().all(|entry| entry.public_key_compressed.is_some()));
+        let (_, raw) = run_with(&["--seed", "2", "--chain-id", "1"]);
+        assert!(!raw.contains("compressed"), "compressed fields are opt-in");
+        assert!(!raw.contains("padded"), "{raw}");
+        let (data, raw) = run_with(&["--seed", "2", "--chain-id", "1", "--compressed", "--padded"]);
+        assert!(raw.contains(r#""public_key_padded": "0x0000"#), "{raw}");
+        assert!(data.iter().all(|entry| entry.public_key_padded.is_some()));
+        assert!(data.iter().all(|entry| entry.public_key_compressed.is_some()));
+    }
+
+    #[test]
+    fn test_pairing_input_flag() {
+        let (data, _) =
+            run_with(&["--seed", "2", "--chain-id", "1", "--include-invalid", "--pairing-input"]);
+        assert!(data.iter().flat_map(|entry| &entry.proof).all(|proof| {
+            proof.pairing_input_stake_manager.is_some() &&
+                proof.pairing_input_validator_manager.is_some()
+        }));
+        assert!(verify_fixture(&data).iter().all(|result| result.passed()));
+        let (_, raw) = run_with(&["--seed", "2", "--chain-id", "1"]);
+        assert!(!raw.contains("pairing_input"), "pairing inputs are opt-in");
+    }
+
+    #[test]
+    fn test_include_intermediates_flag() {
+        let args = ["--seed", "2", "--chain-id", "1", "--include-invalid"];
+        let (data, _) = run_with(&[&args[..], &["--include-intermediates"]].concat());
+        for proof in data.iter().flat_map(|entry| &entry.proof) {
+            let stages = proof.hash_to_curve_stake_manager.as_ref().expect("stages are written");
+            assert_eq!(stages.point, proof.message_hash_stake_manager);
+            assert_eq!(proof.xmd_expansion_stake_manager.as_ref(), Some(&stages.uniform_bytes));
+            let stages = proof.hash_to_curve_validator_manager.as_ref().unwrap();
+            assert_eq!(stages.point, proof.message_hash_validator_manager);
+            assert_eq!(proof.xmd_expansion_validator_manager.as_ref(), Some(&stages.uniform_bytes));
+        }
+        assert!(verify_fixture(&data).iter().all(|result| result.passed()));
+        let (plain, raw) = run_with(&args);
+        assert!(!raw.contains("hash_to_curve"), "intermediates are opt-in");
+        assert!(!raw.contains("xmd_expansion"));
+        assert_eq!(plain[0].public_key, data[0].public_key);
+    }
+
+    #[test]
+    fn test_foundry_layout_flag() {
+        let (cases, _) = run_with(&["--seed", "4", "--chain-id", "1"]);
+        let raw = run_raw(&["--seed", "4", "--chain-id", "1", "--layout", "foundry"]).unwrap();
+        let expected: Vec<bls_test_utils::FoundryFixture> =
+            cases.iter().map(|case| foundry_fixture(case).unwrap()).collect();
+        assert_eq!(raw, OutputFormat::Json.encode_cases(&expected, false).unwrap());
+        assert!(run_raw(&["--layout", "foundry", "--format", "yaml"]).is_err());
+        assert!(
+            Cli::try_parse_from(["bls-test-utils", "--layout", "foundry", "--aggregate"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_emit_solidity_alongside_json() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let sol = dir.path().join("BlsTestVectors.sol");
+        let (data, _) =
+            run_with(&["--seed", "2", "--chain-id", "1", "--emit-solidity", sol.to_str().unwrap()]);
+        assert_eq!(data.len(), DEFAULT_WALLETS.len(), "JSON is still written");
+        let rendered = fs::read_to_string(&sol).expect("solidity output");
+        assert_eq!(rendered, render_solidity(&data).unwrap());
+    }
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    #[test]
+    fn test_wallets_file() {
+        let file = format!("{FIXTURES}/wallets.txt");
+        let (data, _) = run_with(&["--wallets-file", &file, "--chain-id", "1", "--skip-verify"]);
+        let wallets: Vec<&str> = data.iter().map(|entry| entry.wallet_address.as_str()).collect();
+        assert_eq!(wallets, DEFAULT_WALLETS[..3], "duplicates are dropped, order is kept");
+
+        let file = format!("{FIXTURES}/wallets.json");
+        let (data, _) = run_with(&[
+            "--wallet",
+            DEFAULT_WALLETS[4],
+            "--wallets-file",
+            &file,
+            "--chain-id",
+            "1",
+            "--skip-verify",
+        ]);
+        let wallets: Vec<&str> = data.iter().map(|entry| entry.wallet_address.as_str()).collect();
+        assert_eq!(wallets, [DEFAULT_WALLETS[4], DEFAULT_WALLETS[0], DEFAULT_WALLETS[1]]);
+    }
+
+    #[test]
+    fn test_wallets_file_reports_malformed_line() {
+        let file = format!("{FIXTURES}/wallets_malformed.txt");
+        let error = run_raw(&["--wallets-file", &file, "--chain-id", "1"]).unwrap_err();
+        let message = format!("{error:#}");
+        assert!(message.contains("wallets_malformed.txt"), "{message}");
+        assert!(
+            message.contains("line 3: `0x52d4630789F63F9C715a2D30fCe65727D009f8`"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_private_key_flags() {
+        let (generated, _) = run_with(&[
+            "--seed",
+            "42",
+            "--chain-id",
+            "1",
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--include-secrets",
+        ]);
+        let key = generated[0].private_key.clone().unwrap();
+        let (imported, _) = run_with(&[
+            "--chain-id",
+            "1",
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--private-key",
+            &key,
+            "--include-secrets",
+        ]);
+        assert_eq!(imported[0].private_key.as_ref(), Some(&key));
+        assert_eq!(imported[0].public_key, generated[0].public_key);
+        assert_eq!(undated(imported), generated);
+
+        let error = run_raw(&[
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--wallet",
+            DEFAULT_WALLETS[1],
+            "--private-key",
+            &key,
+        ])
+        .unwrap_err();
+        assert!(error.to_string().contains("got 1 keys for 2 wallets"), "{error}");
+
+        let error = Cli::try_parse_from([
+            "bls-test-utils",
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--private-key",
+            "0x00",
+        ])
+        .unwrap_err();
+        assert!(error.to_string().contains("private key must be nonzero"), "{error}");
+
+        let order = format!("{GROUP_ORDER:#x}");
+        let error = Cli::try_parse_from([
+            "bls-test-utils",
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--private-key",
+            &order,
+        ])
+        .unwrap_err();
+        assert!(error.to_string().contains("not below the BN254 group order"), "{error}");
+    }
+
+    #[test]
+    fn test_keys_file() {
+        let file = format!("{FIXTURES}/keys.txt");
+        let (data, _) = run_with(&["--keys-file", &file, "--chain-id", "1", "--include-secrets"]);
+        let keys: Vec<(&str, &str)> = data
+            .iter()
+            .map(|entry| (entry.wallet_address.as_str(), entry.private_key.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                (
+                    DEFAULT_WALLETS[0],
+                    "0x0000000000000000000000000000000000000000000000000000000000000001"
+                ),
+                (
+                    DEFAULT_WALLETS[1],
+                    "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000"
+                ),
+            ]
+        );
+
+        let error = run_raw(&[
+            "--keys-file",
+            &file,
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--private-key",
+            "0x02",
+        ])
+        .unwrap_err();
+        assert!(error.to_string().contains("two different private keys"), "{error}");
+    }
+
+    #[test]
+    fn test_dst_flags() {
+        let (data, _) = run_with(&[
+            "--chain-id",
+            "1",
+            "--dst-stake-manager",
+            "StakeManager:BN254:PoP:v2:",
+            "--dst-validator-manager",
+            "ValidatorManager:BN254:PoP:v2:",
+        ]);
+        assert!(data
+            .iter()
+            .all(|entry| entry.domain_staking_manager == "StakeManager:BN254:PoP:v2:" &&
+                entry.domain_validator_manager == "ValidatorManager:BN254:PoP:v2:"));
+
+        let error = Cli::try_parse_from(["bls-test-utils", "--dst-stake-manager", ""]).unwrap_err();
+        assert!(error.to_string().contains("between 1 and 255 bytes"), "{error}");
+
+        let (data, _) = run_with(&["--chain-id", "8453", "--dst-per-chain", "--seed", "1"]);
+        assert_eq!(
+            data[0].proof[0].domain_staking_manager.as_deref(),
+            Some("StakeManager:BN254:PoP:v1:8453:")
+        );
+        let dst = "D".repeat(252);
+        let error =
+            run_raw(&["--chain-id", "8453", "--dst-per-chain", "--dst-stake-manager", &dst])
+                .unwrap_err();
+        assert!(error.to_string().contains("StakeManager DST"), "{error}");
+    }
+
+    #[test]
+    fn test_generate_wallets() {
+        let args =
+            ["--generate-wallets", "3", "--seed", "5", "--chain-id", "1", "--include-secrets"];
+        let (data, _) = run_with(&args);
+        assert_eq!(data.len(), 3, "generated wallets replace the defaults");
+        for entry in &data {
+            let key = entry.eth_private_key.as_deref().expect("generated wallet has a key");
+            let signer: alloy::signers::local::PrivateKeySigner =
+                key.trim_start_matches("0x").parse().unwrap();
+            assert_eq!(signer.address().to_string(), entry.wallet_address);
+        }
+        let (again, _) = run_with(&args);
+        assert_eq!(again, data);
+
+        let (mixed, _) = run_with(&[
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--generate-wallets",
+            "1",
+            "--chain-id",
+            "1",
+            "--include-secrets",
+        ]);
+        assert_eq!(mixed[0].wallet_address, DEFAULT_WALLETS[0]);
+        assert!(mixed[0].eth_private_key.is_none(), "supplied addresses have no known key");
+        assert!(mixed[1].eth_private_key.is_some());
+    }
+
+    #[test]
+    fn test_ecdsa_binding() {
+        let (data, _) = run_with(&[
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--generate-wallets",
+            "2",
+            "--seed",
+            "58",
+            "--chain-id",
+            "1",
+            "--chain-id",
+            "8453",
+            "--include-invalid",
+        ]);
+        assert!(data[0].proof.iter().all(|proof| proof.ecdsa_binding.is_none()));
+        for entry in &data[1..] {
+            let pk_words = words_from_hex(&entry.public_key).unwrap();
+            for proof in &entry.proof {
+                let binding = proof.ecdsa_binding.as_ref().expect("generated wallet binds its key");
+                let chain_id = proof.chain_id.parse().unwrap();
+                assert_eq!(binding.digest().unwrap(), binding_digest(&pk_words, chain_id));
+                assert_eq!(binding.recover().unwrap().to_string(), entry.wallet_address);
+            }
+        }
+        assert!(verify_fixture(&data).iter().all(bls_test_utils::CheckResult::passed));
+
+        let mut swapped = data[1].clone();
+        swapped.proof[0].ecdsa_binding = data[1].proof.last().unwrap().ecdsa_binding.clone();
+        let error = verify_fixture(&[swapped]).remove(0).outcome.unwrap_err();
+        assert_eq!(error, VerifyFailure::BindingDigestMismatch);
+        // Another wallet's signature does not recover to this one.
+        let mut resigned = data[1].clone();
+        let binding = resigned.proof[0].ecdsa_binding.as_mut().unwrap();
+        binding.signature = data[2].proof[0].ecdsa_binding.as_ref().unwrap().signature.clone();
+        let error = verify_fixture(&[resigned]).remove(0).outcome.unwrap_err();
+        assert!(matches!(error, VerifyFailure::BindingWrongSigner(_)), "{error}");
+    }
+
+    #[test]
+    fn test_secrets_are_opt_in() {
+        let args = ["--generate-wallets", "2", "--seed", "5", "--chain-id", "1"];
+        let (data, raw) = run_with(&args);
+        assert!(!raw.contains("private_key"), "{raw}");
+        let (secret, _) = run_with(&[&args[..], &["--include-secrets"]].concat());
+        assert!(secret.iter().all(|entry| entry.private_key.is_some()));
+        assert!(secret.iter().all(|entry| entry.eth_private_key.is_some()));
+        let mut stripped = secret.clone();
+        stripped.iter_mut().for_each(BlsTestData::strip_secrets);
+        assert_eq!(stripped, data, "only the secrets differ");
+
+        // Keystores are still encrypted from the key, which then stays out of the fixture.
+        let dir = tempfile::tempdir().expect("tempdir");
+        let keystores = dir.path().join("keystores");
+        let raw = run_raw(
+            &[&args[..], &["--export-keystore", keystores.to_str().unwrap(), "--password", "pw"]]
+                .concat(),
+        )
+        .unwrap();
+        assert!(!raw.contains("private_key"), "{raw}");
+        assert_eq!(fs::read_dir(&keystores).unwrap().count(), 2);
+
+        // So are the entries already in an --append target.
+        let out = dir.path().join("out.json");
+        fs::write(&out, OutputFormat::Json.encode_cases(&secret, false).unwrap()).unwrap();
+        let cli = Cli::try_parse_from([
+            "bls-test-utils",
+            "--out",
+            out.to_str().unwrap(),
+            "--append",
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--chain-id",
+            "1",
+        ])
+        .unwrap();
+        run(&cli).unwrap();
+        assert!(!fs::read_to_string(&out).unwrap().contains("private_key"));
+        let merged = load_fixture(&out).unwrap();
+        assert_eq!(merged[..2], data[..]);
+        assert!(verify_fixture(&merged).iter().all(CheckResult::passed), "no key is needed");
+    }
+
+    #[test]
+    fn test_mnemonic_flags() {
+        let mnemonic = "test test test test test test test test test test test junk";
+        let args = ["--mnemonic", mnemonic, "--count", "2", "--chain-id", "1", "--include-secrets"];
+        let (data, _) = run_with(&args);
+        let wallets: Vec<&str> = data.iter().map(|entry| entry.wallet_address.as_str()).collect();
+        assert_eq!(
+            wallets,
+            [
+                "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+            ]
+        );
+        assert_eq!(
+            data[0].eth_private_key.as_deref(),
+            Some("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        );
+        assert_eq!(
+            data[0].public_key[0],
+            "0x145b79c788feafda26b922cfccc668778891b5eae460316fa11af25e6ddacffd"
+        );
+        let (again, _) = run_with(&args);
+        assert_eq!(again, data, "the mnemonic alone fixes keys and wallets");
+
+        let (account, _) = run_with(
+            &[
+                &["--mnemonic", mnemonic, "--count", "1", "--chain-id", "1"][..],
+                &["--derivation", "m/44'/60'/1'/0"],
+            ]
+            .concat(),
+        );
+        assert_ne!(account[0].wallet_address, data[0].wallet_address);
+
+        let error = run_raw(&["--mnemonic", mnemonic]).unwrap_err();
+        assert!(error.to_string().contains("--count"), "{error}");
+        let error = run_raw(&["--mnemonic", "not a mnemonic", "--count", "1"]).unwrap_err();
+        assert!(error.to_string().contains("invalid mnemonic"), "{error}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--derivation", "m/0"]).is_err());
+    }
+
+    #[test]
+    fn test_encoding_flag() {
+        let (_, raw) = run_with(&["--seed", "1", "--chain-id", "1", "--encoding", "standard"]);
+        assert!(raw.contains(r#""message_encoding": "standard""#), "{raw}");
+        let (_, raw) = run_with(&["--seed", "1", "--chain-id", "1"]);
+        assert!(raw.contains(r#""message_encoding": "packed""#), "{raw}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--encoding", "rlp"]).is_err());
+    }
+
+    #[test]
+    fn test_calldata_flag() {
+        use alloy::{
+            dyn_abi::{DynSolValue, JsonAbiExt},
+            json_abi::Function,
+        };
+
+        let (data, _) = run_with(&[
+            "--seed",
+            "33",
+            "--chain-id",
+            "1",
+            "--chain-id",
+            "31337",
+            "--include-invalid",
+            "--include-attacks",
+            "--calldata",
+        ]);
+        let function = Function::parse("registerValidator(uint256[4],uint256[2])").unwrap();
+        let words = |value: &DynSolValue| -> Vec<String> {
+            let DynSolValue::FixedArray(words) = value else { panic!("{value:?}") };
+            words.iter().map(|word| format!("{:#066x}", word.as_uint().unwrap().0)).collect()
+        };
+        for entry in &data {
+            for proof in &entry.proof {
+                let calldata = hex::decode(&proof.calldata.as_ref().unwrap()[2..]).unwrap();
+                assert_eq!(calldata[..4], function.selector()[..]);
+                let arguments = function.abi_decode_input(&calldata[4..]).unwrap();
+                assert_eq!(words(&arguments[0]), entry.public_key);
+                assert_eq!(words(&arguments[1]), proof.proof_of_possession_stake_manager);
+            }
+        }
+        assert!(verify_fixture(&data).iter().all(bls_test_utils::CheckResult::passed));
+
+        let custom = |args: &[&str]| {
+            let (data, _) =
+                run_with(&[&["--seed", "33", "--chain-id", "1", "--calldata"], args].concat());
+            data[0].proof[0].calldata.clone().unwrap()
+        };
+        assert!(custom(&["--selector", "0xdeadbeef"]).starts_with("0xdeadbeef"));
+        let renamed = Function::parse("register(uint256[4],uint256[2])").unwrap();
+        assert!(custom(&["--signature", "register(uint256[4],uint256[2])"])
+            .starts_with(&renamed.selector().to_string()));
+
+        let (plain, raw) = run_with(&["--seed", "33", "--chain-id", "1"]);
+        assert!(!raw.contains("calldata"), "calldata is opt-in");
+        assert_eq!(plain[0].public_key, data[0].public_key);
+        let parse = |args: &[&str]| Cli::try_parse_from([&["bls-test-utils"], args].concat());
+        assert!(parse(&["--selector", "0xdeadbeef"]).is_err(), "--selector needs --calldata");
+        let error = parse(&["--calldata", "--signature", "registerValidator(bytes)"]).unwrap_err();
+        assert!(error.to_string().contains("must take (uint256[4],uint256[2])"), "{error}");
+    }
+
+    #[test]
+    fn test_pop_style_flag() {
+        let (data, raw) = run_with(&["--seed", "1", "--pop-style", "pubkey", "--include-invalid"]);
+        assert!(raw.contains(r#""pop_style": "pubkey""#), "{raw}");
+        for entry in &data {
+            assert_eq!(entry.domain_staking_manager, "StakeManager:BN254:PoP:pubkey:v1:");
+            assert_eq!(entry.domain_validator_manager, "ValidatorManager:BN254:PoP:pubkey:v1:");
+            let chain_ids: Vec<&str> = entry.proof.iter().map(|p| p.chain_id.as_str()).collect();
+            assert_eq!(chain_ids, ["0"; 4], "the default chain ids are not looped over");
+        }
+        assert!(verify_fixture(&data).iter().all(bls_test_utils::CheckResult::passed));
+
+        let (data, raw) = run_with(&["--seed", "1", "--chain-id", "1"]);
+        assert!(raw.contains(r#""pop_style": "message""#), "{raw}");
+        assert_eq!(data[0].domain_staking_manager, DEFAULT_DST_STAKE_MANAGER);
+        let error = run_raw(&["--pop-style", "pubkey", "--dst-per-chain"]).unwrap_err();
+        assert!(error.to_string().contains("--dst-per-chain"), "{error}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--pop-style", "key"]).is_err());
+    }
+
+    #[test]
+    fn test_nonce_flags() {
+        let args = ["--seed", "39", "--chain-id", "1", "--chain-id", "8453", "--include-invalid"];
+        let (plain, _) = run_with(&args);
+        assert!(plain.iter().flat_map(|case| &case.proof).all(|proof| proof.nonce.is_none()));
+
+        let (cases, _) = run_with(&[&args[..], &["--with-nonce", "--nonce-start", "7"]].concat());
+        for (case, nonce) in cases.iter().zip(7..) {
+            let nonce = nonce.to_string();
+            assert!(case.proof.iter().all(|proof| proof.nonce.as_ref() == Some(&nonce)));
+            assert!(case
+                .proof
+                .iter()
+                .any(|proof| proof.invalid_reason.as_deref() == Some("wrong_nonce")));
+        }
+        assert!(verify_fixture(&cases).iter().all(bls_test_utils::CheckResult::passed));
+        let (from_zero, _) = run_with(&[&args[..], &["--with-nonce"]].concat());
+        assert_eq!(from_zero[0].proof[0].nonce.as_deref(), Some("0"));
+
+        let error = run_raw(&["--with-nonce", "--pop-style", "pubkey"]).expect_err("no nonce");
+        assert!(error.to_string().contains("--with-nonce"), "{error}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--nonce-start", "7"]).is_err());
+        assert!(Cli::try_parse_from(["bls-test-utils", "--with-nonce", "--rotate"]).is_err());
+    }
+
+    #[test]
+    fn test_preimage_flag() {
+        let args = ["--seed", "47", "--chain-id", "1", "--preimage", "eip712", "--include-invalid"];
+        let (cases, raw) = run_with(&[&args[..], &["--expiry", "1700000000"]].concat());
+        assert!(raw.contains(r#""preimage": "eip712""#), "{raw}");
+        for (case, nonce) in cases.iter().zip(["0", "1", "2"]) {
+            let fields = case.proof[0].struct_preimage.as_ref().expect("struct fields");
+            assert_eq!((fields.nonce.as_str(), fields.expiry.as_str()), (nonce, "1700000000"));
+            assert_eq!(case.proof[0].nonce.as_deref(), Some(nonce));
+            assert_eq!(case.proof[0].message_bytes.as_ref(), Some(&fields.digest));
+        }
+        assert!(verify_fixture(&cases).iter().all(bls_test_utils::CheckResult::passed));
+        let (never, _) = run_with(&args);
+        let expiry = &never[0].proof[0].struct_preimage.as_ref().unwrap().expiry;
+        assert_eq!(expiry, &U256::MAX.to_string());
+
+        let (plain, raw) = run_with(&["--seed", "47", "--chain-id", "1"]);
+        assert!(!raw.contains("preimage"), "{raw}");
+        assert_eq!(plain[0].public_key, cases[0].public_key);
+        let error = run_raw(&["--preimage", "eip712", "--pop-style", "pubkey"]).unwrap_err();
+        assert!(error.to_string().contains("--pop-style pubkey"), "{error}");
+        let error = run_raw(&["--expiry", "1"]).unwrap_err();
+        assert!(error.to_string().contains("--preimage eip712"), "{error}");
+        assert!(
+            Cli::try_parse_from(["bls-test-utils", "--preimage", "eip712", "--rotate"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["bls-test-utils", "--preimage", "typed"]).is_err());
+    }
+
+    #[test]
+    fn test_prehash_flag() {
+        let args =
+            ["--seed", "57", "--chain-id", "1", "--include-invalid", "--include-intermediates"];
+        let (prehashed, raw) =
+            run_with(&[&args[..], &["--prehash", "eip191", "--edge-cases"]].concat());
+        assert!(raw.contains(r#""prehash": "eip191""#), "{raw}");
+        assert!(verify_fixture(&prehashed).iter().all(bls_test_utils::CheckResult::passed));
+        let (plain, raw) = run_with(&args);
+        assert!(!raw.contains("prehash") && !raw.contains("eip191_digest"), "{raw}");
+        assert_eq!(prehashed[0].proof[0].message_bytes, plain[0].proof[0].message_bytes);
+        assert_ne!(
+            prehashed[0].proof[0].proof_of_possession_stake_manager,
+            plain[0].proof[0].proof_of_possession_stake_manager
+        );
+
+        let error = run_raw(&["--prehash", "eip191", "--preimage", "eip712"]).unwrap_err();
+        assert!(error.to_string().contains("eip712 digest"), "{error}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--prehash", "eip191", "--rotate"]).is_err());
+        assert!(Cli::try_parse_from(["bls-test-utils", "--prehash", "sha3"]).is_err());
+    }
+
+    #[test]
+    fn test_chain_id_width_flag() {
+        let args = ["--seed", "50", "--chain-id", "8453", "--include-invalid"];
+        let (narrow, raw) = run_with(&[&args[..], &["--chain-id-width", "64"]].concat());
+        assert!(raw.contains(r#""chain_id_width": 64"#), "{raw}");
+        assert!(verify_fixture(&narrow).iter().all(bls_test_utils::CheckResult::passed));
+        let (wide, raw) = run_with(&args);
+        assert!(!raw.contains("chain_id_width"), "{raw}");
+        assert_ne!(narrow[0].proof[0].message_keccak, wide[0].proof[0].message_keccak);
+
+        let too_wide = (U256::from(u64::MAX) + U256::from(1)).to_string();
+        let error = run_raw(&["--chain-id-width", "64", "--chain-id", &too_wide]).unwrap_err();
+        assert!(error.to_string().contains("does not fit in a uint64"), "{error}");
+        let error = run_raw(&["--chain-id-width", "64", "--preimage", "eip712"]).unwrap_err();
+        assert!(error.to_string().contains("--chain-id-width"), "{error}");
+        assert!(Cli::try_parse_from(["bls-test-utils", "--chain-id-width", "32"]).is_err());
+    }
+
+    #[test]
+    fn test_number_format_flag() {
+        let args =
+            ["--seed", "41", "--chain-id", "1", "--include-invalid", "--include-intermediates"];
+        let (hex, hex_raw) = run_with(&args);
+        let (dec, dec_raw) = run_with(&[&args[..], &["--number-format", "dec"]].concat());
+        assert!(!hex_raw.contains("number_format"));
+        let header: serde_json::Value = serde_json::from_str(&dec_raw).unwrap();
+        assert_eq!(header["number_format"], "dec");
+        assert_ne!(hex, dec);
+        assert!(dec[0].public_key.iter().all(|word| !word.starts_with("0x")));
+        for (hex, dec) in hex.iter().zip(&dec) {
+            assert_eq!(words_from_hex(&hex.public_key), words_from_hex(&dec.public_key));
+            for (hex, dec) in hex.proof.iter().zip(&dec.proof) {
+                assert_eq!(
+                    words_from_hex(&hex.proof_of_possession_stake_manager),
+                    words_from_hex(&dec.proof_of_possession_stake_manager)
+                );
+                assert_eq!(
+                    words_from_hex(&hex.message_hash_validator_manager),
+                    words_from_hex(&dec.message_hash_validator_manager)
+                );
+            }
+        }
+        assert!(verify_fixture(&dec).iter().all(bls_test_utils::CheckResult::passed));
+        assert!(
+            Cli::try_parse_from(["bls-test-utils", "--number-format", "dec", "--rotate"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["bls-test-utils", "--number-format", "oct"]).is_err());
+    }
+
+    #[test]
+    fn test_hash_flags() {
+        let (keccak, raw) = run_with(&["--seed", "37", "--chain-id", "1"]);
+        assert!(raw.contains(r#""hash_function": "keccak256""#), "{raw}");
+        assert!(raw.contains(r#""expand_len": 96"#), "{raw}");
+
+        let args = ["--seed", "37", "--chain-id", "1", "--hash", "sha256", "--expand-len", "96"];
+        let (sha256, raw) = run_with(&args);
+        assert!(raw.contains(r#""hash_function": "sha256""#), "{raw}");
+        assert_eq!(sha256[0].public_key, keccak[0].public_key);
+        assert_eq!(sha256[0].proof[0].message_bytes, keccak[0].proof[0].message_bytes);
+        for (sha256, keccak) in sha256[0].proof.iter().zip(&keccak[0].proof) {
+            assert_ne!(sha256.message_hash_stake_manager, keccak.message_hash_stake_manager);
+            assert_ne!(
+                sha256.message_hash_validator_manager,
+                keccak.message_hash_validator_manager
+            );
+        }
+        assert!(verify_fixture(&sha256).iter().all(bls_test_utils::CheckResult::passed));
+
+        let mut relabelled = sha256[0].clone();
+        relabelled.hash_function = HashFunction::Keccak256;
+        assert!(!verify_fixture(&[relabelled]).iter().any(bls_test_utils::CheckResult::passed));
+
+        for args in [["--hash", "sha3"], ["--expand-len", "128"], ["--expand-len", "many"]] {
+            let args = ["bls-test-utils", args[0], args[1]];
+            assert!(Cli::try_parse_from(args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let keystores = dir.path().join("keystores");
+        let keystores = keystores.to_str().unwrap();
+        let (exported, _) = run_with(&[
+            "--wallet",
+            DEFAULT_WALLETS[0],
+            "--wallet",
+            DEFAULT_WALLETS[1],
+            "--chain-id",
+            "1",
+            "--export-keystore",
+            keystores,
+            "--password",
+            "hunter2",
+            "--kdf",
+            "pbkdf2",
+        ]);
+
+        let first = format!("{keystores}/{}.json", DEFAULT_WALLETS[0]);
+        let second = format!("{keystores}/{}.json", DEFAULT_WALLETS[1]);
+        let (imported, _) = run_with(&[
+            "--import-keystore",
+            &first,
+            "--import-keystore",
+            &second,
+            "--chain-id",
+            "1",
+            "--password",
+            "hunter2",
+        ]);
+        assert_eq!(
+            undated(imported),
+            undated(exported),
+            "regenerated vectors match the exported keys"
+        );
+
+        let error =
+            run_raw(&["--import-keystore", &first, "--chain-id", "1", "--password", "hunter3"])
+                .unwrap_err();
+        assert!(format!("{error:#}").contains("wrong password"), "{error:#}");
+        let rotate = ["bls-test-utils", "--rotate", "--export-keystore", keystores];
+        assert!(Cli::try_parse_from(rotate).is_err(), "rotate mode writes no keystores");
+    }
+
+    #[test]
+    fn test_invalid_wallet_names_input() {
+        let error = Cli::try_parse_from(["bls-test-utils", "--wallet", "0xnotanaddress"])
+            .expect_err("invalid address is rejected");
+        assert!(error.to_string().contains("0xnotanaddress"), "{error}");
+    }
 }
@@ -0,0 +1,154 @@
+//! Test wallets derived from a BIP-39 mnemonic, so that devnet fixtures sign for accounts a team
+//! already has in its wallets.
+//!
+//! Account `i` is the secp256k1 key at `{derivation}/{i}` (BIP-32, e.g. `m/44'/60'/0'/0/0` for
+//! MetaMask's first account). Its BLS secret is drawn, as by [`random_secret_key`], from a
+//! ChaCha20 RNG seeded with `HKDF-SHA256(ikm = eth key, salt = "", info = BLS_KEY_INFO)`, so the
+//! BLS key follows from the same mnemonic without being stored anywhere.
+
+use crate::keys::random_secret_key;
+use alloy::signers::local::{coins_bip39::English, MnemonicBuilder, PrivateKeySigner};
+use hmac::{Hmac, Mac};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use sha2::Sha256;
+use sylow::Fp;
+
+/// MetaMask's (and anvil's) account path; account `i` is `m/44'/60'/0'/0/i`.
+pub const DEFAULT_DERIVATION: &str = "m/44'/60'/0'/0";
+
+/// HKDF `info` of the BLS secret paired with a derived account.
+pub const BLS_KEY_INFO: &[u8] = b"bls-test-utils:BN254:secret-key:v1";
+
+/// Why accounts could not be derived from a mnemonic.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum MnemonicError {
+    #[error("`{path}` is not a BIP-32 derivation path: {reason}")]
+    InvalidDerivation { path: String, reason: String },
+    #[error("invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+}
+
+/// The first `count` accounts of the English `phrase` under `derivation`.
+pub fn derive_eth_wallets(
+    phrase: &str,
+    derivation: &str,
+    count: u32,
+) -> Result<Vec<PrivateKeySigner>, MnemonicError> {
+    (0..count)
+        .map(|index| {
+            let path = format!("{}/{index}", derivation.trim_end_matches('/'));
+            MnemonicBuilder::<English>::default()
+                .phrase(phrase)
+                .derivation_path(&path)
+                .map_err(|error| MnemonicError::InvalidDerivation {
+                    path: derivation.to_string(),
+                    reason: error.to_string(),
+                })?
+                .build()
+                .map_err(|error| MnemonicError::InvalidMnemonic(error.to_string()))
+        })
+        .collect()
+}
+
+/// The BLS secret paired with a derived account, see the module docs.
+pub fn bls_secret_from_eth_key(signer: &PrivateKeySigner) -> Fp {
+    random_secret_key(&mut ChaCha20Rng::from_seed(hkdf_sha256(&signer.to_bytes().0, BLS_KEY_INFO)))
+}
+
+/// RFC 5869 HKDF-SHA256 with an empty salt and 32 bytes of output, a single expand block.
+fn hkdf_sha256(ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let prk = Hmac::<Sha256>::new_from_slice(&[0; 32])
+        .expect("HMAC takes any key length")
+        .chain_update(ikm)
+        .finalize()
+        .into_bytes();
+    Hmac::<Sha256>::new_from_slice(&prk)
+        .expect("HMAC takes any key length")
+        .chain_update(info)
+        .chain_update([1])
+        .finalize()
+        .into_bytes()
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        keys::keypair_from_secret,
+        words::{g2_to_words_solidity, words_to_hex},
+    };
+    use alloy::primitives::{address, b256};
+    use sylow::G2Affine;
+
+    /// anvil's and hardhat's default mnemonic.
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    /// BLS public keys of the first two accounts of [`TEST_MNEMONIC`].
+    const PINNED_PUBLIC_KEYS: [[&str; 4]; 2] = [
+        [
+            "0x145b79c788feafda26b922cfccc668778891b5eae460316fa11af25e6ddacffd",
+            "0x01cbd86d411b1cd6076996ea483ab8e4f9d21f194b931aa8ae766e9fd26265e0",
+            "0x00dcb95a564ba1dfe4b544d456afdd11785cbde50481cab3ddfac79d7cacf4bc",
+            "0x151b193efc574464022030527dda355b844489c4f9bea6923dd7225e2a5b74de",
+        ],
+        [
+            "0x0964bc0b357206a8a6caa014e4ac142ba217c9b82f4b7e79e33f074b86a0dbd3",
+            "0x14689f51a06bcc726c256511668d2e9cc3a1ded7b1a99ea0ee96d577b0c0d23a",
+            "0x1959cb08df7955f190d121630ab5dedf2e670a5e892ef3d374c764bb193e0056",
+            "0x23cab290e42290fd264955073fda66a29130b68599652c2b547024061fbd2022",
+        ],
+    ];
+
+    #[test]
+    fn test_derives_anvil_accounts() {
+        let signers = derive_eth_wallets(TEST_MNEMONIC, DEFAULT_DERIVATION, 3).unwrap();
+        let addresses: Vec<_> = signers.iter().map(PrivateKeySigner::address).collect();
+        assert_eq!(
+            addresses,
+            [
+                address!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+                address!("0x70997970C51812dc3A010C7d01b50e0d17dc79C8"),
+                address!("0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC"),
+            ]
+        );
+        assert_eq!(
+            signers[0].to_bytes(),
+            b256!("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        );
+        let trailing = derive_eth_wallets(TEST_MNEMONIC, "m/44'/60'/0'/0/", 1).unwrap();
+        assert_eq!(trailing[0].address(), addresses[0]);
+    }
+
+    #[test]
+    fn test_bls_keys_are_pinned() {
+        let signers = derive_eth_wallets(TEST_MNEMONIC, DEFAULT_DERIVATION, 2).unwrap();
+        let public_keys: Vec<[String; 4]> = signers
+            .iter()
+            .map(|signer| {
+                let kp = keypair_from_secret(bls_secret_from_eth_key(signer));
+                words_to_hex(g2_to_words_solidity(&G2Affine::from(kp.public_key)))
+            })
+            .collect();
+        assert_eq!(public_keys, PINNED_PUBLIC_KEYS);
+    }
+
+    #[test]
+    fn test_hkdf_matches_rfc_5869() {
+        // RFC 5869 test case 3: SHA-256 with zero-length salt and info, truncated to one block.
+        let okm = hkdf_sha256(&[0x0b; 22], b"");
+        assert_eq!(
+            hex::encode(okm),
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d"
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let error = derive_eth_wallets("not a mnemonic", DEFAULT_DERIVATION, 1).unwrap_err();
+        assert!(matches!(error, MnemonicError::InvalidMnemonic(_)), "{error}");
+        let error = derive_eth_wallets(TEST_MNEMONIC, "m/44'/sixty", 1).unwrap_err();
+        assert!(matches!(error, MnemonicError::InvalidDerivation { .. }), "{error}");
+        assert!(derive_eth_wallets("not a mnemonic", DEFAULT_DERIVATION, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+//! EIP-2537-style padded encodings of the G2 words produced by [`crate::words`].
+//!
+//! EIP-2537 writes every base field element as 64 big-endian bytes, left-padded with zeros (16
+//! bytes of padding for a 48-byte BLS12-381 element). A BN254 element takes 32 bytes, so here each
+//! limb is preceded by 32 zero bytes. A G2 point is the 256 bytes `x_re || x_im || y_re || y_im`,
+//! the same limb order as [`g2_to_words_solidity`](crate::g2_to_words_solidity).
+
+use crate::words::g2_from_words_solidity;
+use alloy::primitives::U256;
+
+/// Bytes of one padded field element.
+pub const PADDED_FP_LEN: usize = 64;
+
+/// Zero bytes in front of each 32-byte limb.
+const PADDING_LEN: usize = PADDED_FP_LEN - 32;
+
+/// Pad Solidity-ordered `[x_re, x_im, y_re, y_im]` to 256 bytes.
+pub fn pad_g2(words: [U256; 4]) -> [u8; 4 * PADDED_FP_LEN] {
+    let mut bytes = [0u8; 4 * PADDED_FP_LEN];
+    for (chunk, word) in bytes.chunks_mut(PADDED_FP_LEN).zip(words) {
+        chunk[PADDING_LEN..].copy_from_slice(&word.to_be_bytes::<32>());
+    }
+    bytes
+}
+
+/// Recover `[x_re, x_im, y_re, y_im]` from [`pad_g2`]; `None` if any padding byte is set or the
+/// limbs are not a point in the G2 subgroup.
+pub fn unpad_g2(bytes: &[u8; 4 * PADDED_FP_LEN]) -> Option<[U256; 4]> {
+    let mut words = [U256::ZERO; 4];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks(PADDED_FP_LEN)) {
+        let (padding, limb) = chunk.split_at(PADDING_LEN);
+        if padding.iter().any(|byte| *byte != 0) {
+            return None
+        }
+        *word = U256::from_be_slice(limb);
+    }
+    g2_from_words_solidity(words).ok().map(|_| words)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::words::g2_to_words_solidity;
+    use sylow::{Fp, G2Affine, G2Projective, GroupTrait};
+
+    #[test]
+    fn test_g2_round_trip() {
+        let generator = G2Projective::generator();
+        for point in [generator, -generator, generator * Fp::from(5u64)] {
+            let words = g2_to_words_solidity(&G2Affine::from(point));
+            let padded = pad_g2(words);
+            for (chunk, word) in padded.chunks(PADDED_FP_LEN).zip(words) {
+                assert_eq!(chunk[..PADDING_LEN], [0; PADDING_LEN]);
+                assert_eq!(U256::from_be_slice(&chunk[PADDING_LEN..]), word);
+            }
+            assert_eq!(unpad_g2(&padded), Some(words));
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_encodings() {
+        let padded = pad_g2(g2_to_words_solidity(&G2Affine::generator()));
+        for index in [0, PADDING_LEN - 1, PADDED_FP_LEN, 3 * PADDED_FP_LEN + 5] {
+            let mut dirty = padded;
+            dirty[index] = 1;
+            assert_eq!(unpad_g2(&dirty), None, "padding byte {index}");
+        }
+        let mut off_curve = padded;
+        off_curve[PADDED_FP_LEN - 1] ^= 1;
+        assert_eq!(unpad_g2(&off_curve), None);
+    }
+}
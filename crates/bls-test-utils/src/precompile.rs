@@ -0,0 +1,132 @@
+//! Inputs for the BN254 pairing precompile at `0x08` (EIP-197).
+//!
+//! The precompile takes `k` 192-byte pairs `(a_i, b_i)` and returns `1` when
+//! `Π e(a_i, b_i) == 1`. A G1 point is `x || y` and a G2 point is `x_im || x_re || y_im || y_re`,
+//! every coordinate a 32-byte big-endian word: an `Fp2` element `a·i + b` is written `(a, b)`,
+//! imaginary part first, the reverse of [`g2_to_words_solidity`](crate::g2_to_words_solidity).
+//!
+//! A PoP check `e(σ, G2) == e(H(m), pk)` is the two pairs `(σ, -G2)` and `(H(m), pk)`.
+
+use alloy::primitives::{uint, U256};
+
+/// Length of a two-pair pairing input.
+pub const PAIRING_INPUT_LEN: usize = 2 * 192;
+
+/// The negated G2 generator in Solidity limb order `[x_re, x_im, y_re, y_im]`.
+pub const NEG_G2_GENERATOR_SOLIDITY: [U256; 4] = uint!([
+    0x1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed_U256,
+    0x198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2_U256,
+    0x1d9befcd05a5323e6da4d435f3b617cdb3af83285c2df711ef39c01571827f9d_U256,
+    0x275dc4a288d1afb3cbb1ac09187524c7db36395df7be3b99e673b13a075a65ec_U256,
+]);
+
+/// G2 words in Solidity order, rearranged into the precompile's `[x_im, x_re, y_im, y_re]`.
+fn g2_precompile_words(words: [U256; 4]) -> [U256; 4] {
+    let [x_re, x_im, y_re, y_im] = words;
+    [x_im, x_re, y_im, y_re]
+}
+
+/// The precompile input for one PoP check: `(signature, -G2) || (message_hash, public_key)`,
+/// with G1 words as [`g1_to_words`](crate::g1_to_words) returns them and the public key in
+/// Solidity limb order. The precompile returns `1` exactly when the PoP verifies.
+pub fn pairing_input(
+    signature: [U256; 2],
+    message_hash: [U256; 2],
+    public_key: [U256; 4],
+) -> [u8; PAIRING_INPUT_LEN] {
+    let words = signature
+        .into_iter()
+        .chain(g2_precompile_words(NEG_G2_GENERATOR_SOLIDITY))
+        .chain(message_hash)
+        .chain(g2_precompile_words(public_key));
+    let mut input = [0u8; PAIRING_INPUT_LEN];
+    for (chunk, word) in input.as_chunks_mut::<32>().0.iter_mut().zip(words) {
+        *chunk = word.to_be_bytes();
+    }
+    input
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generate::{generate_case, GenOptions},
+        words::{g1_from_words, g2_from_words_solidity, g2_to_words_solidity, words_from_hex},
+    };
+    use alloy::primitives::Address;
+    use sylow::{pairing, G1Projective, G2Affine, G2Projective, GroupTrait, Gt};
+
+    /// What the precompile computes: whether the pairings of all pairs multiply to one (`Gt` is
+    /// written additively in sylow).
+    fn precompile(input: &[u8]) -> bool {
+        let product = input.chunks(192).fold(Gt::identity(), |acc, pair| {
+            let words: Vec<U256> = pair.chunks(32).map(U256::from_be_slice).collect();
+            let a = g1_from_words([words[0], words[1]]).expect("G1 point");
+            let b =
+                g2_from_words_solidity([words[3], words[2], words[5], words[4]]).expect("G2 point");
+            &acc + &pairing(&G1Projective::from(a), &G2Projective::from(b))
+        });
+        product == Gt::identity()
+    }
+
+    #[test]
+    fn test_negated_generator() {
+        let negated = -G2Affine::generator();
+        assert_eq!(g2_to_words_solidity(&negated), NEG_G2_GENERATOR_SOLIDITY);
+    }
+
+    #[test]
+    fn test_layout_matches_eip_197() {
+        let [s_x, s_y, h_x, h_y] = [1u64, 2, 3, 4].map(U256::from);
+        let pk = [5u64, 6, 7, 8].map(U256::from);
+        let input = pairing_input([s_x, s_y], [h_x, h_y], pk);
+        assert_eq!(input.len(), 384);
+
+        let words: Vec<U256> = input.chunks(32).map(U256::from_be_slice).collect();
+        let [g_x_re, g_x_im, g_y_re, g_y_im] = NEG_G2_GENERATOR_SOLIDITY;
+        assert_eq!(words[..6], [s_x, s_y, g_x_im, g_x_re, g_y_im, g_y_re]);
+        let [x_re, x_im, y_re, y_im] = pk;
+        assert_eq!(words[6..], [h_x, h_y, x_im, x_re, y_im, y_re]);
+    }
+
+    #[test]
+    fn test_generated_inputs_satisfy_the_precompile_exactly_when_valid() {
+        let opts = GenOptions {
+            seed: Some("3".parse().unwrap()),
+            include_invalid: true,
+            pairing_input: true,
+            ..Default::default()
+        };
+        let case = generate_case(Address::repeat_byte(3), &[U256::from(1)], &opts).unwrap();
+        let pk = words_from_hex(&case.public_key).unwrap();
+        for proof in &case.proof {
+            for (input, signature, message_hash) in [
+                (
+                    &proof.pairing_input_stake_manager,
+                    &proof.proof_of_possession_stake_manager,
+                    &proof.message_hash_stake_manager,
+                ),
+                (
+                    &proof.pairing_input_validator_manager,
+                    &proof.proof_of_possession_validator_manager,
+                    &proof.message_hash_validator_manager,
+                ),
+            ] {
+                let input = hex::decode(input.as_ref().unwrap().trim_start_matches("0x")).unwrap();
+                let expected = pairing_input(
+                    words_from_hex(signature).unwrap(),
+                    words_from_hex(message_hash).unwrap(),
+                    pk,
+                );
+                assert_eq!(input, expected);
+                // Negative vectors may carry points the precompile rejects outright.
+                let signature = words_from_hex(signature).unwrap();
+                if g1_from_words(signature).is_ok() {
+                    assert_eq!(precompile(&input), proof.valid, "{:?}", proof.invalid_reason);
+                } else {
+                    assert!(!proof.valid);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Key rotation vectors for ValidatorManager's `rotateKey`: the registered (old) key authorizes
+//! its replacement by signing the new public key, chain id and sender.
+
+use crate::{
+    expander::HashFunction,
+    fixture::SCHEMA_VERSION,
+    generate::{
+        case_keypair, pairing_check, pop_message, private_key_hex, GenOptions, GenerateError,
+        MessageEncoding,
+    },
+    keys::{keypair_from_secret, random_secret_key, wallet_rng, SecretKeyPair},
+    words::{g1_to_words, g2_to_words_solidity, words_to_hex},
+};
+use alloy::primitives::{keccak256, Address, U256};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sylow::G2Affine;
+
+/// `invalid_reason` of a rotation signed by the new key instead of the registered one.
+pub const SIGNED_BY_NEW_KEY: &str = "signed_by_new_key";
+
+/// One rotation signature on one chain id.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationProof {
+    pub chain_id: String,
+    /// Hex of the signed preimage, see [`rotation_message`].
+    pub message_bytes: String,
+    /// keccak256 of `message_bytes`.
+    pub message_keccak: String,
+    /// `H(message_bytes)` under `domain_rotate`.
+    pub message_hash: [String; 2],
+    pub signature: [String; 2],
+    /// Whether `signature` verifies under `old_public_key`.
+    pub valid: bool,
+    /// Why a negative vector must be rejected, [`SIGNED_BY_NEW_KEY`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<String>,
+}
+
+/// One wallet's old and new keys with a rotation per chain id.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub wallet_address: String,
+    /// The registered key, which signs the rotation. Only written with `include_secrets` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_private_key: Option<String>,
+    /// Solidity limb order, as in [`BlsTestData`](crate::BlsTestData).
+    pub old_public_key: [String; 4],
+    /// Only written with `include_secrets` set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_private_key: Option<String>,
+    pub new_public_key: [String; 4],
+    pub proof: Vec<RotationProof>,
+}
+
+/// Top-level output of rotate mode, written instead of the `BlsTestData` list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationTestData {
+    /// [`SCHEMA_VERSION`] when written.
+    pub schema_version: u32,
+    pub domain_rotate: String,
+    pub message_encoding: MessageEncoding,
+    pub hash_function: HashFunction,
+    pub expand_len: usize,
+    pub rotations: Vec<KeyRotation>,
+}
+
+/// `abi.encodePacked(chain_id, new_pk_limbs, sender)` (or `abi.encode` of the same values), the
+/// PoP preimage of the new key, which the old key signs under [`GenOptions::dst_rotate`].
+pub fn rotation_message(
+    encoding: MessageEncoding,
+    chain_id: U256,
+    new_pk_words: &[U256; 4],
+    sender: Address,
+) -> Vec<u8> {
+    pop_message(encoding, chain_id, new_pk_words, sender)
+}
+
+/// The key `sender` rotates to, drawn from its own RNG so the old key is unchanged.
+fn new_keypair(sender: Address, opts: &GenOptions) -> SecretKeyPair {
+    let secret_key = random_secret_key(&mut wallet_rng(opts.seed.as_ref(), sender, b"rotate"));
+    keypair_from_secret(secret_key).into()
+}
+
+/// Sign the rotation of `sender` on `chain_id` with `signer`, checking (with `opts.verify`) that
+/// it verifies under the old key exactly when `signer` is the old key.
+fn sign_rotation(
+    sender: Address,
+    chain_id: U256,
+    old: &SecretKeyPair,
+    new: &SecretKeyPair,
+    signer: &SecretKeyPair,
+    opts: &GenOptions,
+) -> Result<RotationProof, GenerateError> {
+    let new_pk_words = g2_to_words_solidity(&G2Affine::from(new.public_key));
+    let message = rotation_message(opts.encoding, chain_id, &new_pk_words, sender);
+    let message_hash = opts.hasher.hash_to_curve(&opts.dst_rotate, &message);
+    let signature = opts.hasher.sign(&opts.dst_rotate, &message, signer.secret_key.expose());
+    let valid = signer.secret_key == old.secret_key;
+
+    if opts.verify {
+        match (valid, pairing_check(&signature, &message_hash, &G2Affine::from(old.public_key))) {
+            (true, false) => {
+                return Err(GenerateError::RotationCheckFailed { wallet: sender, chain_id })
+            }
+            (false, true) => {
+                return Err(GenerateError::NewKeyRotationAccepted { wallet: sender, chain_id })
+            }
+            _ => {}
+        }
+    }
+    Ok(RotationProof {
+        chain_id: chain_id.to_string(),
+        message_bytes: format!("0x{}", hex::encode(&message)),
+        message_keccak: keccak256(&message).to_string(),
+        message_hash: words_to_hex(g1_to_words(&message_hash)),
+        signature: words_to_hex(g1_to_words(&signature)),
+        valid,
+        invalid_reason: (!valid).then(|| SIGNED_BY_NEW_KEY.to_string()),
+    })
+}
+
+/// Give `sender` a new key and sign its rotation on every chain id with the old key, followed by
+/// a negative vector signed by the new key.
+pub fn generate_rotation(
+    sender: Address,
+    chain_ids: &[U256],
+    opts: &GenOptions,
+) -> Result<KeyRotation, GenerateError> {
+    let old = case_keypair(sender, opts);
+    let new = new_keypair(sender, opts);
+    let proof = chain_ids
+        .iter()
+        .flat_map(|chain_id| [(chain_id, &old), (chain_id, &new)])
+        .map(|(chain_id, signer)| sign_rotation(sender, *chain_id, &old, &new, signer, opts))
+        .collect::<Result<_, _>>()?;
+
+    Ok(KeyRotation {
+        wallet_address: sender.to_checksum(None),
+        old_private_key: private_key_hex(opts, &old),
+        old_public_key: words_to_hex(g2_to_words_solidity(&G2Affine::from(old.public_key))),
+        new_private_key: private_key_hex(opts, &new),
+        new_public_key: words_to_hex(g2_to_words_solidity(&G2Affine::from(new.public_key))),
+        proof,
+    })
+}
+
+/// [`generate_rotation`] for every wallet, in parallel.
+pub fn generate_rotations(
+    wallets: &[Address],
+    chain_ids: &[U256],
+    opts: &GenOptions,
+) -> Result<RotationTestData, GenerateError> {
+    let rotations = wallets
+        .par_iter()
+        .map(|wallet| generate_rotation(*wallet, chain_ids, opts))
+        .collect::<Result<_, _>>()?;
+    Ok(RotationTestData {
+        schema_version: SCHEMA_VERSION,
+        domain_rotate: opts.dst_rotate.clone(),
+        message_encoding: opts.encoding,
+        hash_function: opts.hasher.hash_function,
+        expand_len: opts.hasher.expand_len,
+        rotations,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        conventions::DEFAULT_DST_ROTATE,
+        expander::MessageHasher,
+        words::{fp_to_hex, g1_from_words, g2_from_words_solidity, words_from_hex},
+    };
+
+    fn seeded() -> GenOptions {
+        GenOptions {
+            seed: Some("38".parse().unwrap()),
+            include_secrets: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_old_key_signs_new_key() {
+        let wallet = Address::repeat_byte(0x38);
+        let opts = seeded();
+        let rotation =
+            generate_rotation(wallet, &[U256::from(1), U256::from(8453)], &opts).unwrap();
+        assert_ne!(rotation.old_private_key, rotation.new_private_key);
+        assert_eq!(
+            rotation.old_private_key,
+            Some(fp_to_hex(case_keypair(wallet, &opts).secret_key.expose()))
+        );
+        let public = generate_rotation(
+            wallet,
+            &[U256::from(1)],
+            &GenOptions { include_secrets: false, ..opts.clone() },
+        )
+        .unwrap();
+        assert_eq!((public.old_private_key, public.new_private_key), (None, None));
+        assert_eq!(public.new_public_key, rotation.new_public_key);
+        assert_eq!(rotation.proof.len(), 4);
+
+        let old_key =
+            g2_from_words_solidity(words_from_hex(&rotation.old_public_key).unwrap()).unwrap();
+        let new_words = words_from_hex(&rotation.new_public_key).unwrap();
+        for (proof, chain_id) in rotation.proof.iter().zip([1u64, 1, 8453, 8453]) {
+            let message =
+                rotation_message(MessageEncoding::Packed, U256::from(chain_id), &new_words, wallet);
+            assert_eq!(proof.message_bytes, format!("0x{}", hex::encode(&message)));
+            let hash = MessageHasher::default().hash_to_curve(DEFAULT_DST_ROTATE, &message);
+            assert_eq!(proof.message_hash, words_to_hex(g1_to_words(&hash)));
+
+            let signature = g1_from_words(words_from_hex(&proof.signature).unwrap()).unwrap();
+            assert_eq!(pairing_check(&signature, &hash, &old_key), proof.valid);
+        }
+        assert!(rotation.proof[0].valid);
+        assert_eq!(rotation.proof[1].invalid_reason.as_deref(), Some(SIGNED_BY_NEW_KEY));
+    }
+
+    #[test]
+    fn test_rotation_dst_and_determinism() {
+        let wallets = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        let data = generate_rotations(&wallets, &[U256::from(1)], &seeded()).unwrap();
+        assert_eq!(data, generate_rotations(&wallets, &[U256::from(1)], &seeded()).unwrap());
+        assert_eq!(data.domain_rotate, DEFAULT_DST_ROTATE);
+
+        let opts =
+            GenOptions { dst_rotate: "ValidatorManager:BN254:Rotate:v2:".into(), ..seeded() };
+        let other = generate_rotations(&wallets, &[U256::from(1)], &opts).unwrap();
+        assert_eq!(other.rotations[0].new_public_key, data.rotations[0].new_public_key);
+        assert_ne!(
+            other.rotations[0].proof[0].message_hash,
+            data.rotations[0].proof[0].message_hash
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Renders fixtures as a Solidity library so Foundry tests can use them without `vm.parseJson`.
+
+use crate::{fixture::BlsTestData, words::u256_to_0x};
+use alloy::primitives::{Address, U256};
+use std::fmt::Write;
+
+/// A fixture value that cannot be written as a Solidity literal.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RenderError {
+    #[error("wallet {wallet}: `{field}` value `{value}` is not a uint256")]
+    InvalidWord { wallet: String, field: &'static str, value: String },
+    #[error("invalid wallet address `{0}`")]
+    InvalidWallet(String),
+}
+
+/// Quote `value` as a Solidity string literal. Anything but printable ASCII is written as a
+/// `\xNN` escape, since plain string literals may not contain it.
+pub fn solidity_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in value.bytes() {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => write!(out, "\\x{byte:02x}").expect("writing to a String"),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn parse_word(case: &BlsTestData, field: &'static str, value: &str) -> Result<U256, RenderError> {
+    value.parse().map_err(|_| RenderError::InvalidWord {
+        wallet: case.wallet_address.clone(),
+        field,
+        value: value.to_string(),
+    })
+}
+
+/// A `uint256[N]` array literal, with the hex normalised to 32 bytes per word.
+fn words<const N: usize>(
+    case: &BlsTestData,
+    field: &'static str,
+    values: &[String; N],
+) -> Result<String, RenderError> {
+    let literals = values
+        .iter()
+        .map(|value| parse_word(case, field, value).map(u256_to_0x))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Casting the first element fixes the array's type, which would otherwise be inferred from
+    // the magnitude of the first literal.
+    Ok(format!("[uint256({}), {}]", literals[0], literals[1..].join(", ")))
+}
+
+fn render_case(out: &mut String, index: usize, case: &BlsTestData) -> Result<(), RenderError> {
+    let wallet: Address = case
+        .wallet_address
+        .parse()
+        .map_err(|_| RenderError::InvalidWallet(case.wallet_address.clone()))?;
+    let private_key = case
+        .private_key
+        .as_deref()
+        .map(|key| parse_word(case, "private_key", key).map(u256_to_0x))
+        .transpose()?;
+    let chain_id = |value: &str| parse_word(case, "chain_id", value).map(|word| word.to_string());
+
+    writeln!(
+        out,
+        "    function _vector{index}() private pure returns (BlsTestData memory data) {{"
+    )
+    .unwrap();
+    if let Some(private_key) = private_key {
+        writeln!(out, "        data.privateKey = {private_key};").unwrap();
+    }
+    writeln!(out, "        data.publicKey = {};", words(case, "public_key", &case.public_key)?)
+        .unwrap();
+    writeln!(out, "        data.walletAddress = {};", wallet.to_checksum(None)).unwrap();
+    writeln!(out, "        data.domainStake = {};", solidity_string(&case.domain_staking_manager))
+        .unwrap();
+    writeln!(
+        out,
+        "        data.domainValidator = {};",
+        solidity_string(&case.domain_validator_manager)
+    )
+    .unwrap();
+    writeln!(out, "        data.proofs = new ProofData[]({});", case.proof.len()).unwrap();
+    for (i, proof) in case.proof.iter().enumerate() {
+        writeln!(out, "        data.proofs[{i}] = ProofData({{").unwrap();
+        writeln!(out, "            chainId: {},", chain_id(&proof.chain_id)?).unwrap();
+        writeln!(
+            out,
+            "            messageHashStake: {},",
+            words(case, "message_hash_stake_manager", &proof.message_hash_stake_manager)?
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "            messageHashValidator: {},",
+            words(case, "message_hash_validator_manager", &proof.message_hash_validator_manager)?
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "            proofOfPossessionStake: {},",
+            words(
+                case,
+                "proof_of_possession_stake_manager",
+                &proof.proof_of_possession_stake_manager
+            )?
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "            proofOfPossessionValidator: {},",
+            words(
+                case,
+                "proof_of_possession_validator_manager",
+                &proof.proof_of_possession_validator_manager
+            )?
+        )
+        .unwrap();
+        writeln!(out, "            valid: {}", proof.valid).unwrap();
+        writeln!(out, "        }});").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    Ok(())
+}
+
+/// Render `cases` as a `BlsTestVectors` library with one private constructor function per
+/// wallet, reachable through `length()` and `get(index)`.
+pub fn render_solidity(cases: &[BlsTestData]) -> Result<String, RenderError> {
+    let mut out = String::new();
+    out.push_str(HEADER);
+    writeln!(out, "    function length() internal pure returns (uint256) {{").unwrap();
+    writeln!(out, "        return {};", cases.len()).unwrap();
+    writeln!(out, "    }}\n").unwrap();
+    writeln!(out, "    function get(uint256 index) internal pure returns (BlsTestData memory) {{")
+        .unwrap();
+    for index in 0..cases.len() {
+        writeln!(out, "        if (index == {index}) return _vector{index}();").unwrap();
+    }
+    writeln!(out, "        revert(\"BlsTestVectors: index out of range\");").unwrap();
+    writeln!(out, "    }}").unwrap();
+    for (index, case) in cases.iter().enumerate() {
+        out.push('\n');
+        render_case(&mut out, index, case)?;
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+const HEADER: &str = r#"// SPDX-License-Identifier: MIT
+// Generated by bls-test-utils. Do not edit by hand.
+pragma solidity ^0.8.30;
+
+library BlsTestVectors {
+    struct ProofData {
+        uint256 chainId;
+        uint256[2] messageHashStake;
+        uint256[2] messageHashValidator;
+        uint256[2] proofOfPossessionStake;
+        uint256[2] proofOfPossessionValidator;
+        bool valid;
+    }
+
+    struct BlsTestData {
+        uint256 privateKey;
+        uint256[4] publicKey;
+        address walletAddress;
+        string domainStake;
+        string domainValidator;
+        ProofData[] proofs;
+    }
+
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generate::{generate_case, GenOptions};
+
+    #[test]
+    fn test_solidity_string_escaping() {
+        assert_eq!(solidity_string("StakeManager:BN254:PoP:v1:"), "\"StakeManager:BN254:PoP:v1:\"");
+        assert_eq!(solidity_string("a\"b\\c\n"), r#""a\"b\\c\n""#);
+        assert_eq!(solidity_string("é"), r#""\xc3\xa9""#);
+    }
+
+    #[test]
+    fn test_render_rejects_bad_values() {
+        let opts = GenOptions { seed: Some("1".parse().unwrap()), ..Default::default() };
+        let case = generate_case(Address::repeat_byte(1), &[U256::from(1)], &opts).unwrap();
+
+        let mut bad_word = case.clone();
+        bad_word.proof[0].message_hash_stake_manager[1] = "0xzz".into();
+        assert!(matches!(
+            render_solidity(&[bad_word]),
+            Err(RenderError::InvalidWord { field: "message_hash_stake_manager", .. })
+        ));
+
+        let mut bad_wallet = case.clone();
+        bad_wallet.wallet_address = "nope".into();
+        assert_eq!(render_solidity(&[bad_wallet]), Err(RenderError::InvalidWallet("nope".into())));
+
+        let rendered = render_solidity(&[case]).unwrap();
+        assert!(rendered.contains("        if (index == 0) return _vector0();"));
+        assert!(
+            rendered.contains("data.walletAddress = 0x0101010101010101010101010101010101010101;")
+        );
+    }
+}
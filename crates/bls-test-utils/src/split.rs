@@ -0,0 +1,216 @@
+//! A fixture split into one JSON file per wallet, so a Foundry test can parse the validator it
+//! needs instead of the whole list.
+//!
+//! `<dir>/<wallet>.json` holds the wallet's entry, named by its checksummed address, and
+//! `<dir>/index.json` is a [`SplitIndex`] of every wallet in generation order. The index is
+//! written last, so a directory whose generation failed half-way does not load.
+
+use crate::{
+    aggregate::AggregatePublicKey,
+    fixture::{BlsTestData, SCHEMA_VERSION},
+    format::{FormatError, LoadError, OutputFormat},
+    words::NumberFormat,
+};
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// File name of the [`SplitIndex`] in a split directory.
+pub const INDEX_FILE: &str = "index.json";
+
+/// `index.json` of a split fixture.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitIndex {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "NumberFormat::is_hex")]
+    pub number_format: NumberFormat,
+    /// Checksummed addresses, each naming its `<wallet>.json`.
+    pub wallets: Vec<String>,
+    /// Every chain id with a proof, in the order first seen.
+    pub chain_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate_public_key: Option<AggregatePublicKey>,
+}
+
+/// Why a split fixture could not be written.
+#[derive(Debug, thiserror::Error)]
+pub enum SplitError {
+    #[error("{0} has more than one entry, but each wallet gets a single file")]
+    DuplicateWallet(Address),
+    #[error("`{0}` is not an address, which a split fixture names its files by")]
+    InvalidWallet(String),
+    #[error("writing {}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Format(#[from] FormatError),
+}
+
+/// Writes a split fixture one entry at a time, like the combined list.
+#[derive(Debug)]
+pub struct SplitWriter {
+    dir: PathBuf,
+    compact: bool,
+    index: SplitIndex,
+    wallets: HashSet<Address>,
+}
+
+impl SplitWriter {
+    /// Start a split fixture in `dir`, creating it if needed. `number_format` is the one the
+    /// entries are written in, recorded in the index.
+    pub fn create(
+        dir: &Path,
+        number_format: NumberFormat,
+        compact: bool,
+    ) -> Result<Self, SplitError> {
+        fs::create_dir_all(dir)
+            .map_err(|source| SplitError::Write { path: dir.to_owned(), source })?;
+        let index = SplitIndex {
+            schema_version: SCHEMA_VERSION,
+            number_format,
+            wallets: Vec::new(),
+            chain_ids: Vec::new(),
+            aggregate_public_key: None,
+        };
+        Ok(Self { dir: dir.to_owned(), compact, index, wallets: HashSet::new() })
+    }
+
+    /// Write `entry`, which is `case` in the layout being written (the case itself or its
+    /// [`FoundryFixture`](crate::FoundryFixture)), to the file of `case`'s wallet.
+    pub fn push<T: Serialize>(&mut self, case: &BlsTestData, entry: &T) -> Result<(), SplitError> {
+        let wallet: Address = case
+            .wallet_address
+            .parse()
+            .map_err(|_| SplitError::InvalidWallet(case.wallet_address.clone()))?;
+        if !self.wallets.insert(wallet) {
+            return Err(SplitError::DuplicateWallet(wallet))
+        }
+        let path = self.dir.join(format!("{wallet}.json"));
+        fs::write(&path, OutputFormat::Json.encode(entry, self.compact)?)
+            .map_err(|source| SplitError::Write { path, source })?;
+        self.index.wallets.push(wallet.to_string());
+        for proof in &case.proof {
+            if !self.index.chain_ids.contains(&proof.chain_id) {
+                self.index.chain_ids.push(proof.chain_id.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the index, with `aggregate_public_key` (as generated, in hex) if there is one.
+    pub fn finish(
+        mut self,
+        mut aggregate_public_key: Option<AggregatePublicKey>,
+    ) -> Result<SplitIndex, SplitError> {
+        if let Some(key) = &mut aggregate_public_key {
+            self.index.number_format.rewrite(&mut key.public_key);
+        }
+        self.index.aggregate_public_key = aggregate_public_key;
+        let path = self.dir.join(INDEX_FILE);
+        fs::write(&path, OutputFormat::Json.encode(&self.index, self.compact)?)
+            .map_err(|source| SplitError::Write { path, source })?;
+        Ok(self.index)
+    }
+}
+
+/// Read the cases of a split fixture in the order of its index.
+pub fn load_split(dir: &Path) -> Result<Vec<BlsTestData>, LoadError> {
+    let index: SplitIndex = read_json(&dir.join(INDEX_FILE))?;
+    index
+        .wallets
+        .iter()
+        .map(|wallet| {
+            let path = dir.join(format!("{wallet}.json"));
+            let case: BlsTestData = read_json(&path)?;
+            if !case.wallet_address.eq_ignore_ascii_case(wallet) {
+                return Err(LoadError::WrongWallet { path, wallet: case.wallet_address })
+            }
+            Ok(case)
+        })
+        .collect()
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, LoadError> {
+    let raw = fs::read_to_string(path)
+        .map_err(|source| LoadError::Read { path: path.to_owned(), source })?;
+    OutputFormat::Json
+        .decode(&raw)
+        .map_err(|source| LoadError::Parse { path: path.to_owned(), source })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{generate::generate_case, GenOptions};
+    use alloy::primitives::U256;
+
+    fn cases() -> Vec<BlsTestData> {
+        let opts = GenOptions { seed: Some("54".parse().unwrap()), ..Default::default() };
+        [Address::repeat_byte(0xab), Address::repeat_byte(0x54)]
+            .map(|wallet| generate_case(wallet, &[U256::from(1), U256::from(8453)], &opts).unwrap())
+            .into()
+    }
+
+    fn write(dir: &Path, cases: &[BlsTestData]) -> Result<SplitIndex, SplitError> {
+        let mut writer = SplitWriter::create(dir, NumberFormat::Hex, false)?;
+        cases.iter().try_for_each(|case| writer.push(case, case))?;
+        writer.finish(None)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = cases();
+        let index = write(dir.path(), &cases).unwrap();
+        assert_eq!(
+            index,
+            SplitIndex {
+                schema_version: SCHEMA_VERSION,
+                number_format: NumberFormat::Hex,
+                wallets: vec![
+                    "0xABaBaBaBABabABabAbAbABAbABabababaBaBABaB".to_string(),
+                    "0x5454545454545454545454545454545454545454".to_string(),
+                ],
+                chain_ids: vec!["1".to_string(), "8453".to_string()],
+                aggregate_public_key: None,
+            }
+        );
+        let raw = fs::read_to_string(dir.path().join(INDEX_FILE)).unwrap();
+        assert_eq!(serde_json::from_str::<SplitIndex>(&raw).unwrap(), index);
+
+        let file = dir.path().join(format!("{}.json", index.wallets[0]));
+        let entry: BlsTestData = serde_json::from_str(&fs::read_to_string(file).unwrap()).unwrap();
+        assert_eq!(entry, cases[0], "each file is the bare entry");
+        assert_eq!(load_split(dir.path()).unwrap(), cases);
+    }
+
+    #[test]
+    fn test_duplicate_wallet_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = cases();
+        let mut lowercase = cases[0].clone();
+        lowercase.wallet_address = lowercase.wallet_address.to_lowercase();
+        let error = write(dir.path(), &[cases[0].clone(), lowercase]).unwrap_err();
+        assert!(matches!(error, SplitError::DuplicateWallet(_)), "{error}");
+        assert!(!dir.path().join(INDEX_FILE).exists());
+        assert!(matches!(load_split(dir.path()), Err(LoadError::Read { .. })));
+    }
+
+    #[test]
+    fn test_renamed_file_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = cases();
+        let index = write(dir.path(), &cases).unwrap();
+        let [first, second] = [0, 1].map(|i| dir.path().join(format!("{}.json", index.wallets[i])));
+        fs::copy(&second, &first).unwrap();
+        let error = load_split(dir.path()).unwrap_err();
+        assert!(matches!(&error, LoadError::WrongWallet { path, .. } if *path == first), "{error}");
+    }
+}
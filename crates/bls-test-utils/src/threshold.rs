@@ -0,0 +1,261 @@
+//! Threshold (t-of-n) BLS vectors from a trusted dealer.
+//!
+//! The dealer draws a random polynomial `f` of degree `t - 1` over the scalar field, keeps
+//! `f(0)` as the master secret and hands share `i` the key `f(i)`, for `i` in `1..=n`. Every
+//! share signs the message, and any `t` partial signatures `σ_i` combine to the master signature
+//! `Σ λ_i · σ_i`, where `λ_i = Π_{j ≠ i} j / (j - i)` are the Lagrange coefficients at zero of
+//! the signing set. That signature verifies under the group public key `f(0) · G2`.
+
+use crate::{
+    expander::MessageHasher,
+    fixture::SCHEMA_VERSION,
+    generate::{pairing_check, sign_hash},
+    keys::{wallet_rng, Seed},
+    words::{fp_to_hex, g1_to_words, g2_to_words_solidity, words_to_hex},
+};
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use sylow::{
+    FieldExtensionTrait, Fp, Fr, G1Affine, G1Projective, G2Affine, G2Projective, GroupTrait,
+};
+
+/// Why threshold vectors could not be generated.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ThresholdError {
+    #[error("threshold must be between 1 and the number of shares ({shares}), got {threshold}")]
+    InvalidThreshold { threshold: u64, shares: u64 },
+    #[error("signer {index} is not a share index (1 to {shares})")]
+    UnknownSigner { index: u64, shares: u64 },
+    #[error("signer {0} is listed twice")]
+    DuplicateSigner(u64),
+    #[error("{signers} signers cannot reach the threshold of {threshold}")]
+    TooFewSigners { signers: usize, threshold: u64 },
+    #[error("partial signature of share {0} fails the pairing check")]
+    PartialCheckFailed(u64),
+    #[error("reconstructed signature fails the pairing check against the group public key")]
+    ReconstructionFailed,
+}
+
+/// One dealt share.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdShare {
+    /// The share's evaluation point, `1..=n`.
+    pub index: u64,
+    /// `f(index)`.
+    pub private_key: String,
+    pub public_key: [String; 4],
+    /// The share's signature over the message.
+    pub partial_signature: [String; 2],
+}
+
+/// Output of the `threshold` subcommand.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdTestData {
+    /// [`SCHEMA_VERSION`] when written.
+    pub schema_version: u32,
+    pub threshold: u64,
+    /// Hex of the signed bytes.
+    pub message: String,
+    pub dst: String,
+    /// The master secret `f(0)`.
+    pub private_key: String,
+    /// `f(0) · G2`, which the reconstructed signature verifies under.
+    pub group_public_key: [String; 4],
+    /// `H(message)` under `dst`.
+    pub message_hash: [String; 2],
+    pub shares: Vec<ThresholdShare>,
+    /// Indices of the shares whose partial signatures were combined, in the order given.
+    pub signers: Vec<u64>,
+    /// Lagrange coefficient at zero of each signer, in the same order.
+    pub lagrange_coefficients: Vec<String>,
+    /// `Σ λ_i · σ_i` over `signers`.
+    pub signature: [String; 2],
+}
+
+/// Check `signers` (the first `threshold` shares when empty) against the split.
+fn signing_set(threshold: u64, shares: u64, signers: &[u64]) -> Result<Vec<u64>, ThresholdError> {
+    if threshold == 0 || threshold > shares {
+        return Err(ThresholdError::InvalidThreshold { threshold, shares })
+    }
+    if signers.is_empty() {
+        return Ok((1..=threshold).collect())
+    }
+    let mut seen = HashSet::new();
+    for &index in signers {
+        if index == 0 || index > shares {
+            return Err(ThresholdError::UnknownSigner { index, shares })
+        }
+        if !seen.insert(index) {
+            return Err(ThresholdError::DuplicateSigner(index))
+        }
+    }
+    if (signers.len() as u64) < threshold {
+        return Err(ThresholdError::TooFewSigners { signers: signers.len(), threshold })
+    }
+    Ok(signers.to_vec())
+}
+
+/// The Lagrange coefficient at zero of `index` within `signers`.
+pub fn lagrange_coefficient(index: u64, signers: &[u64]) -> Fr {
+    signers
+        .iter()
+        .filter(|&&other| other != index)
+        .fold(Fr::ONE, |acc, &other| acc * Fr::from(other) / (Fr::from(other) - Fr::from(index)))
+}
+
+/// Deal `shares` shares of a fresh master secret with threshold `threshold`, sign `message`
+/// under `dst` with every share and combine the partial signatures of `signers` (the first
+/// `threshold` shares when empty). Every partial signature and the combined one are
+/// pairing-checked before anything is returned. The polynomial is drawn from `seed` when given.
+pub fn generate_threshold(
+    seed: Option<&Seed>,
+    threshold: u64,
+    shares: u64,
+    signers: &[u64],
+    message: &[u8],
+    dst: &str,
+) -> Result<ThresholdTestData, ThresholdError> {
+    let signers = signing_set(threshold, shares, signers)?;
+    let mut rng = wallet_rng(seed, Address::ZERO, b"threshold");
+    let coefficients: Vec<Fr> = (0..threshold).map(|_| Fr::rand(&mut rng)).collect();
+    // Horner's rule, highest coefficient first.
+    let evaluate = |x: u64| {
+        coefficients
+            .iter()
+            .rev()
+            .fold(Fr::ZERO, |acc, coefficient| acc * Fr::from(x) + *coefficient)
+    };
+
+    let message_hash = MessageHasher::default().prepare(dst).hash_to_curve(message);
+    let dealt = (1..=shares)
+        .map(|index| {
+            let secret_key = Fp::from(evaluate(index));
+            let public_key = G2Affine::from(G2Projective::generator() * secret_key);
+            let signature = sign_hash(&message_hash, secret_key);
+            if !pairing_check(&signature, &message_hash, &public_key) {
+                return Err(ThresholdError::PartialCheckFailed(index))
+            }
+            Ok((secret_key, public_key, signature))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let lagrange: Vec<Fr> =
+        signers.iter().map(|&index| lagrange_coefficient(index, &signers)).collect();
+    let signature =
+        signers.iter().zip(&lagrange).fold(G1Projective::zero(), |acc, (&index, coefficient)| {
+            let (_, _, partial) = dealt[index as usize - 1];
+            acc + G1Projective::from(partial) * Fp::from(*coefficient)
+        });
+    let signature = G1Affine::from(signature);
+    let master_secret = Fp::from(coefficients[0]);
+    let group_public_key = G2Affine::from(G2Projective::generator() * master_secret);
+    if !pairing_check(&signature, &message_hash, &group_public_key) {
+        return Err(ThresholdError::ReconstructionFailed)
+    }
+
+    Ok(ThresholdTestData {
+        schema_version: SCHEMA_VERSION,
+        threshold,
+        message: format!("0x{}", hex::encode(message)),
+        dst: dst.to_string(),
+        private_key: fp_to_hex(master_secret),
+        group_public_key: words_to_hex(g2_to_words_solidity(&group_public_key)),
+        message_hash: words_to_hex(g1_to_words(&message_hash)),
+        shares: dealt
+            .iter()
+            .zip(1..)
+            .map(|((secret_key, public_key, signature), index)| ThresholdShare {
+                index,
+                private_key: fp_to_hex(*secret_key),
+                public_key: words_to_hex(g2_to_words_solidity(public_key)),
+                partial_signature: words_to_hex(g1_to_words(signature)),
+            })
+            .collect(),
+        signers,
+        lagrange_coefficients: lagrange.into_iter().map(|c| fp_to_hex(Fp::from(c))).collect(),
+        signature: words_to_hex(g1_to_words(&signature)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{keys::parse_secret_key, sign::sign_message};
+
+    const DST: &str = "Checkpoint:BN254:v1:";
+
+    fn generate(threshold: u64, shares: u64, signers: &[u64]) -> ThresholdTestData {
+        let seed = "31".parse().unwrap();
+        generate_threshold(Some(&seed), threshold, shares, signers, b"checkpoint", DST).unwrap()
+    }
+
+    /// The signature the master secret produces directly.
+    fn master_signature(data: &ThresholdTestData) -> [String; 2] {
+        let master = parse_secret_key(&data.private_key).unwrap();
+        let signed = sign_message(master, b"checkpoint", DST).unwrap();
+        assert_eq!(signed.public_key, data.group_public_key);
+        signed.signature
+    }
+
+    #[test]
+    fn test_any_threshold_subset_reconstructs_the_master_signature() {
+        let data = generate(3, 5, &[]);
+        assert_eq!(data.signers, [1, 2, 3]);
+        assert_eq!(data.shares.len(), 5);
+        let expected = master_signature(&data);
+        assert_eq!(data.signature, expected);
+        for signers in [&[1, 3, 5][..], &[5, 2, 4], &[1, 2, 3, 4, 5]] {
+            let subset = generate(3, 5, signers);
+            assert_eq!(subset.shares, data.shares, "the seed fixes the split");
+            assert_eq!(subset.signature, expected, "{signers:?}");
+        }
+    }
+
+    #[test]
+    fn test_edge_thresholds() {
+        let one = generate(1, 3, &[2]);
+        assert!(one.shares.iter().all(|share| share.private_key == one.private_key));
+        assert_eq!(one.lagrange_coefficients, [fp_to_hex(Fp::ONE)]);
+        assert_eq!(one.signature, master_signature(&one));
+
+        let all = generate(4, 4, &[4, 1, 3, 2]);
+        assert_eq!(all.signature, master_signature(&all));
+        assert_ne!(all.shares[0].private_key, all.private_key);
+    }
+
+    #[test]
+    fn test_rejects_bad_signing_sets() {
+        let seed = "31".parse().unwrap();
+        let generate = |threshold, shares, signers: &[u64]| {
+            generate_threshold(Some(&seed), threshold, shares, signers, b"checkpoint", DST)
+                .unwrap_err()
+        };
+        assert_eq!(
+            generate(0, 3, &[]),
+            ThresholdError::InvalidThreshold { threshold: 0, shares: 3 }
+        );
+        assert_eq!(
+            generate(4, 3, &[]),
+            ThresholdError::InvalidThreshold { threshold: 4, shares: 3 }
+        );
+        assert_eq!(generate(2, 3, &[1, 4]), ThresholdError::UnknownSigner { index: 4, shares: 3 });
+        assert_eq!(generate(2, 3, &[0, 1]), ThresholdError::UnknownSigner { index: 0, shares: 3 });
+        assert_eq!(generate(2, 3, &[2, 2]), ThresholdError::DuplicateSigner(2));
+        assert_eq!(
+            generate(2, 3, &[3]),
+            ThresholdError::TooFewSigners { signers: 1, threshold: 2 }
+        );
+    }
+
+    #[test]
+    fn test_lagrange_coefficients_interpolate_constants() {
+        // For any signing set the coefficients sum to one, since they interpolate f(x) = 1.
+        for signers in [&[1u64][..], &[1, 2], &[2, 5, 7], &[3, 1, 4, 9]] {
+            let sum = signers
+                .iter()
+                .fold(Fr::ZERO, |acc, &index| acc + lagrange_coefficient(index, signers));
+            assert_eq!(sum, Fr::ONE, "{signers:?}");
+        }
+    }
+}
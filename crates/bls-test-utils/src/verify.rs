@@ -0,0 +1,852 @@
+//! Re-checks an existing fixture for internal consistency.
+
+use crate::{
+    attack::CHAIN_REPLAY,
+    binding::binding_digest,
+    edge_case::{naive_pairing_check, EdgeCase},
+    eip712::{pop_registration, PopRegistration},
+    expander::{check_expand_len, MessageHasher},
+    fixture::{BlsTestData, ProofData},
+    generate::{
+        combined_pairing_check, g1_add, pairing_check, pop_preimage, ChainIdWidth, Domain,
+        PopStyle, Prehash, Preimage,
+    },
+    hash_to_curve::hash_to_curve_intermediates,
+    hex_util::decode_hex,
+    precompile::pairing_input,
+    words::{g1_to_words, validate_g1, validate_g2, words_from_hex, NumberFormat},
+};
+use alloy::{
+    primitives::{eip191_hash_message, keccak256, Address, U256},
+    sol_types::SolValue,
+};
+use std::fmt;
+use sylow::{G1Affine, G2Affine};
+
+/// Why one PoP in a fixture does not check out.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum VerifyFailure {
+    #[error("`{field}` is not valid hex")]
+    InvalidHex { field: &'static str },
+    #[error("`{field}` is not a point on the curve")]
+    NotOnCurve { field: &'static str },
+    #[error("invalid wallet address `{0}`")]
+    InvalidWallet(String),
+    #[error("invalid chain id `{0}`")]
+    InvalidChainId(String),
+    #[error("chain id {chain_id} does not fit in the entry's uint{width}")]
+    ChainIdTooWide { chain_id: String, width: ChainIdWidth },
+    #[error("invalid nonce `{0}`")]
+    InvalidNonce(String),
+    #[error("invalid expiry `{0}`")]
+    InvalidExpiry(String),
+    #[error("`preimage` is eip712 but the proof has no `struct_preimage`")]
+    MissingStructPreimage,
+    #[error(
+        "`struct_preimage` is not the registration of this wallet, chain id, public key and nonce"
+    )]
+    StructPreimageMismatch,
+    #[error("`message_bytes` is not the PoP message for this wallet, chain id and public key")]
+    MessageBytesMismatch,
+    #[error("`message_keccak` is not keccak256 of `message_bytes`")]
+    MessageKeccakMismatch,
+    #[error("`eip191_digest` is not the EIP-191 digest of `message_bytes`")]
+    Eip191DigestMismatch,
+    #[error("`expand_len` {0} is not an output length sylow supports")]
+    UnsupportedExpandLen(usize),
+    #[error("message hash does not match hash-to-curve of the PoP message")]
+    MessageHashMismatch,
+    #[error("`{field}` is not the pairing precompile input of this PoP")]
+    PairingInputMismatch { field: &'static str },
+    #[error("`{field}` is not the hash-to-curve intermediates of the PoP message")]
+    IntermediatesMismatch { field: &'static str },
+    #[error("`calldata` does not carry the public key and the StakeManager PoP")]
+    CalldataMismatch,
+    #[error("`ecdsa_binding` digest is not keccak256 of the public key and chain id")]
+    BindingDigestMismatch,
+    #[error("`ecdsa_binding` signature does not recover to the wallet: {0}")]
+    BindingWrongSigner(String),
+    #[error("`{field}` is not the sum of the entry's two domains")]
+    CombinedMismatch { field: &'static str },
+    #[error("pairing check failed")]
+    PairingCheckFailed,
+    #[error("combined pairing check of both domains failed")]
+    CombinedCheckFailed,
+    #[error("negative vector ({reason}) passes the pairing check")]
+    InvalidEntryVerifies { reason: String },
+    #[error("{attack} public key decodes as a G2 point")]
+    AttackKeyAccepted { attack: String },
+    #[error("`chain_replay` entry has no `signed_chain_id`")]
+    MissingSignedChainId,
+    #[error("replayed PoP verifies on the chain id it is labelled with")]
+    ChainReplayVerifies,
+    #[error("edge case entry has no `expected_naive_result`")]
+    MissingNaiveResult,
+    #[error("the naive pairing check gives {actual}, not the recorded `expected_naive_result`")]
+    NaiveResultMismatch { actual: bool },
+}
+
+/// Outcome of checking one PoP (one chain id, one domain) of one wallet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub wallet: String,
+    pub chain_id: String,
+    pub domain: Domain,
+    /// The entry's `valid` flag, i.e. whether the PoP is expected to verify.
+    pub valid: bool,
+    pub outcome: Result<(), VerifyFailure>,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = if self.valid { "valid" } else { "invalid" };
+        let outcome = match &self.outcome {
+            Ok(()) => "ok".to_string(),
+            Err(failure) => format!("FAIL: {failure}"),
+        };
+        write!(
+            f,
+            "{:<42} {:>10} {:<16} {:<7} {outcome}",
+            self.wallet, self.chain_id, self.domain, expected
+        )
+    }
+}
+
+/// Check every PoP of every case: the message hash must be hash-to-curve of the PoP message
+/// re-derived from the chain id, public key and wallet (just the public key in the `pubkey`
+/// [`PopStyle`](crate::PopStyle)) under the case's DST (or the proof's own, if it records one), and
+/// the signature must pass the pairing check exactly when the entry is marked `valid`. When the
+/// entry stores `message_bytes` (and `message_keccak`), those must match the re-derived message and
+/// are what gets hashed (through its `eip191_digest`, which must match too, in
+/// [`Prehash::Eip191`]), so a mismatch is reported before the curve point is compared. Stored
+/// `hash_to_curve_*` stages and `xmd_expansion_*` bytes must be those of that message, a stored
+/// `pairing_input_*` the precompile input of the entry's own words, and stored `calldata` must
+/// carry the public key and StakeManager PoP after its selector. Entries tagged with an `attack`
+/// only need their public key to be rejected, except `chain_replay` ones, whose PoP must verify for
+/// `signed_chain_id` and not for `chain_id`, and [`EdgeCase`] ones, whose PoP must be rejected
+/// and give their `expected_naive_result` under [`naive_pairing_check`].
+pub fn verify_fixture(cases: &[BlsTestData]) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for case in cases {
+        for proof in &case.proof {
+            for (domain, dst) in [
+                (
+                    Domain::StakeManager,
+                    proof.domain_staking_manager.as_ref().unwrap_or(&case.domain_staking_manager),
+                ),
+                (
+                    Domain::ValidatorManager,
+                    proof
+                        .domain_validator_manager
+                        .as_ref()
+                        .unwrap_or(&case.domain_validator_manager),
+                ),
+            ] {
+                results.push(CheckResult {
+                    wallet: case.wallet_address.clone(),
+                    chain_id: proof.chain_id.clone(),
+                    domain,
+                    valid: proof.valid,
+                    outcome: match &case.attack {
+                        Some(attack) if attack == CHAIN_REPLAY => {
+                            check_chain_replay(case, proof, domain, dst)
+                        }
+                        Some(attack) if EdgeCase::from_attack(attack).is_some() => {
+                            check_edge_case(case, proof, domain, dst)
+                        }
+                        Some(attack) => check_attack_key(case, attack),
+                        None => check_pop(case, proof, domain, dst),
+                    },
+                });
+            }
+        }
+    }
+    results
+}
+
+fn parse_g1(words: &[String; 2], field: &'static str) -> Result<G1Affine, VerifyFailure> {
+    let words = words_from_hex(words).ok_or(VerifyFailure::InvalidHex { field })?;
+    validate_g1(words).map_err(|_| VerifyFailure::NotOnCurve { field })
+}
+
+/// A `chain_replay` PoP must check out as a valid PoP labelled with its `signed_chain_id`, and
+/// fail the pairing check against the message re-derived from `chain_id`, as the contract on that
+/// chain would compute it.
+fn check_chain_replay(
+    case: &BlsTestData,
+    proof: &ProofData,
+    domain: Domain,
+    dst: &str,
+) -> Result<(), VerifyFailure> {
+    let signed_chain_id =
+        proof.signed_chain_id.clone().ok_or(VerifyFailure::MissingSignedChainId)?;
+    let original = ProofData { chain_id: signed_chain_id, valid: true, ..proof.clone() };
+    check_pop(case, &original, domain, dst)?;
+
+    let ExpectedMessage { public_key, message, .. } = expected_message(case, proof)?;
+    let (signature, field) = match domain {
+        Domain::StakeManager => {
+            (&proof.proof_of_possession_stake_manager, "proof_of_possession_stake_manager")
+        }
+        Domain::ValidatorManager => {
+            (&proof.proof_of_possession_validator_manager, "proof_of_possession_validator_manager")
+        }
+    };
+    let message_hash = case_hasher(case)?.hash_to_curve(dst, &case.prehash.apply(&message));
+    if pairing_check(&parse_g1(signature, field)?, &message_hash, &public_key) {
+        return Err(VerifyFailure::ChainReplayVerifies)
+    }
+    Ok(())
+}
+
+/// An [`EdgeCase`] PoP must give its `expected_naive_result` under [`naive_pairing_check`] and be
+/// rejected as a negative vector: by the public key failing validation or by [`check_pop`].
+fn check_edge_case(
+    case: &BlsTestData,
+    proof: &ProofData,
+    domain: Domain,
+    dst: &str,
+) -> Result<(), VerifyFailure> {
+    let expected = proof.expected_naive_result.ok_or(VerifyFailure::MissingNaiveResult)?;
+    let (signature, hash, signature_field, hash_field) = match domain {
+        Domain::StakeManager => (
+            &proof.proof_of_possession_stake_manager,
+            &proof.message_hash_stake_manager,
+            "proof_of_possession_stake_manager",
+            "message_hash_stake_manager",
+        ),
+        Domain::ValidatorManager => (
+            &proof.proof_of_possession_validator_manager,
+            &proof.message_hash_validator_manager,
+            "proof_of_possession_validator_manager",
+            "message_hash_validator_manager",
+        ),
+    };
+    let pk_words = words_from_hex(&case.public_key)
+        .ok_or(VerifyFailure::InvalidHex { field: "public_key" })?;
+    let signature =
+        words_from_hex(signature).ok_or(VerifyFailure::InvalidHex { field: signature_field })?;
+    let hash = words_from_hex(hash).ok_or(VerifyFailure::InvalidHex { field: hash_field })?;
+    let actual = naive_pairing_check(signature, hash, pk_words);
+    if actual != expected {
+        return Err(VerifyFailure::NaiveResultMismatch { actual })
+    }
+    match validate_g2(pk_words) {
+        Ok(_) => check_pop(case, &ProofData { valid: false, ..proof.clone() }, domain, dst),
+        Err(_) => Ok(()),
+    }
+}
+
+fn check_attack_key(case: &BlsTestData, attack: &str) -> Result<(), VerifyFailure> {
+    let pk_words = words_from_hex(&case.public_key)
+        .ok_or(VerifyFailure::InvalidHex { field: "public_key" })?;
+    match validate_g2(pk_words) {
+        Ok(_) => Err(VerifyFailure::AttackKeyAccepted { attack: attack.to_string() }),
+        Err(_) => Ok(()),
+    }
+}
+
+/// The entry's `message_bytes`, after checking `message_keccak` against them.
+fn stored_message(proof: &ProofData) -> Result<Option<Vec<u8>>, VerifyFailure> {
+    let Some(message) = &proof.message_bytes else { return Ok(None) };
+    let message =
+        decode_hex(message).map_err(|_| VerifyFailure::InvalidHex { field: "message_bytes" })?;
+    if let Some(expected) = &proof.message_keccak {
+        let expected = decode_hex(expected)
+            .map_err(|_| VerifyFailure::InvalidHex { field: "message_keccak" })?;
+        if keccak256(&message)[..] != expected[..] {
+            return Err(VerifyFailure::MessageKeccakMismatch)
+        }
+    }
+    Ok(Some(message))
+}
+
+/// What [`expected_message`] re-derives for one proof.
+struct ExpectedMessage {
+    pk_words: [U256; 4],
+    public_key: G2Affine,
+    message: Vec<u8>,
+    /// The registration `message` is the digest of, in [`Preimage::Eip712`].
+    registration: Option<PopRegistration>,
+}
+
+/// The public key of `case` and the PoP message of `proof` re-derived from its fields. Only the
+/// expiry of a [`Preimage::Eip712`] registration is taken from the stored `struct_preimage`.
+fn expected_message(
+    case: &BlsTestData,
+    proof: &ProofData,
+) -> Result<ExpectedMessage, VerifyFailure> {
+    let wallet: Address = case
+        .wallet_address
+        .parse()
+        .map_err(|_| VerifyFailure::InvalidWallet(case.wallet_address.clone()))?;
+    let chain_id: U256 = proof
+        .chain_id
+        .parse()
+        .map_err(|_| VerifyFailure::InvalidChainId(proof.chain_id.clone()))?;
+    case.chain_id_width.check(chain_id).map_err(|_| VerifyFailure::ChainIdTooWide {
+        chain_id: proof.chain_id.clone(),
+        width: case.chain_id_width,
+    })?;
+    let nonce: Option<U256> = proof
+        .nonce
+        .as_ref()
+        .map(|nonce| nonce.parse().map_err(|_| VerifyFailure::InvalidNonce(nonce.clone())))
+        .transpose()?;
+    let pk_words = words_from_hex(&case.public_key)
+        .ok_or(VerifyFailure::InvalidHex { field: "public_key" })?;
+    let public_key: G2Affine =
+        validate_g2(pk_words).map_err(|_| VerifyFailure::NotOnCurve { field: "public_key" })?;
+    if case.preimage == Preimage::Eip712 && case.pop_style == PopStyle::Message {
+        let expiry =
+            &proof.struct_preimage.as_ref().ok_or(VerifyFailure::MissingStructPreimage)?.expiry;
+        let expiry = expiry.parse().map_err(|_| VerifyFailure::InvalidExpiry(expiry.clone()))?;
+        let registration = pop_registration(chain_id, &pk_words, wallet, nonce, expiry);
+        let message = registration.digest().to_vec();
+        return Ok(ExpectedMessage {
+            pk_words,
+            public_key,
+            message,
+            registration: Some(registration),
+        })
+    }
+    let message = pop_preimage(
+        case.pop_style,
+        case.message_encoding,
+        case.chain_id_width,
+        chain_id,
+        &pk_words,
+        wallet,
+        nonce,
+    );
+    Ok(ExpectedMessage { pk_words, public_key, message, registration: None })
+}
+
+/// The hasher an entry was generated with.
+fn case_hasher(case: &BlsTestData) -> Result<MessageHasher, VerifyFailure> {
+    check_expand_len(case.expand_len)
+        .map_err(|_| VerifyFailure::UnsupportedExpandLen(case.expand_len))?;
+    Ok(MessageHasher { hash_function: case.hash_function, expand_len: case.expand_len })
+}
+
+fn check_pop(
+    case: &BlsTestData,
+    proof: &ProofData,
+    domain: Domain,
+    dst: &str,
+) -> Result<(), VerifyFailure> {
+    let ExpectedMessage { pk_words, public_key, message: expected_message, registration } =
+        expected_message(case, proof)?;
+    let (signature, message_hash, stored_input, stored_intermediates) = match domain {
+        Domain::StakeManager => (
+            &proof.proof_of_possession_stake_manager,
+            &proof.message_hash_stake_manager,
+            &proof.pairing_input_stake_manager,
+            &proof.hash_to_curve_stake_manager,
+        ),
+        Domain::ValidatorManager => (
+            &proof.proof_of_possession_validator_manager,
+            &proof.message_hash_validator_manager,
+            &proof.pairing_input_validator_manager,
+            &proof.hash_to_curve_validator_manager,
+        ),
+    };
+    let [signature_field, hash_field, input_field, intermediates_field] = match domain {
+        Domain::StakeManager => [
+            "proof_of_possession_stake_manager",
+            "message_hash_stake_manager",
+            "pairing_input_stake_manager",
+            "hash_to_curve_stake_manager",
+        ],
+        Domain::ValidatorManager => [
+            "proof_of_possession_validator_manager",
+            "message_hash_validator_manager",
+            "pairing_input_validator_manager",
+            "hash_to_curve_validator_manager",
+        ],
+    };
+
+    let message = stored_message(proof)?.unwrap_or_else(|| expected_message.clone());
+    if message != expected_message {
+        return Err(VerifyFailure::MessageBytesMismatch)
+    }
+    if let Some(registration) = registration {
+        let mut stored =
+            proof.struct_preimage.clone().ok_or(VerifyFailure::MissingStructPreimage)?;
+        stored.rewrite_numbers(NumberFormat::Hex);
+        if stored != registration.to_struct_preimage() {
+            return Err(VerifyFailure::StructPreimageMismatch)
+        }
+    }
+    if let Some(stored) = &proof.eip191_digest {
+        let stored =
+            decode_hex(stored).map_err(|_| VerifyFailure::InvalidHex { field: "eip191_digest" })?;
+        if case.prehash != Prehash::Eip191 || stored[..] != eip191_hash_message(&message)[..] {
+            return Err(VerifyFailure::Eip191DigestMismatch)
+        }
+    }
+    let hashed = case.prehash.apply(&message);
+
+    let message_hash = parse_g1(message_hash, hash_field)?;
+    let hasher = case_hasher(case)?;
+    let expected_hash = hasher.hash_to_curve(dst, &hashed);
+    if g1_to_words(&expected_hash) != g1_to_words(&message_hash) {
+        return Err(VerifyFailure::MessageHashMismatch)
+    }
+
+    if stored_intermediates.clone().is_some_and(|mut stored| {
+        stored.rewrite_numbers(NumberFormat::Hex);
+        stored != hash_to_curve_intermediates(&hasher, dst, &hashed)
+    }) {
+        return Err(VerifyFailure::IntermediatesMismatch { field: intermediates_field })
+    }
+    let (stored_expansion, expansion_field) = match domain {
+        Domain::StakeManager => (&proof.xmd_expansion_stake_manager, "xmd_expansion_stake_manager"),
+        Domain::ValidatorManager => {
+            (&proof.xmd_expansion_validator_manager, "xmd_expansion_validator_manager")
+        }
+    };
+    if let Some(stored) = stored_expansion {
+        if decode_hex(stored).ok() != Some(hasher.expand_message(dst, &hashed)) {
+            return Err(VerifyFailure::IntermediatesMismatch { field: expansion_field })
+        }
+    }
+
+    if let Some(stored_input) = stored_input {
+        let signature_words = words_from_hex(signature)
+            .ok_or(VerifyFailure::InvalidHex { field: signature_field })?;
+        let expected = pairing_input(signature_words, g1_to_words(&message_hash), pk_words);
+        if decode_hex(stored_input).ok().as_deref() != Some(&expected[..]) {
+            return Err(VerifyFailure::PairingInputMismatch { field: input_field })
+        }
+    }
+
+    if let (Domain::StakeManager, Some(calldata)) = (domain, &proof.calldata) {
+        let calldata =
+            decode_hex(calldata).map_err(|_| VerifyFailure::InvalidHex { field: "calldata" })?;
+        let signature_words = words_from_hex(signature)
+            .ok_or(VerifyFailure::InvalidHex { field: signature_field })?;
+        if calldata.get(4..) != Some(&(pk_words, signature_words).abi_encode_params()[..]) {
+            return Err(VerifyFailure::CalldataMismatch)
+        }
+    }
+
+    if domain == Domain::StakeManager {
+        check_binding(case, proof, &pk_words)?;
+    }
+    if domain == Domain::ValidatorManager {
+        check_combined(proof, &public_key)?;
+    }
+
+    let signature = parse_g1(signature, signature_field);
+    if proof.valid {
+        if !pairing_check(&signature?, &message_hash, &public_key) {
+            return Err(VerifyFailure::PairingCheckFailed)
+        }
+        return Ok(())
+    }
+    // A negative vector passes when its signature is rejected, whether by decoding or pairing.
+    match signature {
+        Ok(signature) if pairing_check(&signature, &message_hash, &public_key) => {
+            Err(VerifyFailure::InvalidEntryVerifies {
+                reason: proof.invalid_reason.clone().unwrap_or_default(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A stored `ecdsa_binding` must sign the [`binding_digest`] of the entry's public key and the
+/// proof's chain id, and recover to `wallet_address`.
+fn check_binding(
+    case: &BlsTestData,
+    proof: &ProofData,
+    pk_words: &[U256; 4],
+) -> Result<(), VerifyFailure> {
+    let Some(binding) = &proof.ecdsa_binding else { return Ok(()) };
+    let chain_id: U256 = proof
+        .chain_id
+        .parse()
+        .map_err(|_| VerifyFailure::InvalidChainId(proof.chain_id.clone()))?;
+    let digest = binding.digest().map_err(|_| VerifyFailure::InvalidHex { field: "digest" })?;
+    if digest != binding_digest(pk_words, chain_id) {
+        return Err(VerifyFailure::BindingDigestMismatch)
+    }
+    let signer =
+        binding.recover().map_err(|error| VerifyFailure::BindingWrongSigner(error.to_string()))?;
+    if !case.wallet_address.eq_ignore_ascii_case(&signer.to_string()) {
+        return Err(VerifyFailure::BindingWrongSigner(format!("recovers to {signer}")))
+    }
+    Ok(())
+}
+
+/// Stored `message_hash_combined` and `proof_of_possession_combined` must be the sums of the
+/// entry's own points in both domains, and pass [`combined_pairing_check`] exactly when the entry
+/// is `valid`.
+fn check_combined(proof: &ProofData, public_key: &G2Affine) -> Result<(), VerifyFailure> {
+    if proof.message_hash_combined.is_none() && proof.proof_of_possession_combined.is_none() {
+        return Ok(())
+    }
+    let hash_field = "message_hash_combined";
+    let signature_field = "proof_of_possession_combined";
+    let stake_manager = parse_g1(&proof.message_hash_stake_manager, "message_hash_stake_manager")?;
+    let validator_manager =
+        parse_g1(&proof.message_hash_validator_manager, "message_hash_validator_manager")?;
+    let stored_hash = proof
+        .message_hash_combined
+        .as_ref()
+        .ok_or(VerifyFailure::CombinedMismatch { field: hash_field })?;
+    if parse_g1(stored_hash, hash_field)? != g1_add(&stake_manager, &validator_manager) {
+        return Err(VerifyFailure::CombinedMismatch { field: hash_field })
+    }
+
+    let signatures = (
+        parse_g1(&proof.proof_of_possession_stake_manager, "proof_of_possession_stake_manager"),
+        parse_g1(
+            &proof.proof_of_possession_validator_manager,
+            "proof_of_possession_validator_manager",
+        ),
+    );
+    let (Ok(signature_stake_manager), Ok(signature_validator_manager)) = signatures else {
+        // Negative vectors whose signatures are not curve points have no sum.
+        return match proof.proof_of_possession_combined {
+            Some(_) => Err(VerifyFailure::CombinedMismatch { field: signature_field }),
+            None => Ok(()),
+        }
+    };
+    let stored_signature = proof
+        .proof_of_possession_combined
+        .as_ref()
+        .ok_or(VerifyFailure::CombinedMismatch { field: signature_field })?;
+    if parse_g1(stored_signature, signature_field)? !=
+        g1_add(&signature_stake_manager, &signature_validator_manager)
+    {
+        return Err(VerifyFailure::CombinedMismatch { field: signature_field })
+    }
+    let verifies = combined_pairing_check(
+        [&signature_stake_manager, &signature_validator_manager],
+        [&stake_manager, &validator_manager],
+        public_key,
+    );
+    match (proof.valid, verifies) {
+        (true, false) => Err(VerifyFailure::CombinedCheckFailed),
+        (false, true) => Err(VerifyFailure::InvalidEntryVerifies {
+            reason: proof.invalid_reason.clone().unwrap_or_default(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generate::{generate_case, GenOptions};
+
+    fn case() -> BlsTestData {
+        let opts = GenOptions {
+            seed: Some("11".parse().unwrap()),
+            include_invalid: true,
+            ..Default::default()
+        };
+        generate_case(Address::repeat_byte(7), &[U256::from(1)], &opts).unwrap()
+    }
+
+    #[test]
+    fn test_generated_case_verifies() {
+        let results = verify_fixture(&[case()]);
+        assert_eq!(results.len(), 2 * 5);
+        assert!(results.iter().all(CheckResult::passed), "{results:#?}");
+    }
+
+    #[test]
+    fn test_nonce_is_part_of_the_message() {
+        let opts = GenOptions {
+            seed: Some("11".parse().unwrap()),
+            nonce_start: Some(U256::from(3)),
+            include_invalid: true,
+            ..Default::default()
+        };
+        let case = generate_case(Address::repeat_byte(7), &[U256::from(1)], &opts).unwrap();
+        assert!(verify_fixture(std::slice::from_ref(&case)).iter().all(CheckResult::passed));
+
+        let mut replayed = case;
+        replayed.proof[0].nonce = Some("4".to_string());
+        let results = verify_fixture(&[replayed.clone()]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::MessageBytesMismatch));
+        replayed.proof[0].message_bytes = None;
+        let results = verify_fixture(&[replayed.clone()]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::MessageHashMismatch));
+
+        replayed.proof[0].nonce = Some("four".to_string());
+        let results = verify_fixture(&[replayed]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::InvalidNonce("four".to_string())));
+    }
+
+    #[test]
+    fn test_chain_id_width_is_part_of_the_message() {
+        let opts = GenOptions {
+            seed: Some("11".parse().unwrap()),
+            chain_id_width: ChainIdWidth::U64,
+            ..Default::default()
+        };
+        let case = generate_case(Address::repeat_byte(7), &[U256::from(1)], &opts).unwrap();
+        assert!(verify_fixture(std::slice::from_ref(&case)).iter().all(CheckResult::passed));
+
+        let mut widened = case.clone();
+        widened.chain_id_width = ChainIdWidth::U256;
+        let results = verify_fixture(&[widened]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::MessageBytesMismatch));
+
+        let mut too_wide = case;
+        too_wide.proof[0].chain_id = U256::from(u128::MAX).to_string();
+        let results = verify_fixture(&[too_wide]);
+        assert_eq!(
+            results[0].outcome,
+            Err(VerifyFailure::ChainIdTooWide {
+                chain_id: u128::MAX.to_string(),
+                width: ChainIdWidth::U64
+            })
+        );
+    }
+
+    #[test]
+    fn test_combined_fields() {
+        let opts = GenOptions {
+            seed: Some("44".parse().unwrap()),
+            include_invalid: true,
+            combined: true,
+            ..Default::default()
+        };
+        let case = generate_case(Address::repeat_byte(7), &[U256::from(1)], &opts).unwrap();
+        assert!(case.proof.iter().any(|proof| proof.proof_of_possession_combined.is_none()));
+        assert!(verify_fixture(std::slice::from_ref(&case)).iter().all(CheckResult::passed));
+
+        let mut tampered = case.clone();
+        tampered.proof[0].proof_of_possession_combined =
+            tampered.proof[1].proof_of_possession_combined.clone();
+        let results = verify_fixture(&[tampered]);
+        assert!(results[0].passed(), "the StakeManager row does not check the sums");
+        assert_eq!(
+            results[1].outcome,
+            Err(VerifyFailure::CombinedMismatch { field: "proof_of_possession_combined" })
+        );
+
+        let mut tampered = case;
+        tampered.proof[0].message_hash_combined =
+            Some(tampered.proof[0].proof_of_possession_stake_manager.clone());
+        let results = verify_fixture(&[tampered]);
+        assert_eq!(
+            results[1].outcome,
+            Err(VerifyFailure::CombinedMismatch { field: "message_hash_combined" })
+        );
+    }
+
+    #[test]
+    fn test_struct_preimage_fields() {
+        let opts = GenOptions {
+            seed: Some("47".parse().unwrap()),
+            preimage: Preimage::Eip712,
+            nonce_start: Some(U256::from(3)),
+            ..Default::default()
+        };
+        let case = generate_case(Address::repeat_byte(7), &[U256::from(1)], &opts).unwrap();
+        assert!(verify_fixture(std::slice::from_ref(&case)).iter().all(CheckResult::passed));
+        let mut decimal = case.clone();
+        decimal.rewrite_numbers(NumberFormat::Dec);
+        assert!(verify_fixture(&[decimal]).iter().all(CheckResult::passed));
+
+        let tamper = |edit: fn(&mut ProofData)| {
+            let mut tampered = case.clone();
+            edit(&mut tampered.proof[0]);
+            verify_fixture(&[tampered])[0].outcome.clone()
+        };
+        assert_eq!(
+            tamper(|proof| proof.struct_preimage = None),
+            Err(VerifyFailure::MissingStructPreimage)
+        );
+        assert_eq!(
+            tamper(|proof| proof.struct_preimage.as_mut().unwrap().expiry = "1".into()),
+            Err(VerifyFailure::MessageBytesMismatch),
+            "the expiry is signed"
+        );
+        assert_eq!(
+            tamper(|proof| proof.struct_preimage.as_mut().unwrap().expiry = "soon".into()),
+            Err(VerifyFailure::InvalidExpiry("soon".into()))
+        );
+        assert_eq!(
+            tamper(|proof| proof.struct_preimage.as_mut().unwrap().chain_id = "2".into()),
+            Err(VerifyFailure::StructPreimageMismatch)
+        );
+        assert_eq!(
+            tamper(|proof| proof.struct_preimage.as_mut().unwrap().digest = "0x00".into()),
+            Err(VerifyFailure::StructPreimageMismatch)
+        );
+    }
+
+    #[test]
+    fn test_edge_case_entries() {
+        let opts = GenOptions { seed: Some("45".parse().unwrap()), ..Default::default() };
+        let wallet = Address::repeat_byte(7);
+        let cases = crate::generate_edge_cases(wallet, &[U256::from(1)], &opts).unwrap();
+        assert!(verify_fixture(&cases).iter().all(CheckResult::passed));
+
+        let mut flipped = cases[0].clone();
+        flipped.proof[0].expected_naive_result = Some(false);
+        let results = verify_fixture(&[flipped]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::NaiveResultMismatch { actual: true }));
+
+        // With the real PoP in place of the offset one, the entry verifies when it must not.
+        let valid = generate_case(wallet, &[U256::from(1)], &opts).unwrap();
+        let mut repaired = cases[2].clone();
+        repaired.proof[0].proof_of_possession_stake_manager =
+            valid.proof[0].proof_of_possession_stake_manager.clone();
+        let results = verify_fixture(&[repaired]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::NaiveResultMismatch { actual: true }));
+        let mut repaired = cases[2].clone();
+        repaired.proof[0].expected_naive_result = Some(true);
+        repaired.proof[0].proof_of_possession_stake_manager =
+            valid.proof[0].proof_of_possession_stake_manager.clone();
+        assert!(matches!(
+            verify_fixture(&[repaired])[0].outcome,
+            Err(VerifyFailure::InvalidEntryVerifies { .. })
+        ));
+    }
+
+    #[test]
+    fn test_chain_replay_entries() {
+        let opts = GenOptions { seed: Some("11".parse().unwrap()), ..Default::default() };
+        let case =
+            generate_case(Address::repeat_byte(7), &[U256::from(1), U256::from(2)], &opts).unwrap();
+        let mut replays = crate::chain_replays(&case);
+        assert!(verify_fixture(&replays).iter().all(CheckResult::passed));
+
+        replays[0].proof[0].chain_id = replays[0].proof[0].signed_chain_id.clone().unwrap();
+        let results = verify_fixture(&replays[..1]);
+        assert!(results.iter().all(|r| r.outcome == Err(VerifyFailure::ChainReplayVerifies)));
+
+        replays[1].proof[0].signed_chain_id = None;
+        let results = verify_fixture(&replays[1..]);
+        assert!(results.iter().all(|r| r.outcome == Err(VerifyFailure::MissingSignedChainId)));
+    }
+
+    #[test]
+    fn test_attack_entries_verify_only_when_rejected() {
+        let opts = GenOptions { seed: Some("11".parse().unwrap()), ..Default::default() };
+        let mut cases =
+            crate::generate_attacks(Address::repeat_byte(7), &[U256::from(1)], &opts).unwrap();
+        assert!(verify_fixture(&cases).iter().all(CheckResult::passed));
+
+        cases[0].public_key = case().public_key;
+        let results = verify_fixture(&cases[..1]);
+        assert!(results
+            .iter()
+            .all(|r| r.outcome ==
+                Err(VerifyFailure::AttackKeyAccepted { attack: "off_curve".into() })));
+    }
+
+    #[test]
+    fn test_detects_tampering() {
+        let mut wrong_wallet = case();
+        wrong_wallet.wallet_address = Address::repeat_byte(8).to_string();
+        let results = verify_fixture(&[wrong_wallet.clone()]);
+        assert!(results.iter().all(|r| r.outcome == Err(VerifyFailure::MessageBytesMismatch)));
+        for proof in &mut wrong_wallet.proof {
+            proof.message_bytes = None;
+            proof.message_keccak = None;
+        }
+        let results = verify_fixture(&[wrong_wallet]);
+        assert!(results.iter().all(|r| r.outcome == Err(VerifyFailure::MessageHashMismatch)));
+
+        let mut wrong_preimage = case();
+        wrong_preimage.proof[0].message_bytes = Some("0x00".into());
+        let results = verify_fixture(&[wrong_preimage]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::MessageKeccakMismatch));
+        wrong_preimage = case();
+        wrong_preimage.proof[0].message_bytes = Some("0x00".into());
+        wrong_preimage.proof[0].message_keccak = None;
+        let results = verify_fixture(&[wrong_preimage]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::MessageBytesMismatch));
+
+        let mut bad_hex = case();
+        bad_hex.proof[0].proof_of_possession_stake_manager[0] = "0xnothex".into();
+        let results = verify_fixture(&[bad_hex]);
+        assert_eq!(
+            results[0].outcome,
+            Err(VerifyFailure::InvalidHex { field: "proof_of_possession_stake_manager" })
+        );
+        assert!(results[1..].iter().all(CheckResult::passed));
+
+        let mut off_curve = case();
+        off_curve.proof[0].message_hash_validator_manager[1] = "0x05".into();
+        let results = verify_fixture(&[off_curve]);
+        assert_eq!(
+            results[1].outcome,
+            Err(VerifyFailure::NotOnCurve { field: "message_hash_validator_manager" })
+        );
+
+        let mut wrong_input = case();
+        let input = crate::pairing_input(
+            [U256::from(1), U256::from(2)],
+            [U256::from(1), U256::from(2)],
+            words_from_hex(&wrong_input.public_key).unwrap(),
+        );
+        wrong_input.proof[0].pairing_input_validator_manager =
+            Some(format!("0x{}", hex::encode(input)));
+        let results = verify_fixture(&[wrong_input]);
+        assert_eq!(
+            results[1].outcome,
+            Err(VerifyFailure::PairingInputMismatch { field: "pairing_input_validator_manager" })
+        );
+
+        let mut wrong_stage = case();
+        let hasher = MessageHasher::default();
+        let mut stages =
+            crate::hash_to_curve_intermediates(&hasher, crate::DEFAULT_DST_STAKE_MANAGER, b"");
+        wrong_stage.proof[0].hash_to_curve_stake_manager = Some(stages.clone());
+        let results = verify_fixture(&[wrong_stage.clone()]);
+        assert_eq!(
+            results[0].outcome,
+            Err(VerifyFailure::IntermediatesMismatch { field: "hash_to_curve_stake_manager" })
+        );
+        let message = hex::decode(&wrong_stage.proof[0].message_bytes.as_ref().unwrap()[2..]);
+        stages = crate::hash_to_curve_intermediates(
+            &hasher,
+            crate::DEFAULT_DST_STAKE_MANAGER,
+            &message.unwrap(),
+        );
+        wrong_stage.proof[0].hash_to_curve_stake_manager = Some(stages.clone());
+        assert!(verify_fixture(std::slice::from_ref(&wrong_stage))[0].passed());
+        wrong_stage.proof[0].xmd_expansion_stake_manager = Some(stages.uniform_bytes.clone());
+        assert!(verify_fixture(std::slice::from_ref(&wrong_stage))[0].passed());
+        wrong_stage.proof[0].xmd_expansion_stake_manager =
+            Some(stages.uniform_bytes.replace("0x", "0x00"));
+        assert_eq!(
+            verify_fixture(&[wrong_stage])[0].outcome,
+            Err(VerifyFailure::IntermediatesMismatch { field: "xmd_expansion_stake_manager" })
+        );
+
+        let mut wrong_calldata = case();
+        let call = crate::RegisterCall::default();
+        let signature =
+            words_from_hex(&wrong_calldata.proof[0].proof_of_possession_validator_manager);
+        let calldata =
+            call.encode(words_from_hex(&wrong_calldata.public_key).unwrap(), signature.unwrap());
+        wrong_calldata.proof[0].calldata = Some(format!("0x{}", hex::encode(calldata)));
+        let results = verify_fixture(&[wrong_calldata]);
+        assert_eq!(results[0].outcome, Err(VerifyFailure::CalldataMismatch));
+        assert!(results[1].passed(), "calldata only carries the StakeManager PoP");
+
+        let mut relabelled = case();
+        relabelled.proof[0].valid = false;
+        relabelled.proof[1].valid = true;
+        let results = verify_fixture(&[relabelled]);
+        assert!(matches!(results[0].outcome, Err(VerifyFailure::InvalidEntryVerifies { .. })));
+        assert_eq!(results[2].outcome, Err(VerifyFailure::PairingCheckFailed));
+    }
+}
@@ -0,0 +1,164 @@
+//! Wallet lists for bulk generation.
+
+use crate::hex_util::{hex_digits, HexError};
+use alloy::primitives::Address;
+use std::collections::HashSet;
+
+/// Why a string is not a wallet address.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AddressError {
+    #[error("expected 40 hex digits, got {0}")]
+    Length(usize),
+    #[error("`{character}` at position {position} is not a hex digit")]
+    NonHex { character: char, position: usize },
+    #[error("EIP-55 checksum mismatch, expected {expected}")]
+    Checksum { expected: Address },
+}
+
+/// Why a wallet list could not be read.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum WalletsFileError {
+    #[error("line {line}: `{input}` is not a valid address: {reason}")]
+    InvalidLine { line: usize, input: String, reason: AddressError },
+    #[error("entry {index}: `{input}` is not a valid address: {reason}")]
+    InvalidEntry { index: usize, input: String, reason: AddressError },
+    #[error("not a JSON array of address strings: {0}")]
+    InvalidJson(String),
+}
+
+/// Parse a wallet address, with or without `0x` and with `_` or whitespace between digits, see
+/// [`hex_digits`]. Mixed-case input must carry a valid EIP-55 checksum; all-lowercase and
+/// all-uppercase input is accepted as is. Positions in errors are 1-based and count the prefix.
+/// The parsed address displays in checksummed form.
+pub fn parse_address(input: &str) -> Result<Address, AddressError> {
+    let digits = hex_digits(input).map_err(|error| match error {
+        HexError::NonHex { character, position } => AddressError::NonHex { character, position },
+        error => unreachable!("hex_digits only rejects characters, not {error}"),
+    })?;
+    if digits.len() != 40 {
+        return Err(AddressError::Length(digits.len()))
+    }
+    let mut address = Address::ZERO;
+    hex::decode_to_slice(&digits, address.as_mut_slice()).expect("40 hex digits");
+    let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) &&
+        digits.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case && address.to_checksum(None)[2..] != *digits {
+        return Err(AddressError::Checksum { expected: address })
+    }
+    Ok(address)
+}
+
+/// Parse a wallet list: either a JSON array of address strings, or one address per line with
+/// blank lines and `#` comments ignored. Line and entry numbers in errors are 1-based.
+pub fn parse_wallets(contents: &str) -> Result<Vec<Address>, WalletsFileError> {
+    if contents.trim_start().starts_with('[') {
+        let entries: Vec<String> = serde_json::from_str(contents)
+            .map_err(|error| WalletsFileError::InvalidJson(error.to_string()))?;
+        return entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, input)| {
+                parse_address(input.trim()).map_err(|reason| WalletsFileError::InvalidEntry {
+                    index: i + 1,
+                    reason,
+                    input,
+                })
+            })
+            .collect()
+    }
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line, input)| {
+            parse_address(input).map_err(|reason| WalletsFileError::InvalidLine {
+                line,
+                input: input.to_string(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// Drop repeated wallets, keeping the first occurrence of each.
+pub fn dedup_wallets(wallets: impl IntoIterator<Item = Address>) -> Vec<Address> {
+    let mut seen = HashSet::new();
+    wallets.into_iter().filter(|wallet| seen.insert(*wallet)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const A: &str = "0x328809Bc894f92807417D2dAD6b7C998c1aFdac6";
+    const B: &str = "0x1D96F2f6BeF1202E4Ce1Ff6Dad0c2CB002861d3e";
+
+    #[test]
+    fn test_parse_lines_and_json() {
+        let expected = [A, B].map(|wallet| Address::from_str(wallet).unwrap());
+        let lines = format!("# exported validators\n{A}\n\n  {B}  # second\n");
+        assert_eq!(parse_wallets(&lines).unwrap(), expected);
+        assert_eq!(parse_wallets(&format!(r#"["{A}", "{B}"]"#)).unwrap(), expected);
+        assert!(parse_wallets("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors_locate_the_entry() {
+        let error = parse_wallets(&format!("{A}\n\n0x1234\n")).unwrap_err();
+        assert!(matches!(error, WalletsFileError::InvalidLine { line: 3, .. }), "{error}");
+        assert!(error.to_string().starts_with("line 3: `0x1234`"), "{error}");
+
+        let error = parse_wallets(&format!(r#"["{A}", "nope"]"#)).unwrap_err();
+        assert!(matches!(error, WalletsFileError::InvalidEntry { index: 2, .. }), "{error}");
+
+        let error = parse_wallets("[1, 2]").unwrap_err();
+        assert!(matches!(error, WalletsFileError::InvalidJson(_)), "{error}");
+    }
+
+    #[test]
+    fn test_parse_address_failure_classes() {
+        assert_eq!(parse_address("0x1234"), Err(AddressError::Length(4)));
+        assert_eq!(parse_address(&format!("{A}00")), Err(AddressError::Length(42)));
+        assert_eq!(
+            parse_address("0x328809Bc894f92807417D2dAD6b7C998c1aFdaz6"),
+            Err(AddressError::NonHex { character: 'z', position: 41 })
+        );
+        assert_eq!(
+            parse_address("nope"),
+            Err(AddressError::NonHex { character: 'n', position: 1 })
+        );
+
+        let wrong_case = A.replace("Bc", "bC");
+        let error = parse_address(&wrong_case).unwrap_err();
+        assert_eq!(error, AddressError::Checksum { expected: Address::from_str(A).unwrap() });
+        assert_eq!(error.to_string(), format!("EIP-55 checksum mismatch, expected {A}"));
+
+        let error = parse_wallets(&format!("{A}\n{wrong_case}\n")).unwrap_err();
+        assert!(matches!(
+            error,
+            WalletsFileError::InvalidLine { line: 2, reason: AddressError::Checksum { .. }, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_address_normalizes_case() {
+        let expected = Address::from_str(A).unwrap();
+        let lower = A.to_lowercase();
+        let upper = format!("0x{}", A[2..].to_uppercase());
+        let grouped = format!("{}_{} {}", &A[..12], &A[12..22], &A[22..]);
+        for input in [A, &lower, &upper, &lower[2..], &grouped] {
+            let address = parse_address(input).unwrap();
+            assert_eq!(address, expected, "{input}");
+            assert_eq!(address.to_string(), A);
+        }
+    }
+
+    #[test]
+    fn test_dedup_keeps_first_occurrence() {
+        let [a, b] = [A, B].map(|wallet| Address::from_str(wallet).unwrap());
+        let lowercase = Address::from_str(&A.to_lowercase()).unwrap();
+        assert_eq!(dedup_wallets([a, b, lowercase, b]), [a, b]);
+    }
+}
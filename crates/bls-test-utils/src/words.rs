@@ -0,0 +1,334 @@
+//! Conversions between sylow points and the `uint256` words the Solidity contracts consume.
+
+use crate::conventions::{g2_solidity_to_sylow, g2_sylow_to_solidity};
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use sylow::{FieldExtensionTrait, Fp, Fp2, G1Affine, G1Projective, G2Affine, G2Projective};
+
+/// Render a word as a `0x`-prefixed, zero-padded 32-byte hex string.
+pub fn u256_to_0x(x: U256) -> String {
+    format!("0x{}", hex::encode(x.to_be_bytes::<32>()))
+}
+
+/// Render a word in decimal, the form circom and gnark take field elements in.
+pub fn u256_to_dec(x: U256) -> String {
+    x.to_string()
+}
+
+/// Render a base field element as a `0x`-prefixed, zero-padded 32-byte hex string.
+pub fn fp_to_hex(x: Fp) -> String {
+    format!("0x{}", hex::encode(x.to_be_bytes()))
+}
+
+/// Render every word of a point with [`u256_to_0x`].
+pub fn words_to_hex<const N: usize>(words: [U256; N]) -> [String; N] {
+    words.map(u256_to_0x)
+}
+
+/// How limb fields are written. Both parse back with [`words_from_hex`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberFormat {
+    /// [`u256_to_0x`], what the Solidity tests use.
+    #[default]
+    Hex,
+    /// [`u256_to_dec`], for ZK circuit inputs.
+    Dec,
+}
+
+impl NumberFormat {
+    pub fn is_hex(&self) -> bool {
+        *self == Self::Hex
+    }
+
+    pub fn render(self, x: U256) -> String {
+        match self {
+            Self::Hex => u256_to_0x(x),
+            Self::Dec => u256_to_dec(x),
+        }
+    }
+
+    /// Re-render words written in either format; words that do not parse are left as they are.
+    pub fn rewrite<const N: usize>(self, words: &mut [String; N]) {
+        if let Some(parsed) = words_from_hex(words) {
+            *words = parsed.map(|word| self.render(word));
+        }
+    }
+}
+
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hex => f.pad("hex"),
+            Self::Dec => f.pad("dec"),
+        }
+    }
+}
+
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "hex" => Ok(Self::Hex),
+            "dec" => Ok(Self::Dec),
+            _ => Err(format!("`{input}` is not a number format, expected `hex` or `dec`")),
+        }
+    }
+}
+
+/// Why words do not decode to a point, see [`validate_g1`] and [`validate_g2`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PointError {
+    #[error("limb {0} is not below the base field modulus")]
+    NotInField(usize),
+    #[error("not a point on the curve")]
+    NotOnCurve,
+    #[error("on the twist but not in the G2 subgroup")]
+    NotInSubgroup,
+    /// The all-zero words, which the EIP-196/197 precompiles read as the point at infinity.
+    #[error("the point at infinity")]
+    Identity,
+}
+
+/// Split a fixed-size big-endian encoding into its 32-byte words. The sizes are checked at
+/// compile time, so a change to sylow's encoding lengths fails the build instead of misreading
+/// limbs.
+fn split_words<const B: usize, const N: usize>(bytes: [u8; B]) -> [U256; N] {
+    const { assert!(B == 32 * N, "encoding is not N 32-byte words") };
+    let mut words = [U256::ZERO; N];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks(32)) {
+        *word = U256::from_be_slice(chunk);
+    }
+    words
+}
+
+fn fp_from_word(words: &[U256], limb: usize) -> Result<Fp, PointError> {
+    Option::from(Fp::from_be_bytes(&words[limb].to_be_bytes::<32>()))
+        .ok_or(PointError::NotInField(limb))
+}
+
+/// Return the G1 point as `[x, y]`.
+pub fn g1_to_words(p: &G1Affine) -> [U256; 2] {
+    split_words(p.to_be_bytes())
+}
+
+/// Return limbs in Solidity order: `[x_re, x_im, y_re, y_im]`.
+///
+/// Sylow serializes G2 in [`LimbOrder::Sylow`](crate::LimbOrder::Sylow), `[x_im, x_re, y_im,
+/// y_re]`, so the two halves of every coordinate are swapped here.
+pub fn g2_to_words_solidity(p: &G2Affine) -> [U256; 4] {
+    g2_sylow_to_solidity(split_words(p.to_be_bytes()))
+}
+
+/// Inverse of [`g1_to_words`]: rebuild the point, checking both limbs are field elements and
+/// `y^2 == x^3 + 3`. G1 has cofactor one, so that is the whole check. The all-zero words of the
+/// point at infinity are off the curve and rejected.
+pub fn g1_from_words(words: [U256; 2]) -> Result<G1Affine, PointError> {
+    let x = fp_from_word(&words, 0)?;
+    let y = fp_from_word(&words, 1)?;
+    if y.square() != x.square() * x + <Fp as FieldExtensionTrait<1, 1>>::curve_constant() {
+        return Err(PointError::NotOnCurve)
+    }
+    let bytes: Vec<u8> = words.iter().flat_map(U256::to_be_bytes::<32>).collect();
+    let point = G1Affine::from_be_bytes(&bytes.try_into().expect("two 32-byte words"));
+    Option::<G1Projective>::from(point).map(G1Affine::from).ok_or(PointError::NotOnCurve)
+}
+
+/// Inverse of [`g2_to_words_solidity`]: rebuild the point, checking all four limbs are field
+/// elements, the point is on the twist and it lies in the order-r subgroup. Limb indices in
+/// errors refer to the Solidity order. The all-zero words are off the twist and rejected.
+pub fn g2_from_words_solidity(words: [U256; 4]) -> Result<G2Affine, PointError> {
+    let x = Fp2::new(&[fp_from_word(&words, 0)?, fp_from_word(&words, 1)?]);
+    let y = Fp2::new(&[fp_from_word(&words, 2)?, fp_from_word(&words, 3)?]);
+    // sylow's subgroup check panics on points that are off the curve, so reject those first.
+    if y.square() != x.square() * x + <Fp2 as FieldExtensionTrait<2, 2>>::curve_constant() {
+        return Err(PointError::NotOnCurve)
+    }
+    let bytes: Vec<u8> =
+        g2_solidity_to_sylow(words).iter().flat_map(U256::to_be_bytes::<32>).collect();
+    let point = G2Affine::from_be_bytes(&bytes.try_into().expect("four 32-byte words"));
+    Option::<G2Projective>::from(point).map(G2Affine::from).ok_or(PointError::NotInSubgroup)
+}
+
+/// Check `[x, y]` is a G1 point a contract can accept: not the all-zero encoding of the identity,
+/// both limbs field elements and on the curve. G1 has cofactor one, so it is never
+/// [`PointError::NotInSubgroup`].
+pub fn validate_g1(words: [U256; 2]) -> Result<G1Affine, PointError> {
+    if words == [U256::ZERO; 2] {
+        return Err(PointError::Identity)
+    }
+    g1_from_words(words)
+}
+
+/// Check Solidity-order limbs are a G2 point a contract can accept: not the all-zero encoding of
+/// the identity, all four limbs field elements, on the twist and in the order-r subgroup.
+pub fn validate_g2(words: [U256; 4]) -> Result<G2Affine, PointError> {
+    if words == [U256::ZERO; 4] {
+        return Err(PointError::Identity)
+    }
+    g2_from_words_solidity(words)
+}
+
+/// Parse words as written by [`words_to_hex`], or in decimal as [`NumberFormat::Dec`] writes them.
+pub fn words_from_hex<const N: usize>(words: &[String; N]) -> Option<[U256; N]> {
+    let mut out = [U256::ZERO; N];
+    for (word, hex) in out.iter_mut().zip(words) {
+        *word = hex.parse().ok()?;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::primitives::uint;
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+    use sylow::GroupTrait;
+
+    /// The EIP-197 G2 generator in Solidity limb order.
+    const G2_GENERATOR_SOLIDITY: [U256; 4] = uint!([
+        0x1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed_U256,
+        0x198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2_U256,
+        0x12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa_U256,
+        0x090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b_U256,
+    ]);
+
+    #[test]
+    fn test_g1_generator_words() {
+        assert_eq!(g1_to_words(&G1Affine::generator()), [U256::from(1), U256::from(2)]);
+    }
+
+    #[test]
+    fn test_g2_generator_matches_solidity_order() {
+        assert_eq!(g2_to_words_solidity(&G2Affine::generator()), G2_GENERATOR_SOLIDITY);
+    }
+
+    #[test]
+    fn test_g2_words_swap_sylow_halves() {
+        let bytes = G2Affine::generator().to_be_bytes();
+        let sylow: Vec<U256> = bytes.chunks(32).map(U256::from_be_slice).collect();
+        let [x_re, x_im, y_re, y_im] = g2_to_words_solidity(&G2Affine::generator());
+        assert_eq!(sylow, [x_im, x_re, y_im, y_re]);
+    }
+
+    #[test]
+    fn test_words_round_trip() {
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        assert_eq!(g1_from_words(g1_to_words(&g1)), Ok(g1));
+        assert_eq!(g2_from_words_solidity(g2_to_words_solidity(&g2)), Ok(g2));
+        assert_eq!(
+            words_from_hex(&words_to_hex(G2_GENERATOR_SOLIDITY)),
+            Some(G2_GENERATOR_SOLIDITY)
+        );
+
+        let [x, y] = g1_to_words(&g1);
+        assert_eq!(
+            g1_from_words([y, x]),
+            Err(PointError::NotOnCurve),
+            "swapped coordinates are off the curve"
+        );
+        let [x_re, x_im, y_re, y_im] = G2_GENERATOR_SOLIDITY;
+        assert_eq!(
+            g2_from_words_solidity([x_im, x_re, y_im, y_re]),
+            Err(PointError::NotOnCurve),
+            "sylow order is rejected"
+        );
+    }
+
+    #[test]
+    fn test_random_and_doubled_points_round_trip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(24);
+        let mut g1 = vec![G1Affine::generator()];
+        let mut g2 = vec![G2Affine::generator()];
+        for _ in 0..16 {
+            g1.push(G1Affine::rand(&mut rng));
+            g2.push(G2Affine::rand(&mut rng));
+        }
+        for i in 0..g1.len() {
+            g1.push(G1Affine::from(G1Projective::from(g1[i]).double()));
+            g2.push(G2Affine::from(G2Projective::from(g2[i]).double()));
+        }
+        for p in g1 {
+            assert_eq!(g1_from_words(g1_to_words(&p)), Ok(p));
+        }
+        for p in g2 {
+            assert_eq!(g2_from_words_solidity(g2_to_words_solidity(&p)), Ok(p));
+        }
+    }
+
+    #[test]
+    fn test_decoding_errors() {
+        const MODULUS: U256 =
+            uint!(0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47_U256);
+        let [x, _] = g1_to_words(&G1Affine::generator());
+        assert_eq!(g1_from_words([x, MODULUS]), Err(PointError::NotInField(1)));
+        assert_eq!(g1_from_words([U256::ZERO; 2]), Err(PointError::NotOnCurve));
+
+        let mut words = G2_GENERATOR_SOLIDITY;
+        words[2] = U256::MAX;
+        assert_eq!(g2_from_words_solidity(words), Err(PointError::NotInField(2)));
+        assert_eq!(g2_from_words_solidity([U256::ZERO; 4]), Err(PointError::NotOnCurve));
+        let cofactor_point =
+            crate::attack_public_key(crate::PublicKeyAttack::WrongSubgroup, G2_GENERATOR_SOLIDITY);
+        assert_eq!(g2_from_words_solidity(cofactor_point), Err(PointError::NotInSubgroup));
+        assert_eq!(
+            PointError::NotInField(3).to_string(),
+            "limb 3 is not below the base field modulus"
+        );
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        const MODULUS: U256 =
+            uint!(0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47_U256);
+        let [x, y] = g1_to_words(&G1Affine::generator());
+        assert_eq!(validate_g1([x, y]), Ok(G1Affine::generator()));
+        assert_eq!(validate_g1([MODULUS, y]), Err(PointError::NotInField(0)));
+        assert_eq!(validate_g1([x, MODULUS]), Err(PointError::NotInField(1)));
+        assert_eq!(validate_g1([x, y + U256::from(1)]), Err(PointError::NotOnCurve));
+        assert_eq!(validate_g1([U256::ZERO; 2]), Err(PointError::Identity));
+        assert_eq!(validate_g1([U256::ZERO, y]), Err(PointError::NotOnCurve));
+
+        assert_eq!(validate_g2(G2_GENERATOR_SOLIDITY), Ok(G2Affine::generator()));
+        for limb in 0..4 {
+            let mut words = G2_GENERATOR_SOLIDITY;
+            words[limb] = MODULUS;
+            assert_eq!(validate_g2(words), Err(PointError::NotInField(limb)));
+        }
+        let mut words = G2_GENERATOR_SOLIDITY;
+        words[2] += U256::from(1);
+        assert_eq!(validate_g2(words), Err(PointError::NotOnCurve));
+        let cofactor_point =
+            crate::attack_public_key(crate::PublicKeyAttack::WrongSubgroup, G2_GENERATOR_SOLIDITY);
+        assert_eq!(validate_g2(cofactor_point), Err(PointError::NotInSubgroup));
+        assert_eq!(validate_g2([U256::ZERO; 4]), Err(PointError::Identity));
+        assert_eq!(PointError::Identity.to_string(), "the point at infinity");
+    }
+
+    #[test]
+    fn test_hex_rendering_is_zero_padded() {
+        assert_eq!(u256_to_0x(U256::from(1)), format!("0x{}01", "0".repeat(62)));
+        assert_eq!(fp_to_hex(Fp::ONE), u256_to_0x(U256::from(1)));
+        assert_eq!(words_to_hex([U256::from(2)]), [format!("0x{}02", "0".repeat(62))]);
+    }
+
+    #[test]
+    fn test_decimal_rendering_round_trips() {
+        assert_eq!(u256_to_dec(U256::ZERO), "0");
+        assert_eq!(u256_to_dec(U256::MAX), U256::MAX.to_string());
+        let mut words = words_to_hex(G2_GENERATOR_SOLIDITY);
+        NumberFormat::Dec.rewrite(&mut words);
+        assert_eq!(
+            words[0],
+            "10857046999023057135944570762232829481370756359578518086990519993285655852781"
+        );
+        assert_eq!(words_from_hex(&words), Some(G2_GENERATOR_SOLIDITY));
+        NumberFormat::Hex.rewrite(&mut words);
+        assert_eq!(words, words_to_hex(G2_GENERATOR_SOLIDITY));
+        assert_eq!("dec".parse(), Ok(NumberFormat::Dec));
+        assert!("oct".parse::<NumberFormat>().is_err());
+    }
+}
@@ -0,0 +1,41 @@
+//! Runs the built `bls-test-utils` binary, for behaviour only visible from outside the process:
+//! what lands on stdout and stderr, and the exit status.
+
+use bls_test_utils::{verify_fixture, AggregateTestData, OutputFormat};
+use std::process::{Command, Output};
+
+fn bls_test_utils(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bls-test-utils")).args(args).output().expect("binary runs")
+}
+
+#[test]
+fn test_out_dash_writes_only_the_fixture_to_stdout() {
+    let output = bls_test_utils(&["--seed", "30", "--chain-id", "1", "--out", "-", "--compact"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "--compact writes one line");
+    let cases = OutputFormat::Json.decode_cases(&stdout).expect("stdout is the fixture");
+    assert_eq!(cases.len(), 5);
+    assert!(verify_fixture(&cases).iter().all(|result| result.passed()));
+
+    let output =
+        bls_test_utils(&["--seed", "30", "--chain-id", "1", "--out", "-", "--format", "yaml"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(OutputFormat::Yaml.decode_cases(&stdout).unwrap(), cases);
+
+    let output = bls_test_utils(&["--aggregate", "--seed", "30", "--chain-id", "1", "--out", "-"]);
+    let aggregate: AggregateTestData = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(aggregate.validators, cases);
+}
+
+#[test]
+fn test_write_failures_exit_non_zero() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out = dir.path().join("missing").join("out.json");
+    let output = bls_test_utils(&["--chain-id", "1", "--out", out.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(&format!("writing {}", out.display())), "{stderr}");
+}
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: MIT
+// Generated by bls-test-utils. Do not edit by hand.
+pragma solidity ^0.8.30;
+
+library BlsTestVectors {
+    struct ProofData {
+        uint256 chainId;
+        uint256[2] messageHashStake;
+        uint256[2] messageHashValidator;
+        uint256[2] proofOfPossessionStake;
+        uint256[2] proofOfPossessionValidator;
+        bool valid;
+    }
+
+    struct BlsTestData {
+        uint256 privateKey;
+        uint256[4] publicKey;
+        address walletAddress;
+        string domainStake;
+        string domainValidator;
+        ProofData[] proofs;
+    }
+
+    function length() internal pure returns (uint256) {
+        return 2;
+    }
+
+    function get(uint256 index) internal pure returns (BlsTestData memory) {
+        if (index == 0) return _vector0();
+        if (index == 1) return _vector1();
+        revert("BlsTestVectors: index out of range");
+    }
+
+    function _vector0() private pure returns (BlsTestData memory data) {
+        data.privateKey = 0x27fd0b68e2a0de33c5c0d217d79ddff5aef32f0e9e6cde9e1baa8f3e62a2e396;
+        data.publicKey = [uint256(0x17ada1f15259da9b236451fbe890f1c92053e325b2a6812a0323c63633a4347f), 0x2f27053f678877961edbbe4327cebcdad143d632c4a398a6cb8e88a9cd7bec91, 0x06e2e35ff139136aaf9b4286300c3c107c39b35320a0f278c9cb5daa291dc870, 0x0287106114cfeb39764b6c1f90da70247123eecb82cac67b3f29e582ee082992];
+        data.walletAddress = 0x328809Bc894f92807417D2dAD6b7C998c1aFdac6;
+        data.domainStake = "StakeManager:BN254:PoP:v1:";
+        data.domainValidator = "ValidatorManager:BN254:PoP:v1:";
+        data.proofs = new ProofData[](5);
+        data.proofs[0] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0d14b5ac3008309a14d1beeb7694267a7fefbb8fcf5fb970c05b85e169f93107), 0x2fb2040c5d1bca52fa3e1540220e72e60ab0153095dbef02802a2f32e43c9219],
+            messageHashValidator: [uint256(0x15c4fb99aaeeec1be4b4d9c74ce7191d0ac2b2de2a9ad00e01468d14271e061a), 0x2658d9186cf5046a98d231efba8dad8d633177f1626a17d88fe439c1d5515e36],
+            proofOfPossessionStake: [uint256(0x2f816a94467531d6dcf04cfc78651637448b1029e5eedc64149781c6c2a8a3fd), 0x099137cc211d1dc138de701fbe23bdb22b78547635ecd0a8dc353c7c8a61978d],
+            proofOfPossessionValidator: [uint256(0x0640ed22230a99a03b31f62b3f054525e6d70770b0e65d3e68f309d2564f20a8), 0x2f36b2547ebc77673bc3a9b324d62e65594e81a2e54c398a3e6feec0c2829ac0],
+            valid: true
+        });
+        data.proofs[1] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0d14b5ac3008309a14d1beeb7694267a7fefbb8fcf5fb970c05b85e169f93107), 0x2fb2040c5d1bca52fa3e1540220e72e60ab0153095dbef02802a2f32e43c9219],
+            messageHashValidator: [uint256(0x15c4fb99aaeeec1be4b4d9c74ce7191d0ac2b2de2a9ad00e01468d14271e061a), 0x2658d9186cf5046a98d231efba8dad8d633177f1626a17d88fe439c1d5515e36],
+            proofOfPossessionStake: [uint256(0x205d9b1d0b8e8dbe5710d8fb3238f65a095d9107455d7bb661246b9ac5029164), 0x0deedd4fd84844121e2044141878950d86132a6d04bb6411069a19937b2bf692],
+            proofOfPossessionValidator: [uint256(0x20f315441fa3531f5eae8bc0405d7f32551e45b1afe23f13a3f5b1879e51d121), 0x0eeb87e61e4301f6f99f58de0d6f03f7c63ccd25009617222a020cc1d885029e],
+            valid: false
+        });
+        data.proofs[2] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0d14b5ac3008309a14d1beeb7694267a7fefbb8fcf5fb970c05b85e169f93107), 0x2fb2040c5d1bca52fa3e1540220e72e60ab0153095dbef02802a2f32e43c9219],
+            messageHashValidator: [uint256(0x15c4fb99aaeeec1be4b4d9c74ce7191d0ac2b2de2a9ad00e01468d14271e061a), 0x2658d9186cf5046a98d231efba8dad8d633177f1626a17d88fe439c1d5515e36],
+            proofOfPossessionStake: [uint256(0x196bed44fd3afecd9a42ba2fe16021006e56d5cdbe1f20958d91439a876e9466), 0x24d56ab762e55f38413629a61eafc44e1a03167da9aa4cdaf2a3a78a56118307],
+            proofOfPossessionValidator: [uint256(0x2c5a478bde8ed8d1bb73b95479a2ea4c7e01796624b2bf7fba1a125392c9666f), 0x0b68bc0ec846e286bce1ede55538484b4940f8834efa39692516537586b3b0ba],
+            valid: false
+        });
+        data.proofs[3] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0d14b5ac3008309a14d1beeb7694267a7fefbb8fcf5fb970c05b85e169f93107), 0x2fb2040c5d1bca52fa3e1540220e72e60ab0153095dbef02802a2f32e43c9219],
+            messageHashValidator: [uint256(0x15c4fb99aaeeec1be4b4d9c74ce7191d0ac2b2de2a9ad00e01468d14271e061a), 0x2658d9186cf5046a98d231efba8dad8d633177f1626a17d88fe439c1d5515e36],
+            proofOfPossessionStake: [uint256(0x099137cc211d1dc138de701fbe23bdb22b78547635ecd0a8dc353c7c8a61978d), 0x2f816a94467531d6dcf04cfc78651637448b1029e5eedc64149781c6c2a8a3fd],
+            proofOfPossessionValidator: [uint256(0x2f36b2547ebc77673bc3a9b324d62e65594e81a2e54c398a3e6feec0c2829ac0), 0x0640ed22230a99a03b31f62b3f054525e6d70770b0e65d3e68f309d2564f20a8],
+            valid: false
+        });
+        data.proofs[4] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0d14b5ac3008309a14d1beeb7694267a7fefbb8fcf5fb970c05b85e169f93107), 0x2fb2040c5d1bca52fa3e1540220e72e60ab0153095dbef02802a2f32e43c9219],
+            messageHashValidator: [uint256(0x15c4fb99aaeeec1be4b4d9c74ce7191d0ac2b2de2a9ad00e01468d14271e061a), 0x2658d9186cf5046a98d231efba8dad8d633177f1626a17d88fe439c1d5515e36],
+            proofOfPossessionStake: [uint256(0x2f2e37a5741076221912bcdb883061dd15d324ab0dacc8bb65bcf508bf590cc3), 0x23ff43e66195a32d10ea2b31db381afd3723a0429b16706eb3acc6432b2a3ae2],
+            proofOfPossessionValidator: [uint256(0x1d7b00d0567b8061e3316e8047bb5f86192d300cda50ab0ed2ee39dd891b3c0c), 0x1d49facdf73363a49869116cd127c93748de0bf7454df7708057641ce659e530],
+            valid: false
+        });
+    }
+
+    function _vector1() private pure returns (BlsTestData memory data) {
+        data.privateKey = 0x2ce12c370fd18e87d95481e1e6431c60b2660ed0fd82beca45b0c83548ac6dd2;
+        data.publicKey = [uint256(0x0fb5f4568bb287fd4be40d31fa195e100f7be53436439dfc067401da768e2e8a), 0x21a4cdfbb1a33123ed597e5ef02b3474c6ba0dd66b3e78c5bf819d6ae823a7d7, 0x2c4070bd449df821b90f3ff84835c8e8d1c304c541bd13adb831bc5ec069446f, 0x1077465430e5a83578e58dd7c5d14b023530163c69971bcfa1831c8045435f68];
+        data.walletAddress = 0x1D96F2f6BeF1202E4Ce1Ff6Dad0c2CB002861d3e;
+        data.domainStake = "StakeManager:BN254:PoP:v1:";
+        data.domainValidator = "ValidatorManager:BN254:PoP:v1:";
+        data.proofs = new ProofData[](5);
+        data.proofs[0] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0835e1311c036bf940551de8e61184ddd6552f88a2d8f576cbd3d574b5ac96fb), 0x11147d3f8dce462d66e0a78802b6bf125d58703bd73d1ab667ea98e3c095cbed],
+            messageHashValidator: [uint256(0x0696718993550a1429334044620ebc58f47d09bb2170d63bcfc2398426176988), 0x2f76abef95388d472d08860994dd1aa437cdf4d5f71af145e89d10a8e807ef2e],
+            proofOfPossessionStake: [uint256(0x1968d28007698734ad28a101ae3533fdb3378cfa351a07a434d468d8246d606a), 0x18e1a1a02b3a03b89011512958d4a19b9c2bbd90c8f482584f4e7e4928d71c7e],
+            proofOfPossessionValidator: [uint256(0x18392ebceb35263bb017641d539ff21547f2e0ca4860a24dbc5280e0cbcc2329), 0x26b56adf579755010a0b8375dbd0e53989d1d8237db92e00ae3640a18af9dd4f],
+            valid: true
+        });
+        data.proofs[1] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0835e1311c036bf940551de8e61184ddd6552f88a2d8f576cbd3d574b5ac96fb), 0x11147d3f8dce462d66e0a78802b6bf125d58703bd73d1ab667ea98e3c095cbed],
+            messageHashValidator: [uint256(0x0696718993550a1429334044620ebc58f47d09bb2170d63bcfc2398426176988), 0x2f76abef95388d472d08860994dd1aa437cdf4d5f71af145e89d10a8e807ef2e],
+            proofOfPossessionStake: [uint256(0x2f493636d67b4587c2633edb20690c89c1b2de9fc4ce1658c6869910551bdadd), 0x189dd00ba2201c6d1debf343c9d8a894a293cfae483c76b95e3b8207e94bfdf0],
+            proofOfPossessionValidator: [uint256(0x24eedb1d7b197596c5820e0632a05eea7640eaf7b5d57bdab849f2ddcc50ab7f), 0x1b6c065b4a005b71957d048aadda092aff16c7f02bf4b999dc7b183dabfa4f2b],
+            valid: false
+        });
+        data.proofs[2] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0835e1311c036bf940551de8e61184ddd6552f88a2d8f576cbd3d574b5ac96fb), 0x11147d3f8dce462d66e0a78802b6bf125d58703bd73d1ab667ea98e3c095cbed],
+            messageHashValidator: [uint256(0x0696718993550a1429334044620ebc58f47d09bb2170d63bcfc2398426176988), 0x2f76abef95388d472d08860994dd1aa437cdf4d5f71af145e89d10a8e807ef2e],
+            proofOfPossessionStake: [uint256(0x016bb4dc4aabd0703ebb81515f7148fecea5e8ac6024dab9381884dc0082cc19), 0x0b535315a02bcfd295692ca6424b4abc6f0a53011b7530d95e2ce82f182344d1],
+            proofOfPossessionValidator: [uint256(0x01c9aa6cd536b387c13d619db97c25bbe1716f3eaea4974800809750f1aa9260), 0x06743e0dbe07dace659a13d108fb81bb1dd32db18f846086b9ecb42c911fb06c],
+            valid: false
+        });
+        data.proofs[3] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0835e1311c036bf940551de8e61184ddd6552f88a2d8f576cbd3d574b5ac96fb), 0x11147d3f8dce462d66e0a78802b6bf125d58703bd73d1ab667ea98e3c095cbed],
+            messageHashValidator: [uint256(0x0696718993550a1429334044620ebc58f47d09bb2170d63bcfc2398426176988), 0x2f76abef95388d472d08860994dd1aa437cdf4d5f71af145e89d10a8e807ef2e],
+            proofOfPossessionStake: [uint256(0x18e1a1a02b3a03b89011512958d4a19b9c2bbd90c8f482584f4e7e4928d71c7e), 0x1968d28007698734ad28a101ae3533fdb3378cfa351a07a434d468d8246d606a],
+            proofOfPossessionValidator: [uint256(0x26b56adf579755010a0b8375dbd0e53989d1d8237db92e00ae3640a18af9dd4f), 0x18392ebceb35263bb017641d539ff21547f2e0ca4860a24dbc5280e0cbcc2329],
+            valid: false
+        });
+        data.proofs[4] = ProofData({
+            chainId: 1,
+            messageHashStake: [uint256(0x0835e1311c036bf940551de8e61184ddd6552f88a2d8f576cbd3d574b5ac96fb), 0x11147d3f8dce462d66e0a78802b6bf125d58703bd73d1ab667ea98e3c095cbed],
+            messageHashValidator: [uint256(0x0696718993550a1429334044620ebc58f47d09bb2170d63bcfc2398426176988), 0x2f76abef95388d472d08860994dd1aa437cdf4d5f71af145e89d10a8e807ef2e],
+            proofOfPossessionStake: [uint256(0x260368448b28df08b100e6a88fe0fcdb4f2003bfa127c5676a67cbc5b71ad9cd), 0x2b9223aee7abd5baa4194f1f315b8734e74dc959c4c4acf52daef545604e7d56],
+            proofOfPossessionValidator: [uint256(0x1b968bd7cb4887e81d33ccd7e9c1d2bd5172b4e460dbd61e71f9b663b12b8339), 0x06fe99ac32c063c08acf00f923941a8c22b7dd29e109e6593d58b8081565cad8],
+            valid: false
+        });
+    }
+}
@@ -0,0 +1,20 @@
+//! Golden test for the Foundry layout.
+//!
+//! `fixtures/foundry.json` is the first entry of `fixtures/good.json` in the Foundry layout.
+//! After an intentional change to the layout, regenerate it with
+//! `cargo run -p bls-test-utils -- --seed 1 --chain-id 1 --include-invalid --wallet
+//! 0x328809Bc894f92807417D2dAD6b7C998c1aFdac6 --layout foundry --out tests/fixtures/foundry.json`.
+
+use bls_test_utils::{foundry_fixture, OutputFormat};
+
+#[test]
+fn test_foundry_layout_matches_golden_file() {
+    let cases = OutputFormat::Json
+        .decode_cases(include_str!("fixtures/good.json"))
+        .expect("fixture parses");
+    let fixture = foundry_fixture(&cases[0]).unwrap();
+    assert_eq!(
+        OutputFormat::Json.encode_cases(&[fixture], false).unwrap(),
+        include_str!("fixtures/foundry.json")
+    );
+}
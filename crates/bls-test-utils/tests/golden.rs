@@ -0,0 +1,67 @@
+//! Re-verifies the committed golden fixture from scratch, so a change in sylow, the limb order
+//! or the message encoding fails here instead of silently producing fixtures that no longer match
+//! the deployed contracts.
+//!
+//! `testdata/golden_v1.json` was written with `--seed 55 --chain-id 1 --chain-id 8453` for the
+//! default wallets. It is never regenerated: a new golden file gets a new version.
+
+use alloy::primitives::{Address, U256};
+use bls_test_utils::{
+    g1_to_words, load_fixture, pairing_check, pop_message, validate_g1, validate_g2,
+    verify_fixture, words_from_hex, words_to_hex, MessageEncoding, MessageHasher,
+};
+use std::path::Path;
+
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/golden_v1.json");
+
+/// `public_key` of the first entry, in Solidity limb order.
+const FIRST_PUBLIC_KEY: [&str; 4] = [
+    "0x22d7a5e3a3b4fba2275f86964478da2d4ef5c753024949523c1d7e016e9c7adc",
+    "0x26c296d2b6e43d4f8bff71c32fc186019ec0259d7eda8bfb68569e20df761fb4",
+    "0x24c9160b700b7b5a8580f4c10657c84b4b483e6f7ab9d506f5f506842a4121b9",
+    "0x04b67bada743b7341de0f9cc76e19597a4257cf62a9cec0b6a588daca00b9846",
+];
+
+#[test]
+fn test_golden_fixture_still_verifies() {
+    let cases = load_fixture(Path::new(GOLDEN)).expect("golden fixture loads");
+    assert_eq!(cases.len(), 5);
+    assert_eq!(cases[0].public_key, FIRST_PUBLIC_KEY, "first public key changed");
+
+    let hasher = MessageHasher::default();
+    for case in &cases {
+        let pk_words = words_from_hex(&case.public_key).expect("public key limbs");
+        let public_key = validate_g2(pk_words).expect("public key is a G2 point");
+        let wallet: Address = case.wallet_address.parse().expect("wallet address");
+        for proof in &case.proof {
+            assert!(proof.valid);
+            let chain_id: U256 = proof.chain_id.parse().expect("chain id");
+            let message = pop_message(MessageEncoding::Packed, chain_id, &pk_words, wallet);
+            assert_eq!(
+                proof.message_bytes.as_deref(),
+                Some(format!("0x{}", hex::encode(&message)).as_str()),
+                "preimage of {} on chain {chain_id}",
+                case.wallet_address
+            );
+            for (dst, message_hash, signature) in [
+                (
+                    &case.domain_staking_manager,
+                    &proof.message_hash_stake_manager,
+                    &proof.proof_of_possession_stake_manager,
+                ),
+                (
+                    &case.domain_validator_manager,
+                    &proof.message_hash_validator_manager,
+                    &proof.proof_of_possession_validator_manager,
+                ),
+            ] {
+                let hash = hasher.hash_to_curve(dst, &message);
+                assert_eq!(&words_to_hex(g1_to_words(&hash)), message_hash, "H(m) under {dst}");
+                let signature =
+                    validate_g1(words_from_hex(signature).expect("signature limbs")).unwrap();
+                assert!(pairing_check(&signature, &hash, &public_key), "pairing under {dst}");
+            }
+        }
+    }
+    assert!(verify_fixture(&cases).iter().all(|result| result.passed()));
+}
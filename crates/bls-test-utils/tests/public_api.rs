@@ -0,0 +1,24 @@
+//! Exercises the library the way a downstream crate would, without going through the CLI.
+
+use alloy::primitives::{Address, U256};
+use bls_test_utils::{
+    generate_case, GenOptions, DEFAULT_DST_STAKE_MANAGER, DEFAULT_DST_VALIDATOR_MANAGER,
+};
+
+#[test]
+fn test_generate_case_from_another_crate() {
+    let wallet = Address::repeat_byte(0x11);
+    let opts = GenOptions { seed: Some("42".parse().unwrap()), ..Default::default() };
+    let case = generate_case(wallet, &[U256::from(1), U256::from(8453)], &opts).unwrap();
+
+    assert_eq!(case.wallet_address, wallet.to_string());
+    assert_eq!(case.domain_staking_manager, DEFAULT_DST_STAKE_MANAGER);
+    assert_eq!(case.domain_validator_manager, DEFAULT_DST_VALIDATOR_MANAGER);
+    let chain_ids: Vec<&str> = case.proof.iter().map(|proof| proof.chain_id.as_str()).collect();
+    assert_eq!(chain_ids, ["1", "8453"]);
+    assert!(case.proof.iter().all(|proof| proof.valid));
+    assert_eq!(
+        generate_case(wallet, &[U256::from(1), U256::from(8453)], &opts).unwrap().public_key,
+        case.public_key
+    );
+}
@@ -0,0 +1,46 @@
+//! Loads fixtures of every schema version through `load_fixture`.
+//!
+//! `schema_v1.json` was written before `schema_version` existed, with `--seed 27 --chain-id 1
+//! --include-invalid --wallet 0x328809Bc894f92807417D2dAD6b7C998c1aFdac6`, and
+//! `schema_v2.json` with the same flags afterwards. `schema_future.json` is the latter claiming
+//! version 3, with made-up fields added at the top level, to each case and to each proof.
+
+use bls_test_utils::{load_fixture, verify_fixture, LoadError, OutputFormat};
+use std::path::Path;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+fn load(name: &str) -> Vec<bls_test_utils::BlsTestData> {
+    load_fixture(&Path::new(FIXTURES).join(name)).expect("fixture loads")
+}
+
+#[test]
+fn test_every_version_loads_the_same_cases() {
+    let v2 = load("schema_v2.json");
+    assert_eq!(v2.len(), 1);
+    assert!(verify_fixture(&v2).iter().all(|result| result.passed()));
+    assert_eq!(load("schema_v1.json"), v2, "version 1 is upgraded in memory");
+    assert_eq!(load("schema_future.json"), v2, "unknown fields are skipped");
+}
+
+#[test]
+fn test_writing_upgrades_to_the_current_version() {
+    let v1 = include_str!("fixtures/schema_v1.json");
+    let cases = OutputFormat::Json.decode_cases(v1).unwrap();
+    assert_eq!(
+        OutputFormat::Json.encode_cases(&cases, false).unwrap(),
+        include_str!("fixtures/schema_v2.json")
+    );
+}
+
+#[test]
+fn test_load_errors_name_the_file() {
+    let missing = Path::new(FIXTURES).join("missing.json");
+    let error = load_fixture(&missing).unwrap_err();
+    assert!(matches!(error, LoadError::Read { .. }));
+    assert!(error.to_string().contains("missing.json"), "{error}");
+
+    let not_a_fixture = Path::new(FIXTURES).join("wallets.json");
+    let error = load_fixture(&not_a_fixture).unwrap_err();
+    assert!(matches!(error, LoadError::Parse { .. }), "{error}");
+}
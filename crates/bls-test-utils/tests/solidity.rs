@@ -0,0 +1,16 @@
+//! Golden test for the Solidity fixture library.
+//!
+//! `fixtures/BlsTestVectors.sol` is `render_solidity` of `fixtures/good.json`. After an
+//! intentional change to the output, regenerate it with
+//! `cargo run -p bls-test-utils -- --seed 1 --chain-id 1 --include-invalid --wallet
+//! 0x328809Bc894f92807417D2dAD6b7C998c1aFdac6 --wallet 0x1D96F2f6BeF1202E4Ce1Ff6Dad0c2CB002861d3e
+//! --out tests/fixtures/good.json --emit-solidity tests/fixtures/BlsTestVectors.sol`.
+
+use bls_test_utils::{render_solidity, BlsTestData};
+
+#[test]
+fn test_render_matches_golden_file() {
+    let cases: Vec<BlsTestData> =
+        serde_json::from_str(include_str!("fixtures/good.json")).expect("fixture parses");
+    assert_eq!(render_solidity(&cases).unwrap(), include_str!("fixtures/BlsTestVectors.sol"));
+}
@@ -0,0 +1,40 @@
+//! Runs `verify_fixture` over checked-in fixtures.
+//!
+//! `good.json` was written with `--seed 1 --chain-id 1 --include-invalid` for the first two
+//! default wallets. `corrupted.json` is a copy with the first wallet's StakeManager signature
+//! nudged off the curve and the second wallet's valid entry relabelled to chain id 8453.
+
+use bls_test_utils::{verify_fixture, BlsTestData, Domain, OutputFormat, VerifyFailure};
+
+fn load(raw: &str) -> Vec<BlsTestData> {
+    OutputFormat::Json.decode_cases(raw).expect("fixture parses")
+}
+
+#[test]
+fn test_good_fixture_verifies() {
+    let results = verify_fixture(&load(include_str!("fixtures/good.json")));
+    assert_eq!(results.len(), 20);
+    assert!(results.iter().all(|result| result.passed()), "{results:#?}");
+}
+
+#[test]
+fn test_corrupted_fixture_is_rejected() {
+    let results = verify_fixture(&load(include_str!("fixtures/corrupted.json")));
+    let failures: Vec<_> = results
+        .iter()
+        .filter(|result| !result.passed())
+        .map(|result| (result.chain_id.as_str(), result.domain, result.outcome.clone()))
+        .collect();
+    assert_eq!(
+        failures,
+        [
+            (
+                "1",
+                Domain::StakeManager,
+                Err(VerifyFailure::NotOnCurve { field: "proof_of_possession_stake_manager" })
+            ),
+            ("8453", Domain::StakeManager, Err(VerifyFailure::MessageHashMismatch)),
+            ("8453", Domain::ValidatorManager, Err(VerifyFailure::MessageHashMismatch)),
+        ]
+    );
+}
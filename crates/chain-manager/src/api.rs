@@ -1,32 +1,258 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    future::Future,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
 use alloy::{
     consensus::Header,
-    primitives::B256,
-    providers::{Provider, ProviderBuilder},
-    rpc::types::{eth::TransactionReceipt, BlockNumberOrTag},
+    eips::{BlockId, Encodable2718},
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    providers::{IpcConnect, Provider, ProviderBuilder, WsConnect},
+    rpc::types::{
+        eth::{Transaction, TransactionReceipt, TransactionRequest},
+        BlockNumberOrTag, EIP1186AccountProofResponse, Filter, FilterBlockOption, Log,
+    },
+    transports::{RpcError, TransportError, TransportErrorKind, TransportResult},
 };
 use dashmap::DashMap;
+use futures::{future::join_all, stream, StreamExt, TryStreamExt};
 use jsonrpsee::{
-    core::{async_trait, RpcResult},
+    core::{async_trait, RpcResult, SubscriptionResult},
     proc_macros::rpc,
-    types::ErrorObjectOwned,
+    types::{error::INVALID_PARAMS_CODE, ErrorObjectOwned},
+    PendingSubscriptionSink,
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
-#[rpc(server, client)]
-pub trait ChainManager {
-    #[method(name = "finalisedHeader")]
-    async fn finalised_header(&self, chain_id: u64, at: BlockNumberOrTag) -> RpcResult<Header>;
+use crate::{
+    cache::{HeaderCache, ReceiptCache},
+    metrics::{error_class, Metrics},
+    proofs::{receipts_trie_proof, trie_key, ReceiptProof},
+    retry::{is_transient, with_retry},
+    shutdown::{InFlight, ShutdownHandle},
+    subscriptions::{forward_finalised, forward_new_heads},
+    trace::request_id,
+    ChainConfig, FinalisedHeader, Finality, FinalityMode, RpcEndpoint,
+    DEFAULT_FINALISED_POLL_INTERVAL,
+};
 
-    #[method(name = "transactionReceipt")]
-    async fn transaction_receipt(
-        &self,
-        chain_id: u64,
-        tx_hash: B256,
-    ) -> RpcResult<Option<TransactionReceipt>>;
+pub use rpc::*;
+
+// The macro marks the generated client methods `#[must_use]`, on futures that already are, and
+// only the module around it can allow that.
+#[allow(clippy::double_must_use)]
+mod rpc {
+    use super::*;
+
+    #[rpc(server, client)]
+    pub trait ChainManager {
+        #[method(name = "finalisedHeader")]
+        async fn finalised_header(&self, chain_id: u64, at: BlockNumberOrTag) -> RpcResult<Header>;
+
+        /// The header of every chain in `chain_ids` at `at`, fetched concurrently. A chain that
+        /// fails gets its error in place of a header, the others are still answered.
+        #[method(name = "finalisedHeaders")]
+        async fn finalised_headers(
+            &self,
+            chain_ids: Vec<u64>,
+            at: BlockNumberOrTag,
+        ) -> RpcResult<BTreeMap<u64, HeaderOrError>>;
+
+        #[method(name = "headerByHash")]
+        async fn header_by_hash(&self, chain_id: u64, block_hash: B256) -> RpcResult<Header>;
+
+        /// The headers of blocks `start` to `end`, both included, ordered by number.
+        #[method(name = "headersRange")]
+        async fn headers_range(
+            &self,
+            chain_id: u64,
+            start: u64,
+            end: u64,
+        ) -> RpcResult<Vec<Header>>;
+
+        #[method(name = "transactionReceipt")]
+        async fn transaction_receipt(
+            &self,
+            chain_id: u64,
+            tx_hash: B256,
+        ) -> RpcResult<Option<TransactionReceipt>>;
+
+        #[method(name = "transactionByHash")]
+        async fn transaction_by_hash(
+            &self,
+            chain_id: u64,
+            tx_hash: B256,
+        ) -> RpcResult<Option<Transaction>>;
+
+        /// Receipts of `tx_hashes` in the same order, `None` for those the node does not know.
+        #[method(name = "transactionReceipts")]
+        async fn transaction_receipts(
+            &self,
+            chain_id: u64,
+            tx_hashes: Vec<B256>,
+        ) -> RpcResult<Vec<Option<TransactionReceipt>>>;
+
+        /// The receipt of `tx_hash` with its Merkle-Patricia proof against the block's
+        /// `receiptsRoot`.
+        #[method(name = "receiptProof")]
+        async fn receipt_proof(&self, chain_id: u64, tx_hash: B256) -> RpcResult<ReceiptProof>;
+
+        /// Serve another chain without a restart, see [`ChainManagerImpl::add_chain`]. `verify`
+        /// defaults to true. Only admin API keys may call it.
+        #[method(name = "admin_addChain")]
+        async fn admin_add_chain(&self, config: ChainConfig, verify: Option<bool>)
+            -> RpcResult<()>;
+
+        /// Stop serving a chain and forget its config. Calls already in flight finish.
+        #[method(name = "admin_removeChain")]
+        async fn admin_remove_chain(&self, chain_id: u64) -> RpcResult<()>;
+
+        /// Stop serving a chain but keep its config, to `admin_enableChain` it later.
+        #[method(name = "admin_disableChain")]
+        async fn admin_disable_chain(&self, chain_id: u64) -> RpcResult<()>;
+
+        #[method(name = "admin_enableChain")]
+        async fn admin_enable_chain(&self, chain_id: u64) -> RpcResult<()>;
+
+        #[method(name = "logs")]
+        async fn logs(&self, chain_id: u64, filter: Filter) -> RpcResult<Vec<Log>>;
+
+        /// The logs of the first `page_size` blocks `filter` spans, and where the next page starts.
+        /// `page_size` defaults to the chain's `log_page_size` and is capped at its log range.
+        #[method(name = "logsPaged")]
+        async fn logs_paged(
+            &self,
+            chain_id: u64,
+            filter: Filter,
+            page_size: Option<u64>,
+        ) -> RpcResult<LogsPage>;
+
+        #[method(name = "getProof")]
+        async fn get_proof(
+            &self,
+            chain_id: u64,
+            address: Address,
+            storage_keys: Vec<B256>,
+            at: BlockNumberOrTag,
+        ) -> RpcResult<EIP1186AccountProofResponse>;
+
+        #[method(name = "accountState")]
+        async fn account_state(
+            &self,
+            chain_id: u64,
+            address: Address,
+            at: BlockNumberOrTag,
+        ) -> RpcResult<AccountState>;
+
+        /// Every new head of `chain_id`, pushed by WebSocket upstreams and polled from HTTP ones.
+        #[subscription(name = "subscribeNewHeads", unsubscribe = "unsubscribeNewHeads", item = Header)]
+        async fn subscribe_new_heads(&self, chain_id: u64) -> SubscriptionResult;
+
+        /// The finalised header of `chain_id` each time it advances, see [`crate::Finality`].
+        #[subscription(
+            name = "subscribeFinalised",
+            unsubscribe = "unsubscribeFinalised",
+            item = FinalisedHeader
+        )]
+        async fn subscribe_finalised(&self, chain_id: u64) -> SubscriptionResult;
+
+        #[method(name = "listChains")]
+        async fn list_chains(&self) -> RpcResult<Vec<ChainStatus>>;
+
+        #[method(name = "call")]
+        async fn call(
+            &self,
+            chain_id: u64,
+            tx: TransactionRequest,
+            at: BlockNumberOrTag,
+        ) -> RpcResult<Bytes>;
+    }
+}
+
+/// What a claim submitter checks about an account before sending to it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountState {
+    pub balance: U256,
+    #[serde(with = "alloy::serde::quantity")]
+    pub nonce: u64,
+    /// keccak256 of the deployed code, that of the empty string for an EOA.
+    pub code_hash: B256,
+    /// Whether any code is deployed at the address.
+    pub has_code: bool,
+}
+
+/// How one configured chain looks from this instance, as reported by `listChains`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStatus {
+    pub chain_id: u64,
+    pub name: Option<String>,
+    /// Whether a provider for the RPC URL in use is cached.
+    pub connected: bool,
+    /// Which of the chain's RPC URLs is in use, counting from 0 for the primary.
+    pub active_url: usize,
+    /// The node's latest block, when it answered the health probe.
+    pub latest_block: Option<u64>,
+    /// Why the health probe failed, when it did.
+    pub last_error: Option<String>,
+    /// Whether the chain is taken out of rotation, it is not probed then.
+    pub disabled: bool,
 }
 
+/// One chain's answer in `finalisedHeaders`, `{ "header": .. }` or `{ "error": .. }` with the
+/// error object the chain alone would have been answered with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HeaderOrError {
+    Header(Box<Header>),
+    Error(ErrorObjectOwned),
+}
+
+/// Most chains a single `finalisedHeaders` request may ask for.
+pub const MAX_CHAINS_PER_REQUEST: usize = 32;
+
+/// One page of `logsPaged`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsPage {
+    pub logs: Vec<Log>,
+    /// The `fromBlock` of the next page, `None` once the filter's range is done.
+    pub next_from_block: Option<u64>,
+}
+
+/// How long `listChains` waits for each chain's node before reporting it unhealthy.
+pub const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a chain stays on a fallback RPC URL before its primary is tried again.
+pub const PRIMARY_REPROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Widest block range a single `logs` request may cover, unless set with
+/// [`ChainManagerImpl::with_max_log_range`].
+pub const DEFAULT_MAX_LOG_RANGE: u64 = 10_000;
+
+/// Blocks one `logsPaged` page covers, unless the request or the chain's `log_page_size` sets it.
+pub const DEFAULT_LOG_PAGE_SIZE: u64 = 1_000;
+
+/// Most receipts a single `transactionReceipts` request may ask for, unless set with
+/// [`ChainManagerImpl::with_max_receipt_batch`].
+pub const DEFAULT_MAX_RECEIPT_BATCH: usize = 256;
+
+/// Receipts of one `transactionReceipts` request fetched at the same time.
+pub const RECEIPT_BATCH_CONCURRENCY: usize = 8;
+
+/// Most headers a single `headersRange` request may ask for, unless set with
+/// [`ChainManagerImpl::with_max_header_range`].
+pub const DEFAULT_MAX_HEADER_RANGE: u64 = 512;
+
+/// Headers of one `headersRange` request fetched at the same time.
+pub const HEADER_RANGE_CONCURRENCY: usize = 8;
+
 #[derive(Error, Debug, Clone)]
 pub enum ChainManagerError {
     #[error("The chain id used was not part of the chains configured")]
@@ -37,271 +263,2917 @@ pub enum ChainManagerError {
     ProviderFailure { reason: String, chain_id: u64 },
     #[error("We use this for generic errors")]
     GenericFailure { reason: String, chain_id: u64 },
+    #[error("The node does not have the requested block")]
+    BlockNotFound { reason: String, chain_id: u64 },
+    /// The error object carries `{ "chain_id": .., "from_block": .., "to_block": .. }`, the
+    /// widest range starting at the requested `fromBlock` that is served.
+    #[error("The requested block range is wider than we serve")]
+    LogRangeTooLarge { reason: String, chain_id: u64, from_block: u64, to_block: u64 },
+    /// Unlike the other variants the error object carries `data`, the revert data, instead of
+    /// the chain id.
+    #[error("The call reverted")]
+    CallReverted { reason: String, chain_id: u64, data: Bytes },
+    /// Not tied to a chain, the error object carries `{ "retry_after_ms": .. }` as its data.
+    #[error("The client sent more requests than its rate limit allows")]
+    RateLimited { reason: String, retry_after_ms: u64 },
+    #[error("The request asks for more items than we serve at once")]
+    BatchTooLarge { reason: String, chain_id: u64 },
+    #[error("The node does not have the requested transaction")]
+    TransactionNotFound { reason: String, chain_id: u64 },
+    #[error("The requested range ends before it starts")]
+    InvalidRange { reason: String, chain_id: u64 },
+    /// Not tied to a chain, the error object carries no data.
+    #[error("The request came without a known API key")]
+    Unauthorized { reason: String },
+    /// Not tied to a chain, the error object carries no data.
+    #[error("The API key may not call the method")]
+    Forbidden { reason: String },
+    /// Not tied to a chain, the error object carries no data.
+    #[error("The server no longer takes calls")]
+    ShuttingDown { reason: String },
+    #[error("The chain config cannot be served")]
+    InvalidChainConfig { reason: String, chain_id: u64 },
+    /// The node answered with a JSON-RPC error, the error object carries
+    /// `{ "chain_id": .., "code": .., "message": .. }` with the node's code and message.
+    #[error("The node rejected the request")]
+    UpstreamRpcError { reason: String, chain_id: u64, code: i64, message: String },
+    #[error("The node does not have what was asked for")]
+    NotFound { reason: String, chain_id: u64 },
+    /// The error object carries `{ "chain_id": .., "method": .., "timeout_ms": .. }`, `method`
+    /// being the upstream method, or `connect` for connecting to the RPC URL.
+    #[error("The node did not answer in time")]
+    Timeout { chain_id: u64, method: String, timeout_ms: u64 },
+    /// The error object carries `{ "chain_id": .., "timeout_ms": .. }`.
+    #[error("The chain has too many calls in flight")]
+    QueueTimeout { chain_id: u64, timeout_ms: u64 },
+    /// The node answered with something that contradicts itself or the request, e.g. a header
+    /// that does not hash to the block hash it came with.
+    #[error("The node answered with inconsistent data")]
+    UpstreamInconsistent { reason: String, chain_id: u64 },
+    /// An RPC URL of the chain serves another chain, the error object carries
+    /// `{ "chain_id": .., "expected": .., "actual": .. }`, `chain_id` being the `expected` one.
+    #[error("The RPC URL serves a different chain")]
+    ChainIdMismatch { expected: u64, actual: u64 },
+    /// Not tied to a chain, the error object carries no data.
+    #[error("The request asks for more chains than we serve at once")]
+    TooManyChains { reason: String },
+    /// Answered with the standard JSON-RPC `-32602` code.
+    #[error("The request parameters are not served")]
+    InvalidParams { reason: String, chain_id: u64 },
+}
+
+/// Fragments of the messages nodes reject requests for missing blocks, state or transactions
+/// with, e.g. geth's `header not found` and `missing trie node`.
+const NOT_FOUND_MESSAGES: [&str; 4] =
+    ["not found", "missing trie node", "unknown block", "does not exist"];
+
+impl ChainManagerError {
+    /// Classify an error the upstream of `chain_id` gave while `what`. The node being down,
+    /// unreachable or answering garbage is a `NodeFailure`, worth retrying later. The node
+    /// rejecting the request is an `UpstreamRpcError`, or `NotFound` when it says what was asked
+    /// for does not exist.
+    pub(crate) fn from_upstream(error: TransportError, chain_id: u64, what: &str) -> Self {
+        match error {
+            RpcError::ErrorResp(payload) => {
+                let lowercase = payload.message.to_lowercase();
+                if NOT_FOUND_MESSAGES.iter().any(|fragment| lowercase.contains(fragment)) {
+                    return Self::NotFound {
+                        reason: format!("The node found nothing while {what}: {}", payload.message),
+                        chain_id,
+                    }
+                }
+                Self::UpstreamRpcError {
+                    reason: format!("The node rejected {what}: {}", payload.message),
+                    chain_id,
+                    code: payload.code,
+                    message: payload.message.into_owned(),
+                }
+            }
+            RpcError::NullResp => {
+                Self::NotFound { reason: format!("The node answered null while {what}"), chain_id }
+            }
+            RpcError::Transport(_) | RpcError::DeserError { .. } => Self::NodeFailure {
+                reason: format!("Something went wrong while {what} {error:?}"),
+                chain_id,
+            },
+            error => Self::GenericFailure {
+                reason: format!("Something went wrong while {what} {error:?}"),
+                chain_id,
+            },
+        }
+    }
+}
+
+/// [`ChainManagerError::from_upstream`] for the errors of one [`ChainManagerImpl`] upstream call.
+fn upstream_error(
+    chain_id: u64,
+    what: &'static str,
+) -> impl FnOnce(TransportError) -> ChainManagerError {
+    move |error| ChainManagerError::from_upstream(error, chain_id, what)
+}
+
+/// Run `future`, failing with `Timeout` if it takes longer than the chain's
+/// [`ChainConfig::request_timeout`].
+async fn within_timeout<T>(
+    config: &ChainConfig,
+    method: &str,
+    future: impl Future<Output = T>,
+) -> Result<T, ChainManagerError> {
+    let timeout = config.request_timeout();
+    tokio::time::timeout(timeout, future).await.map_err(|_| ChainManagerError::Timeout {
+        chain_id: config.chain_id,
+        method: method.into(),
+        timeout_ms: timeout.as_millis() as u64,
+    })
 }
-#[derive(Clone, Debug, Default)]
-pub struct ChainConfig {
+
+/// The consensus header of `header`, a block the node of `chain_id` returned for `requested`,
+/// once it hashes to the block hash the node reported and is the block that was asked for.
+fn verified_header(
     chain_id: u64,
-    rpc_url: String,
+    header: alloy::rpc::types::Header,
+    requested: BlockId,
+) -> Result<Header, ChainManagerError> {
+    let inconsistent = |reason| ChainManagerError::UpstreamInconsistent { reason, chain_id };
+    let reported = header.hash;
+    let header: Header = header.into();
+    let hash = header.hash_slow();
+    if hash != reported {
+        return Err(inconsistent(format!(
+            "The node reported block {} as {reported}, its header hashes to {hash}",
+            header.number
+        )))
+    }
+    match requested {
+        BlockId::Number(BlockNumberOrTag::Number(number)) if header.number != number => {
+            Err(inconsistent(format!("The node returned block {} for {number}", header.number)))
+        }
+        BlockId::Hash(requested) if hash != requested.block_hash => Err(inconsistent(format!(
+            "The node returned block {hash} for {}",
+            requested.block_hash
+        ))),
+        _ => Ok(header),
+    }
+}
+
+/// Connect to `url` of `config` over the transport its scheme picks.
+async fn connect(config: &ChainConfig, url: &str) -> Result<Arc<dyn Provider>, ChainManagerError> {
+    let chain_id = config.chain_id;
+    let failure = |transport: &str, error: TransportError| ChainManagerError::ProviderFailure {
+        reason: format!("Could not connect to {url} over {transport}: {error}"),
+        chain_id,
+    };
+    let endpoint = RpcEndpoint::parse(url)
+        .map_err(|reason| ChainManagerError::ProviderFailure { reason, chain_id })?;
+    let builder = ProviderBuilder::new();
+    let provider = match endpoint {
+        RpcEndpoint::Http(url) => builder.connect_http(url),
+        RpcEndpoint::Ws(url) => {
+            let reconnect = &config.ws_reconnect;
+            let ws = WsConnect::new(url)
+                .with_max_retries(reconnect.max_retries)
+                .with_retry_interval(Duration::from_millis(reconnect.retry_interval_ms));
+            within_timeout(config, "connect", builder.connect_ws(ws))
+                .await?
+                .map_err(|error| failure("a websocket", error))?
+        }
+        RpcEndpoint::Ipc(path) => {
+            within_timeout(config, "connect", builder.connect_ipc(IpcConnect::new(path)))
+                .await?
+                .map_err(|error| failure("IPC", error))?
+        }
+    };
+    Ok(Arc::new(provider))
 }
 
 /// We dont need to create a provider since validators
 /// Are going to query on demand so we init a provider based on chn id
 pub struct ChainManagerImpl {
-    configs: Vec<ChainConfig>,
-    providers: Arc<DashMap<u64, Arc<dyn Provider>>>,
+    /// Only locked to look a chain up or change the list, never across an await.
+    configs: RwLock<Vec<Arc<ChainConfig>>>,
+    /// Keyed by chain id and the index of the RPC URL.
+    providers: Arc<DashMap<(u64, usize), Arc<dyn Provider>>>,
+    failover: Arc<DashMap<u64, Failover>>,
+    /// Keyed like `providers`.
+    provider_health: DashMap<(u64, usize), ProviderHealth>,
+    /// Of the chains with `max_concurrent_requests`, created on their first call.
+    permits: DashMap<u64, Arc<Semaphore>>,
+    max_log_range: u64,
+    max_receipt_batch: usize,
+    max_header_range: u64,
+    finalised_poll_interval: Duration,
+    metrics: Arc<Metrics>,
+    header_caches: Arc<DashMap<u64, HeaderCache>>,
+    receipt_caches: Arc<DashMap<u64, ReceiptCache>>,
+    in_flight: Arc<InFlight>,
+}
+
+/// Which of a chain's RPC URLs is in use and how it has been doing.
+#[derive(Clone, Copy, Debug, Default)]
+struct Failover {
+    active: usize,
+    consecutive_failures: u32,
+    failed_over_at: Option<Instant>,
+}
+
+/// How a cached provider has been doing since it connected.
+#[derive(Clone, Copy, Debug, Default)]
+struct ProviderHealth {
+    consecutive_failures: u32,
+    answered: bool,
+}
+
+// Providers and caches have no useful `Debug`, the chains and limits are what tell managers apart.
+impl std::fmt::Debug for ChainManagerImpl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let configs = self.configs.read().expect("configs lock");
+        let chain_ids: Vec<u64> = configs.iter().map(|config| config.chain_id).collect();
+        f.debug_struct("ChainManagerImpl")
+            .field("chain_ids", &chain_ids)
+            .field("providers", &self.providers.len())
+            .field("max_log_range", &self.max_log_range)
+            .field("max_receipt_batch", &self.max_receipt_batch)
+            .field("max_header_range", &self.max_header_range)
+            .field("finalised_poll_interval", &self.finalised_poll_interval)
+            .finish_non_exhaustive()
+    }
 }
 
 impl From<ChainManagerError> for ErrorObjectOwned {
     fn from(error: ChainManagerError) -> Self {
-        match error {
+        use serde_json::json;
+        let (code, reason, mut data) = match error {
             ChainManagerError::ChainIdNotFound { reason, chain_id } => {
-                ErrorObjectOwned::owned(-4004, reason, Some(chain_id))
+                (-4004, reason, json!({ "chain_id": chain_id }))
             }
             ChainManagerError::NodeFailure { reason, chain_id } => {
-                ErrorObjectOwned::owned(-4005, reason, Some(chain_id))
+                (-4005, reason, json!({ "chain_id": chain_id }))
             }
             ChainManagerError::ProviderFailure { reason, chain_id } => {
-                ErrorObjectOwned::owned(-4006, reason, Some(chain_id))
+                (-4006, reason, json!({ "chain_id": chain_id }))
             }
             ChainManagerError::GenericFailure { reason, chain_id } => {
-                ErrorObjectOwned::owned(-4007, reason, Some(chain_id))
+                (-4007, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::BlockNotFound { reason, chain_id } => {
+                (-4008, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::LogRangeTooLarge { reason, chain_id, from_block, to_block } => (
+                -4009,
+                reason,
+                json!({ "chain_id": chain_id, "from_block": from_block, "to_block": to_block }),
+            ),
+            // The revert data alone, as nodes answer it, so tooling can decode it.
+            ChainManagerError::CallReverted { reason, data, .. } => {
+                return ErrorObjectOwned::owned(-4010, reason, Some(data))
+            }
+            ChainManagerError::RateLimited { reason, retry_after_ms } => {
+                (-4011, reason, json!({ "retry_after_ms": retry_after_ms }))
+            }
+            ChainManagerError::BatchTooLarge { reason, chain_id } => {
+                (-4012, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::TransactionNotFound { reason, chain_id } => {
+                (-4013, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::InvalidRange { reason, chain_id } => {
+                (-4014, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::Unauthorized { reason } => (-4015, reason, json!({})),
+            ChainManagerError::Forbidden { reason } => (-4016, reason, json!({})),
+            ChainManagerError::ShuttingDown { reason } => (-4017, reason, json!({})),
+            ChainManagerError::InvalidChainConfig { reason, chain_id } => {
+                (-4018, reason, json!({ "chain_id": chain_id }))
             }
+            ChainManagerError::UpstreamRpcError { reason, chain_id, code, message } => {
+                (-4019, reason, json!({ "chain_id": chain_id, "code": code, "message": message }))
+            }
+            ChainManagerError::NotFound { reason, chain_id } => {
+                (-4020, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::Timeout { chain_id, method, timeout_ms } => (
+                -4021,
+                format!("{method} on chain {chain_id} did not answer within {timeout_ms}ms"),
+                json!({ "chain_id": chain_id, "method": method, "timeout_ms": timeout_ms }),
+            ),
+            ChainManagerError::UpstreamInconsistent { reason, chain_id } => {
+                (-4022, reason, json!({ "chain_id": chain_id }))
+            }
+            ChainManagerError::QueueTimeout { chain_id, timeout_ms } => (
+                -4023,
+                format!(
+                    "Chain {chain_id} has no free upstream request permit after {timeout_ms}ms"
+                ),
+                json!({ "chain_id": chain_id, "timeout_ms": timeout_ms }),
+            ),
+            ChainManagerError::TooManyChains { reason } => (-4024, reason, json!({})),
+            ChainManagerError::ChainIdMismatch { expected, actual } => (
+                -4025,
+                format!("An RPC URL of chain {expected} serves chain {actual}"),
+                json!({ "chain_id": expected, "expected": expected, "actual": actual }),
+            ),
+            ChainManagerError::InvalidParams { reason, chain_id } => {
+                (INVALID_PARAMS_CODE, reason, json!({ "chain_id": chain_id }))
+            }
+        };
+        if let Some(request_id) = request_id() {
+            data["request_id"] = request_id.into();
         }
+        let data = (data != json!({})).then_some(data);
+        ErrorObjectOwned::owned(code, reason, data)
     }
 }
 
 impl ChainManagerImpl {
+    /// The provider of the RPC URL `chain_id` currently uses.
+    #[tracing::instrument(level = "debug", skip(self))]
     pub async fn get_provider(
         &self,
         chain_id: u64,
     ) -> Result<Arc<dyn Provider>, ChainManagerError> {
-        if let Some(provider) = self.providers.get(&chain_id) {
+        let config = self.known_chain(chain_id)?;
+        self.provider_at(&config, self.active_url(chain_id)).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, config), fields(chain_id = config.chain_id))]
+    async fn provider_at(
+        &self,
+        config: &ChainConfig,
+        index: usize,
+    ) -> Result<Arc<dyn Provider>, ChainManagerError> {
+        let chain_id = config.chain_id;
+        if let Some(provider) = self.providers.get(&(chain_id, index)) {
+            self.metrics.provider_cache(chain_id, true);
             return Ok(provider.clone())
         }
-        let chain_config =
-            self.configs.iter().find(|config| config.chain_id == chain_id).ok_or_else(|| {
-                ChainManagerError::ChainIdNotFound {
-                    reason: "Chain id not configured".into(),
-                    chain_id,
-                }
-            })?;
+        self.metrics.provider_cache(chain_id, false);
 
-        let url = chain_config.rpc_url.as_str();
-        let provider = ProviderBuilder::new().connect(url).await.map_err(|error| {
-            ChainManagerError::GenericFailure {
-                reason: format!("Something went wrong while initialising provider {error:?}")
-                    .into(),
-                chain_id,
+        let provider = connect(config, config.urls()[index]).await?;
+        if !config.skip_chain_id_check {
+            let actual = within_timeout(config, "eth_chainId", provider.get_chain_id())
+                .await?
+                .map_err(upstream_error(chain_id, "getting the chain id"))?;
+            if actual != chain_id {
+                tracing::error!(url_index = index, actual, "The RPC URL serves another chain");
+                return Err(ChainManagerError::ChainIdMismatch { expected: chain_id, actual })
             }
-        })?;
-        let provider = Arc::new(provider);
-        self.providers.insert(chain_id, provider.clone());
+        }
+        // A call that outlived its chain does not leave a provider behind for whatever replaces it.
+        let configs = self.configs.read().expect("configs lock");
+        if configs.iter().any(|current| std::ptr::eq(&**current, config)) {
+            self.providers.insert((chain_id, index), provider.clone());
+            self.metrics.set_providers(self.providers.len());
+        }
         Ok(provider)
     }
-}
-
-#[async_trait]
-impl ChainManagerServer for ChainManagerImpl {
-    async fn finalised_header(&self, chain_id: u64, at: BlockNumberOrTag) -> RpcResult<Header> {
-        let provider = self.get_provider(chain_id).await.map_err(|error| error);
-
-        let header = provider.unwrap().get_block_by_number(at).full().await.map_err(|error| {
-            ChainManagerError::GenericFailure {
-                reason: format!("Something went wrong while getting finalised header {error:?}")
-                    .into(),
-                chain_id,
-            }
-        });
 
-        Ok(header.unwrap().unwrap().header.into())
-    }
-    async fn transaction_receipt(
+    /// Look a header of `chain_id` up in its cache, counting the hit or miss.
+    fn cached_header(
         &self,
         chain_id: u64,
-        tx_hash: B256,
-    ) -> RpcResult<Option<TransactionReceipt>> {
-        let provider = self.get_provider(chain_id).await.map_err(|error| error);
+        lookup: impl FnOnce(&mut HeaderCache) -> Option<Header>,
+    ) -> Option<Header> {
+        let header = lookup(&mut *self.header_caches.get_mut(&chain_id)?);
+        self.metrics.header_cache(chain_id, header.is_some());
+        header
+    }
 
-        let receipt = provider.unwrap().get_transaction_receipt(tx_hash).await.map_err(|error| {
-            ChainManagerError::GenericFailure {
-                reason: format!("Something went wrong while getting transaction receipt {error:?}")
-                    .into(),
-                chain_id,
+    fn cache_header(&self, chain_id: u64, header: &Header) {
+        if let Some(mut cache) = self.header_caches.get_mut(&chain_id) {
+            cache.insert(header.clone());
+        }
+    }
+
+    /// The verified header of the block `at` names on the chain of `config`.
+    async fn header_at(
+        &self,
+        config: &ChainConfig,
+        at: BlockNumberOrTag,
+    ) -> Result<Header, ChainManagerError> {
+        let chain_id = config.chain_id;
+        // Tags name a different block over time, they are cached once resolved to a number.
+        if let BlockNumberOrTag::Number(number) = at {
+            if let Some(header) = self.cached_header(chain_id, |cache| cache.by_number(number)) {
+                return Ok(header)
             }
-        });
+        }
 
-        Ok(receipt.unwrap())
-    }
-}
+        let block = self
+            .upstream_call(
+                config,
+                "eth_getBlockByNumber",
+                |provider| async move { provider.get_block_by_number(at).full().await },
+                upstream_error(chain_id, "getting finalised header"),
+            )
+            .await?;
+        let block = block.ok_or_else(|| ChainManagerError::BlockNotFound {
+            reason: format!("No block {at} on this chain"),
+            chain_id,
+        })?;
 
-impl ChainManagerImpl {
-    fn new(configs: Vec<ChainConfig>) -> Self {
-        Self { configs, providers: Default::default() }
+        let header = verified_header(chain_id, block.header, at.into())?;
+        self.cache_header(chain_id, &header);
+        Ok(header)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::{
-        api::{ChainManagerServer, Header},
-        ChainConfig, ChainManagerClient, ChainManagerImpl,
-    };
-    use alloy::{
-        network::TransactionBuilder,
-        node_bindings::{Anvil, AnvilInstance},
-        primitives::U256,
-        providers::{Provider, ProviderBuilder},
-        rpc::types::{eth::TransactionRequest, BlockNumberOrTag},
-    };
-    use jsonrpsee::{http_client::HttpClientBuilder, rpc_params, server::ServerBuilder};
-    use jsonrpsee_core::client::ClientT;
-    use serial_test::serial;
-    use std::net::SocketAddr;
-
-    fn create_anvil_instances(count: u16, base_port: u16) -> Vec<AnvilInstance> {
-        let mut instances = Vec::new();
-        for i in 0..count {
-            let port = base_port + i;
-            let instance = Anvil::new()
-                .port(port)
-                .chain_id((i + 1).into())
-                .try_spawn()
-                .expect(&format!("Failed to spawn anvil instance on port {}", port));
-            instances.push(instance);
-        }
-        instances
-    }
-
-    fn create_configs(anvils: &[AnvilInstance]) -> Vec<ChainConfig> {
-        anvils
-            .iter()
-            .map(|anvil| ChainConfig { rpc_url: anvil.endpoint(), chain_id: anvil.chain_id() })
-            .collect()
-    }
-
-    async fn create_start_server(
-        manager: impl ChainManagerServer,
-        address: &str,
-    ) -> Result<
-        (jsonrpsee::server::ServerHandle, jsonrpsee::http_client::HttpClient),
-        Box<dyn std::error::Error>,
-    > {
-        let server_addr: SocketAddr = address.parse()?;
-        let server = ServerBuilder::default().build(server_addr).await?;
-        let handle = server.start(manager.into_rpc());
-        let client = HttpClientBuilder::default().build(format!("http://{}", address))?;
-        Ok((handle, client))
-    }
-
-    #[tokio::test]
-    #[serial]
-    async fn test_basic_header_retrieval() -> Result<(), Box<dyn std::error::Error>> {
-        let anvils = create_anvil_instances(1, 8545);
-        let configs = create_configs(&anvils);
-        let manager = ChainManagerImpl::new(configs);
-        let (handle, client) = create_start_server(manager, "127.0.0.1:3000").await?;
+    /// The verified header of the block `block_hash` on the chain of `config`.
+    async fn header_with_hash(
+        &self,
+        config: &ChainConfig,
+        block_hash: B256,
+    ) -> Result<Header, ChainManagerError> {
+        let chain_id = config.chain_id;
+        if let Some(header) = self.cached_header(chain_id, |cache| cache.by_hash(block_hash)) {
+            return Ok(header)
+        }
 
-        let chain_id = anvils[0].chain_id();
-        let header: Header = client
-            .request("finalisedHeader", rpc_params!(chain_id, BlockNumberOrTag::Latest))
+        let block = self
+            .upstream_call(
+                config,
+                "eth_getBlockByHash",
+                |provider| async move { provider.get_block_by_hash(block_hash).await },
+                upstream_error(chain_id, "getting header by hash"),
+            )
             .await?;
+        let block = block.ok_or_else(|| ChainManagerError::BlockNotFound {
+            reason: format!("No block with hash {block_hash} on this chain"),
+            chain_id,
+        })?;
 
-        assert_eq!(header.number, 0, "Should start at genesis");
-
-        handle.stop()?;
-        handle.stopped().await;
-        Ok(())
+        let header = verified_header(chain_id, block.header, block_hash.into())?;
+        self.cache_header(chain_id, &header);
+        Ok(header)
     }
 
-    #[tokio::test]
-    #[serial]
-    async fn test_provider_caching() -> Result<(), Box<dyn std::error::Error>> {
-        let anvils = create_anvil_instances(1, 8545);
-        let configs = create_configs(&anvils);
-        let manager = ChainManagerImpl::new(configs);
-        let (handle, client) = create_start_server(manager, "127.0.0.1:3000").await?;
+    /// The config of `chain_id`, unless the chain is disabled.
+    fn chain_config(&self, chain_id: u64) -> Option<Arc<ChainConfig>> {
+        let configs = self.configs.read().expect("configs lock");
+        configs.iter().find(|config| config.chain_id == chain_id && !config.disabled).cloned()
+    }
 
-        let chain_id = anvils[0].chain_id();
+    fn known_chain(&self, chain_id: u64) -> Result<Arc<ChainConfig>, ChainManagerError> {
+        self.chain_config(chain_id).ok_or_else(|| match self.configured(chain_id) {
+            true => {
+                ChainManagerError::ChainIdNotFound { reason: "Chain id disabled".into(), chain_id }
+            }
+            false => Self::not_configured(chain_id),
+        })
+    }
 
-        for _ in 0..5 {
-            let header: Header = client
-                .request("finalisedHeader", rpc_params!(chain_id, BlockNumberOrTag::Latest))
-                .await?;
-            assert_eq!(header.number, 0);
+    /// The index of the RPC URL `chain_id` uses, back at the primary once
+    /// [`PRIMARY_REPROBE_INTERVAL`] has passed since failing over from it.
+    fn active_url(&self, chain_id: u64) -> usize {
+        let mut failover = self.failover.entry(chain_id).or_default();
+        if failover.failed_over_at.is_some_and(|at| at.elapsed() >= PRIMARY_REPROBE_INTERVAL) {
+            *failover = Failover::default();
         }
-
-        handle.stop()?;
-        handle.stopped().await;
-        Ok(())
+        failover.active
     }
 
-    #[tokio::test]
-    #[serial]
-    async fn test_multi_chain_routing() -> Result<(), Box<dyn std::error::Error>> {
-        let anvils = create_anvil_instances(2, 8545);
-        let configs = create_configs(&anvils);
-        let manager = ChainManagerImpl::new(configs);
-        let (handle, client) = create_start_server(manager, "127.0.0.1:3000").await?;
+    /// Count a transient failure of URL `index`, moving on to the next URL once the active one
+    /// has failed `failover_after` times in a row.
+    fn record_failure(&self, config: &ChainConfig, index: usize) {
+        let mut failover = self.failover.entry(config.chain_id).or_default();
+        if failover.active != index {
+            return
+        }
+        failover.consecutive_failures += 1;
+        let urls = config.urls().len();
+        if failover.consecutive_failures >= config.failover_after() && urls > 1 {
+            let active = (index + 1) % urls;
+            *failover =
+                Failover { active, consecutive_failures: 0, failed_over_at: Some(Instant::now()) };
+        }
+    }
 
-        let header_1: Header =
-            client.request("finalisedHeader", rpc_params!(1u64, BlockNumberOrTag::Latest)).await?;
+    fn record_answer(&self, chain_id: u64, index: usize) {
+        let mut failover = self.failover.entry(chain_id).or_default();
+        if failover.active == index {
+            failover.consecutive_failures = 0;
+        }
+        drop(failover);
+        self.provider_health
+            .insert((chain_id, index), ProviderHealth { consecutive_failures: 0, answered: true });
+    }
 
-        let header_2: Header =
-            client.request("finalisedHeader", rpc_params!(2u64, BlockNumberOrTag::Latest)).await?;
+    /// Count a failed call through the cached provider of URL `index`, dropping it so the next
+    /// call connects again once it has failed `reconnect_after` times in a row, or right away if
+    /// it never answered since connecting.
+    fn record_provider_failure(&self, config: &ChainConfig, index: usize) {
+        let key = (config.chain_id, index);
+        let mut health = self.provider_health.entry(key).or_default();
+        health.consecutive_failures += 1;
+        let failures = health.consecutive_failures;
+        if health.answered && failures < config.reconnect_after() {
+            return
+        }
+        drop(health);
+        self.provider_health.remove(&key);
+        if self.providers.remove(&key).is_some() {
+            self.metrics.provider_evicted(config.chain_id);
+            self.metrics.set_providers(self.providers.len());
+            tracing::warn!(
+                chain_id = config.chain_id,
+                url_index = index,
+                failures,
+                "Dropping the provider, the next call connects again"
+            );
+        }
+    }
 
-        assert_eq!(header_1.number, 0);
-        assert_eq!(header_2.number, 0);
+    /// A permit for one upstream call of `config`, once fewer than its
+    /// `max_concurrent_requests` are in flight. `None` for chains without a limit.
+    async fn permit(
+        &self,
+        config: &ChainConfig,
+    ) -> Result<Option<OwnedSemaphorePermit>, ChainManagerError> {
+        let Some(limit) = config.max_concurrent_requests else { return Ok(None) };
+        let chain_id = config.chain_id;
+        let semaphore = self
+            .permits
+            .entry(chain_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(limit.max(1))))
+            .clone();
+        let started = Instant::now();
+        let acquired = match config.queue_timeout_ms {
+            Some(timeout_ms) => {
+                let timeout = Duration::from_millis(timeout_ms);
+                tokio::time::timeout(timeout, semaphore.acquire_owned())
+                    .await
+                    .map_err(|_| ChainManagerError::QueueTimeout { chain_id, timeout_ms })
+            }
+            None => Ok(semaphore.acquire_owned().await),
+        };
+        self.metrics.observe_permit_wait(chain_id, started.elapsed());
+        Ok(Some(acquired?.expect("Permits are never closed")))
+    }
 
-        handle.stop()?;
-        handle.stopped().await;
-        Ok(())
+    /// Run `call`, a request for the upstream `method`, against the active RPC URL of the chain of
+    /// `config` and, while it fails transiently or times out, each URL after it, retrying every
+    /// one per the chain's [`RetryPolicy`]. Handlers look `config` up once, so a call that began
+    /// before its chain was removed still finishes. Errors a node answers with go through
+    /// `on_error`, usually [`upstream_error`]; if no URL answers at all the call fails with
+    /// `NodeFailure`, or `Timeout` if the last URL tried did not answer in time.
+    #[tracing::instrument(
+        level = "debug",
+        skip(self, config, call, on_error),
+        fields(chain_id = config.chain_id)
+    )]
+    async fn upstream_call<T, F, Fut>(
+        &self,
+        config: &ChainConfig,
+        method: &'static str,
+        call: F,
+        on_error: impl FnOnce(TransportError) -> ChainManagerError,
+    ) -> Result<T, ChainManagerError>
+    where
+        F: Fn(Arc<dyn Provider>) -> Fut,
+        Fut: Future<Output = TransportResult<T>>,
+    {
+        let chain_id = config.chain_id;
+        let urls = config.urls().len();
+        let start = self.active_url(chain_id);
+        let mut last_error = String::new();
+        let mut timed_out = None;
+        let mut mismatched = None;
+        for index in (0..urls).map(|offset| (start + offset) % urls) {
+            // Connecting, and checking the chain id, count against the limit too. The permit is
+            // held through the first attempt, and taken again for each retry.
+            let mut permit = Some(self.permit(config).await?);
+            let provider = match self.provider_at(config, index).await {
+                Ok(provider) => provider,
+                Err(error) => {
+                    self.metrics.upstream_error(chain_id, "connect");
+                    self.record_failure(config, index);
+                    last_error = format!("{error:?}");
+                    match error {
+                        ChainManagerError::ChainIdMismatch { .. } => mismatched = Some(error),
+                        error => {
+                            timed_out =
+                                matches!(error, ChainManagerError::Timeout { .. }).then_some(error)
+                        }
+                    }
+                    continue
+                }
+            };
+            let retried = with_retry(&config.retry, || {
+                let (held, provider, call, config) =
+                    (permit.take(), provider.clone(), &call, config);
+                async move {
+                    let _permit = match held {
+                        Some(permit) => permit,
+                        None => self.permit(config).await?,
+                    };
+                    // Every attempt gets the whole timeout. One running out is retried like an
+                    // upstream that could not be reached, the error is unwrapped again below.
+                    Ok(within_timeout(config, method, call(provider))
+                        .await
+                        .unwrap_or_else(|timeout| Err(TransportErrorKind::custom(timeout))))
+                }
+            });
+            let result = match retried.await? {
+                Err(RpcError::Transport(TransportErrorKind::Custom(error)))
+                    if error.is::<ChainManagerError>() =>
+                {
+                    let error = *error.downcast::<ChainManagerError>().expect("Checked above");
+                    self.metrics.upstream_error(chain_id, "timeout");
+                    self.record_failure(config, index);
+                    self.record_provider_failure(config, index);
+                    last_error = format!("{error:?}");
+                    timed_out = Some(error);
+                    continue
+                }
+                result => result,
+            };
+            timed_out = None;
+            if let Err(error) = &result {
+                self.metrics.upstream_error(chain_id, error_class(error));
+            }
+            match result {
+                Err(error) if is_transient(&error) => {
+                    self.record_failure(config, index);
+                    self.record_provider_failure(config, index);
+                    last_error = format!("{error:?}");
+                }
+                result => {
+                    self.record_answer(chain_id, index);
+                    return result.map_err(on_error)
+                }
+            }
+        }
+        // A misconfigured URL needs fixing more than a slow one needs waiting for.
+        if let Some(error) = mismatched.or(timed_out) {
+            return Err(error)
+        }
+        Err(ChainManagerError::NodeFailure {
+            reason: format!(
+                "None of the {urls} RPC URLs answered, the last error was {last_error}"
+            ),
+            chain_id,
+        })
     }
 
-    #[tokio::test]
-    #[serial]
-    async fn test_transaction_receipt() -> Result<(), Box<dyn std::error::Error>> {
-        let anvils = create_anvil_instances(1, 8545);
-        let configs = create_configs(&anvils);
-        let manager = ChainManagerImpl::new(configs);
-        let (handle, client) = create_start_server(manager, "127.0.0.1:3000").await?;
+    /// The receipt of `tx_hash`, from the cache if it was confirmed deep enough before.
+    async fn receipt(
+        &self,
+        config: &ChainConfig,
+        tx_hash: B256,
+    ) -> Result<Option<TransactionReceipt>, ChainManagerError> {
+        let chain_id = config.chain_id;
+        if let Some(mut cache) = self.receipt_caches.get_mut(&chain_id) {
+            let receipt = cache.get(tx_hash);
+            self.metrics.receipt_cache(chain_id, receipt.is_some());
+            if receipt.is_some() {
+                return Ok(receipt)
+            }
+        }
 
-        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
-        let provider =
-            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let receipt = self
+            .upstream_call(
+                config,
+                "eth_getTransactionReceipt",
+                |provider| async move { provider.get_transaction_receipt(tx_hash).await },
+                upstream_error(chain_id, "getting transaction receipt"),
+            )
+            .await?;
 
-        let tx = TransactionRequest::default()
-            .with_from(signer.address())
-            .with_to(anvils[0].addresses()[1])
-            .with_value(U256::from(1000));
+        // Receipts still missing or near the head can change, those are never cached.
+        let Some(receipt) = receipt else { return Ok(None) };
+        let Some(block) = receipt.block_number else { return Ok(Some(receipt)) };
+        let confirmations = config.receipt_cache.confirmations_for_cache;
+        let latest = self
+            .upstream_call(
+                config,
+                "eth_blockNumber",
+                |provider| async move { provider.get_block_number().await },
+                upstream_error(chain_id, "getting the latest block"),
+            )
+            .await;
+        // The receipt is good without the head, it just is not cached.
+        if latest.is_ok_and(|latest| latest.saturating_sub(block) >= confirmations) {
+            if let Some(mut cache) = self.receipt_caches.get_mut(&chain_id) {
+                cache.insert(receipt.clone());
+            }
+        }
+        Ok(Some(receipt))
+    }
 
-        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
-        let tx_hash = receipt.transaction_hash;
+    /// Probe the node of `config` for its latest block, giving up after
+    /// [`HEALTH_PROBE_TIMEOUT`].
+    async fn chain_status(&self, config: &ChainConfig) -> ChainStatus {
+        let chain_id = config.chain_id;
+        let name = (!config.name.is_empty()).then(|| config.name.clone());
+        if config.disabled {
+            return ChainStatus {
+                chain_id,
+                name,
+                connected: false,
+                active_url: 0,
+                latest_block: None,
+                last_error: None,
+                disabled: true,
+            }
+        }
+        let probe = self.upstream_call(
+            config,
+            "eth_blockNumber",
+            |provider| async move { provider.get_block_number().await },
+            upstream_error(chain_id, "getting the latest block"),
+        );
+        let latest_block =
+            tokio::time::timeout(HEALTH_PROBE_TIMEOUT, probe).await.unwrap_or_else(|_| {
+                Err(ChainManagerError::NodeFailure {
+                    reason: format!("No answer within {HEALTH_PROBE_TIMEOUT:?}"),
+                    chain_id,
+                })
+            });
+        let active_url = self.active_url(chain_id);
+        ChainStatus {
+            chain_id,
+            name,
+            connected: self.providers.contains_key(&(chain_id, active_url)),
+            active_url,
+            latest_block: latest_block.as_ref().ok().copied(),
+            last_error: latest_block
+                .err()
+                .map(|error| ErrorObjectOwned::from(error).message().to_string()),
+            disabled: false,
+        }
+    }
 
-        let manager_receipt = client
-            .transaction_receipt(anvils[0].chain_id(), tx_hash)
+    /// The widest log range `config` serves.
+    fn max_log_range(&self, config: &ChainConfig) -> u64 {
+        config.max_log_range.unwrap_or(self.max_log_range).max(1)
+    }
+
+    /// The first and last block `filter` spans, `None` for a filter on one block hash. Tags
+    /// other than `earliest` are resolved to the latest block, as the node would for an
+    /// open-ended range.
+    async fn log_range(
+        &self,
+        config: &ChainConfig,
+        filter: &Filter,
+    ) -> Result<Option<(u64, u64)>, ChainManagerError> {
+        let FilterBlockOption::Range { from_block, to_block } = filter.block_option else {
+            return Ok(None)
+        };
+        let number = |bound: Option<BlockNumberOrTag>| match bound {
+            Some(BlockNumberOrTag::Number(number)) => Some(number),
+            Some(BlockNumberOrTag::Earliest) => Some(0),
+            _ => None,
+        };
+        let latest = if number(from_block).is_none() || number(to_block).is_none() {
+            self.upstream_call(
+                config,
+                "eth_blockNumber",
+                |provider| async move { provider.get_block_number().await },
+                upstream_error(config.chain_id, "getting the latest block"),
+            )
             .await?
-            .expect("Receipt should exist");
+        } else {
+            0
+        };
+        Ok(Some((number(from_block).unwrap_or(latest), number(to_block).unwrap_or(latest))))
+    }
 
-        assert_eq!(manager_receipt.transaction_hash, tx_hash);
-        assert!(manager_receipt.status());
+    async fn fetch_logs(
+        &self,
+        config: &ChainConfig,
+        filter: &Filter,
+    ) -> Result<Vec<Log>, ChainManagerError> {
+        self.upstream_call(
+            config,
+            "eth_getLogs",
+            |provider| async move { provider.get_logs(filter).await },
+            upstream_error(config.chain_id, "getting logs"),
+        )
+        .await
+    }
+}
 
-        handle.stop()?;
-        handle.stopped().await;
-        Ok(())
+#[async_trait]
+impl ChainManagerServer for ChainManagerImpl {
+    async fn finalised_header(&self, chain_id: u64, at: BlockNumberOrTag) -> RpcResult<Header> {
+        let config = self.known_chain(chain_id)?;
+        if !config.allows(at) {
+            let reason = match at {
+                BlockNumberOrTag::Pending => "Pending blocks are never served".into(),
+                at => format!("`{at}` is not in the `allowed_tags` of chain {chain_id}"),
+            };
+            return Err(ChainManagerError::InvalidParams { reason, chain_id }.into())
+        }
+        if at != BlockNumberOrTag::Finalized {
+            return Ok(self.header_at(&config, at).await?)
+        }
+        let header = match config.finality {
+            FinalityMode::Tag => self.header_at(&config, at).await?,
+            FinalityMode::Confirmations(confirmations) => {
+                let latest = self
+                    .upstream_call(
+                        &config,
+                        "eth_blockNumber",
+                        |provider| async move { provider.get_block_number().await },
+                        upstream_error(chain_id, "getting the latest block number"),
+                    )
+                    .await?;
+                let number = latest.saturating_sub(confirmations);
+                tracing::debug!(latest, confirmations, number, "Resolved finalized by depth");
+                self.header_at(&config, BlockNumberOrTag::Number(number)).await?
+            }
+        };
+        self.metrics.finalised_block(chain_id, config.finality.label(), header.number);
+        Ok(header)
+    }
+    async fn finalised_headers(
+        &self,
+        chain_ids: Vec<u64>,
+        at: BlockNumberOrTag,
+    ) -> RpcResult<BTreeMap<u64, HeaderOrError>> {
+        if chain_ids.len() > MAX_CHAINS_PER_REQUEST {
+            return Err(ChainManagerError::TooManyChains {
+                reason: format!(
+                    "{} chains requested, at most {MAX_CHAINS_PER_REQUEST} are served at once",
+                    chain_ids.len()
+                ),
+            }
+            .into())
+        }
+        let headers = join_all(chain_ids.into_iter().map(|chain_id| async move {
+            let header = match self.finalised_header(chain_id, at).await {
+                Ok(header) => HeaderOrError::Header(Box::new(header)),
+                Err(error) => HeaderOrError::Error(error),
+            };
+            (chain_id, header)
+        }))
+        .await;
+        Ok(headers.into_iter().collect())
+    }
+    async fn header_by_hash(&self, chain_id: u64, block_hash: B256) -> RpcResult<Header> {
+        Ok(self.header_with_hash(&*self.known_chain(chain_id)?, block_hash).await?)
+    }
+    async fn headers_range(&self, chain_id: u64, start: u64, end: u64) -> RpcResult<Vec<Header>> {
+        let config = self.known_chain(chain_id)?;
+        if end < start {
+            return Err(ChainManagerError::InvalidRange {
+                reason: format!("Range {start}..={end} ends before it starts"),
+                chain_id,
+            }
+            .into())
+        }
+        if end - start >= self.max_header_range {
+            return Err(ChainManagerError::BatchTooLarge {
+                reason: format!(
+                    "{} headers requested, at most {} are served at once",
+                    u128::from(end - start) + 1,
+                    self.max_header_range
+                ),
+                chain_id,
+            }
+            .into())
+        }
+
+        // `buffered` keeps the order of the numbers.
+        let headers = stream::iter(start..=end)
+            .map(|number| self.header_at(&config, BlockNumberOrTag::Number(number)))
+            .buffered(HEADER_RANGE_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(headers)
+    }
+    async fn transaction_receipt(
+        &self,
+        chain_id: u64,
+        tx_hash: B256,
+    ) -> RpcResult<Option<TransactionReceipt>> {
+        Ok(self.receipt(&*self.known_chain(chain_id)?, tx_hash).await?)
+    }
+    async fn transaction_by_hash(
+        &self,
+        chain_id: u64,
+        tx_hash: B256,
+    ) -> RpcResult<Option<Transaction>> {
+        let transaction = self
+            .upstream_call(
+                &*self.known_chain(chain_id)?,
+                "eth_getTransactionByHash",
+                |provider| async move { provider.get_transaction_by_hash(tx_hash).await },
+                upstream_error(chain_id, "getting transaction"),
+            )
+            .await?;
+
+        Ok(transaction)
+    }
+    async fn transaction_receipts(
+        &self,
+        chain_id: u64,
+        tx_hashes: Vec<B256>,
+    ) -> RpcResult<Vec<Option<TransactionReceipt>>> {
+        let config = self.known_chain(chain_id)?;
+        if tx_hashes.len() > self.max_receipt_batch {
+            return Err(ChainManagerError::BatchTooLarge {
+                reason: format!(
+                    "{} receipts requested, at most {} are served at once",
+                    tx_hashes.len(),
+                    self.max_receipt_batch
+                ),
+                chain_id,
+            }
+            .into())
+        }
+
+        // `buffered` keeps the order of the hashes.
+        let receipts = stream::iter(tx_hashes)
+            .map(|tx_hash| self.receipt(&config, tx_hash))
+            .buffered(RECEIPT_BATCH_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(receipts)
+    }
+    async fn receipt_proof(&self, chain_id: u64, tx_hash: B256) -> RpcResult<ReceiptProof> {
+        let config = self.known_chain(chain_id)?;
+        let receipt = self.receipt(&config, tx_hash).await?.ok_or_else(|| {
+            ChainManagerError::TransactionNotFound {
+                reason: format!("No receipt for {tx_hash} on this chain"),
+                chain_id,
+            }
+        })?;
+        let (Some(block_hash), Some(index)) = (receipt.block_hash, receipt.transaction_index)
+        else {
+            return Err(ChainManagerError::TransactionNotFound {
+                reason: format!("{tx_hash} is not in a block yet"),
+                chain_id,
+            }
+            .into())
+        };
+        let header = self.header_with_hash(&config, block_hash).await?;
+
+        let receipts = self
+            .upstream_call(
+                &config,
+                "eth_getBlockReceipts",
+                |provider| async move { provider.get_block_receipts(block_hash.into()).await },
+                upstream_error(chain_id, "getting the block receipts"),
+            )
+            .await?
+            .ok_or_else(|| ChainManagerError::BlockNotFound {
+                reason: format!("No receipts for block {block_hash} on this chain"),
+                chain_id,
+            })?;
+        let receipts: Vec<_> =
+            receipts.into_iter().map(|receipt| receipt.inner.into_primitives_receipt()).collect();
+        let index = index as usize;
+        let Some(target) = receipts.get(index) else {
+            return Err(ChainManagerError::NodeFailure {
+                reason: format!("Block {block_hash} has no receipt at index {index}"),
+                chain_id,
+            }
+            .into())
+        };
+
+        let (receipts_root, proof) = receipts_trie_proof(&receipts, index);
+        if receipts_root != header.receipts_root {
+            return Err(ChainManagerError::NodeFailure {
+                reason: format!(
+                    "The receipts of block {block_hash} hash to {receipts_root}, not its \
+                     receiptsRoot {}",
+                    header.receipts_root
+                ),
+                chain_id,
+            }
+            .into())
+        }
+        Ok(ReceiptProof {
+            receipt: target.encoded_2718().into(),
+            key: trie_key(index),
+            receipts_root,
+            proof,
+            header,
+        })
+    }
+    async fn logs(&self, chain_id: u64, filter: Filter) -> RpcResult<Vec<Log>> {
+        let config = self.known_chain(chain_id)?;
+        let max_log_range = self.max_log_range(&config);
+        if let Some((from, to)) = self.log_range(&config, &filter).await? {
+            if to.saturating_sub(from) >= max_log_range {
+                let next_to = from.saturating_add(max_log_range - 1);
+                return Err(ChainManagerError::LogRangeTooLarge {
+                    reason: format!(
+                        "Blocks {from} to {to} span more than {max_log_range} blocks, ask for \
+                         {from} to {next_to} first or page through them with logsPaged"
+                    ),
+                    chain_id,
+                    from_block: from,
+                    to_block: next_to,
+                }
+                .into())
+            }
+        }
+        Ok(self.fetch_logs(&config, &filter).await?)
+    }
+    async fn logs_paged(
+        &self,
+        chain_id: u64,
+        filter: Filter,
+        page_size: Option<u64>,
+    ) -> RpcResult<LogsPage> {
+        let config = self.known_chain(chain_id)?;
+        let Some((from, to)) = self.log_range(&config, &filter).await? else {
+            let logs = self.fetch_logs(&config, &filter).await?;
+            return Ok(LogsPage { logs, next_from_block: None })
+        };
+        let page_size = page_size
+            .unwrap_or_else(|| config.log_page_size())
+            .clamp(1, self.max_log_range(&config));
+        let page_to = to.min(from.saturating_add(page_size - 1));
+        let logs = self.fetch_logs(&config, &filter.from_block(from).to_block(page_to)).await?;
+        Ok(LogsPage { logs, next_from_block: (page_to < to).then(|| page_to + 1) })
+    }
+    async fn get_proof(
+        &self,
+        chain_id: u64,
+        address: Address,
+        storage_keys: Vec<B256>,
+        at: BlockNumberOrTag,
+    ) -> RpcResult<EIP1186AccountProofResponse> {
+        let storage_keys = &storage_keys;
+        let proof = self
+            .upstream_call(
+                &*self.known_chain(chain_id)?,
+                "eth_getProof",
+                |provider| async move {
+                    provider.get_proof(address, storage_keys.clone()).block_id(at.into()).await
+                },
+                upstream_error(chain_id, "getting the proof"),
+            )
+            .await?;
+
+        Ok(proof)
+    }
+    async fn account_state(
+        &self,
+        chain_id: u64,
+        address: Address,
+        at: BlockNumberOrTag,
+    ) -> RpcResult<AccountState> {
+        let config = self.known_chain(chain_id)?;
+        // A tag can move on between the three calls, so it is resolved to one block first.
+        let block = match at {
+            BlockNumberOrTag::Number(_) => at.into(),
+            tag => BlockId::hash(self.header_at(&config, tag).await?.hash_slow()),
+        };
+        let balance = self
+            .upstream_call(
+                &config,
+                "eth_getBalance",
+                |provider| async move { provider.get_balance(address).block_id(block).await },
+                upstream_error(chain_id, "getting the balance"),
+            )
+            .await?;
+        let nonce = self
+            .upstream_call(
+                &config,
+                "eth_getTransactionCount",
+                |provider| async move {
+                    provider.get_transaction_count(address).block_id(block).await
+                },
+                upstream_error(chain_id, "getting the nonce"),
+            )
+            .await?;
+        let code = self
+            .upstream_call(
+                &config,
+                "eth_getCode",
+                |provider| async move { provider.get_code_at(address).block_id(block).await },
+                upstream_error(chain_id, "getting the code"),
+            )
+            .await?;
+
+        Ok(AccountState { balance, nonce, code_hash: keccak256(&code), has_code: !code.is_empty() })
+    }
+    async fn subscribe_new_heads(
+        &self,
+        pending: PendingSubscriptionSink,
+        chain_id: u64,
+    ) -> SubscriptionResult {
+        let provider = match self.get_provider(chain_id).await {
+            Ok(provider) => provider,
+            Err(error) => {
+                pending.reject(error).await;
+                return Ok(())
+            }
+        };
+        forward_new_heads(pending.accept().await?, provider).await
+    }
+    async fn subscribe_finalised(
+        &self,
+        pending: PendingSubscriptionSink,
+        chain_id: u64,
+    ) -> SubscriptionResult {
+        let config = match self.known_chain(chain_id) {
+            Ok(config) => config,
+            Err(error) => {
+                pending.reject(error).await;
+                return Ok(())
+            }
+        };
+        let provider = match self.provider_at(&config, self.active_url(chain_id)).await {
+            Ok(provider) => provider,
+            Err(error) => {
+                pending.reject(error).await;
+                return Ok(())
+            }
+        };
+        let finality = match config.finality {
+            FinalityMode::Tag => {
+                // A node that cannot be reached is asked again, not taken to lack the tag.
+                let Ok(detected) = with_retry(&config.retry, || async {
+                    Ok::<_, Infallible>(
+                        Finality::detect(provider.as_ref(), config.confirmations()).await,
+                    )
+                })
+                .await;
+                match detected {
+                    Ok(finality) => finality,
+                    Err(error) => {
+                        pending.reject(upstream_error(chain_id, "detecting finality")(error)).await;
+                        return Ok(())
+                    }
+                }
+            }
+            FinalityMode::Confirmations(confirmations) => {
+                Finality::ConfirmationDepth { confirmations }
+            }
+        };
+        let sink = pending.accept().await?;
+        forward_finalised(sink, provider, finality, self.finalised_poll_interval).await
+    }
+    async fn list_chains(&self) -> RpcResult<Vec<ChainStatus>> {
+        let configs = self.configs.read().expect("configs lock").clone();
+        Ok(join_all(configs.iter().map(|config| self.chain_status(config))).await)
+    }
+    async fn admin_add_chain(&self, config: ChainConfig, verify: Option<bool>) -> RpcResult<()> {
+        Ok(self.add_chain(config, verify.unwrap_or(true)).await?)
+    }
+    async fn admin_remove_chain(&self, chain_id: u64) -> RpcResult<()> {
+        Ok(self.remove_chain(chain_id)?)
+    }
+    async fn admin_disable_chain(&self, chain_id: u64) -> RpcResult<()> {
+        Ok(self.set_chain_disabled(chain_id, true)?)
+    }
+    async fn admin_enable_chain(&self, chain_id: u64) -> RpcResult<()> {
+        Ok(self.set_chain_disabled(chain_id, false)?)
+    }
+    async fn call(
+        &self,
+        chain_id: u64,
+        tx: TransactionRequest,
+        at: BlockNumberOrTag,
+    ) -> RpcResult<Bytes> {
+        let tx = &tx;
+        let output = self
+            .upstream_call(
+                &*self.known_chain(chain_id)?,
+                "eth_call",
+                |provider| async move { provider.call(tx.clone()).block(at.into()).await },
+                |error| match error.as_error_resp().and_then(|response| response.as_revert_data()) {
+                    Some(data) => ChainManagerError::CallReverted {
+                        reason: format!("Call reverted {error}"),
+                        chain_id,
+                        data,
+                    },
+                    None => ChainManagerError::from_upstream(error, chain_id, "calling"),
+                },
+            )
+            .await?;
+
+        Ok(output)
+    }
+}
+
+impl ChainManagerImpl {
+    pub fn new(configs: Vec<ChainConfig>) -> Self {
+        Self {
+            metrics: Arc::new(Metrics::new(configs.iter().map(|config| config.chain_id))),
+            header_caches: Arc::new(
+                configs
+                    .iter()
+                    .filter(|config| !config.disabled)
+                    .map(|config| (config.chain_id, HeaderCache::new(&config.header_cache)))
+                    .collect(),
+            ),
+            receipt_caches: Arc::new(
+                configs
+                    .iter()
+                    .filter(|config| !config.disabled)
+                    .map(|config| (config.chain_id, ReceiptCache::new(&config.receipt_cache)))
+                    .collect(),
+            ),
+            configs: RwLock::new(configs.into_iter().map(Arc::new).collect()),
+            providers: Default::default(),
+            failover: Default::default(),
+            provider_health: Default::default(),
+            permits: Default::default(),
+            max_log_range: DEFAULT_MAX_LOG_RANGE,
+            max_receipt_batch: DEFAULT_MAX_RECEIPT_BATCH,
+            max_header_range: DEFAULT_MAX_HEADER_RANGE,
+            in_flight: Default::default(),
+            finalised_poll_interval: DEFAULT_FINALISED_POLL_INTERVAL,
+        }
+    }
+
+    /// Poll for `subscribeFinalised` every `interval`.
+    pub fn with_finalised_poll_interval(mut self, interval: Duration) -> Self {
+        self.finalised_poll_interval = interval;
+        self
+    }
+
+    /// The counters of this manager, shared with the server it is started on.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Serve `config` from now on. With `verify` its first RPC URL is connected right away and
+    /// has to report `config.chain_id`, the provider is then ready for the first call.
+    pub async fn add_chain(
+        &self,
+        config: ChainConfig,
+        verify: bool,
+    ) -> Result<(), ChainManagerError> {
+        let chain_id = config.chain_id;
+        let invalid = |reason: String| ChainManagerError::InvalidChainConfig { reason, chain_id };
+        let duplicate = || invalid("Chain id already configured".into());
+        config.validate().map_err(|(key, reason)| invalid(format!("`{key}` {reason}")))?;
+        if self.configured(chain_id) {
+            return Err(duplicate())
+        }
+
+        let provider = match verify {
+            true => {
+                let url = config.urls()[0];
+                let provider = connect(&config, url).await?;
+                let reported = within_timeout(&config, "eth_chainId", provider.get_chain_id())
+                    .await?
+                    .map_err(upstream_error(chain_id, "getting the chain id"))?;
+                if reported != chain_id && !config.skip_chain_id_check {
+                    return Err(invalid(format!("{url} serves chain id {reported}")))
+                }
+                Some(provider)
+            }
+            false => None,
+        };
+
+        // Everything the chain needs is in place before other calls can see it.
+        let mut configs = self.configs.write().expect("configs lock");
+        if configs.iter().any(|configured| configured.chain_id == chain_id) {
+            return Err(duplicate())
+        }
+        self.header_caches.insert(chain_id, HeaderCache::new(&config.header_cache));
+        self.receipt_caches.insert(chain_id, ReceiptCache::new(&config.receipt_cache));
+        self.drop_providers(chain_id);
+        if let Some(provider) = provider {
+            self.providers.insert((chain_id, 0), provider);
+            self.metrics.set_providers(self.providers.len());
+        }
+        self.metrics.add_chain(chain_id);
+        configs.push(Arc::new(config));
+        Ok(())
+    }
+
+    /// Stop serving `chain_id` and forget its config. Calls already in flight keep the config
+    /// and provider they started with and finish.
+    pub fn remove_chain(&self, chain_id: u64) -> Result<(), ChainManagerError> {
+        let mut configs = self.configs.write().expect("configs lock");
+        let Some(index) = configs.iter().position(|config| config.chain_id == chain_id) else {
+            return Err(Self::not_configured(chain_id))
+        };
+        configs.remove(index);
+        self.drop_caches(chain_id);
+        self.drop_providers(chain_id);
+        Ok(())
+    }
+
+    /// Take `chain_id` out of rotation or put it back, keeping its config either way.
+    pub fn set_chain_disabled(
+        &self,
+        chain_id: u64,
+        disabled: bool,
+    ) -> Result<(), ChainManagerError> {
+        let mut configs = self.configs.write().expect("configs lock");
+        let Some(config) = configs.iter_mut().find(|config| config.chain_id == chain_id) else {
+            return Err(Self::not_configured(chain_id))
+        };
+        *config = Arc::new(ChainConfig { disabled, ..ChainConfig::clone(config) });
+        // The caches answer before the chain is looked up, so a disabled chain has none.
+        match disabled {
+            true => self.drop_caches(chain_id),
+            false => {
+                self.header_caches.insert(chain_id, HeaderCache::new(&config.header_cache));
+                self.receipt_caches.insert(chain_id, ReceiptCache::new(&config.receipt_cache));
+            }
+        }
+        self.drop_providers(chain_id);
+        Ok(())
+    }
+
+    /// Whether `chain_id` is configured, disabled or not.
+    fn configured(&self, chain_id: u64) -> bool {
+        self.configs.read().expect("configs lock").iter().any(|config| config.chain_id == chain_id)
+    }
+
+    fn not_configured(chain_id: u64) -> ChainManagerError {
+        ChainManagerError::ChainIdNotFound { reason: "Chain id not configured".into(), chain_id }
+    }
+
+    fn drop_caches(&self, chain_id: u64) {
+        self.header_caches.remove(&chain_id);
+        self.receipt_caches.remove(&chain_id);
+    }
+
+    /// Forget the providers, failover state and permits of `chain_id`, so it starts over on its
+    /// primary with the limits of its current config.
+    fn drop_providers(&self, chain_id: u64) {
+        self.providers.retain(|&(provider_chain, _), _| provider_chain != chain_id);
+        self.failover.remove(&chain_id);
+        self.provider_health.retain(|&(provider_chain, _), _| provider_chain != chain_id);
+        self.permits.remove(&chain_id);
+        self.metrics.set_providers(self.providers.len());
+    }
+
+    /// The handle shutting this manager down, shared with the server it is started on.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            in_flight: self.in_flight.clone(),
+            providers: self.providers.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    /// Stop taking calls, wait up to `grace` for those in flight and drop the providers. Returns
+    /// whether every call finished in time.
+    pub async fn shutdown(&self, grace: Duration) -> bool {
+        self.shutdown_handle().shutdown(grace).await
+    }
+
+    /// Serve `logs` requests spanning at most `max_log_range` blocks.
+    pub fn with_max_log_range(mut self, max_log_range: u64) -> Self {
+        self.max_log_range = max_log_range;
+        self
+    }
+
+    /// Serve `transactionReceipts` requests for at most `max_receipt_batch` hashes.
+    pub fn with_max_receipt_batch(mut self, max_receipt_batch: usize) -> Self {
+        self.max_receipt_batch = max_receipt_batch;
+        self
+    }
+
+    /// Serve `headersRange` requests for at most `max_header_range` headers.
+    pub fn with_max_header_range(mut self, max_header_range: u64) -> Self {
+        self.max_header_range = max_header_range;
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        api::{
+            AccountState, ChainManagerServer, Header, HeaderOrError, DEFAULT_MAX_HEADER_RANGE,
+            DEFAULT_MAX_LOG_RANGE, MAX_CHAINS_PER_REQUEST,
+        },
+        start_server,
+        test_utils::{
+            block_json, create_anvil_instances, create_configs, create_start_server,
+            flaky_upstream, mock_upstream, start_test_server, tracked_upstream,
+        },
+        AuthConfig, ChainConfig, ChainManagerClient, ChainManagerError, ChainManagerImpl, Finality,
+        FinalityMode, HeaderCacheConfig, Metrics, RetryPolicy, ServerConfig, UnfinalisedTag,
+    };
+    use alloy::{
+        consensus::{Transaction as _, TxType},
+        eips::Encodable2718,
+        network::TransactionBuilder,
+        node_bindings::{Anvil, AnvilInstance},
+        primitives::{Address, Bytes, B256, KECCAK256_EMPTY, U256},
+        providers::{ext::AnvilApi, Provider, ProviderBuilder},
+        rpc::types::{eth::TransactionRequest, BlockNumberOrTag, Filter, Transaction},
+    };
+    use jsonrpsee::{
+        http_client::HttpClientBuilder, rpc_params, types::error::INVALID_PARAMS_CODE,
+        ws_client::WsClientBuilder,
+    };
+    use jsonrpsee_core::client::{ClientT, Error as ClientError};
+    use std::{
+        sync::atomic::Ordering,
+        time::{Duration, Instant},
+    };
+
+    #[tokio::test]
+    async fn test_basic_header_retrieval() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let chain_id = anvils[0].chain_id();
+        let header: Header = client
+            .request("finalisedHeader", rpc_params!(chain_id, BlockNumberOrTag::Finalized))
+            .await?;
+
+        assert_eq!(header.number, 0, "Should start at genesis");
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_finalised_headers_of_many_chains() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(2);
+        let manager = ChainManagerImpl::new(create_configs(&anvils));
+        let (handle, client) = create_start_server(manager).await?;
+
+        let headers =
+            client.finalised_headers(vec![1, 2, 999], BlockNumberOrTag::Finalized).await?;
+        assert_eq!(headers.len(), 3);
+        for chain_id in [1, 2] {
+            let HeaderOrError::Header(header) = &headers[&chain_id] else {
+                panic!("Expected a header of chain {chain_id}")
+            };
+            assert_eq!(header.number, 0);
+        }
+        let HeaderOrError::Error(error) = &headers[&999] else {
+            panic!("Expected the unknown chain to fail")
+        };
+        assert_eq!(error.code(), -4004);
+
+        let Err(ClientError::Call(error)) = client
+            .finalised_headers(vec![1; MAX_CHAINS_PER_REQUEST + 1], BlockNumberOrTag::Finalized)
+            .await
+        else {
+            panic!("Expected too many chains to fail")
+        };
+        assert_eq!(error.code(), -4024);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_headers_over_every_transport() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let ipc_path = dir.path().join("anvil.ipc");
+        let anvil = Anvil::new()
+            .chain_id(1)
+            .ipc_path(ipc_path.to_string_lossy())
+            .try_spawn()
+            .expect("Failed to spawn anvil");
+
+        for rpc_url in [anvil.endpoint(), anvil.ws_endpoint(), anvil.ipc_path().to_string()] {
+            let configs = vec![ChainConfig { chain_id: 1, rpc_url, ..Default::default() }];
+            let manager = ChainManagerImpl::new(configs);
+            let (handle, client) = create_start_server(manager).await?;
+
+            let header = client.finalised_header(1, BlockNumberOrTag::Finalized).await?;
+            assert_eq!(header.number, 0, "Should start at genesis");
+            assert_eq!(client.header_by_hash(1, header.hash_slow()).await?, header);
+
+            handle.stop()?;
+            handle.stopped().await;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_failures_per_transport() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let missing_socket = dir.path().join("missing.ipc").to_string_lossy().into_owned();
+        let configs = [(1, "ws://127.0.0.1:1".to_string()), (2, missing_socket)]
+            .map(|(chain_id, rpc_url)| ChainConfig {
+                chain_id,
+                rpc_url,
+                retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+                ..Default::default()
+            })
+            .to_vec();
+        let manager = ChainManagerImpl::new(Vec::new());
+        for config in &configs {
+            let error = manager.add_chain(config.clone(), true).await.unwrap_err();
+            assert!(matches!(error, ChainManagerError::ProviderFailure { .. }), "{error:?}");
+        }
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        // Calls report why the only URL did not connect.
+        for (chain_id, transport) in [(1, "over a websocket"), (2, "over IPC")] {
+            let Err(ClientError::Call(error)) =
+                client.finalised_header(chain_id, BlockNumberOrTag::Finalized).await
+            else {
+                panic!("Expected chain {chain_id} not to connect")
+            };
+            assert_eq!(error.code(), -4005);
+            assert!(error.message().contains(transport), "{}", error.message());
+        }
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inconsistent_headers_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let genuine = block_json(7);
+        let mut forged = genuine.clone();
+        forged["hash"] = serde_json::json!(B256::repeat_byte(1));
+        let (genuine_url, _) = flaky_upstream(0, genuine).await;
+        let (forged_url, _) = flaky_upstream(0, forged).await;
+        let configs = [(1, genuine_url), (2, forged_url)]
+            .map(|(chain_id, rpc_url)| ChainConfig {
+                chain_id,
+                rpc_url,
+                skip_chain_id_check: true,
+                ..Default::default()
+            })
+            .to_vec();
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let header = client.finalised_header(1, BlockNumberOrTag::Number(7)).await?;
+        assert_eq!(header, Header { number: 7, ..Default::default() });
+        let code = |result: Result<Header, ClientError>| match result {
+            Err(ClientError::Call(error)) => error.code(),
+            result => panic!("Expected a call error, got {result:?}"),
+        };
+        // The node answers block 7 whatever is asked for.
+        assert_eq!(code(client.finalised_header(1, BlockNumberOrTag::Number(8)).await), -4022);
+        assert_eq!(code(client.header_by_hash(1, B256::repeat_byte(2)).await), -4022);
+        assert_eq!(code(client.finalised_header(2, BlockNumberOrTag::Finalized).await), -4022);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_provider_caching() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let chain_id = anvils[0].chain_id();
+
+        for _ in 0..5 {
+            let header: Header = client
+                .request("finalisedHeader", rpc_params!(chain_id, BlockNumberOrTag::Finalized))
+                .await?;
+            assert_eq!(header.number, 0);
+        }
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multi_chain_routing() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(2);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let header_1: Header = client
+            .request("finalisedHeader", rpc_params!(1u64, BlockNumberOrTag::Finalized))
+            .await?;
+
+        let header_2: Header = client
+            .request("finalisedHeader", rpc_params!(2u64, BlockNumberOrTag::Finalized))
+            .await?;
+
+        assert_eq!(header_1.number, 0);
+        assert_eq!(header_2.number, 0);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_receipt() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+
+        let tx = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(anvils[0].addresses()[1])
+            .with_value(U256::from(1000));
+
+        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+        let tx_hash = receipt.transaction_hash;
+
+        let manager_receipt = client
+            .transaction_receipt(anvils[0].chain_id(), tx_hash)
+            .await?
+            .expect("Receipt should exist");
+
+        assert_eq!(manager_receipt.transaction_hash, tx_hash);
+        assert!(manager_receipt.status());
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receipt_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let metrics = manager.metrics();
+        let (handle, client) = create_start_server(manager).await?;
+        let hits = |metrics: &Metrics| {
+            metrics.render().lines().find_map(|line| {
+                line.strip_prefix(r#"chain_manager_receipt_cache_hits_total{chain_id="1"} "#)
+                    .map(|hits| hits.to_string())
+            })
+        };
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let tx = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(anvils[0].addresses()[1])
+            .with_value(U256::from(1000));
+        let tx_hash = provider.send_transaction(tx).await?.get_receipt().await?.transaction_hash;
+
+        // Neither a fresh receipt nor a missing one is cached.
+        for _ in 0..2 {
+            assert!(client.transaction_receipt(1, tx_hash).await?.is_some());
+            assert_eq!(client.transaction_receipt(1, B256::ZERO).await?, None);
+        }
+        assert_eq!(hits(&metrics), None);
+
+        provider.anvil_mine(Some(12), None).await?;
+        let first = client.transaction_receipt(1, tx_hash).await?;
+        let second = client.transaction_receipt(1, tx_hash).await?;
+        assert_eq!(first, second);
+        assert_eq!(hits(&metrics).as_deref(), Some("1"));
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_by_hash() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let tx = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(anvils[0].addresses()[1])
+            .with_value(U256::from(1000));
+        let tx_hash = provider.send_transaction(tx).await?.get_receipt().await?.transaction_hash;
+
+        let transaction = client
+            .transaction_by_hash(anvils[0].chain_id(), tx_hash)
+            .await?
+            .expect("Transaction should exist");
+        assert_eq!(*transaction.inner.tx_hash(), tx_hash);
+        assert_eq!(transaction.to(), Some(anvils[0].addresses()[1]));
+        assert_eq!(transaction.value(), U256::from(1000));
+        assert_eq!(client.transaction_by_hash(anvils[0].chain_id(), B256::ZERO).await?, None);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_receipts() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let mut tx_hashes = Vec::new();
+        for value in 1..=3 {
+            let tx = TransactionRequest::default()
+                .with_from(signer.address())
+                .with_to(anvils[0].addresses()[1])
+                .with_value(U256::from(value));
+            tx_hashes
+                .push(provider.send_transaction(tx).await?.get_receipt().await?.transaction_hash);
+        }
+        let unknown = B256::repeat_byte(0xab);
+        tx_hashes.insert(1, unknown);
+
+        let receipts = client.transaction_receipts(1, tx_hashes.clone()).await?;
+        assert_eq!(receipts.len(), 4);
+        assert!(receipts[1].is_none());
+        for (tx_hash, receipt) in
+            tx_hashes.iter().zip(&receipts).filter(|(hash, _)| **hash != unknown)
+        {
+            assert_eq!(receipt.as_ref().expect("Receipt should exist").transaction_hash, *tx_hash);
+        }
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receipt_batch_too_large() -> Result<(), Box<dyn std::error::Error>> {
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: "http://127.0.0.1:1".into(),
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs).with_max_receipt_batch(2);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let Err(ClientError::Call(error)) =
+            client.transaction_receipts(1, vec![B256::ZERO; 3]).await
+        else {
+            panic!("Expected a call error for a batch over the limit")
+        };
+        assert_eq!(error.code(), -4012);
+        let Err(ClientError::Call(error)) = client.transaction_receipts(2, Vec::new()).await else {
+            panic!("Expected a call error for an unknown chain")
+        };
+        assert_eq!(error.code(), -4004);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_headers_range() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+        let chain_id = anvils[0].chain_id();
+
+        let provider = ProviderBuilder::new().connect_http(anvils[0].endpoint_url());
+        provider.anvil_mine(Some(20), None).await?;
+
+        let headers = client.headers_range(chain_id, 5, 15).await?;
+        assert_eq!(headers.len(), 11);
+        for (number, header) in (5..).zip(&headers) {
+            assert_eq!(header.number, number);
+        }
+        for pair in headers.windows(2) {
+            assert_eq!(pair[1].parent_hash, pair[0].hash_slow());
+        }
+
+        let Err(ClientError::Call(error)) = client.headers_range(chain_id, 15, 25).await else {
+            panic!("Expected a call error for a range past the latest block")
+        };
+        assert_eq!(error.code(), -4008);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_headers_range_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: "http://127.0.0.1:1".into(),
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let Err(ClientError::Call(error)) = client.headers_range(1, 10, 9).await else {
+            panic!("Expected a call error for a range ending before it starts")
+        };
+        assert_eq!(error.code(), -4014);
+        let Err(ClientError::Call(error)) =
+            client.headers_range(1, 0, DEFAULT_MAX_HEADER_RANGE).await
+        else {
+            panic!("Expected a call error for a range over the limit")
+        };
+        assert_eq!(error.code(), -4012);
+        let Err(ClientError::Call(error)) = client.headers_range(1, 0, u64::MAX).await else {
+            panic!("Expected a call error for the widest range")
+        };
+        assert_eq!(error.code(), -4012);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receipt_proof() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        // Both kinds of transaction in one block, so the trie has a legacy and a typed receipt.
+        provider.anvil_set_auto_mine(false).await?;
+        let transfer = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(anvils[0].addresses()[1])
+            .with_value(U256::from(1000));
+        let legacy = provider
+            .send_transaction(transfer.clone().with_gas_price(2_000_000_000).with_nonce(0))
+            .await?;
+        let eip1559 = provider.send_transaction(transfer.with_nonce(1)).await?;
+        provider.anvil_mine(Some(1), None).await?;
+        let legacy = legacy.get_receipt().await?;
+        let eip1559 = eip1559.get_receipt().await?;
+        assert_eq!(legacy.block_hash, eip1559.block_hash);
+        assert_eq!(legacy.inner.tx_type(), TxType::Legacy);
+        assert_eq!(eip1559.inner.tx_type(), TxType::Eip1559);
+
+        for receipt in [legacy, eip1559] {
+            let proof = client.receipt_proof(1, receipt.transaction_hash).await?;
+            assert_eq!(proof.receipts_root, proof.header.receipts_root);
+            assert_eq!(Some(proof.header.hash_slow()), receipt.block_hash);
+            assert_eq!(
+                proof.receipt,
+                Bytes::from(receipt.inner.into_primitives_receipt().encoded_2718())
+            );
+            assert_eq!(proof.verify(), Ok(()));
+        }
+
+        let Err(ClientError::Call(error)) = client.receipt_proof(1, B256::repeat_byte(0xab)).await
+        else {
+            panic!("Expected a call error for an unknown transaction")
+        };
+        assert_eq!(error.code(), -4013);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_chain_error() -> Result<(), Box<dyn std::error::Error>> {
+        let manager = ChainManagerImpl::new(Vec::new());
+        let (handle, client) = create_start_server(manager).await?;
+
+        let result: Result<Header, _> = client
+            .request("finalisedHeader", rpc_params!(9999u64, BlockNumberOrTag::Finalized))
+            .await;
+        let Err(ClientError::Call(error)) = result else {
+            panic!("Expected a call error, got {result:?}")
+        };
+        assert_eq!(error.code(), -4004);
+
+        let Err(ClientError::Call(error)) = client.transaction_receipt(9999, B256::ZERO).await
+        else {
+            panic!("Expected a call error for the receipt")
+        };
+        assert_eq!(error.code(), -4004);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_block_error() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let chain_id = anvils[0].chain_id();
+        let result: Result<Header, _> = client
+            .request("finalisedHeader", rpc_params!(chain_id, BlockNumberOrTag::Number(1000)))
+            .await;
+        let Err(ClientError::Call(error)) = result else {
+            panic!("Expected a call error, got {result:?}")
+        };
+        assert_eq!(error.code(), -4008);
+
+        // The worker survived and still serves requests.
+        let header: Header = client
+            .request("finalisedHeader", rpc_params!(chain_id, BlockNumberOrTag::Finalized))
+            .await?;
+        assert_eq!(header.number, 0);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_finality_by_confirmations() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(2);
+        let mut configs = create_configs(&anvils);
+        configs[0].finality = FinalityMode::Confirmations(5);
+        configs[1].finality = FinalityMode::Confirmations(50);
+        let manager = ChainManagerImpl::new(configs);
+        let metrics = manager.metrics();
+        let (handle, client) = create_start_server(manager).await?;
+        let finalised = |chain_id| client.finalised_header(chain_id, BlockNumberOrTag::Finalized);
+
+        // A head below the depth resolves to genesis.
+        assert_eq!(finalised(1).await?.number, 0);
+        for anvil in &anvils {
+            let provider = ProviderBuilder::new().connect_http(anvil.endpoint_url());
+            provider.anvil_mine(Some(20), None).await?;
+        }
+        assert_eq!(finalised(1).await?.number, 15);
+        assert_eq!(finalised(2).await?.number, 0);
+        let rendered = metrics.render();
+        let lines: Vec<_> = rendered.lines().collect();
+        assert!(lines
+            .contains(&r#"chain_manager_finalised_block{chain_id="1",mode="confirmations"} 15"#));
+        assert!(lines
+            .contains(&r#"chain_manager_finalised_block{chain_id="2",mode="confirmations"} 0"#));
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_tags() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(2);
+        let mut configs = create_configs(&anvils);
+        configs[1].allowed_tags = vec![UnfinalisedTag::Latest, UnfinalisedTag::Safe];
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+        let code = |result: Result<Header, ClientError>| match result {
+            Err(ClientError::Call(error)) => error.code(),
+            result => panic!("Expected a call error, got {result:?}"),
+        };
+
+        for chain_id in [1, 2] {
+            for at in [
+                BlockNumberOrTag::Finalized,
+                BlockNumberOrTag::Earliest,
+                BlockNumberOrTag::Number(0),
+            ] {
+                assert_eq!(client.finalised_header(chain_id, at).await?.number, 0, "{at}");
+            }
+            let pending = client.finalised_header(chain_id, BlockNumberOrTag::Pending).await;
+            assert_eq!(code(pending), INVALID_PARAMS_CODE);
+            let beyond_head = client.finalised_header(chain_id, BlockNumberOrTag::Number(1000));
+            assert_eq!(code(beyond_head.await), -4008);
+        }
+        for at in [BlockNumberOrTag::Latest, BlockNumberOrTag::Safe] {
+            assert_eq!(code(client.finalised_header(1, at).await), INVALID_PARAMS_CODE, "{at}");
+            assert_eq!(client.finalised_header(2, at).await?.number, 0, "{at}");
+        }
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_header_by_hash() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let tx = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(anvils[0].addresses()[1])
+            .with_value(U256::from(1000));
+        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+        let block_hash = receipt.block_hash.expect("Mined receipts have a block hash");
+
+        let chain_id = anvils[0].chain_id();
+        let header = client.header_by_hash(chain_id, block_hash).await?;
+        assert_eq!(header.hash_slow(), block_hash);
+        assert_eq!(Some(header.number), receipt.block_number);
+
+        let Err(ClientError::Call(error)) =
+            client.header_by_hash(chain_id, B256::repeat_byte(0xab)).await
+        else {
+            panic!("Expected a call error for an unknown hash")
+        };
+        assert_eq!(error.code(), -4008);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    /// Init code that emits one log with `topic` from its constructor and deploys nothing.
+    fn log_emitter(topic: B256) -> Bytes {
+        // PUSH32 topic, PUSH1 0 (size), PUSH1 0 (offset), LOG1, STOP
+        [&[0x7f][..], topic.as_slice(), &[0x60, 0x00, 0x60, 0x00, 0xa1, 0x00]].concat().into()
+    }
+
+    #[tokio::test]
+    async fn test_logs_by_address_and_topic() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let mut emitters = Vec::new();
+        for topic in [B256::repeat_byte(1), B256::repeat_byte(2)] {
+            let tx = TransactionRequest::default()
+                .with_from(signer.address())
+                .with_deploy_code(log_emitter(topic));
+            let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+            emitters.push((receipt.contract_address.expect("Deployment has an address"), topic));
+        }
+
+        let chain_id = anvils[0].chain_id();
+        let by_address = Filter::new().from_block(0).address(emitters[0].0);
+        let logs = client.logs(chain_id, by_address).await?;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].topic0(), Some(&emitters[0].1));
+
+        let by_topic = Filter::new().from_block(0).event_signature(emitters[1].1);
+        let logs = client.logs(chain_id, by_topic).await?;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].address(), emitters[1].0);
+
+        let too_wide = Filter::new().from_block(0).to_block(DEFAULT_MAX_LOG_RANGE);
+        let Err(ClientError::Call(error)) = client.logs(chain_id, too_wide).await else {
+            panic!("Expected a call error for a range over the limit")
+        };
+        assert_eq!(error.code(), -4009);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get())?;
+        assert_eq!(data["from_block"], 0);
+        assert_eq!(data["to_block"], DEFAULT_MAX_LOG_RANGE - 1);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
     }
 
     #[tokio::test]
-    #[serial]
-    async fn test_unknown_chain_error() -> Result<(), Box<dyn std::error::Error>> {
-        let anvils = create_anvil_instances(1, 8545);
+    async fn test_logs_paged() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let mut configs = create_configs(&anvils);
+        let permissive = ChainManagerImpl::new(configs.clone()).with_max_log_range(u64::MAX);
+        let (permissive_handle, permissive) = create_start_server(permissive).await?;
+        configs[0].max_log_range = Some(8);
+        configs[0].log_page_size = Some(5);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        // One log in each of 30 blocks.
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        for index in 0..30 {
+            let tx = TransactionRequest::default()
+                .with_from(signer.address())
+                .with_deploy_code(log_emitter(B256::with_last_byte(index)));
+            provider.send_transaction(tx).await?.get_receipt().await?;
+        }
+
+        let chain_id = anvils[0].chain_id();
+        let everything = Filter::new().from_block(0);
+        let Err(ClientError::Call(error)) = client.logs(chain_id, everything.clone()).await else {
+            panic!("Expected a call error for a range over the chain's limit")
+        };
+        assert_eq!(error.code(), -4009);
+
+        let mut paged = Vec::new();
+        let mut pages = 0;
+        let mut from_block = Some(0);
+        while let Some(from) = from_block {
+            let page =
+                client.logs_paged(chain_id, everything.clone().from_block(from), None).await?;
+            paged.extend(page.logs);
+            from_block = page.next_from_block;
+            pages += 1;
+        }
+        // Blocks 0 to 30 in pages of 5.
+        assert_eq!(pages, 7);
+        assert_eq!(paged.len(), 30);
+        assert_eq!(paged, permissive.logs(chain_id, everything.clone()).await?);
+
+        // Pages are never wider than the chain's log range.
+        let page = client.logs_paged(chain_id, everything, Some(100)).await?;
+        assert_eq!(page.next_from_block, Some(8));
+
+        handle.stop()?;
+        handle.stopped().await;
+        permissive_handle.stop()?;
+        permissive_handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_proof() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let funded = Address::repeat_byte(0x42);
+        let tx = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(funded)
+            .with_value(U256::from(1000));
+        provider.send_transaction(tx).await?.get_receipt().await?;
+
+        let proof = client
+            .get_proof(anvils[0].chain_id(), funded, vec![B256::ZERO], BlockNumberOrTag::Latest)
+            .await?;
+        assert_eq!(proof.address, funded);
+        assert_eq!(proof.balance, U256::from(1000));
+        assert!(!proof.account_proof.is_empty());
+        assert_eq!(proof.storage_proof.len(), 1);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_account_state() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let chain_id = anvils[0].chain_id();
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let state =
+            client.account_state(chain_id, signer.address(), BlockNumberOrTag::Latest).await?;
+        assert!(state.balance > U256::ZERO);
+        assert_eq!(state.nonce, 0);
+        assert_eq!(state.code_hash, KECCAK256_EMPTY);
+        assert!(!state.has_code);
+
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let tx = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(anvils[0].addresses()[1])
+            .with_value(U256::from(1000));
+        provider.send_transaction(tx).await?.get_receipt().await?;
+
+        let after =
+            client.account_state(chain_id, signer.address(), BlockNumberOrTag::Latest).await?;
+        assert_eq!(after.nonce, 1);
+        assert!(after.balance < state.balance);
+        let genesis =
+            client.account_state(chain_id, signer.address(), BlockNumberOrTag::Number(0)).await?;
+        assert_eq!(genesis, state);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_account_state_encoding() {
+        let state = AccountState {
+            balance: U256::from(1000),
+            nonce: 7,
+            code_hash: KECCAK256_EMPTY,
+            has_code: false,
+        };
+        let encoded = serde_json::to_value(&state).unwrap();
+        assert_eq!(encoded["balance"], "0x3e8");
+        assert_eq!(encoded["nonce"], "0x7");
+        assert_eq!(encoded["hasCode"], false);
+        assert_eq!(serde_json::from_value::<AccountState>(encoded).unwrap(), state);
+    }
+
+    /// Init code for a contract returning the word 7 when called without calldata and
+    /// reverting with the word 42 otherwise.
+    const REVERTER: &str = concat!(
+        "601a600c600039601a6000f3",
+        // CALLDATASIZE ISZERO PUSH1 15 JUMPI
+        "3615600f57",
+        // MSTORE(0, 42) REVERT(0, 32)
+        "602a60005260206000fd",
+        // JUMPDEST MSTORE(0, 7) RETURN(0, 32)
+        "5b600760005260206000f3",
+    );
+
+    #[tokio::test]
+    async fn test_call_and_revert() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(1);
+        let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let signer: alloy::signers::local::PrivateKeySigner = anvils[0].keys()[0].clone().into();
+        let provider =
+            ProviderBuilder::new().wallet(signer.clone()).connect_http(anvils[0].endpoint_url());
+        let deploy = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_deploy_code(REVERTER.parse::<Bytes>()?);
+        let receipt = provider.send_transaction(deploy).await?.get_receipt().await?;
+        let contract = receipt.contract_address.expect("Deployment has an address");
+
+        let chain_id = anvils[0].chain_id();
+        let call = TransactionRequest::default().with_to(contract);
+        let output = client.call(chain_id, call.clone(), BlockNumberOrTag::Latest).await?;
+        assert_eq!(output[..], B256::from(U256::from(7))[..]);
+
+        let reverting = call.with_input(Bytes::from_static(&[1]));
+        let Err(ClientError::Call(error)) =
+            client.call(chain_id, reverting, BlockNumberOrTag::Latest).await
+        else {
+            panic!("Expected a call error for a revert")
+        };
+        assert_eq!(error.code(), -4010);
+        let data: Bytes = serde_json::from_str(error.data().expect("Revert data").get())?;
+        assert_eq!(data[..], B256::from(U256::from(42))[..]);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_chain_at_runtime() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(2);
         let configs = create_configs(&anvils);
+        let manager = ChainManagerImpl::new(vec![configs[0].clone()]);
+        let (handle, client) = create_start_server(manager).await?;
+        let at = BlockNumberOrTag::Number(0);
+
+        let Err(ClientError::Call(error)) = client.finalised_header(2, at).await else {
+            panic!("Expected a call error before the chain is added")
+        };
+        assert_eq!(error.code(), -4004);
+
+        // The URL serves chain 2, not 3.
+        let mislabelled = ChainConfig { chain_id: 3, ..configs[1].clone() };
+        let Err(ClientError::Call(error)) = client.admin_add_chain(mislabelled, None).await else {
+            panic!("Expected a call error for a chain id the URL does not serve")
+        };
+        assert_eq!(error.code(), -4018);
+
+        client.admin_add_chain(configs[1].clone(), None).await?;
+        assert_eq!(client.finalised_header(2, at).await?.number, 0);
+        assert_eq!(client.list_chains().await?.len(), 2);
+
+        let Err(ClientError::Call(error)) = client.admin_add_chain(configs[1].clone(), None).await
+        else {
+            panic!("Expected a call error for a chain added twice")
+        };
+        assert_eq!(error.code(), -4018);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chain_id_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+        let anvil = create_anvil_instances(1).remove(0);
+        let mislabelled =
+            ChainConfig { chain_id: 99, rpc_url: anvil.endpoint(), ..Default::default() };
+        let unchecked =
+            ChainConfig { chain_id: 100, skip_chain_id_check: true, ..mislabelled.clone() };
+        let manager = ChainManagerImpl::new(vec![mislabelled, unchecked]);
+        let metrics = manager.metrics();
+        assert!(matches!(
+            manager.get_provider(99).await,
+            Err(ChainManagerError::ChainIdMismatch { expected: 99, actual: 1 })
+        ));
+        let (handle, client) = create_start_server(manager).await?;
+
+        let Err(ClientError::Call(error)) =
+            client.finalised_header(99, BlockNumberOrTag::Finalized).await
+        else {
+            panic!("Expected a chain served by the wrong node to fail")
+        };
+        assert_eq!(error.code(), -4025);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get())?;
+        assert_eq!(data["expected"], 99);
+        assert_eq!(data["actual"], 1);
+        assert_eq!(client.finalised_header(100, BlockNumberOrTag::Finalized).await?.number, 0);
+        // Only the provider of the chain that skips the check is kept.
+        assert!(metrics.render().lines().any(|line| line == "chain_manager_providers 1"));
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_chains() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils = create_anvil_instances(2);
+        let mut configs = create_configs(&anvils);
+        configs[0].name = "first".into();
+        configs.push(ChainConfig {
+            chain_id: 99,
+            rpc_url: "http://127.0.0.1:1".into(),
+            ..Default::default()
+        });
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let statuses = client.list_chains().await?;
+        assert_eq!(statuses.iter().map(|status| status.chain_id).collect::<Vec<_>>(), [1, 2, 99]);
+        assert_eq!(statuses[0].name.as_deref(), Some("first"));
+        assert_eq!(statuses[1].name, None);
+        for healthy in &statuses[..2] {
+            assert!(healthy.connected);
+            assert_eq!(healthy.active_url, 0);
+            assert_eq!(healthy.latest_block, Some(0));
+            assert_eq!(healthy.last_error, None);
+        }
+        assert_eq!(statuses[2].latest_block, None);
+        assert!(statuses[2].last_error.is_some());
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_new_heads() -> Result<(), Box<dyn std::error::Error>> {
+        let anvils: Vec<AnvilInstance> = (1..=2)
+            .map(|chain_id| {
+                Anvil::new()
+                    .chain_id(chain_id)
+                    .block_time(1)
+                    .try_spawn()
+                    .expect("Failed to spawn interval mining anvil")
+            })
+            .collect();
+        // One upstream pushes heads over WebSocket, the other is polled over HTTP.
+        let mut configs = create_configs(&anvils);
+        configs[0].rpc_url = anvils[0].ws_endpoint();
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, address) = start_test_server(manager).await?;
+        let client = WsClientBuilder::default().build(format!("ws://{address}")).await?;
+
+        for anvil in &anvils {
+            let mut heads = client.subscribe_new_heads(anvil.chain_id()).await?;
+            let first = heads.next().await.expect("A first head")?;
+            let second = heads.next().await.expect("A second head")?;
+            assert_eq!(second.number, first.number + 1);
+            assert_eq!(second.parent_hash, first.hash_slow());
+            heads.unsubscribe().await?;
+        }
+
+        let unknown = client.subscribe_new_heads(9999).await;
+        assert!(
+            matches!(unknown, Err(ClientError::Call(ref error)) if error.code() == -4004),
+            "{unknown:?}"
+        );
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_finalised() -> Result<(), Box<dyn std::error::Error>> {
+        // Every block finalises the one two epochs back.
+        let anvil = Anvil::new()
+            .chain_id(1)
+            .block_time(1)
+            .args(["--slots-in-an-epoch", "1"])
+            .try_spawn()
+            .expect("Failed to spawn interval mining anvil");
+        let configs = create_configs(std::slice::from_ref(&anvil));
+        let manager = ChainManagerImpl::new(configs)
+            .with_finalised_poll_interval(std::time::Duration::from_millis(200));
+        let (handle, address) = start_test_server(manager).await?;
+        let client = WsClientBuilder::default().build(format!("ws://{address}")).await?;
+
+        let mut finalised = client.subscribe_finalised(anvil.chain_id()).await?;
+        let first = finalised.next().await.expect("A first header")?;
+        assert_eq!(first.metadata, Some(Finality::FinalizedTag));
+        let second = finalised.next().await.expect("A second header")?;
+        assert_eq!(second.metadata, None);
+        assert!(second.header.number > first.header.number);
+        assert_ne!(second.header.hash_slow(), first.header.hash_slow());
+
+        // The fallback for upstreams without the tag.
+        let provider = ProviderBuilder::new().connect_http(anvil.endpoint_url());
+        let depth = Finality::ConfirmationDepth { confirmations: 2 };
+        let latest = provider.get_block_number().await?;
+        let header = depth.header(&provider).await?.expect("A block two below the latest");
+        assert!(header.number + 2 >= latest);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_calls_in_flight() -> Result<(), Box<dyn std::error::Error>> {
+        let (url, requests) =
+            mock_upstream(0, Duration::from_millis(500), Ok(serde_json::Value::Null)).await;
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            skip_chain_id_check: true,
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let shutdown = manager.shutdown_handle();
+        let metrics = manager.metrics();
+        let config = ServerConfig {
+            auth: AuthConfig { disabled: true, ..Default::default() },
+            ..Default::default()
+        };
+        let (handle, address) = start_server("127.0.0.1:0", manager, config).await?;
+        let client = HttpClientBuilder::default().build(format!("http://{address}"))?;
+
+        let slow = tokio::spawn({
+            let client = client.clone();
+            async move { client.transaction_by_hash(1, B256::ZERO).await }
+        });
+        while requests.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let draining = tokio::spawn(async move { shutdown.shutdown(Duration::from_secs(5)).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let Err(ClientError::Call(error)) = client.list_chains().await else {
+            panic!("Expected new calls to be rejected while shutting down")
+        };
+        assert_eq!(error.code(), -4017);
+        assert_eq!(slow.await??, None);
+        assert!(draining.await?, "The slow call should finish within the grace period");
+        assert!(metrics.render().lines().any(|line| line == "chain_manager_providers 0"));
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upstream_error_classes() -> Result<(), Box<dyn std::error::Error>> {
+        let (down, _) = flaky_upstream(usize::MAX, serde_json::Value::Null).await;
+        let (rejecting, _) =
+            mock_upstream(0, Duration::ZERO, Err((-32602, "invalid argument"))).await;
+        let (missing, _) =
+            mock_upstream(0, Duration::ZERO, Err((-32000, "header not found"))).await;
+        let retry = RetryPolicy { max_attempts: 1, ..Default::default() };
+        let configs = [down, rejecting, missing, "http://127.0.0.1:1".into()]
+            .into_iter()
+            .zip(1..)
+            .map(|(rpc_url, chain_id)| ChainConfig {
+                chain_id,
+                rpc_url,
+                retry: retry.clone(),
+                skip_chain_id_check: true,
+                ..Default::default()
+            })
+            .collect();
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+        let error = |result: Result<Option<Transaction>, ClientError>| match result {
+            Err(ClientError::Call(error)) => error,
+            other => panic!("Expected a call error, got {other:?}"),
+        };
+
+        // A 5xx and a refused connection both mean the node is down.
+        assert_eq!(error(client.transaction_by_hash(1, B256::ZERO).await).code(), -4005);
+        assert_eq!(error(client.transaction_by_hash(4, B256::ZERO).await).code(), -4005);
+
+        let rejected = error(client.transaction_by_hash(2, B256::ZERO).await);
+        assert_eq!(rejected.code(), -4019);
+        let data: serde_json::Value = serde_json::from_str(rejected.data().unwrap().get())?;
+        assert_eq!(
+            data,
+            serde_json::json!({ "chain_id": 2, "code": -32602, "message": "invalid argument" })
+        );
+
+        assert_eq!(error(client.transaction_by_hash(3, B256::ZERO).await).code(), -4020);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upstream_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let (slow, _) = mock_upstream(0, Duration::from_secs(5), Ok(serde_json::Value::Null)).await;
+        let config = ChainConfig {
+            chain_id: 1,
+            rpc_url: slow,
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            request_timeout_ms: Some(100),
+            skip_chain_id_check: true,
+            ..Default::default()
+        };
+        let manager = ChainManagerImpl::new(vec![config]);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let started = Instant::now();
+        let Err(ClientError::Call(error)) = client.transaction_by_hash(1, B256::ZERO).await else {
+            panic!("Expected the call to time out")
+        };
+        assert!(started.elapsed() < Duration::from_secs(1), "took {:?}", started.elapsed());
+        assert_eq!(error.code(), -4021);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get())?;
+        assert_eq!(
+            data,
+            serde_json::json!({
+                "chain_id": 1,
+                "method": "eth_getTransactionByHash",
+                "timeout_ms": 100,
+            })
+        );
+
+        // Each attempt gets the whole timeout, so a hung one is retried rather than using it up.
+        let (slow, requests) =
+            mock_upstream(0, Duration::from_secs(5), Ok(serde_json::Value::Null)).await;
+        let config = ChainConfig {
+            chain_id: 2,
+            rpc_url: slow,
+            retry: RetryPolicy { max_attempts: 3, base_delay_ms: 10, jitter: false },
+            request_timeout_ms: Some(100),
+            skip_chain_id_check: true,
+            ..Default::default()
+        };
+        let manager = ChainManagerImpl::new(vec![config]);
+        let (retried, client) = create_start_server(manager).await?;
+        let started = Instant::now();
+        let Err(ClientError::Call(error)) = client.transaction_by_hash(2, B256::ZERO).await else {
+            panic!("Expected every attempt to time out")
+        };
+        assert_eq!(error.code(), -4021);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(300), "took {:?}", started.elapsed());
+
+        for handle in [handle, retried] {
+            handle.stop()?;
+            handle.stopped().await;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_upstream_failures() -> Result<(), Box<dyn std::error::Error>> {
+        let (recovering_url, recovering) = flaky_upstream(2, serde_json::Value::Null).await;
+        let (failing_url, failing) = flaky_upstream(usize::MAX, serde_json::Value::Null).await;
+        let retry = RetryPolicy { max_attempts: 3, base_delay_ms: 10, jitter: true };
+        let configs = [(1, recovering_url), (2, failing_url)]
+            .map(|(chain_id, rpc_url)| ChainConfig {
+                chain_id,
+                rpc_url,
+                retry: retry.clone(),
+                skip_chain_id_check: true,
+                ..Default::default()
+            })
+            .to_vec();
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        assert_eq!(client.transaction_receipt(1, B256::ZERO).await?, None);
+        assert_eq!(recovering.load(Ordering::SeqCst), 3);
+
+        let Err(ClientError::Call(error)) = client.transaction_receipt(2, B256::ZERO).await else {
+            panic!("Expected a call error once the retries are used up")
+        };
+        assert_eq!(error.code(), -4005);
+        assert_eq!(failing.load(Ordering::SeqCst), 3);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failover_after_consecutive_failures() -> Result<(), Box<dyn std::error::Error>> {
+        let (primary_url, primary) = flaky_upstream(usize::MAX, serde_json::Value::Null).await;
+        let (fallback_url, fallback) = flaky_upstream(0, serde_json::Value::Null).await;
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: primary_url,
+            rpc_urls: vec![fallback_url],
+            failover_after: Some(2),
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            skip_chain_id_check: true,
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        for _ in 0..3 {
+            assert_eq!(client.transaction_receipt(1, B256::ZERO).await?, None);
+        }
+        // The primary is skipped once it has failed twice in a row.
+        assert_eq!(primary.load(Ordering::SeqCst), 2);
+        assert_eq!(fallback.load(Ordering::SeqCst), 3);
+        assert_eq!(client.list_chains().await?[0].active_url, 1);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_limited() -> Result<(), Box<dyn std::error::Error>> {
+        let (url, requests, peak) =
+            tracked_upstream(0, Duration::from_millis(50), Ok(block_json(0))).await;
+        let config = ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            max_concurrent_requests: Some(2),
+            header_cache: HeaderCacheConfig { ttl_ms: 0, ..Default::default() },
+            skip_chain_id_check: true,
+            ..Default::default()
+        };
+        let manager = ChainManagerImpl::new(vec![config.clone()]);
+        let calls = (0..50).map(|_| manager.finalised_header(1, BlockNumberOrTag::Number(0)));
+        for header in futures::future::join_all(calls).await {
+            assert_eq!(header?.number, 0);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 50);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        let rendered = manager.metrics().render();
+        assert!(rendered
+            .lines()
+            .any(|line| line == r#"chain_manager_permit_wait_seconds_count{chain_id="1"} 50"#));
+
+        // With one permit and a short queue, the second call gives up while the first is upstream.
+        let (url, _) = mock_upstream(0, Duration::from_millis(500), Ok(block_json(0))).await;
+        let config = ChainConfig {
+            rpc_url: url,
+            max_concurrent_requests: Some(1),
+            queue_timeout_ms: Some(50),
+            ..config
+        };
+        let manager = ChainManagerImpl::new(vec![config.clone()]);
+        let (first, second) = tokio::join!(
+            manager.finalised_header(1, BlockNumberOrTag::Number(0)),
+            manager.finalised_header(1, BlockNumberOrTag::Number(0)),
+        );
+        assert_eq!(first?.number, 0);
+        let error = second.unwrap_err();
+        assert_eq!(error.code(), -4023);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get())?;
+        assert_eq!(data, serde_json::json!({ "chain_id": 1, "timeout_ms": 50 }));
+
+        // A call backing off before its retry leaves the permit to others.
+        let (url, requests) = flaky_upstream(1, block_json(0)).await;
+        let config = ChainConfig {
+            rpc_url: url,
+            queue_timeout_ms: None,
+            retry: RetryPolicy { max_attempts: 2, base_delay_ms: 500, jitter: false },
+            ..config
+        };
+        let manager = ChainManagerImpl::new(vec![config]);
+        let started = Instant::now();
+        let (retried, second) =
+            tokio::join!(manager.finalised_header(1, BlockNumberOrTag::Number(0)), async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let header = manager.finalised_header(1, BlockNumberOrTag::Number(0)).await;
+                (header, started.elapsed())
+            },);
+        assert_eq!(retried?.number, 0);
+        let (second, elapsed) = second;
+        assert_eq!(second?.number, 0);
+        assert!(elapsed < Duration::from_millis(400), "{elapsed:?}");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_failing_providers() -> Result<(), Box<dyn std::error::Error>> {
+        let (url, requests) = flaky_upstream(2, serde_json::Value::Null).await;
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            reconnect_after: Some(2),
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            skip_chain_id_check: true,
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let metrics = manager.metrics();
+        let (handle, client) = create_start_server(manager).await?;
+
+        // A provider failing its first call is dropped right away, before reaching the threshold.
+        for _ in 0..2 {
+            let Err(ClientError::Call(error)) = client.transaction_receipt(1, B256::ZERO).await
+            else {
+                panic!("Expected the upstream to fail")
+            };
+            assert_eq!(error.code(), -4005);
+        }
+        assert_eq!(client.transaction_receipt(1, B256::ZERO).await?, None);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        let rendered = metrics.render();
+        let lines: Vec<_> = rendered.lines().collect();
+        assert!(lines.contains(&r#"chain_manager_provider_evictions_total{chain_id="1"} 2"#));
+        assert!(lines.contains(&r#"chain_manager_provider_cache_misses_total{chain_id="1"} 3"#));
+        assert!(lines.contains(&"chain_manager_providers 1"));
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recovers_once_the_node_restarts() -> Result<(), Box<dyn std::error::Error>> {
+        let spawn = |port: u16| {
+            Anvil::new().port(port).chain_id(1).try_spawn().expect("Failed to spawn anvil")
+        };
+        let anvil = spawn(0);
+        let port = anvil.port();
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: anvil.ws_endpoint(),
+            reconnect_after: Some(2),
+            request_timeout_ms: Some(2_000),
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let metrics = manager.metrics();
+        let (handle, client) = create_start_server(manager).await?;
+        assert_eq!(client.finalised_header(1, BlockNumberOrTag::Finalized).await?.number, 0);
+
+        drop(anvil);
+        for _ in 0..2 {
+            assert!(client.finalised_header(1, BlockNumberOrTag::Finalized).await.is_err());
+        }
+        assert!(metrics
+            .render()
+            .lines()
+            .any(|line| line == r#"chain_manager_provider_evictions_total{chain_id="1"} 1"#));
+
+        // Back on the port the provider was connected to.
+        let _anvil = spawn(port);
+        let header = client.finalised_header(1, BlockNumberOrTag::Finalized).await?;
+        assert_eq!(header.number, 0);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_live_rpc_url() -> Result<(), Box<dyn std::error::Error>> {
+        let anvil = create_anvil_instances(1).remove(0);
+        let configs = vec![ChainConfig {
+            chain_id: anvil.chain_id(),
+            rpc_urls: vec!["http://127.0.0.1:1".into(), anvil.endpoint()],
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let (handle, client) = create_start_server(manager).await?;
+
+        let header = client.finalised_header(anvil.chain_id(), BlockNumberOrTag::Finalized).await?;
+        assert_eq!(header.number, 0);
+
+        let chain_id = anvil.chain_id();
+        drop(anvil);
+        let Err(ClientError::Call(error)) =
+            client.finalised_header(chain_id, BlockNumberOrTag::Finalized).await
+        else {
+            panic!("Expected a call error once no RPC URL answers")
+        };
+        assert_eq!(error.code(), -4005);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_and_disable_chain() -> Result<(), Box<dyn std::error::Error>> {
+        let genesis = block_json(0);
+        let (url, requests) = flaky_upstream(0, genesis).await;
+        let config = ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            skip_chain_id_check: true,
+            ..Default::default()
+        };
+        let manager = ChainManagerImpl::new(vec![config.clone()]);
+        let (handle, client) = create_start_server(manager).await?;
+        let at = BlockNumberOrTag::Number(0);
+        let not_found = |result: Result<Header, ClientError>| match result {
+            Err(ClientError::Call(error)) => error.code() == -4004,
+            _ => false,
+        };
+
+        client.finalised_header(1, at).await?;
+        client.admin_remove_chain(1).await?;
+        assert!(not_found(client.finalised_header(1, at).await));
+        assert!(client.list_chains().await?.is_empty());
+        client.admin_add_chain(config, Some(false)).await?;
+        assert_eq!(client.finalised_header(1, at).await?.number, 0);
+        // Removing the chain dropped its cached headers.
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        client.admin_disable_chain(1).await?;
+        assert!(not_found(client.finalised_header(1, at).await));
+        assert!(client.list_chains().await?[0].disabled);
+        client.admin_enable_chain(1).await?;
+        client.finalised_header(1, at).await?;
+
+        let Err(ClientError::Call(error)) = client.admin_remove_chain(2).await else {
+            panic!("Expected a call error for removing an unknown chain")
+        };
+        assert_eq!(error.code(), -4004);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_removed_chain_finishes_calls_in_flight() -> Result<(), Box<dyn std::error::Error>>
+    {
+        // Read as a zero balance and nonce and one byte of code.
+        let answer = Ok(serde_json::json!("0x00"));
+        let (url, requests) = mock_upstream(0, Duration::from_millis(200), answer).await;
+        let config = ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            skip_chain_id_check: true,
+            ..Default::default()
+        };
+        let manager = ChainManagerImpl::new(vec![config]);
+        let (handle, client) = create_start_server(manager).await?;
+        let at = BlockNumberOrTag::Number(0);
+
+        let in_flight = tokio::spawn({
+            let client = client.clone();
+            async move { client.account_state(1, Address::ZERO, at).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        client.admin_remove_chain(1).await?;
+        let state = in_flight.await??;
+        assert_eq!((state.balance, state.nonce, state.has_code), (U256::ZERO, 0, true));
+        assert_eq!(requests.load(Ordering::SeqCst), 3, "every step reached the node");
+        let Err(ClientError::Call(error)) = client.account_state(1, Address::ZERO, at).await else {
+            panic!("Expected the removed chain to be unknown")
+        };
+        assert_eq!(error.code(), -4004);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_header_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let genesis = block_json(0);
+        let (url, requests) = flaky_upstream(0, genesis).await;
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            header_cache: HeaderCacheConfig { ttl_ms: 200, ..Default::default() },
+            skip_chain_id_check: true,
+            ..Default::default()
+        }];
         let manager = ChainManagerImpl::new(configs);
-        let (handle, client) = create_start_server(manager, "127.0.0.1:3000").await?;
+        let (handle, client) = create_start_server(manager).await?;
+
+        let first = client.finalised_header(1, BlockNumberOrTag::Number(0)).await?;
+        let second = client.finalised_header(1, BlockNumberOrTag::Number(0)).await?;
+        assert_eq!(first, second);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Tags always go upstream, but what they resolve to is cached.
+        client.finalised_header(1, BlockNumberOrTag::Finalized).await?;
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        client.header_by_hash(1, first.hash_slow()).await?;
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
 
-        let result: Result<Header, _> =
-            client.request("finalisedHeader", rpc_params!(9999u64, BlockNumberOrTag::Latest)).await;
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        client.finalised_header(1, BlockNumberOrTag::Number(0)).await?;
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
 
-        assert!(result.is_err());
         handle.stop()?;
         handle.stopped().await;
         Ok(())
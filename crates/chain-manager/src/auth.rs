@@ -0,0 +1,231 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+};
+
+use alloy::primitives::B256;
+use jsonrpsee::{
+    core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT},
+    server::{HttpRequest, MethodResponse},
+    types::{ErrorObjectOwned, Request},
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::ChainManagerError;
+
+/// An API key clients send as `Authorization: Bearer <key>`. Only its SHA-256 hash is
+/// configured, so the config file does not hold the key.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ApiKey {
+    pub sha256: B256,
+    /// Methods the key may call, all of them if `None`.
+    #[serde(default)]
+    pub methods: Option<HashSet<String>>,
+    /// Whether the key may call the `admin_` methods, which `methods` cannot grant on its own.
+    #[serde(default)]
+    pub admin: bool,
+}
+
+/// Methods that change what the server serves, only for [`ApiKey::admin`] keys.
+const ADMIN_PREFIX: &str = "admin_";
+
+/// Who may call the server.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Serve every request without asking for a key, for local development.
+    pub disabled: bool,
+    pub keys: Vec<ApiKey>,
+}
+
+/// The hash [`ApiKey::sha256`] is configured with for `key`.
+pub fn hash_api_key(key: &str) -> B256 {
+    B256::from_slice(&Sha256::digest(key))
+}
+
+/// The hash of the API key a request came with, attached to it by
+/// [`start_server`](crate::start_server).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ApiKeyHash(pub B256);
+
+impl ApiKeyHash {
+    /// The key in logs, the first 8 hex digits of its hash.
+    pub fn id(&self) -> String {
+        alloy::hex::encode(&self.0[..4])
+    }
+}
+
+/// Attach the hash of the key in the `Authorization: Bearer` header of `request`, if there is one.
+pub fn tag_api_key<B>(mut request: HttpRequest<B>) -> HttpRequest<B> {
+    let key = request
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(key) = key {
+        let hash = hash_api_key(key.trim());
+        request.extensions_mut().insert(ApiKeyHash(hash));
+    }
+    request
+}
+
+/// The configured keys by hash.
+#[derive(Debug)]
+pub struct Authenticator {
+    disabled: bool,
+    keys: HashMap<B256, ApiKey>,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        let keys = config.keys.into_iter().map(|key| (key.sha256, key)).collect();
+        Self { disabled: config.disabled, keys }
+    }
+
+    /// Check that the key hashing to `key` may call `method`.
+    pub fn check(&self, key: Option<B256>, method: &str) -> Result<(), ChainManagerError> {
+        if self.disabled {
+            return Ok(())
+        }
+        let Some(key) = key else {
+            return Err(ChainManagerError::Unauthorized {
+                reason: "Missing `Authorization: Bearer` API key".into(),
+            })
+        };
+        let Some(key) = self.keys.get(&key) else {
+            return Err(ChainManagerError::Unauthorized { reason: "Unknown API key".into() })
+        };
+        if method.starts_with(ADMIN_PREFIX) && !key.admin {
+            return Err(ChainManagerError::Forbidden {
+                reason: format!("{method} needs an admin API key"),
+            })
+        }
+        if key.methods.as_ref().is_some_and(|methods| !methods.contains(method)) {
+            return Err(ChainManagerError::Forbidden {
+                reason: format!("The API key may not call {method}"),
+            })
+        }
+        Ok(())
+    }
+}
+
+/// RPC middleware rejecting calls without a key allowed to make them, with `Unauthorized` or
+/// `Forbidden`. Batches are checked call by call, notifications without a key are dropped.
+#[derive(Clone, Debug)]
+pub struct AuthService<S> {
+    service: S,
+    authenticator: Arc<Authenticator>,
+}
+
+impl<S> AuthService<S> {
+    pub fn new(service: S, authenticator: Arc<Authenticator>) -> Self {
+        Self { service, authenticator }
+    }
+
+    fn check(&self, request: &Request<'_>) -> Result<(), ErrorObjectOwned> {
+        let key = request.extensions.get::<ApiKeyHash>().map(|ApiKeyHash(key)| *key);
+        Ok(self.authenticator.check(key, &request.method)?)
+    }
+}
+
+impl<S> RpcServiceT for AuthService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, NotificationResponse = MethodResponse>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let checked = self.check(&request);
+        let service = self.service.clone();
+        async move {
+            match checked {
+                Ok(()) => service.call(request).await,
+                Err(error) => MethodResponse::error(request.id, error),
+            }
+        }
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        for entry in batch.iter_mut() {
+            let Ok(BatchEntry::Call(request)) = entry else { continue };
+            if let Err(error) = self.check(request) {
+                *entry = Err(BatchEntryErr::new(request.id.clone(), error));
+            }
+        }
+        self.service.batch(batch)
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        let key = notification.extensions.get::<ApiKeyHash>().map(|ApiKeyHash(key)| *key);
+        let checked = self.authenticator.check(key, &notification.method);
+        let service = self.service.clone();
+        async move {
+            match checked {
+                Ok(()) => service.notification(notification).await,
+                Err(_) => MethodResponse::notification(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keys_and_their_methods() {
+        let config = AuthConfig {
+            disabled: false,
+            keys: vec![
+                ApiKey { sha256: hash_api_key("admin"), methods: None, admin: true },
+                ApiKey {
+                    sha256: hash_api_key("reader"),
+                    methods: Some(["finalisedHeader".to_string()].into()),
+                    admin: false,
+                },
+                ApiKey { sha256: hash_api_key("any"), methods: None, admin: false },
+            ],
+        };
+        let authenticator = Authenticator::new(config.clone());
+        let (admin, reader) = (Some(hash_api_key("admin")), Some(hash_api_key("reader")));
+        assert!(authenticator.check(admin, "logs").is_ok());
+        assert!(authenticator.check(admin, "admin_addChain").is_ok());
+        assert!(matches!(
+            authenticator.check(Some(hash_api_key("any")), "admin_addChain"),
+            Err(ChainManagerError::Forbidden { .. })
+        ));
+        assert!(authenticator.check(reader, "finalisedHeader").is_ok());
+        assert!(matches!(
+            authenticator.check(reader, "logs"),
+            Err(ChainManagerError::Forbidden { .. })
+        ));
+        assert!(matches!(
+            authenticator.check(Some(hash_api_key("wrong")), "logs"),
+            Err(ChainManagerError::Unauthorized { .. })
+        ));
+        assert!(matches!(
+            authenticator.check(None, "logs"),
+            Err(ChainManagerError::Unauthorized { .. })
+        ));
+
+        let disabled = Authenticator::new(AuthConfig { disabled: true, ..config });
+        assert!(disabled.check(None, "logs").is_ok());
+    }
+}
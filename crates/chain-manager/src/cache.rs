@@ -0,0 +1,221 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use alloy::{consensus::Header, primitives::B256, rpc::types::eth::TransactionReceipt};
+use serde::{Deserialize, Serialize};
+
+/// How headers of one chain are cached, as set under `[chains.header_cache]` in the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderCacheConfig {
+    /// How long a header is served from the cache. 0 disables the cache.
+    pub ttl_ms: u64,
+    /// Headers kept at most, the oldest are dropped first.
+    pub max_entries: usize,
+}
+
+impl Default for HeaderCacheConfig {
+    fn default() -> Self {
+        Self { ttl_ms: 60_000, max_entries: 1024 }
+    }
+}
+
+/// How receipts of one chain are cached, as set under `[chains.receipt_cache]` in the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiptCacheConfig {
+    /// Depth below the latest block a receipt's block must have before the receipt is cached,
+    /// so receipts a reorg could still drop are always looked up again.
+    pub confirmations_for_cache: u64,
+    /// Receipts kept at most, the least recently used are dropped first. 0 disables the cache.
+    pub capacity: usize,
+}
+
+impl Default for ReceiptCacheConfig {
+    fn default() -> Self {
+        Self { confirmations_for_cache: 12, capacity: 4096 }
+    }
+}
+
+/// Headers of one chain by number and by hash.
+#[derive(Debug)]
+pub(crate) struct HeaderCache {
+    ttl: Duration,
+    max_entries: usize,
+    headers: HashMap<u64, (Header, Instant)>,
+    numbers: HashMap<B256, u64>,
+    /// Numbers in the order they were inserted, to find the oldest.
+    order: VecDeque<u64>,
+}
+
+impl HeaderCache {
+    pub(crate) fn new(config: &HeaderCacheConfig) -> Self {
+        Self {
+            ttl: Duration::from_millis(config.ttl_ms),
+            max_entries: config.max_entries,
+            headers: HashMap::new(),
+            numbers: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn by_number(&mut self, number: u64) -> Option<Header> {
+        let (header, inserted_at) = self.headers.get(&number)?;
+        if inserted_at.elapsed() < self.ttl {
+            return Some(header.clone())
+        }
+        self.remove(number);
+        None
+    }
+
+    pub(crate) fn by_hash(&mut self, hash: B256) -> Option<Header> {
+        let number = *self.numbers.get(&hash)?;
+        self.by_number(number)
+    }
+
+    pub(crate) fn insert(&mut self, header: Header) {
+        if self.ttl.is_zero() || self.max_entries == 0 {
+            return
+        }
+        let number = header.number;
+        // A reorg may have replaced the header under this number.
+        self.remove(number);
+        while self.headers.len() >= self.max_entries {
+            let Some(oldest) = self.order.pop_front() else { break };
+            self.remove(oldest);
+        }
+        self.numbers.insert(header.hash_slow(), number);
+        self.headers.insert(number, (header, Instant::now()));
+        self.order.push_back(number);
+    }
+
+    fn remove(&mut self, number: u64) {
+        let Some((header, _)) = self.headers.remove(&number) else { return };
+        self.numbers.remove(&header.hash_slow());
+        self.order.retain(|&cached| cached != number);
+    }
+}
+
+/// Receipts of one chain by transaction hash, least recently used first out.
+#[derive(Debug)]
+pub(crate) struct ReceiptCache {
+    capacity: usize,
+    receipts: HashMap<B256, (TransactionReceipt, u64)>,
+    /// Hashes by when they were last used, counting up.
+    used: BTreeMap<u64, B256>,
+    tick: u64,
+}
+
+impl ReceiptCache {
+    pub(crate) fn new(config: &ReceiptCacheConfig) -> Self {
+        Self { capacity: config.capacity, receipts: HashMap::new(), used: BTreeMap::new(), tick: 0 }
+    }
+
+    pub(crate) fn get(&mut self, tx_hash: B256) -> Option<TransactionReceipt> {
+        self.tick += 1;
+        let (receipt, used_at) = self.receipts.get_mut(&tx_hash)?;
+        self.used.remove(used_at);
+        *used_at = self.tick;
+        self.used.insert(self.tick, tx_hash);
+        Some(receipt.clone())
+    }
+
+    pub(crate) fn insert(&mut self, receipt: TransactionReceipt) {
+        if self.capacity == 0 {
+            return
+        }
+        self.tick += 1;
+        let tx_hash = receipt.transaction_hash;
+        if let Some((_, used_at)) = self.receipts.insert(tx_hash, (receipt, self.tick)) {
+            self.used.remove(&used_at);
+        }
+        self.used.insert(self.tick, tx_hash);
+        while self.receipts.len() > self.capacity {
+            let Some((_, oldest)) = self.used.pop_first() else { break };
+            self.receipts.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn header(number: u64) -> Header {
+        Header { number, ..Default::default() }
+    }
+
+    #[test]
+    fn test_lookup_by_number_and_hash() {
+        let mut cache = HeaderCache::new(&HeaderCacheConfig::default());
+        cache.insert(header(7));
+        assert_eq!(cache.by_number(7), Some(header(7)));
+        assert_eq!(cache.by_hash(header(7).hash_slow()), Some(header(7)));
+        assert_eq!(cache.by_number(8), None);
+
+        // A different header under the same number replaces the first one.
+        let replacement = Header { gas_limit: 1, ..header(7) };
+        cache.insert(replacement.clone());
+        assert_eq!(cache.by_number(7), Some(replacement));
+        assert_eq!(cache.by_hash(header(7).hash_slow()), None);
+    }
+
+    #[test]
+    fn test_oldest_evicted_first() {
+        let mut cache =
+            HeaderCache::new(&HeaderCacheConfig { max_entries: 2, ..Default::default() });
+        for number in 0..3 {
+            cache.insert(header(number));
+        }
+        assert_eq!(cache.by_number(0), None);
+        assert_eq!(cache.by_number(1), Some(header(1)));
+        assert_eq!(cache.by_number(2), Some(header(2)));
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let mut cache = HeaderCache::new(&HeaderCacheConfig { ttl_ms: 20, ..Default::default() });
+        cache.insert(header(1));
+        assert_eq!(cache.by_number(1), Some(header(1)));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.by_number(1), None);
+        assert_eq!(cache.by_hash(header(1).hash_slow()), None);
+
+        let mut disabled = HeaderCache::new(&HeaderCacheConfig { ttl_ms: 0, ..Default::default() });
+        disabled.insert(header(1));
+        assert_eq!(disabled.by_number(1), None);
+    }
+
+    #[test]
+    fn test_least_recently_used_receipt_evicted() {
+        let receipt = |byte| {
+            let mut receipt: TransactionReceipt = serde_json::from_value(serde_json::json!({
+                "transactionHash": B256::ZERO, "transactionIndex": "0x0", "blockHash": B256::ZERO,
+                "blockNumber": "0x1", "from": "0x0000000000000000000000000000000000000000",
+                "to": null, "gasUsed": "0x0", "effectiveGasPrice": "0x0", "contractAddress": null,
+                "type": "0x0", "status": "0x1", "cumulativeGasUsed": "0x0", "logs": [],
+                "logsBloom": format!("0x{}", "0".repeat(512)),
+            }))
+            .expect("A receipt");
+            receipt.transaction_hash = B256::repeat_byte(byte);
+            receipt
+        };
+        let mut cache =
+            ReceiptCache::new(&ReceiptCacheConfig { capacity: 2, ..Default::default() });
+        cache.insert(receipt(1));
+        cache.insert(receipt(2));
+        // Using the first makes the second the least recently used.
+        assert_eq!(cache.get(B256::repeat_byte(1)), Some(receipt(1)));
+        cache.insert(receipt(3));
+        assert_eq!(cache.get(B256::repeat_byte(2)), None);
+        assert_eq!(cache.get(B256::repeat_byte(1)), Some(receipt(1)));
+        assert_eq!(cache.get(B256::repeat_byte(3)), Some(receipt(3)));
+
+        let mut disabled =
+            ReceiptCache::new(&ReceiptCacheConfig { capacity: 0, ..Default::default() });
+        disabled.insert(receipt(1));
+        assert_eq!(disabled.get(B256::repeat_byte(1)), None);
+    }
+}
@@ -0,0 +1,553 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use alloy::{
+    consensus::Header,
+    primitives::{Address, Bytes, B256},
+    rpc::types::{
+        eth::{Transaction, TransactionReceipt, TransactionRequest},
+        BlockNumberOrTag, EIP1186AccountProofResponse, Filter, Log,
+    },
+};
+use futures::{stream::BoxStream, StreamExt};
+use jsonrpsee::{
+    core::{
+        client::{ClientT, Error as RpcClientError, Subscription, SubscriptionClientT},
+        params::ArrayParams,
+    },
+    http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuilder},
+    rpc_params,
+    types::{error::INVALID_PARAMS_CODE, ErrorObjectOwned},
+    ws_client::{WsClient, WsClientBuilder},
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    AccountState, ChainConfig, ChainManagerError, ChainStatus, FinalisedHeader, HeaderOrError,
+    LogsPage, ReceiptProof, RetryPolicy,
+};
+
+/// How a [`ChainManagerHandle`] talks to the server.
+#[derive(Clone, Debug)]
+pub struct ClientOptions {
+    /// Sent as `Authorization: Bearer <key>` with every request.
+    pub api_key: Option<String>,
+    /// How long a request may take before it fails, retries not included.
+    pub request_timeout: Duration,
+    /// How calls failing with `NodeFailure`, `Timeout` or `QueueTimeout` are retried. Defaults to
+    /// a single attempt.
+    pub retry: RetryPolicy,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            request_timeout: Duration::from_secs(60),
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+        }
+    }
+}
+
+/// What a [`ChainManagerHandle`] call failed with.
+#[derive(Error, Debug, Clone)]
+pub enum ClientError {
+    /// The server answered with one of its own errors, decoded back from its code and data.
+    #[error("{message}")]
+    Server { error: ChainManagerError, message: String, request_id: Option<String> },
+    /// An error response the server does not send itself, e.g. from a proxy in front of it.
+    #[error("JSON-RPC error {code}: {message}")]
+    Rpc { code: i32, message: String, data: Option<Value> },
+    #[error("Cannot connect to {url}: {reason}")]
+    Connect { url: String, reason: String },
+    /// The request or its answer did not make it, or the answer could not be read.
+    #[error("The request to the chain manager failed: {0}")]
+    Transport(String),
+}
+
+impl ClientError {
+    /// Whether the server gave up on the upstream in a way that may pass: `NodeFailure`,
+    /// `Timeout` and `QueueTimeout`.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Server {
+                error: ChainManagerError::NodeFailure { .. } |
+                    ChainManagerError::Timeout { .. } |
+                    ChainManagerError::QueueTimeout { .. },
+                ..
+            }
+        )
+    }
+
+    /// `error`, from a call about `chain_id` when it is about one chain.
+    fn from_rpc(error: RpcClientError, chain_id: Option<u64>) -> Self {
+        match error {
+            RpcClientError::Call(error) => Self::from_error_object(error, chain_id),
+            error => Self::Transport(error.to_string()),
+        }
+    }
+
+    fn from_error_object(error: ErrorObjectOwned, chain_id: Option<u64>) -> Self {
+        let data = error.data().and_then(|data| serde_json::from_str::<Value>(data.get()).ok());
+        let request_id = data
+            .as_ref()
+            .and_then(|data| data.get("request_id"))
+            .and_then(Value::as_str)
+            .map(String::from);
+        match decode(&error, data.as_ref(), chain_id) {
+            Some(decoded) => {
+                Self::Server { error: decoded, message: error.message().into(), request_id }
+            }
+            None => Self::Rpc { code: error.code(), message: error.message().into(), data },
+        }
+    }
+}
+
+/// The [`ChainManagerError`] the server turned into `error`, whose data is `data`, if it is one.
+/// The chain id falls back to `chain_id`, that of the call, for errors whose data leaves it out.
+fn decode(
+    error: &ErrorObjectOwned,
+    data: Option<&Value>,
+    chain_id: Option<u64>,
+) -> Option<ChainManagerError> {
+    let reason = error.message().to_string();
+    let field = |name: &str| data.and_then(|data| data.get(name));
+    let number = |name: &str| field(name).and_then(Value::as_u64);
+    let chain_id = number("chain_id").or(chain_id);
+    Some(match error.code() {
+        -4004 => ChainManagerError::ChainIdNotFound { reason, chain_id: chain_id? },
+        -4005 => ChainManagerError::NodeFailure { reason, chain_id: chain_id? },
+        -4006 => ChainManagerError::ProviderFailure { reason, chain_id: chain_id? },
+        -4007 => ChainManagerError::GenericFailure { reason, chain_id: chain_id? },
+        -4008 => ChainManagerError::BlockNotFound { reason, chain_id: chain_id? },
+        -4009 => ChainManagerError::LogRangeTooLarge {
+            reason,
+            chain_id: chain_id?,
+            from_block: number("from_block")?,
+            to_block: number("to_block")?,
+        },
+        -4010 => ChainManagerError::CallReverted {
+            reason,
+            chain_id: chain_id?,
+            data: serde_json::from_value::<Bytes>(data?.clone()).ok()?,
+        },
+        -4011 => {
+            ChainManagerError::RateLimited { reason, retry_after_ms: number("retry_after_ms")? }
+        }
+        -4012 => ChainManagerError::BatchTooLarge { reason, chain_id: chain_id? },
+        -4013 => ChainManagerError::TransactionNotFound { reason, chain_id: chain_id? },
+        -4014 => ChainManagerError::InvalidRange { reason, chain_id: chain_id? },
+        -4015 => ChainManagerError::Unauthorized { reason },
+        -4016 => ChainManagerError::Forbidden { reason },
+        -4017 => ChainManagerError::ShuttingDown { reason },
+        -4018 => ChainManagerError::InvalidChainConfig { reason, chain_id: chain_id? },
+        -4019 => ChainManagerError::UpstreamRpcError {
+            reason,
+            chain_id: chain_id?,
+            code: field("code")?.as_i64()?,
+            message: field("message")?.as_str()?.into(),
+        },
+        -4020 => ChainManagerError::NotFound { reason, chain_id: chain_id? },
+        -4021 => ChainManagerError::Timeout {
+            chain_id: chain_id?,
+            method: field("method")?.as_str()?.into(),
+            timeout_ms: number("timeout_ms")?,
+        },
+        -4022 => ChainManagerError::UpstreamInconsistent { reason, chain_id: chain_id? },
+        -4023 => ChainManagerError::QueueTimeout {
+            chain_id: chain_id?,
+            timeout_ms: number("timeout_ms")?,
+        },
+        -4024 => ChainManagerError::TooManyChains { reason },
+        -4025 => ChainManagerError::ChainIdMismatch {
+            expected: number("expected")?,
+            actual: number("actual")?,
+        },
+        INVALID_PARAMS_CODE => ChainManagerError::InvalidParams { reason, chain_id: chain_id? },
+        _ => return None,
+    })
+}
+
+#[derive(Debug)]
+enum Transport {
+    Http(HttpClient),
+    Ws(WsClient),
+}
+
+/// A client of the chain manager at one URL, over HTTP for `http://` and `https://` URLs and
+/// over a WebSocket for `ws://` and `wss://` ones. Its methods mirror the RPC methods, with the
+/// server's errors decoded into [`ClientError::Server`].
+#[derive(Clone, Debug)]
+pub struct ChainManagerHandle {
+    transport: Arc<Transport>,
+    options: ClientOptions,
+}
+
+impl ChainManagerHandle {
+    pub async fn connect(url: &str, options: ClientOptions) -> Result<Self, ClientError> {
+        let failed = |reason: String| ClientError::Connect { url: url.into(), reason };
+        let mut headers = HeaderMap::new();
+        if let Some(key) = &options.api_key {
+            let value = HeaderValue::from_str(&format!("Bearer {key}"))
+                .map_err(|_| failed("The API key is not a valid header value".into()))?;
+            headers.insert("authorization", value);
+        }
+        let parsed = Url::parse(url).map_err(|error| failed(error.to_string()))?;
+        let transport = match parsed.scheme() {
+            "http" | "https" => Transport::Http(
+                HttpClientBuilder::default()
+                    .set_headers(headers)
+                    .request_timeout(options.request_timeout)
+                    .build(url)
+                    .map_err(|error| failed(error.to_string()))?,
+            ),
+            "ws" | "wss" => Transport::Ws(
+                WsClientBuilder::default()
+                    .set_headers(headers)
+                    .request_timeout(options.request_timeout)
+                    .build(url)
+                    .await
+                    .map_err(|error| failed(error.to_string()))?,
+            ),
+            scheme => return Err(failed(format!("Unsupported scheme {scheme}"))),
+        };
+        Ok(Self { transport: Arc::new(transport), options })
+    }
+
+    pub async fn finalised_header(
+        &self,
+        chain_id: u64,
+        at: BlockNumberOrTag,
+    ) -> Result<Header, ClientError> {
+        self.request("finalisedHeader", Some(chain_id), rpc_params![chain_id, at]).await
+    }
+
+    /// The header of every chain in `chain_ids` at `at`, each with its own result.
+    pub async fn finalised_headers(
+        &self,
+        chain_ids: &[u64],
+        at: BlockNumberOrTag,
+    ) -> Result<BTreeMap<u64, Result<Header, ClientError>>, ClientError> {
+        let headers: BTreeMap<u64, HeaderOrError> =
+            self.request("finalisedHeaders", None, rpc_params![chain_ids, at]).await?;
+        Ok(headers
+            .into_iter()
+            .map(|(chain_id, header)| {
+                let header = match header {
+                    HeaderOrError::Header(header) => Ok(*header),
+                    HeaderOrError::Error(error) => {
+                        Err(ClientError::from_error_object(error, Some(chain_id)))
+                    }
+                };
+                (chain_id, header)
+            })
+            .collect())
+    }
+
+    pub async fn header_by_hash(
+        &self,
+        chain_id: u64,
+        block_hash: B256,
+    ) -> Result<Header, ClientError> {
+        self.request("headerByHash", Some(chain_id), rpc_params![chain_id, block_hash]).await
+    }
+
+    pub async fn headers_range(
+        &self,
+        chain_id: u64,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<Header>, ClientError> {
+        self.request("headersRange", Some(chain_id), rpc_params![chain_id, start, end]).await
+    }
+
+    pub async fn transaction_receipt(
+        &self,
+        chain_id: u64,
+        tx_hash: B256,
+    ) -> Result<Option<TransactionReceipt>, ClientError> {
+        self.request("transactionReceipt", Some(chain_id), rpc_params![chain_id, tx_hash]).await
+    }
+
+    pub async fn transaction_by_hash(
+        &self,
+        chain_id: u64,
+        tx_hash: B256,
+    ) -> Result<Option<Transaction>, ClientError> {
+        self.request("transactionByHash", Some(chain_id), rpc_params![chain_id, tx_hash]).await
+    }
+
+    pub async fn transaction_receipts(
+        &self,
+        chain_id: u64,
+        tx_hashes: &[B256],
+    ) -> Result<Vec<Option<TransactionReceipt>>, ClientError> {
+        self.request("transactionReceipts", Some(chain_id), rpc_params![chain_id, tx_hashes]).await
+    }
+
+    pub async fn receipt_proof(
+        &self,
+        chain_id: u64,
+        tx_hash: B256,
+    ) -> Result<ReceiptProof, ClientError> {
+        self.request("receiptProof", Some(chain_id), rpc_params![chain_id, tx_hash]).await
+    }
+
+    pub async fn admin_add_chain(
+        &self,
+        config: &ChainConfig,
+        verify: Option<bool>,
+    ) -> Result<(), ClientError> {
+        self.request("admin_addChain", Some(config.chain_id), rpc_params![config, verify]).await
+    }
+
+    pub async fn admin_remove_chain(&self, chain_id: u64) -> Result<(), ClientError> {
+        self.request("admin_removeChain", Some(chain_id), rpc_params![chain_id]).await
+    }
+
+    pub async fn admin_disable_chain(&self, chain_id: u64) -> Result<(), ClientError> {
+        self.request("admin_disableChain", Some(chain_id), rpc_params![chain_id]).await
+    }
+
+    pub async fn admin_enable_chain(&self, chain_id: u64) -> Result<(), ClientError> {
+        self.request("admin_enableChain", Some(chain_id), rpc_params![chain_id]).await
+    }
+
+    pub async fn logs(&self, chain_id: u64, filter: &Filter) -> Result<Vec<Log>, ClientError> {
+        self.request("logs", Some(chain_id), rpc_params![chain_id, filter]).await
+    }
+
+    pub async fn logs_paged(
+        &self,
+        chain_id: u64,
+        filter: &Filter,
+        page_size: Option<u64>,
+    ) -> Result<LogsPage, ClientError> {
+        self.request("logsPaged", Some(chain_id), rpc_params![chain_id, filter, page_size]).await
+    }
+
+    pub async fn get_proof(
+        &self,
+        chain_id: u64,
+        address: Address,
+        storage_keys: &[B256],
+        at: BlockNumberOrTag,
+    ) -> Result<EIP1186AccountProofResponse, ClientError> {
+        let params = rpc_params![chain_id, address, storage_keys, at];
+        self.request("getProof", Some(chain_id), params).await
+    }
+
+    pub async fn account_state(
+        &self,
+        chain_id: u64,
+        address: Address,
+        at: BlockNumberOrTag,
+    ) -> Result<AccountState, ClientError> {
+        self.request("accountState", Some(chain_id), rpc_params![chain_id, address, at]).await
+    }
+
+    pub async fn list_chains(&self) -> Result<Vec<ChainStatus>, ClientError> {
+        self.request("listChains", None, rpc_params![]).await
+    }
+
+    pub async fn call(
+        &self,
+        chain_id: u64,
+        tx: &TransactionRequest,
+        at: BlockNumberOrTag,
+    ) -> Result<Bytes, ClientError> {
+        self.request("call", Some(chain_id), rpc_params![chain_id, tx, at]).await
+    }
+
+    /// Every new head of `chain_id`. Only handles connected over a WebSocket can subscribe.
+    pub async fn subscribe_new_heads(
+        &self,
+        chain_id: u64,
+    ) -> Result<BoxStream<'static, Result<Header, ClientError>>, ClientError> {
+        self.subscribe("subscribeNewHeads", "unsubscribeNewHeads", chain_id).await
+    }
+
+    /// The finalised header of `chain_id` each time it advances. Only handles connected over a
+    /// WebSocket can subscribe.
+    pub async fn subscribe_finalised(
+        &self,
+        chain_id: u64,
+    ) -> Result<BoxStream<'static, Result<FinalisedHeader, ClientError>>, ClientError> {
+        self.subscribe("subscribeFinalised", "unsubscribeFinalised", chain_id).await
+    }
+
+    /// Call `method`, retrying as [`ClientOptions::retry`] allows while the error
+    /// [`ClientError::is_retryable`].
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        chain_id: Option<u64>,
+        params: ArrayParams,
+    ) -> Result<R, ClientError> {
+        let mut attempt = 1;
+        loop {
+            let result = match &*self.transport {
+                Transport::Http(client) => client.request(method, params.clone()).await,
+                Transport::Ws(client) => client.request(method, params.clone()).await,
+            };
+            match result.map_err(|error| ClientError::from_rpc(error, chain_id)) {
+                Err(error) if attempt < self.options.retry.max_attempts && error.is_retryable() => {
+                    tokio::time::sleep(self.options.retry.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn subscribe<T: DeserializeOwned + Send + 'static>(
+        &self,
+        method: &str,
+        unsubscribe: &str,
+        chain_id: u64,
+    ) -> Result<BoxStream<'static, Result<T, ClientError>>, ClientError> {
+        let Transport::Ws(client) = &*self.transport else {
+            return Err(ClientError::Transport(format!("{method} needs a ws:// or wss:// URL")))
+        };
+        let subscription: Subscription<T> = client
+            .subscribe(method, rpc_params![chain_id], unsubscribe)
+            .await
+            .map_err(|error| ClientError::from_rpc(error, Some(chain_id)))?;
+        Ok(subscription
+            .map(|item| item.map_err(|error| ClientError::Transport(error.to_string())))
+            .boxed())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        hash_api_key, start_server,
+        test_utils::{block_json, flaky_upstream},
+        ApiKey, AuthConfig, ChainManagerImpl, ServerConfig,
+    };
+    use jsonrpsee::server::ServerHandle;
+    use std::{net::SocketAddr, sync::atomic::Ordering};
+
+    async fn serve(manager: ChainManagerImpl) -> (ServerHandle, SocketAddr) {
+        let auth = AuthConfig {
+            disabled: false,
+            keys: vec![ApiKey { sha256: hash_api_key("valid"), methods: None, admin: true }],
+        };
+        let config = ServerConfig { auth, ..Default::default() };
+        start_server("127.0.0.1:0", manager, config).await.expect("A server")
+    }
+
+    fn options(max_attempts: u32) -> ClientOptions {
+        ClientOptions {
+            api_key: Some("valid".into()),
+            retry: RetryPolicy { max_attempts, base_delay_ms: 1, jitter: false },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decodes_every_server_error() {
+        let errors = [
+            ChainManagerError::LogRangeTooLarge {
+                reason: "Too wide".into(),
+                chain_id: 1,
+                from_block: 10,
+                to_block: 19,
+            },
+            ChainManagerError::CallReverted {
+                reason: "Reverted".into(),
+                chain_id: 1,
+                data: Bytes::from_static(&[1, 2]),
+            },
+            ChainManagerError::RateLimited { reason: "Slow down".into(), retry_after_ms: 50 },
+            ChainManagerError::UpstreamRpcError {
+                reason: "Rejected".into(),
+                chain_id: 1,
+                code: -32000,
+                message: "nope".into(),
+            },
+            ChainManagerError::Timeout { chain_id: 1, method: "eth_call".into(), timeout_ms: 5 },
+            ChainManagerError::QueueTimeout { chain_id: 1, timeout_ms: 5 },
+            ChainManagerError::TooManyChains { reason: "Too many".into() },
+            ChainManagerError::ChainIdMismatch { expected: 99, actual: 1 },
+        ];
+        for error in errors {
+            let expected = format!("{error:?}");
+            let object = ErrorObjectOwned::from(error);
+            let data = object.data().map(|data| serde_json::from_str(data.get()).unwrap());
+            let decoded = decode(&object, data.as_ref(), Some(1)).expect("A server error");
+            assert_eq!(format!("{decoded:?}"), expected);
+        }
+
+        let foreign = ErrorObjectOwned::owned(-32000, "Bad gateway", None::<()>);
+        assert!(decode(&foreign, None, Some(1)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_typed_calls_over_http_and_ws() -> Result<(), Box<dyn std::error::Error>> {
+        // Two 502s the server does not retry, so the handle sees two `NodeFailure`s.
+        let (url, requests) = flaky_upstream(2, block_json(0)).await;
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: url,
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            skip_chain_id_check: true,
+            ..Default::default()
+        }];
+        let (handle, address) = serve(ChainManagerImpl::new(configs)).await;
+
+        let once = ChainManagerHandle::connect(&format!("http://{address}"), options(1)).await?;
+        let error = once.finalised_header(1, BlockNumberOrTag::Number(0)).await.unwrap_err();
+        let ClientError::Server {
+            error: ChainManagerError::NodeFailure { chain_id: 1, .. }, ..
+        } = &error
+        else {
+            panic!("Expected a NodeFailure, got {error:?}")
+        };
+        assert!(error.is_retryable());
+
+        let retrying =
+            ChainManagerHandle::connect(&format!("http://{address}"), options(3)).await?;
+        let header = retrying.finalised_header(1, BlockNumberOrTag::Number(0)).await?;
+        assert_eq!(header.number, 0);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let Err(ClientError::Server {
+            error: ChainManagerError::ChainIdNotFound { chain_id: 999, .. },
+            request_id: Some(_),
+            ..
+        }) = retrying.finalised_header(999, BlockNumberOrTag::Finalized).await
+        else {
+            panic!("Expected an unknown chain to fail")
+        };
+        let anonymous = ChainManagerHandle::connect(
+            &format!("http://{address}"),
+            ClientOptions { api_key: None, ..options(1) },
+        )
+        .await?;
+        assert!(matches!(
+            anonymous.list_chains().await,
+            Err(ClientError::Server { error: ChainManagerError::Unauthorized { .. }, .. })
+        ));
+        assert!(retrying.subscribe_new_heads(1).await.is_err());
+
+        let ws = ChainManagerHandle::connect(&format!("ws://{address}"), options(1)).await?;
+        let header = ws.finalised_header(1, BlockNumberOrTag::Number(0)).await?;
+        assert_eq!(header.number, 0);
+        assert_eq!(ws.list_chains().await?[0].chain_id, 1);
+
+        assert!(matches!(
+            ChainManagerHandle::connect(&format!("tcp://{address}"), options(1)).await,
+            Err(ClientError::Connect { .. })
+        ));
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,669 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy::eips::BlockNumberOrTag;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    ChainManagerImpl, HeaderCacheConfig, ReceiptCacheConfig, RetryPolicy, ServerConfig,
+    DEFAULT_LOG_PAGE_SIZE,
+};
+
+/// Keys a `[[chains]]` entry may have, anything else is warned about and ignored.
+const CHAIN_KEYS: [&str; 20] = [
+    "chain_id",
+    "rpc_url",
+    "rpc_urls",
+    "failover_after",
+    "name",
+    "confirmations",
+    "retry",
+    "header_cache",
+    "receipt_cache",
+    "disabled",
+    "request_timeout_ms",
+    "reconnect_after",
+    "ws_reconnect",
+    "allowed_tags",
+    "finality",
+    "max_log_range",
+    "log_page_size",
+    "max_concurrent_requests",
+    "queue_timeout_ms",
+    "skip_chain_id_check",
+];
+
+/// Keys the `[server]` section may have, warned about like [`CHAIN_KEYS`].
+const SERVER_KEYS: [&str; 10] = [
+    "rate_limits",
+    "auth",
+    "shutdown_grace_ms",
+    "tls_cert_path",
+    "tls_key_path",
+    "tls_handshake_timeout_ms",
+    "cors",
+    "max_request_body_bytes",
+    "allowed_hosts",
+    "max_connections",
+];
+const AUTH_KEYS: [&str; 2] = ["disabled", "keys"];
+const API_KEY_KEYS: [&str; 3] = ["sha256", "methods", "admin"];
+const CORS_KEYS: [&str; 2] = ["allowed_origins", "permissive"];
+const RATE_LIMITS_KEYS: [&str; 2] = ["default", "methods"];
+const RATE_LIMIT_KEYS: [&str; 2] = ["requests_per_second", "burst"];
+
+/// Depth below the latest block treated as final on chains without a `finalized` tag, unless a
+/// chain sets `confirmations`.
+pub const DEFAULT_CONFIRMATIONS: u64 = 64;
+
+/// Consecutive transient failures after which a chain moves on to its next RPC URL, unless a
+/// chain sets `failover_after`.
+pub const DEFAULT_FAILOVER_AFTER: u32 = 3;
+
+/// Consecutive failed calls after which a provider is dropped and connected again, unless a chain
+/// sets `reconnect_after`.
+pub const DEFAULT_RECONNECT_AFTER: u32 = 3;
+
+/// How long each attempt at an upstream request, or connecting to an RPC URL, may take before it
+/// fails with `Timeout`, unless a chain sets `request_timeout_ms`.
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+
+/// Schemes a provider can be connected with.
+const RPC_SCHEMES: [&str; 5] = ["http", "https", "ws", "wss", "ipc"];
+
+/// What an RPC URL connects to, picked by its scheme.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RpcEndpoint {
+    Http(Url),
+    Ws(Url),
+    /// A socket at this path, set as an `ipc://` URL or as the absolute path itself.
+    Ipc(PathBuf),
+}
+
+impl RpcEndpoint {
+    pub fn parse(rpc_url: &str) -> Result<Self, String> {
+        if Path::new(rpc_url).is_absolute() {
+            return Ok(Self::Ipc(rpc_url.into()))
+        }
+        let url = Url::parse(rpc_url)
+            .map_err(|error| format!("{rpc_url:?} is not a URL or an absolute path: {error}"))?;
+        match url.scheme() {
+            "http" | "https" => Ok(Self::Http(url)),
+            "ws" | "wss" => Ok(Self::Ws(url)),
+            "ipc" if !url.path().is_empty() => Ok(Self::Ipc(url.path().into())),
+            "ipc" => Err(format!("{rpc_url:?} does not name a socket path")),
+            _ => Err(format!("{rpc_url:?} must use one of {}", RPC_SCHEMES.join(", "))),
+        }
+    }
+}
+
+/// Tags naming blocks that are not final yet, which `finalisedHeader` only serves for chains
+/// listing them in `allowed_tags`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnfinalisedTag {
+    Latest,
+    Safe,
+}
+
+/// How `finalisedHeader` resolves `finalized` on one chain, set as `finality = "tag"` or
+/// `finality = { confirmations = 5 }` in the config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FinalityMode {
+    /// Ask the upstream for its `finalized` block.
+    #[default]
+    Tag,
+    /// The block this many below the latest, for upstreams without a meaningful `finalized` tag.
+    Confirmations(u64),
+}
+
+impl FinalityMode {
+    /// The mode in logs and metrics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Tag => "tag",
+            Self::Confirmations(_) => "confirmations",
+        }
+    }
+}
+
+/// How websocket providers of one chain reconnect once their connection drops, as set under
+/// `[chains.ws_reconnect]` in the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsReconnectConfig {
+    /// Attempts before the provider gives up and its calls fail.
+    pub max_retries: u32,
+    /// Wait between attempts.
+    pub retry_interval_ms: u64,
+}
+
+impl Default for WsReconnectConfig {
+    fn default() -> Self {
+        Self { max_retries: 10, retry_interval_ms: 3_000 }
+    }
+}
+
+/// One chain the manager serves, as listed under `[[chains]]` in the config file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    /// The primary RPC URL, tried before any of `rpc_urls`.
+    #[serde(default)]
+    pub rpc_url: String,
+    /// Fallback RPC URLs in the order they are failed over to, or all of them if `rpc_url` is
+    /// not set.
+    #[serde(default)]
+    pub rpc_urls: Vec<String>,
+    /// Overrides [`DEFAULT_FAILOVER_AFTER`] for this chain.
+    #[serde(default)]
+    pub failover_after: Option<u32>,
+    /// Only used to label the chain in logs.
+    #[serde(default)]
+    pub name: String,
+    /// Overrides [`DEFAULT_CONFIRMATIONS`] for this chain.
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub header_cache: HeaderCacheConfig,
+    #[serde(default)]
+    pub receipt_cache: ReceiptCacheConfig,
+    /// Keeps the chain listed but answers its calls with `ChainIdNotFound`, until
+    /// `admin_enableChain`.
+    #[serde(default)]
+    pub disabled: bool,
+    /// Overrides [`DEFAULT_REQUEST_TIMEOUT_MS`] for this chain.
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+    /// Overrides [`DEFAULT_RECONNECT_AFTER`] for this chain.
+    #[serde(default)]
+    pub reconnect_after: Option<u32>,
+    /// Only used for `ws://` and `wss://` URLs.
+    #[serde(default)]
+    pub ws_reconnect: WsReconnectConfig,
+    #[serde(default)]
+    pub allowed_tags: Vec<UnfinalisedTag>,
+    /// With [`FinalityMode::Confirmations`] also what `subscribeFinalised` follows, instead of
+    /// checking whether the upstream knows the `finalized` tag.
+    #[serde(default)]
+    pub finality: FinalityMode,
+    /// Overrides [`ChainManagerImpl::with_max_log_range`] for this chain.
+    #[serde(default)]
+    pub max_log_range: Option<u64>,
+    /// Overrides [`DEFAULT_LOG_PAGE_SIZE`] for this chain.
+    #[serde(default)]
+    pub log_page_size: Option<u64>,
+    /// Upstream calls of the chain in flight at once, any number if `None`.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+    /// How long a call waits for one of the `max_concurrent_requests` before failing with
+    /// `QueueTimeout`, for as long as it takes if `None`.
+    #[serde(default)]
+    pub queue_timeout_ms: Option<u64>,
+    /// Serve the chain from RPC URLs reporting another chain id, for dev chains that report a
+    /// different one than they are known by.
+    #[serde(default)]
+    pub skip_chain_id_check: bool,
+}
+
+impl ChainConfig {
+    /// Depth below the latest block treated as final if the upstream lacks a `finalized` tag.
+    pub fn confirmations(&self) -> u64 {
+        self.confirmations.unwrap_or(DEFAULT_CONFIRMATIONS)
+    }
+
+    /// Consecutive transient failures after which the next RPC URL is used.
+    pub fn failover_after(&self) -> u32 {
+        self.failover_after.unwrap_or(DEFAULT_FAILOVER_AFTER).max(1)
+    }
+
+    /// Consecutive failed calls after which a provider is connected again.
+    pub fn reconnect_after(&self) -> u32 {
+        self.reconnect_after.unwrap_or(DEFAULT_RECONNECT_AFTER).max(1)
+    }
+
+    /// How long one request to an RPC URL of the chain may take.
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms.unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS))
+    }
+
+    /// Whether `finalisedHeader` serves the block `at` names on this chain: `finalized`,
+    /// `earliest` and numbers always, `latest` and `safe` once listed in `allowed_tags`, and
+    /// `pending` never.
+    pub fn allows(&self, at: BlockNumberOrTag) -> bool {
+        match at {
+            BlockNumberOrTag::Latest => self.allowed_tags.contains(&UnfinalisedTag::Latest),
+            BlockNumberOrTag::Safe => self.allowed_tags.contains(&UnfinalisedTag::Safe),
+            BlockNumberOrTag::Pending => false,
+            BlockNumberOrTag::Finalized |
+            BlockNumberOrTag::Earliest |
+            BlockNumberOrTag::Number(_) => true,
+        }
+    }
+
+    /// Blocks one `logsPaged` page covers unless the request says otherwise.
+    pub fn log_page_size(&self) -> u64 {
+        self.log_page_size.unwrap_or(DEFAULT_LOG_PAGE_SIZE)
+    }
+
+    /// All RPC URLs of the chain, primary first.
+    pub fn urls(&self) -> Vec<&str> {
+        let primary = Some(self.rpc_url.as_str()).filter(|url| !url.is_empty());
+        primary.into_iter().chain(self.rpc_urls.iter().map(String::as_str)).collect()
+    }
+
+    /// Check that the chain has RPC URLs a provider can connect to, returning the offending key
+    /// and why otherwise.
+    pub(crate) fn validate(&self) -> Result<(), (String, String)> {
+        if self.urls().is_empty() {
+            return Err((
+                "rpc_url".into(),
+                "must be set unless rpc_urls lists at least one URL".into(),
+            ))
+        }
+        let primary = (!self.rpc_url.is_empty()).then(|| (&self.rpc_url, "rpc_url".into()));
+        let fallbacks =
+            self.rpc_urls.iter().enumerate().map(|(i, url)| (url, format!("rpc_urls[{i}]")));
+        for (url, key) in primary.into_iter().chain(fallbacks) {
+            RpcEndpoint::parse(url).map_err(|reason| (key, reason))?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything a config file sets.
+#[derive(Debug, Deserialize)]
+pub struct ConfigFile {
+    pub chains: Vec<ChainConfig>,
+    /// How the server started by the binary treats its clients, set under `[server]`.
+    #[serde(default)]
+    pub server: ServerConfig,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("{} is neither a .toml nor a .json file", path.display())]
+    UnknownFormat { path: PathBuf },
+    #[error("{}: {source}", path.display())]
+    Toml { path: PathBuf, source: toml::de::Error },
+    #[error("{}: {source}", path.display())]
+    Json { path: PathBuf, source: serde_json::Error },
+    #[error("{}: `{field}` {reason}", path.display())]
+    Invalid { path: PathBuf, field: String, reason: String },
+}
+
+/// The formats a config file can be written in, picked by its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Read and validate the chains configured in the TOML or JSON file at `path`.
+pub fn load_chain_configs(path: &Path) -> Result<Vec<ChainConfig>, ConfigError> {
+    Ok(load_config(path)?.chains)
+}
+
+/// Read and validate the TOML or JSON config file at `path`, chains and server settings.
+pub fn load_config(path: &Path) -> Result<ConfigFile, ConfigError> {
+    let format = ConfigFormat::from_path(path)
+        .ok_or_else(|| ConfigError::UnknownFormat { path: path.to_path_buf() })?;
+    let contents = fs::read_to_string(path)
+        .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+    let (file, unknown) = parse_config(path, &contents, format)?;
+    for key in unknown {
+        tracing::warn!("{}: ignoring unknown key `{key}`", path.display());
+    }
+    Ok(file)
+}
+
+/// Parse `contents` of the file at `path`, returning it and the unknown keys found next to the
+/// chains.
+fn parse_config(
+    path: &Path,
+    contents: &str,
+    format: ConfigFormat,
+) -> Result<(ConfigFile, Vec<String>), ConfigError> {
+    // The typed parse reports syntax and type errors with their line, the untyped one finds the
+    // keys the typed one silently skips.
+    let (file, value): (ConfigFile, serde_json::Value) = match format {
+        ConfigFormat::Toml => {
+            let toml_error = |source| ConfigError::Toml { path: path.to_path_buf(), source };
+            let file = toml::from_str(contents).map_err(toml_error)?;
+            let table: toml::Table = toml::from_str(contents).map_err(toml_error)?;
+            (file, serde_json::to_value(table).expect("a TOML table converts to JSON"))
+        }
+        ConfigFormat::Json => {
+            let json_error = |source| ConfigError::Json { path: path.to_path_buf(), source };
+            let file = serde_json::from_str(contents).map_err(json_error)?;
+            (file, serde_json::from_str(contents).map_err(json_error)?)
+        }
+    };
+    validate(path, &file.chains)?;
+    file.server.rate_limits.validate().map_err(|(key, reason)| ConfigError::Invalid {
+        path: path.to_path_buf(),
+        field: format!("server.rate_limits.{key}"),
+        reason,
+    })?;
+    let unknown = unknown_keys(&value);
+    Ok((file, unknown))
+}
+
+/// Reject duplicate chain ids and RPC URLs a provider cannot connect to.
+fn validate(path: &Path, chains: &[ChainConfig]) -> Result<(), ConfigError> {
+    let mut seen = HashMap::new();
+    for (index, chain) in chains.iter().enumerate() {
+        if let Some(first) = seen.insert(chain.chain_id, index) {
+            return Err(ConfigError::Invalid {
+                path: path.to_path_buf(),
+                field: format!("chains[{index}].chain_id"),
+                reason: format!("{} is already used by chains[{first}]", chain.chain_id),
+            })
+        }
+        chain.validate().map_err(|(key, reason)| ConfigError::Invalid {
+            path: path.to_path_buf(),
+            field: format!("chains[{index}].{key}"),
+            reason,
+        })?;
+    }
+    Ok(())
+}
+
+/// Paths of the keys in `value` that are not part of the schema.
+fn unknown_keys(value: &serde_json::Value) -> Vec<String> {
+    let Some(table) = value.as_object() else { return Vec::new() };
+    let mut unknown: Vec<String> =
+        table.keys().filter(|key| !["chains", "server"].contains(&key.as_str())).cloned().collect();
+    let chains = table.get("chains").and_then(|chains| chains.as_array());
+    for (index, chain) in chains.into_iter().flatten().enumerate() {
+        unknown_in(&mut unknown, Some(chain), &format!("chains[{index}]"), &CHAIN_KEYS);
+    }
+
+    let server = table.get("server");
+    let section = |key: &str| server.and_then(|server| server.get(key));
+    unknown_in(&mut unknown, server, "server", &SERVER_KEYS);
+    unknown_in(&mut unknown, section("auth"), "server.auth", &AUTH_KEYS);
+    let keys = section("auth").and_then(|auth| auth.get("keys")).and_then(|keys| keys.as_array());
+    for (index, key) in keys.into_iter().flatten().enumerate() {
+        unknown_in(&mut unknown, Some(key), &format!("server.auth.keys[{index}]"), &API_KEY_KEYS);
+    }
+    unknown_in(&mut unknown, section("cors"), "server.cors", &CORS_KEYS);
+    let rate_limits = section("rate_limits");
+    unknown_in(&mut unknown, rate_limits, "server.rate_limits", &RATE_LIMITS_KEYS);
+    let default = rate_limits.and_then(|limits| limits.get("default"));
+    unknown_in(&mut unknown, default, "server.rate_limits.default", &RATE_LIMIT_KEYS);
+    let methods = rate_limits
+        .and_then(|limits| limits.get("methods"))
+        .and_then(|methods| methods.as_object());
+    for (method, limit) in methods.into_iter().flatten() {
+        let path = format!("server.rate_limits.methods.{method}");
+        unknown_in(&mut unknown, Some(limit), &path, &RATE_LIMIT_KEYS);
+    }
+    unknown
+}
+
+/// Add the keys of the table `value` that are not in `known` to `unknown`, as `path.key`.
+fn unknown_in(
+    unknown: &mut Vec<String>,
+    value: Option<&serde_json::Value>,
+    path: &str,
+    known: &[&str],
+) {
+    let keys = value.and_then(|value| value.as_object()).into_iter().flat_map(|table| table.keys());
+    unknown.extend(
+        keys.filter(|key| !known.contains(&key.as_str())).map(|key| format!("{path}.{key}")),
+    );
+}
+
+impl ChainManagerImpl {
+    /// A manager for the chains in the TOML or JSON config file at `path`, see
+    /// [`load_chain_configs`].
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(Self::new(load_chain_configs(path.as_ref())?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VALID_TOML: &str = r#"
+[[chains]]
+chain_id = 8453
+rpc_url = "https://mainnet.base.org"
+name = "base"
+
+[[chains]]
+chain_id = 1
+confirmations = 12
+retry = { max_attempts = 5 }
+rpc_url = "ws://127.0.0.1:8546"
+rpc_urls = ["http://127.0.0.1:8545"]
+header_cache = { max_entries = 16 }
+receipt_cache = { confirmations_for_cache = 6 }
+request_timeout_ms = 2500
+ws_reconnect = { max_retries = 2 }
+allowed_tags = ["latest"]
+finality = { confirmations = 5 }
+log_page_size = 100
+"#;
+
+    fn write_config(name: &str, contents: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join(name);
+        fs::write(&path, contents).expect("config written");
+        (dir, path)
+    }
+
+    #[test]
+    fn test_valid_file() {
+        let (_dir, path) = write_config("chains.toml", VALID_TOML);
+        let configs = load_chain_configs(&path).unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].chain_id, 8453);
+        assert_eq!(configs[0].rpc_url, "https://mainnet.base.org");
+        assert_eq!(configs[0].name, "base");
+        assert_eq!(configs[1].name, "");
+        assert_eq!(configs[0].confirmations(), DEFAULT_CONFIRMATIONS);
+        assert_eq!(configs[1].confirmations(), 12);
+        assert_eq!(configs[0].retry, RetryPolicy::default());
+        assert_eq!(configs[1].retry, RetryPolicy { max_attempts: 5, ..Default::default() });
+        assert_eq!(configs[0].header_cache, HeaderCacheConfig::default());
+        assert_eq!(configs[1].header_cache.max_entries, 16);
+        assert_eq!(configs[1].receipt_cache.confirmations_for_cache, 6);
+        assert_eq!(configs[1].receipt_cache.capacity, ReceiptCacheConfig::default().capacity);
+        assert_eq!(configs[1].urls(), ["ws://127.0.0.1:8546", "http://127.0.0.1:8545"]);
+        assert_eq!(configs[0].failover_after(), DEFAULT_FAILOVER_AFTER);
+        assert_eq!(configs[0].request_timeout(), Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MS));
+        assert_eq!(configs[1].request_timeout(), Duration::from_millis(2500));
+        assert_eq!(configs[0].ws_reconnect, WsReconnectConfig::default());
+        assert_eq!(configs[0].finality, FinalityMode::Tag);
+        assert_eq!(configs[0].log_page_size(), DEFAULT_LOG_PAGE_SIZE);
+        assert_eq!(configs[1].log_page_size(), 100);
+        assert_eq!(configs[1].finality, FinalityMode::Confirmations(5));
+        assert!(!configs[0].allows(BlockNumberOrTag::Latest));
+        assert!(configs[1].allows(BlockNumberOrTag::Latest));
+        assert!(!configs[1].allows(BlockNumberOrTag::Safe));
+        for config in &configs {
+            assert!(config.allows(BlockNumberOrTag::Finalized));
+            assert!(config.allows(BlockNumberOrTag::Number(7)));
+            assert!(!config.allows(BlockNumberOrTag::Pending));
+        }
+        assert_eq!(
+            configs[1].ws_reconnect,
+            WsReconnectConfig { max_retries: 2, ..Default::default() }
+        );
+        assert!(ChainManagerImpl::from_config_file(&path).is_ok());
+
+        let json = r#"{"chains": [{"chain_id": 8453, "rpc_url": "https://mainnet.base.org"}]}"#;
+        let (_dir, path) = write_config("chains.json", json);
+        assert_eq!(load_chain_configs(&path).unwrap()[0].chain_id, 8453);
+
+        let json =
+            r#"{"chains": [{"chain_id": 1, "rpc_urls": ["http://a:8545", "http://b:8545"]}]}"#;
+        let (_dir, path) = write_config("chains.json", json);
+        assert_eq!(
+            load_chain_configs(&path).unwrap()[0].urls(),
+            ["http://a:8545", "http://b:8545"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_chain_id() {
+        let duplicate = format!(
+            "{VALID_TOML}\n[[chains]]\nchain_id = 8453\nrpc_url = \"http://localhost:8545\"\n"
+        );
+        let (_dir, path) = write_config("chains.toml", &duplicate);
+        let error = load_chain_configs(&path).unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::Invalid { field, .. } if field == "chains[2].chain_id"),
+            "{error}"
+        );
+        assert!(error.to_string().contains("already used by chains[0]"), "{error}");
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let error = load_chain_configs(&dir.path().join("chains.toml")).unwrap_err();
+        assert!(matches!(error, ConfigError::Read { .. }), "{error}");
+        assert!(matches!(
+            load_chain_configs(Path::new("chains.yaml")),
+            Err(ConfigError::UnknownFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rpc_endpoints() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert_eq!(
+            RpcEndpoint::parse("https://mainnet.base.org"),
+            Ok(RpcEndpoint::Http(url("https://mainnet.base.org")))
+        );
+        assert_eq!(
+            RpcEndpoint::parse("wss://127.0.0.1:8546"),
+            Ok(RpcEndpoint::Ws(url("wss://127.0.0.1:8546")))
+        );
+        assert_eq!(
+            RpcEndpoint::parse("ipc:///tmp/anvil.ipc"),
+            Ok(RpcEndpoint::Ipc("/tmp/anvil.ipc".into()))
+        );
+        assert_eq!(
+            RpcEndpoint::parse("/tmp/anvil.ipc"),
+            Ok(RpcEndpoint::Ipc("/tmp/anvil.ipc".into()))
+        );
+        for invalid in ["ipc://", "anvil.ipc", "tcp://127.0.0.1:8545"] {
+            assert!(RpcEndpoint::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_malformed_values() {
+        let bad_url = VALID_TOML.replace("ws://127.0.0.1:8546", "127.0.0.1:8546");
+        let error =
+            parse_config(Path::new("chains.toml"), &bad_url, ConfigFormat::Toml).unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::Invalid { field, .. } if field == "chains[1].rpc_url"),
+            "{error}"
+        );
+        let bad_fallback = VALID_TOML.replace("http://127.0.0.1:8545", "tcp://127.0.0.1:8545");
+        let error =
+            parse_config(Path::new("chains.toml"), &bad_fallback, ConfigFormat::Toml).unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::Invalid { field, .. } if field == "chains[1].rpc_urls[0]"),
+            "{error}"
+        );
+        let no_url = VALID_TOML.replace("rpc_url = \"https://mainnet.base.org\"\n", "");
+        let error =
+            parse_config(Path::new("chains.toml"), &no_url, ConfigFormat::Toml).unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::Invalid { field, .. } if field == "chains[0].rpc_url"),
+            "{error}"
+        );
+        for (limit, field) in [
+            ("default = { requests_per_second = nan, burst = 0 }", "default.requests_per_second"),
+            (
+                "methods.logs = { requests_per_second = 0, burst = 5 }",
+                "methods.logs.requests_per_second",
+            ),
+            ("methods.logs = { requests_per_second = 1, burst = 0 }", "methods.logs.burst"),
+        ] {
+            let toml = format!("{VALID_TOML}\n[server.rate_limits]\n{limit}\n");
+            let error =
+                parse_config(Path::new("chains.toml"), &toml, ConfigFormat::Toml).unwrap_err();
+            let expected = format!("server.rate_limits.{field}");
+            assert!(
+                matches!(&error, ConfigError::Invalid { field, .. } if *field == expected),
+                "{error}"
+            );
+        }
+
+        // Syntax and type errors keep the parser's line.
+        let bad_id = VALID_TOML.replace("chain_id = 1\n", "chain_id = \"one\"\n");
+        let error =
+            parse_config(Path::new("chains.toml"), &bad_id, ConfigFormat::Toml).unwrap_err();
+        assert!(error.to_string().contains("line 8"), "{error}");
+        let error =
+            parse_config(Path::new("chains.json"), "{\"chains\": [\n{]}", ConfigFormat::Json)
+                .unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{error}");
+    }
+
+    #[test]
+    fn test_unknown_keys_are_not_fatal() {
+        let extra = format!("poll_interval = 5\n{VALID_TOML}\n[server]\nshutdown_grace_ms = 500\n")
+            .replace("name = \"base\"", "nmae = \"base\"");
+        let (file, unknown) =
+            parse_config(Path::new("chains.toml"), &extra, ConfigFormat::Toml).unwrap();
+        assert_eq!(file.chains.len(), 2);
+        assert_eq!(file.server.shutdown_grace_ms, Some(500));
+        assert_eq!(unknown, ["poll_interval", "chains[0].nmae"]);
+
+        // A misspelt server key would otherwise silently fall back to its default.
+        let server = format!(
+            "{VALID_TOML}\n[server]\nmax_connection = 5\ntls_cert = \"cert.pem\"\n\
+             [server.auth]\ndisable = true\n\
+             [[server.auth.keys]]\nsha256 = \"0x{:064x}\"\nadmn = true\n\
+             [server.cors]\nallowed_origin = [\"https://a.example\"]\n\
+             [server.rate_limits]\ndefaults = {{ requests_per_second = 1, burst = 1 }}\n\
+             methods.logs = {{ requests_per_second = 1, burst = 1, bursts = 2 }}\n",
+            1
+        );
+        let (file, unknown) =
+            parse_config(Path::new("chains.toml"), &server, ConfigFormat::Toml).unwrap();
+        assert_eq!(file.server.max_connections, None);
+        assert_eq!(
+            unknown,
+            [
+                "server.max_connection",
+                "server.tls_cert",
+                "server.auth.disable",
+                "server.auth.keys[0].admn",
+                "server.cors.allowed_origin",
+                "server.rate_limits.defaults",
+                "server.rate_limits.methods.logs.bursts",
+            ]
+        );
+    }
+}
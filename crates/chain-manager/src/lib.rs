@@ -0,0 +1,36 @@
+//! A JSON-RPC server in front of the RPC nodes of many chains, serving verified headers,
+//! receipts, proofs and logs by chain id.
+//!
+//! The `chain-manager` binary is a thin CLI over [`start_server`]; other crates can depend on this
+//! library to talk to a running server through [`ChainManagerHandle`].
+
+pub mod api;
+pub mod auth;
+pub mod cache;
+pub mod client;
+pub mod config;
+pub mod metrics;
+pub mod proofs;
+pub mod rate_limit;
+pub mod retry;
+pub mod server;
+pub mod shutdown;
+pub mod subscriptions;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod tls;
+pub mod trace;
+pub use api::*;
+pub use auth::*;
+pub use cache::*;
+pub use client::*;
+pub use config::*;
+pub use metrics::*;
+pub use proofs::*;
+pub use rate_limit::*;
+pub use retry::*;
+pub use server::*;
+pub use shutdown::*;
+pub use subscriptions::*;
+pub use tls::*;
+pub use trace::*;
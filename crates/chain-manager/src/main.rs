@@ -1,6 +1,132 @@
-pub mod api;
-pub use api::*;
+use std::{net::SocketAddr, path::PathBuf};
 
-fn main() {
-    println!("Hello, world!");
+use chain_manager::*;
+use clap::{Parser, ValueEnum};
+use eyre::WrapErr;
+use jsonrpsee::server::ServerHandle;
+use tracing_subscriber::EnvFilter;
+
+/// Serve the chains of a config file over JSON-RPC.
+#[derive(Parser, Debug)]
+#[command(name = "chain-manager", version, about)]
+struct Cli {
+    /// TOML or JSON file listing the chains under `[[chains]]`, and optionally how clients are
+    /// treated under `[server]`.
+    #[arg(long, value_name = "PATH")]
+    config: PathBuf,
+    /// Address the JSON-RPC server listens on, for HTTP and WebSocket.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:3000")]
+    listen: SocketAddr,
+    /// Also answer `GET /metrics` on this address.
+    #[arg(long, value_name = "ADDR")]
+    metrics_listen: Option<SocketAddr>,
+    /// A `tracing` filter, e.g. `info` or `chain_manager=debug,warn`.
+    #[arg(long, value_name = "FILTER", default_value = "info")]
+    log_level: String,
+    /// How log lines are written.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// For people reading a terminal.
+    Text,
+    /// One JSON object per line, with the fields of the request span it was logged in.
+    Json,
+}
+
+/// Load the config and start serving it, returning the server's handle and the address it
+/// listens on.
+async fn start(cli: &Cli) -> eyre::Result<(ServerHandle, SocketAddr)> {
+    let file = load_config(&cli.config)?;
+    let chains = file.chains.len();
+    let manager = ChainManagerImpl::new(file.chains);
+    if let Some(address) = cli.metrics_listen {
+        let address = manager
+            .metrics()
+            .serve(address)
+            .await
+            .wrap_err_with(|| format!("Could not serve metrics on {address}"))?;
+        tracing::info!("Serving metrics on http://{address}/metrics");
+    }
+    let (handle, address) = start_server(cli.listen, manager, file.server)
+        .await
+        .wrap_err_with(|| format!("Could not listen on {}", cli.listen))?;
+    tracing::info!("Serving {chains} chains on {address}");
+    Ok((handle, address))
+}
+
+/// Serve until ctrl-c, then shut down gracefully.
+async fn run(cli: &Cli) -> eyre::Result<()> {
+    let (handle, _) = start(cli).await?;
+    stop_on_ctrl_c(handle.clone()).await.wrap_err("Could not listen for ctrl-c")?;
+    handle.stopped().await;
+    tracing::info!("Stopped");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let cli = Cli::parse();
+    let filter = EnvFilter::try_new(&cli.log_level)
+        .wrap_err_with(|| format!("Invalid --log-level {:?}", cli.log_level))?;
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match cli.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+    run(&cli).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::{eips::BlockNumberOrTag, node_bindings::Anvil};
+    use chain_manager::ChainManagerClient;
+    use jsonrpsee::http_client::HttpClientBuilder;
+    use std::{fs, net::TcpListener};
+
+    fn cli(config: &std::path::Path, listen: &str) -> Cli {
+        let config = config.to_str().expect("A UTF-8 path");
+        Cli::parse_from(["chain-manager", "--config", config, "--listen", listen])
+    }
+
+    #[tokio::test]
+    async fn test_serves_the_config_file() -> eyre::Result<()> {
+        let anvil = Anvil::new().try_spawn()?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chains.toml");
+        let config = format!(
+            "[[chains]]\nchain_id = {}\nrpc_url = \"{}\"\n\n[server.auth]\ndisabled = true\n",
+            anvil.chain_id(),
+            anvil.endpoint()
+        );
+        fs::write(&path, config)?;
+
+        let (handle, address) = start(&cli(&path, "127.0.0.1:0")).await?;
+        let client = HttpClientBuilder::default().build(format!("http://{address}"))?;
+        let header = client.finalised_header(anvil.chain_id(), BlockNumberOrTag::Finalized).await?;
+        assert_eq!(header.number, 0);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_startup_failures() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chains.toml");
+        fs::write(&path, "[[chains]]\nchain_id = 1\nrpc_url = \"localhost\"\n")?;
+        let error = start(&cli(&path, "127.0.0.1:0")).await.unwrap_err();
+        assert!(error.to_string().contains("`chains[0].rpc_url`"), "{error}");
+
+        fs::write(&path, "[[chains]]\nchain_id = 1\nrpc_url = \"http://127.0.0.1:8545\"\n")?;
+        let taken = TcpListener::bind("127.0.0.1:0")?;
+        let address = taken.local_addr()?.to_string();
+        let error = start(&cli(&path, &address)).await.unwrap_err();
+        assert_eq!(error.to_string(), format!("Could not listen on {address}"));
+        Ok(())
+    }
 }
@@ -0,0 +1,524 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write as _,
+    future::Future,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use alloy::transports::{RpcError, TransportError, TransportErrorKind};
+use jsonrpsee::{
+    core::middleware::{Batch, Notification, RpcServiceT},
+    server::MethodResponse,
+    types::Request,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use crate::server::ACCEPT_ERROR_BACKOFF;
+
+/// Upper bounds, in seconds, of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// How long a scrape may take to send its request headers before the connection is dropped.
+const SCRAPE_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Label values, rendered as `{name="value",..}`, to what was counted under them.
+type Series<V> = Mutex<BTreeMap<String, V>>;
+
+#[derive(Clone, Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters of the chain manager, rendered in the Prometheus text format by [`Metrics::render`].
+/// Chain ids outside those configured are labelled `unknown` so clients cannot add series.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    chain_ids: RwLock<HashSet<u64>>,
+    requests: Series<u64>,
+    latency: Series<Histogram>,
+    permit_wait: Series<Histogram>,
+    cache_hits: Series<u64>,
+    cache_misses: Series<u64>,
+    header_cache_hits: Series<u64>,
+    header_cache_misses: Series<u64>,
+    receipt_cache_hits: Series<u64>,
+    receipt_cache_misses: Series<u64>,
+    upstream_errors: Series<u64>,
+    provider_evictions: Series<u64>,
+    finalised_blocks: Series<u64>,
+    providers: AtomicU64,
+}
+
+/// `value` as a label value of the text format, with backslashes, `"` and line feeds escaped.
+fn escape(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', r#"\""#).replace('\n', r"\n")
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> =
+        pairs.iter().map(|(name, value)| format!("{name}=\"{}\"", escape(value))).collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn bump(series: &Series<u64>, labels: String) {
+    *series.lock().expect("metrics lock").entry(labels).or_default() += 1;
+}
+
+/// What kind of failure an upstream call ended in.
+pub(crate) fn error_class(error: &TransportError) -> &'static str {
+    match error {
+        RpcError::ErrorResp(_) => "rpc",
+        RpcError::Transport(TransportErrorKind::HttpError(_)) => "http",
+        RpcError::Transport(_) => "transport",
+        RpcError::NullResp | RpcError::DeserError { .. } => "response",
+        _ => "other",
+    }
+}
+
+impl Metrics {
+    pub fn new(chain_ids: impl IntoIterator<Item = u64>) -> Self {
+        Self { chain_ids: RwLock::new(chain_ids.into_iter().collect()), ..Default::default() }
+    }
+
+    /// Label the series of `chain_id` with it from now on, for chains added at runtime.
+    pub(crate) fn add_chain(&self, chain_id: u64) {
+        self.chain_ids.write().expect("metrics lock").insert(chain_id);
+    }
+
+    fn chain_label(&self, chain_id: u64) -> String {
+        match self.chain_ids.read().expect("metrics lock").contains(&chain_id) {
+            true => chain_id.to_string(),
+            false => "unknown".into(),
+        }
+    }
+
+    /// Count a served request, and how long it took. `chain_id` is `None` for methods not tied to
+    /// a chain.
+    pub fn observe_request(
+        &self,
+        method: &str,
+        chain_id: Option<u64>,
+        ok: bool,
+        elapsed: Duration,
+    ) {
+        let chain = chain_id.map_or_else(String::new, |chain_id| self.chain_label(chain_id));
+        let status = if ok { "ok" } else { "error" };
+        bump(
+            &self.requests,
+            labels(&[("method", method), ("chain_id", &chain), ("status", status)]),
+        );
+        let mut latency = self.latency.lock().expect("metrics lock");
+        latency
+            .entry(labels(&[("method", method), ("chain_id", &chain)]))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    /// How long an upstream call of `chain_id` waited for one of its concurrent request permits.
+    pub(crate) fn observe_permit_wait(&self, chain_id: u64, waited: Duration) {
+        let labels = labels(&[("chain_id", &self.chain_label(chain_id))]);
+        self.permit_wait.lock().expect("metrics lock").entry(labels).or_default().observe(waited);
+    }
+
+    pub(crate) fn provider_cache(&self, chain_id: u64, hit: bool) {
+        let series = if hit { &self.cache_hits } else { &self.cache_misses };
+        bump(series, labels(&[("chain_id", &self.chain_label(chain_id))]));
+    }
+
+    pub(crate) fn header_cache(&self, chain_id: u64, hit: bool) {
+        let series = if hit { &self.header_cache_hits } else { &self.header_cache_misses };
+        bump(series, labels(&[("chain_id", &self.chain_label(chain_id))]));
+    }
+
+    pub(crate) fn receipt_cache(&self, chain_id: u64, hit: bool) {
+        let series = if hit { &self.receipt_cache_hits } else { &self.receipt_cache_misses };
+        bump(series, labels(&[("chain_id", &self.chain_label(chain_id))]));
+    }
+
+    pub(crate) fn upstream_error(&self, chain_id: u64, class: &str) {
+        bump(
+            &self.upstream_errors,
+            labels(&[("chain_id", &self.chain_label(chain_id)), ("class", class)]),
+        );
+    }
+
+    pub(crate) fn provider_evicted(&self, chain_id: u64) {
+        bump(&self.provider_evictions, labels(&[("chain_id", &self.chain_label(chain_id))]));
+    }
+
+    /// Record the number `finalized` last resolved to on `chain_id`, and the `mode` it was
+    /// resolved with.
+    pub(crate) fn finalised_block(&self, chain_id: u64, mode: &str, number: u64) {
+        let labels = labels(&[("chain_id", &self.chain_label(chain_id)), ("mode", mode)]);
+        self.finalised_blocks.lock().expect("metrics lock").insert(labels, number);
+    }
+
+    pub(crate) fn set_providers(&self, providers: usize) {
+        self.providers.store(providers as u64, Ordering::Relaxed);
+    }
+
+    /// All series in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("chain_manager_requests_total", "Requests served", &self.requests),
+            ("chain_manager_provider_cache_hits_total", "Providers found cached", &self.cache_hits),
+            (
+                "chain_manager_provider_cache_misses_total",
+                "Providers connected on demand",
+                &self.cache_misses,
+            ),
+            (
+                "chain_manager_header_cache_hits_total",
+                "Headers served from the cache",
+                &self.header_cache_hits,
+            ),
+            (
+                "chain_manager_header_cache_misses_total",
+                "Headers not found in the cache",
+                &self.header_cache_misses,
+            ),
+            (
+                "chain_manager_receipt_cache_hits_total",
+                "Receipts served from the cache",
+                &self.receipt_cache_hits,
+            ),
+            (
+                "chain_manager_receipt_cache_misses_total",
+                "Receipts not found in the cache",
+                &self.receipt_cache_misses,
+            ),
+            ("chain_manager_upstream_errors_total", "Failed upstream calls", &self.upstream_errors),
+            (
+                "chain_manager_provider_evictions_total",
+                "Providers dropped after failing, to be connected again",
+                &self.provider_evictions,
+            ),
+        ];
+        for (name, help, series) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}.\n# TYPE {name} counter");
+            for (labels, value) in series.lock().expect("metrics lock").iter() {
+                let _ = writeln!(out, "{name}{labels} {value}");
+            }
+        }
+
+        let histograms = [
+            (
+                "chain_manager_request_duration_seconds",
+                "Time spent serving requests",
+                &self.latency,
+            ),
+            (
+                "chain_manager_permit_wait_seconds",
+                "Time upstream calls waited for a concurrent request permit",
+                &self.permit_wait,
+            ),
+        ];
+        for (name, help, series) in histograms {
+            let _ = writeln!(out, "# HELP {name} {help}.\n# TYPE {name} histogram");
+            for (labels, histogram) in series.lock().expect("metrics lock").iter() {
+                // `labels` ends in `}`, the bucket bound goes in front of it.
+                let inner = &labels[1..labels.len() - 1];
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                    let _ = writeln!(out, "{name}_bucket{{{inner},le=\"{bound}\"}} {count}");
+                }
+                let _ = writeln!(out, "{name}_bucket{{{inner},le=\"+Inf\"}} {}", histogram.count);
+                let _ = writeln!(out, "{name}_sum{labels} {}", histogram.sum);
+                let _ = writeln!(out, "{name}_count{labels} {}", histogram.count);
+            }
+        }
+
+        let name = "chain_manager_finalised_block";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Number the finalized tag last resolved to.\n# TYPE {name} gauge"
+        );
+        for (labels, number) in self.finalised_blocks.lock().expect("metrics lock").iter() {
+            let _ = writeln!(out, "{name}{labels} {number}");
+        }
+
+        let name = "chain_manager_providers";
+        let _ = writeln!(out, "# HELP {name} Providers currently cached.\n# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {}", self.providers.load(Ordering::Relaxed));
+        out
+    }
+
+    /// Answer `GET /metrics` on `address` with [`Metrics::render`] for as long as the process
+    /// runs. Returns the address listened on.
+    pub async fn serve(self: Arc<Self>, address: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address).await?;
+        let local_addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(self.clone().answer(stream));
+                    }
+                    Err(error) => {
+                        tracing::warn!("Failed to accept a metrics connection: {error}");
+                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    }
+                }
+            }
+        });
+        Ok(local_addr)
+    }
+
+    async fn answer(self: Arc<Self>, mut stream: TcpStream) {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        let read = async {
+            while !request.ends_with(b"\r\n\r\n") && request.len() < 8192 {
+                match stream.read(&mut buffer).await {
+                    Ok(0) | Err(_) => return false,
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            }
+            true
+        };
+        if !matches!(tokio::time::timeout(SCRAPE_READ_TIMEOUT, read).await, Ok(true)) {
+            return
+        }
+        let (status, body) = if request.starts_with(b"GET /metrics ") {
+            ("200 OK", self.render())
+        } else {
+            ("404 Not Found", String::new())
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\ncontent-type: text/plain; version=0.0.4\r\n\
+             content-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+}
+
+/// RPC middleware counting and timing every call in [`Metrics`], labelled by its method and the
+/// chain id it was given as first parameter. Methods outside `methods`, the ones the server
+/// registered, are labelled `unknown`, as the calls are counted before they are authenticated.
+#[derive(Clone, Debug)]
+pub struct MetricsService<S> {
+    service: S,
+    metrics: Arc<Metrics>,
+    methods: Arc<HashSet<&'static str>>,
+}
+
+impl<S> MetricsService<S> {
+    pub fn new(service: S, metrics: Arc<Metrics>, methods: Arc<HashSet<&'static str>>) -> Self {
+        Self { service, metrics, methods }
+    }
+}
+
+/// The chain id a request is for, its first or `chain_id` parameter.
+pub(crate) fn chain_id_param(request: &Request<'_>) -> Option<u64> {
+    let params = request.params();
+    params.sequence().next::<u64>().ok().or_else(|| {
+        let named: serde_json::Value = params.parse().ok()?;
+        named.get("chain_id")?.as_u64()
+    })
+}
+
+impl<S> RpcServiceT for MetricsService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        // Only methods that exist get their own series.
+        let method = self.methods.get(request.method_name()).copied().unwrap_or("unknown");
+        let chain_id = chain_id_param(&request);
+        let (service, metrics) = (self.service.clone(), self.metrics.clone());
+        async move {
+            let start = Instant::now();
+            let response = service.call(request).await;
+            metrics.observe_request(method, chain_id, response.is_success(), start.elapsed());
+            response
+        }
+    }
+
+    fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let (service, metrics) = (self.service.clone(), self.metrics.clone());
+        async move {
+            let start = Instant::now();
+            let response = service.batch(batch).await;
+            metrics.observe_request("batch", None, response.is_success(), start.elapsed());
+            response
+        }
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.service.notification(notification)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{labels, Metrics, SCRAPE_READ_TIMEOUT};
+    use crate::{
+        start_server, AuthConfig, ChainConfig, ChainManagerClient, ChainManagerImpl, RetryPolicy,
+        ServerConfig,
+    };
+    use alloy::primitives::B256;
+    use jsonrpsee::http_client::HttpClientBuilder;
+    use std::sync::Arc;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(
+            labels(&[("method", "a\\b\"c\nd"), ("chain_id", "1")]),
+            r#"{method="a\\b\"c\nd",chain_id="1"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stalled_scrapes_are_dropped() -> Result<(), Box<dyn std::error::Error>> {
+        let address = Arc::new(Metrics::new([1])).serve("127.0.0.1:0").await?;
+        let mut stalled = TcpStream::connect(address).await?;
+        stalled.write_all(b"GET /metrics").await?;
+        let read =
+            tokio::time::timeout(SCRAPE_READ_TIMEOUT * 2, stalled.read(&mut [0; 64])).await?;
+        assert_eq!(read?, 0, "closed without an answer");
+
+        let mut scrape = TcpStream::connect(address).await?;
+        scrape.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").await?;
+        let mut response = String::new();
+        scrape.read_to_string(&mut response).await?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        Ok(())
+    }
+
+    /// The value of the series named exactly `series` in a scrape.
+    fn sample(scrape: &str, series: &str) -> Option<f64> {
+        scrape.lines().find_map(|line| line.strip_prefix(series)?.strip_prefix(' ')?.parse().ok())
+    }
+
+    #[tokio::test]
+    async fn test_scrape_after_calls() -> Result<(), Box<dyn std::error::Error>> {
+        let configs = vec![ChainConfig {
+            chain_id: 1,
+            rpc_url: "http://127.0.0.1:1".into(),
+            retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+            // Connect without asking the missing node for its chain id, so it is cached and
+            // dropped.
+            skip_chain_id_check: true,
+            ..Default::default()
+        }];
+        let manager = ChainManagerImpl::new(configs);
+        let metrics_address = manager.metrics().serve("127.0.0.1:0").await?;
+        let config = ServerConfig {
+            auth: AuthConfig { disabled: true, ..Default::default() },
+            ..Default::default()
+        };
+        let (handle, address) = start_server("127.0.0.1:0", manager, config).await?;
+        let client = HttpClientBuilder::default().build(format!("http://{address}"))?;
+
+        for _ in 0..2 {
+            assert!(client.transaction_receipt(1, B256::ZERO).await.is_err());
+        }
+        assert!(client.transaction_receipt(77, B256::ZERO).await.is_err());
+        client.list_chains().await?;
+
+        let scrape =
+            reqwest::get(format!("http://{metrics_address}/metrics")).await?.text().await?;
+        let requests = "chain_manager_requests_total";
+        for (series, expected) in [
+            (
+                format!(r#"{requests}{{method="transactionReceipt",chain_id="1",status="error"}}"#),
+                2.0,
+            ),
+            (
+                format!(
+                    r#"{requests}{{method="transactionReceipt",chain_id="unknown",status="error"}}"#
+                ),
+                1.0,
+            ),
+            (format!(r#"{requests}{{method="listChains",chain_id="",status="ok"}}"#), 1.0),
+            (
+                r#"chain_manager_request_duration_seconds_count{method="transactionReceipt",chain_id="1"}"#
+                    .into(),
+                2.0,
+            ),
+            // The provider never answers, so every failed call drops it and the next connects.
+            (r#"chain_manager_provider_cache_misses_total{chain_id="1"}"#.into(), 3.0),
+            (r#"chain_manager_upstream_errors_total{chain_id="1",class="transport"}"#.into(), 3.0),
+            (r#"chain_manager_provider_evictions_total{chain_id="1"}"#.into(), 3.0),
+            ("chain_manager_providers".into(), 0.0),
+        ] {
+            assert_eq!(sample(&scrape, &series), Some(expected), "{series} in\n{scrape}");
+        }
+        let not_found = reqwest::get(format!("http://{metrics_address}/")).await?;
+        assert_eq!(not_found.status(), 404);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_methods_share_a_series() -> Result<(), Box<dyn std::error::Error>> {
+        let manager = ChainManagerImpl::new(Vec::new());
+        let metrics_address = manager.metrics().serve("127.0.0.1:0").await?;
+        // With auth on, the calls are rejected before any method is looked up.
+        let (handle, address) = start_server("127.0.0.1:0", manager, Default::default()).await?;
+        let http = reqwest::Client::new();
+        for method in ["madeUp1", "madeUp2", "listChains"] {
+            let body = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": method});
+            http.post(format!("http://{address}")).json(&body).send().await?;
+        }
+
+        let scrape =
+            reqwest::get(format!("http://{metrics_address}/metrics")).await?.text().await?;
+        let requests = "chain_manager_requests_total";
+        let unknown = format!(r#"{requests}{{method="unknown",chain_id="",status="error"}}"#);
+        assert_eq!(sample(&scrape, &unknown), Some(2.0), "{scrape}");
+        let list_chains =
+            format!(r#"{requests}{{method="listChains",chain_id="",status="error"}}"#);
+        assert_eq!(sample(&scrape, &list_chains), Some(1.0), "{scrape}");
+        assert!(!scrape.contains("madeUp"), "{scrape}");
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use alloy::{
+    consensus::{Header, ReceiptEnvelope},
+    eips::Encodable2718,
+    primitives::{Bytes, B256},
+    rlp,
+    trie::{
+        proof::{verify_proof, ProofRetainer, ProofVerificationError},
+        root::adjust_index_for_rlp,
+        HashBuilder, Nibbles, EMPTY_ROOT_HASH,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// A receipt with the receipts trie nodes proving it is part of its block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptProof {
+    pub header: Header,
+    /// The root the proof leads to, the same as `header.receipts_root`.
+    pub receipts_root: B256,
+    /// The receipt's key in the trie, its transaction index RLP encoded.
+    pub key: Bytes,
+    /// The EIP-2718 encoding of the receipt, the value under `key`.
+    pub receipt: Bytes,
+    /// The trie nodes on the path from the root down to the receipt.
+    pub proof: Vec<Bytes>,
+}
+
+impl ReceiptProof {
+    /// Check that the proof leads from the header's receipts root to the receipt.
+    pub fn verify(&self) -> Result<(), ProofVerificationError> {
+        if self.receipts_root != self.header.receipts_root {
+            return Err(ProofVerificationError::RootMismatch {
+                got: self.receipts_root,
+                expected: self.header.receipts_root,
+            })
+        }
+        verify_proof(
+            self.receipts_root,
+            Nibbles::unpack(&self.key),
+            Some(self.receipt.to_vec()),
+            &self.proof,
+        )
+    }
+}
+
+/// The key of the receipt or transaction at `index` in its block's tries.
+pub fn trie_key(index: usize) -> Bytes {
+    rlp::encode_fixed_size(&index).to_vec().into()
+}
+
+/// Build the receipts trie of a block from its receipts, in transaction order, and return its
+/// root and the proof for the receipt at `index`.
+pub fn receipts_trie_proof(receipts: &[ReceiptEnvelope], index: usize) -> (B256, Vec<Bytes>) {
+    if receipts.is_empty() {
+        return (EMPTY_ROOT_HASH, Vec::new())
+    }
+    let target = Nibbles::unpack(trie_key(index));
+    let mut builder = HashBuilder::default().with_proof_retainer(ProofRetainer::new(vec![target]));
+    // Leaves have to be added in key order, and RLP sorts index 0 after indices 1 to 127.
+    for i in 0..receipts.len() {
+        let index = adjust_index_for_rlp(i, receipts.len());
+        builder.add_leaf(Nibbles::unpack(trie_key(index)), &receipts[index].encoded_2718());
+    }
+    let root = builder.root();
+    let proof = builder
+        .take_proof_nodes()
+        .matching_nodes_sorted(&target)
+        .into_iter()
+        .map(|(_, node)| node)
+        .collect();
+    (root, proof)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::{
+        consensus::{proofs::calculate_receipt_root, Receipt, ReceiptWithBloom},
+        primitives::{Address, Log, LogData},
+    };
+
+    fn receipt(index: usize) -> ReceiptEnvelope {
+        let log = Log {
+            address: Address::repeat_byte(index as u8),
+            data: LogData::new_unchecked(vec![B256::repeat_byte(1)], Bytes::from(vec![7; index])),
+        };
+        let receipt = Receipt {
+            status: (!index.is_multiple_of(3)).into(),
+            cumulative_gas_used: 21_000 * (index as u64 + 1),
+            logs: vec![log],
+        };
+        let receipt = ReceiptWithBloom::from(receipt);
+        match index % 2 {
+            0 => ReceiptEnvelope::Legacy(receipt),
+            _ => ReceiptEnvelope::Eip1559(receipt),
+        }
+    }
+
+    #[test]
+    fn test_proof_for_every_receipt() {
+        // Past 128 receipts the keys get longer and 0x80 follows 0x7f out of order.
+        for count in [1, 2, 16, 130] {
+            let receipts: Vec<_> = (0..count).map(receipt).collect();
+            let header =
+                Header { receipts_root: calculate_receipt_root(&receipts), ..Default::default() };
+            for index in 0..count {
+                let (root, proof) = receipts_trie_proof(&receipts, index);
+                assert_eq!(root, header.receipts_root);
+                let proof = ReceiptProof {
+                    header: header.clone(),
+                    receipts_root: root,
+                    key: trie_key(index),
+                    receipt: receipts[index].encoded_2718().into(),
+                    proof,
+                };
+                assert_eq!(proof.verify(), Ok(()), "receipt {index} of {count}");
+
+                let wrong = ReceiptProof {
+                    receipt: receipts[(index + 1) % count].encoded_2718().into(),
+                    ..proof
+                };
+                assert!(count == 1 || wrong.verify().is_err());
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use jsonrpsee::{
+    core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT},
+    server::MethodResponse,
+    types::{ErrorObjectOwned, Request},
+};
+use serde::Deserialize;
+
+use crate::ChainManagerError;
+
+/// The address a request came from, attached to it by [`start_server`](crate::start_server).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ClientIp(pub IpAddr);
+
+/// A token bucket: `burst` requests at once, refilled at `requests_per_second`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+/// The limits each client IP is held to.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    /// Shared by every method without an entry in `methods`. `None` leaves them unlimited.
+    pub default: Option<RateLimit>,
+    /// Methods limited on their own, e.g. to make `logs` dearer than `transactionReceipt`.
+    pub methods: HashMap<String, RateLimit>,
+}
+
+impl RateLimits {
+    /// Check that every limit holds a token and refills, returning the offending key and why
+    /// otherwise.
+    pub(crate) fn validate(&self) -> Result<(), (String, String)> {
+        let methods =
+            self.methods.iter().map(|(method, limit)| (format!("methods.{method}"), limit));
+        for (key, limit) in
+            self.default.iter().map(|limit| ("default".into(), limit)).chain(methods)
+        {
+            // Also false for NaN.
+            if !(limit.requests_per_second > 0.0 && limit.requests_per_second.is_finite()) {
+                return Err((
+                    format!("{key}.requests_per_second"),
+                    format!("must be a positive number, not {}", limit.requests_per_second),
+                ))
+            }
+            if limit.burst == 0 {
+                return Err((format!("{key}.burst"), "must be at least 1".into()))
+            }
+        }
+        Ok(())
+    }
+
+    fn limit(&self, method: Option<&str>) -> Option<&RateLimit> {
+        match method {
+            Some(method) => self.methods.get(method),
+            None => self.default.as_ref(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self { tokens: f64::from(limit.burst), refilled_at: now }
+    }
+
+    /// Whether the bucket has refilled by `now`, and so is no different from a new one.
+    fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens + elapsed * limit.requests_per_second >= f64::from(limit.burst)
+    }
+
+    /// Take a token, or say how long until one is available.
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * limit.requests_per_second).min(f64::from(limit.burst));
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(())
+        }
+        Err(Duration::try_from_secs_f64((1.0 - self.tokens) / limit.requests_per_second)
+            .unwrap_or(Duration::MAX))
+    }
+}
+
+/// How often [`RateLimiter`] drops the buckets that have refilled.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The buckets of every client, one for the methods under `default` and one per method with its
+/// own limit. Buckets that have refilled are dropped every [`SWEEP_INTERVAL`], so clients that come
+/// and go do not add up.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: DashMap<(IpAddr, Option<String>), Bucket>,
+    swept_at: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits, buckets: Default::default(), swept_at: Mutex::new(Instant::now()) }
+    }
+
+    /// Drop the buckets full by `now`, which [`Self::check`] would create again as they were.
+    fn sweep(&self, now: Instant) {
+        self.buckets.retain(|(_, method), bucket| {
+            self.limits.limit(method.as_deref()).is_some_and(|limit| !bucket.is_full(limit, now))
+        });
+    }
+
+    /// Count a call of `method` from `ip` against its limit.
+    pub fn check(&self, ip: IpAddr, method: &str) -> Result<(), ChainManagerError> {
+        let (key, limit) = match self.limits.methods.get(method) {
+            Some(limit) => (Some(method.to_string()), limit),
+            None => match &self.limits.default {
+                Some(limit) => (None, limit),
+                None => return Ok(()),
+            },
+        };
+        let now = Instant::now();
+        {
+            let mut swept_at = self.swept_at.lock().expect("rate limiter lock");
+            if now.saturating_duration_since(*swept_at) >= SWEEP_INTERVAL {
+                *swept_at = now;
+                self.sweep(now);
+            }
+        }
+        let mut bucket = self.buckets.entry((ip, key)).or_insert_with(|| Bucket::full(limit, now));
+        bucket.take(limit, now).map_err(|wait| ChainManagerError::RateLimited {
+            reason: format!("Too many {method} requests from {ip}"),
+            retry_after_ms: wait.as_millis().try_into().unwrap_or(u64::MAX).max(1),
+        })
+    }
+}
+
+/// RPC middleware rejecting calls over their [`RateLimits`] with `RateLimited`. Requests are
+/// only limited if they carry a [`ClientIp`], batches count every call in them.
+#[derive(Clone, Debug)]
+pub struct RateLimitService<S> {
+    service: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> RateLimitService<S> {
+    pub fn new(service: S, limiter: Arc<RateLimiter>) -> Self {
+        Self { service, limiter }
+    }
+
+    fn check(&self, request: &Request<'_>) -> Result<(), ErrorObjectOwned> {
+        let Some(ClientIp(ip)) = request.extensions.get::<ClientIp>() else { return Ok(()) };
+        Ok(self.limiter.check(*ip, &request.method)?)
+    }
+}
+
+impl<S> RpcServiceT for RateLimitService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Clone + Send + Sync + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let checked = self.check(&request);
+        let service = self.service.clone();
+        async move {
+            match checked {
+                Ok(()) => service.call(request).await,
+                Err(error) => MethodResponse::error(request.id, error),
+            }
+        }
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        for entry in batch.iter_mut() {
+            let Ok(BatchEntry::Call(request)) = entry else { continue };
+            if let Err(error) = self.check(request) {
+                *entry = Err(BatchEntryErr::new(request.id.clone(), error));
+            }
+        }
+        self.service.batch(batch)
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.service.notification(notification)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_at_its_rate() {
+        let limit = RateLimit { requests_per_second: 10.0, burst: 2 };
+        let start = Instant::now();
+        let mut bucket = Bucket::full(&limit, start);
+        assert_eq!(bucket.take(&limit, start), Ok(()));
+        assert_eq!(bucket.take(&limit, start), Ok(()));
+        let wait = bucket.take(&limit, start).unwrap_err();
+        assert_eq!(wait.as_millis(), 100);
+
+        assert!(bucket.take(&limit, start + Duration::from_millis(50)).is_err());
+        assert_eq!(bucket.take(&limit, start + Duration::from_millis(100)), Ok(()));
+        // Idle time refills no further than the burst.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(&limit, later), Ok(()));
+        assert_eq!(bucket.take(&limit, later), Ok(()));
+        assert!(bucket.take(&limit, later).is_err());
+    }
+
+    #[test]
+    fn test_refilled_buckets_are_swept() {
+        let limit = RateLimit { requests_per_second: 10.0, burst: 2 };
+        let limits =
+            RateLimits { methods: [("logs".to_string(), limit)].into(), ..Default::default() };
+        let limiter = RateLimiter::new(limits);
+        let (client, other) = ([127, 0, 0, 1].into(), [127, 0, 0, 2].into());
+        limiter.check(client, "logs").unwrap();
+        limiter.check(client, "logs").unwrap();
+        limiter.check(other, "logs").unwrap();
+        assert_eq!(limiter.buckets.len(), 2);
+
+        let start = Instant::now();
+        // A bucket taken from is kept until it is full again.
+        limiter.sweep(start);
+        assert_eq!(limiter.buckets.len(), 2);
+        limiter.sweep(start + Duration::from_millis(150));
+        assert_eq!(limiter.buckets.len(), 1);
+        limiter.sweep(start + Duration::from_millis(250));
+        assert!(limiter.buckets.is_empty());
+    }
+
+    #[test]
+    fn test_method_limits_are_separate() {
+        let limits = RateLimits {
+            default: Some(RateLimit { requests_per_second: 0.0, burst: 1 }),
+            methods: [("logs".to_string(), RateLimit { requests_per_second: 0.0, burst: 1 })]
+                .into(),
+        };
+        let limiter = RateLimiter::new(limits);
+        let (client, other) = ([127, 0, 0, 1].into(), [127, 0, 0, 2].into());
+        assert!(limiter.check(client, "transactionReceipt").is_ok());
+        // Every method under the default shares one bucket.
+        assert!(matches!(
+            limiter.check(client, "finalisedHeader"),
+            Err(ChainManagerError::RateLimited { .. })
+        ));
+        assert!(limiter.check(client, "logs").is_ok());
+        assert!(limiter.check(client, "logs").is_err());
+        assert!(limiter.check(other, "logs").is_ok());
+
+        let unlimited = RateLimiter::new(RateLimits::default());
+        for _ in 0..100 {
+            assert!(unlimited.check(client, "logs").is_ok());
+        }
+    }
+}
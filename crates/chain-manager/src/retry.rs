@@ -0,0 +1,152 @@
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use alloy::transports::{RpcError, TransportErrorKind, TransportResult};
+use serde::{Deserialize, Serialize};
+
+/// How upstream calls of one chain are retried, as set under `[chains.retry]` in the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts per call, the first one included. 1 disables retries.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each one after it.
+    pub base_delay_ms: u64,
+    /// Add up to half the wait again at random, so callers that failed together do not retry
+    /// together.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay_ms: 200, jitter: true }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait after failed attempt number `attempt`, counting from 1.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.base_delay_ms.saturating_mul(1 << (attempt - 1).min(16));
+        // Sub-second clock noise is random enough to spread retries apart.
+        let jitter = if self.jitter {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .subsec_nanos();
+            u64::from(nanos) % (delay / 2 + 1)
+        } else {
+            0
+        };
+        Duration::from_millis(delay + jitter)
+    }
+}
+
+/// Whether `error` is worth retrying: the upstream could not be reached or answered with a 5xx.
+/// JSON-RPC error responses, including reverts, and malformed responses are final.
+pub(crate) fn is_transient(error: &RpcError<TransportErrorKind>) -> bool {
+    match error {
+        RpcError::Transport(TransportErrorKind::HttpError(error)) => error.status >= 500,
+        RpcError::Transport(TransportErrorKind::PubsubUnavailable) => false,
+        RpcError::Transport(_) => true,
+        _ => false,
+    }
+}
+
+/// Run `call` until it succeeds, fails with an error that is not [`is_transient`], or has been
+/// attempted `policy.max_attempts` times. A call that returns `Ok(None)` is not retried. An
+/// attempt failing before it reaches the upstream, with the outer `Err`, ends the call at once.
+pub(crate) async fn with_retry<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    mut call: F,
+) -> Result<TransportResult<T>, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<TransportResult<T>, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call().await? {
+            Err(error) if attempt < policy.max_attempts && is_transient(&error) => {
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+            result => return Ok(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy::transports::HttpError;
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    fn http_error(status: u16) -> RpcError<TransportErrorKind> {
+        TransportErrorKind::HttpError(HttpError { status, body: String::new() }).into()
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy { jitter: false, ..Default::default() };
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(800));
+        let jittered = RetryPolicy::default().delay(2);
+        assert!(jittered >= Duration::from_millis(400) && jittered <= Duration::from_millis(600));
+    }
+
+    #[tokio::test]
+    async fn test_retries_only_transient_errors() {
+        let policy = RetryPolicy { base_delay_ms: 1, ..Default::default() };
+        let attempts = AtomicU32::new(0);
+        let result = with_retry(&policy, || async {
+            Ok::<_, Infallible>(match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(http_error(502)),
+                _ => Ok(Some(7)),
+            })
+        })
+        .await;
+        assert_eq!(result.unwrap().unwrap(), Some(7));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let final_errors: [fn() -> RpcError<TransportErrorKind>; 2] =
+            [|| http_error(404), || RpcError::NullResp];
+        for final_error in final_errors {
+            let attempts = AtomicU32::new(0);
+            let result: TransportResult<()> = with_retry(&policy, || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, Infallible>(Err(final_error()))
+            })
+            .await
+            .unwrap();
+            assert!(result.is_err());
+            assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        }
+
+        let attempts = AtomicU32::new(0);
+        let result: TransportResult<()> = with_retry(&policy, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, Infallible>(Err(http_error(503)))
+        })
+        .await
+        .unwrap();
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), policy.max_attempts);
+
+        // Failing before the upstream is not retried.
+        let attempts = AtomicU32::new(0);
+        let result: Result<TransportResult<()>, &str> = with_retry(&policy, || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(Err(http_error(503))),
+                _ => Err("no permit"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap_err(), "no permit");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,501 @@
+use std::{collections::HashSet, io, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use http::{header, HeaderValue, Method};
+use jsonrpsee::{
+    core::middleware::RpcServiceBuilder,
+    server::{
+        middleware::http::HostFilterLayer, serve_with_graceful_shutdown, stop_channel,
+        ConnectionGuard, Methods, Server, ServerHandle,
+    },
+};
+use serde::Deserialize;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tower_http::{
+    add_extension::AddExtensionLayer,
+    cors::{AllowOrigin, CorsLayer},
+};
+
+use crate::{
+    tag_api_key, AuthConfig, AuthService, Authenticator, CertResolver, ChainManagerImpl,
+    ChainManagerServer, ClientIp, MetricsService, RateLimitService, RateLimiter, RateLimits,
+    ShutdownService, TlsError, TraceService, DEFAULT_SHUTDOWN_GRACE, DEFAULT_TLS_HANDSHAKE_TIMEOUT,
+};
+
+/// Largest request body served unless a server sets `max_request_body_bytes`, 10 MiB.
+pub const DEFAULT_MAX_REQUEST_BODY_BYTES: u32 = 10 * 1024 * 1024;
+
+/// Most connections held open at once unless a server sets `max_connections`.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 512;
+
+/// How long the accept loop backs off after failing to accept, e.g. out of file descriptors.
+pub(crate) const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Which browser origins may call the server, as set under `[server.cors]`. Neither set means no
+/// CORS headers, so browsers only allow same-origin calls.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Origins allowed to call, e.g. `https://status.example.com`.
+    pub allowed_origins: Vec<String>,
+    /// Allow every origin, for local development.
+    pub permissive: bool,
+}
+
+impl CorsConfig {
+    /// The layer answering preflight requests and adding CORS headers, if any origin is allowed.
+    fn layer(&self) -> io::Result<Option<CorsLayer>> {
+        if self.permissive {
+            return Ok(Some(CorsLayer::permissive()))
+        }
+        if self.allowed_origins.is_empty() {
+            return Ok(None)
+        }
+        let origins = self
+            .allowed_origins
+            .iter()
+            .map(|origin| match origin.as_str() {
+                "*" => Err(invalid_input(
+                    "`cors.allowed_origins` cannot hold `*`, set `cors.permissive`",
+                )),
+                origin => HeaderValue::from_str(origin).map_err(|_| {
+                    invalid_input(format!("`cors.allowed_origins` holds {origin:?}, not an origin"))
+                }),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let layer = CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]);
+        Ok(Some(layer))
+    }
+}
+
+fn invalid_input(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
+
+/// How the server treats its clients.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub rate_limits: RateLimits,
+    pub auth: AuthConfig,
+    /// Overrides [`DEFAULT_SHUTDOWN_GRACE`].
+    pub shutdown_grace_ms: Option<u64>,
+    /// PEM certificate chain to serve HTTPS and WSS with, together with `tls_key_path`.
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key of `tls_cert_path`.
+    pub tls_key_path: Option<PathBuf>,
+    /// Overrides [`DEFAULT_TLS_HANDSHAKE_TIMEOUT`].
+    pub tls_handshake_timeout_ms: Option<u64>,
+    pub cors: CorsConfig,
+    /// Overrides [`DEFAULT_MAX_REQUEST_BODY_BYTES`], larger requests fail with HTTP 413.
+    pub max_request_body_bytes: Option<u32>,
+    /// `Host` headers served, e.g. `rpc.example.com` or `localhost:*`. Requests for other hosts
+    /// fail with HTTP 403; empty serves every host.
+    pub allowed_hosts: Vec<String>,
+    /// Overrides [`DEFAULT_MAX_CONNECTIONS`], connections past it are closed once accepted.
+    pub max_connections: Option<u32>,
+}
+
+impl ServerConfig {
+    /// How long a stopping server waits for the calls in flight.
+    pub fn shutdown_grace(&self) -> Duration {
+        self.shutdown_grace_ms.map_or(DEFAULT_SHUTDOWN_GRACE, Duration::from_millis)
+    }
+
+    /// How long a client gets to finish the TLS handshake.
+    pub fn tls_handshake_timeout(&self) -> Duration {
+        self.tls_handshake_timeout_ms.map_or(DEFAULT_TLS_HANDSHAKE_TIMEOUT, Duration::from_millis)
+    }
+
+    /// The largest request body served, in bytes.
+    pub fn max_request_body_bytes(&self) -> u32 {
+        self.max_request_body_bytes.unwrap_or(DEFAULT_MAX_REQUEST_BODY_BYTES)
+    }
+
+    /// The most connections held open at once.
+    pub fn max_connections(&self) -> u32 {
+        self.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS)
+    }
+}
+
+/// Serve `manager` over HTTP and WebSocket on `address`, asking every call for an API key
+/// allowed to make it, holding every client IP to its rate limits and counting each call in
+/// [`ChainManagerImpl::metrics`]. Returns the handle stopping the server and the address it
+/// listens on.
+///
+/// With `auth.disabled` the server only listens on loopback addresses, failing to start on any
+/// other.
+///
+/// With `tls_cert_path` and `tls_key_path` set the server only speaks TLS, failing to start if the
+/// pair does not load, and picks the files up again on SIGHUP or once they change.
+///
+/// Once stopped the server takes no new connections and rejects new calls with `ShuttingDown`,
+/// waits up to [`ServerConfig::shutdown_grace`] for those in flight and drops the manager's
+/// providers. [`ServerHandle::stopped`] returns after that.
+pub async fn start_server(
+    address: impl ToSocketAddrs,
+    manager: ChainManagerImpl,
+    config: ServerConfig,
+) -> io::Result<(ServerHandle, SocketAddr)> {
+    let tls = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            Some(Arc::new(CertResolver::new(cert_path, key_path).map_err(invalid_input)?))
+        }
+        (None, None) => None,
+        _ => return Err(invalid_input(TlsError::Incomplete)),
+    };
+    let cors = config.cors.layer()?;
+    let host_filter = match config.allowed_hosts.is_empty() {
+        true => None,
+        false => Some(HostFilterLayer::new(config.allowed_hosts.clone()).map_err(|error| {
+            invalid_input(format!("`allowed_hosts` holds an invalid host: {error}"))
+        })?),
+    };
+    let listener = TcpListener::bind(address).await?;
+    let local_addr = listener.local_addr()?;
+    // Without keys anyone who can reach the server could call the admin methods.
+    if config.auth.disabled && !local_addr.ip().is_loopback() {
+        return Err(invalid_input(format!(
+            "`auth.disabled` only serves loopback addresses, not {local_addr}"
+        )))
+    }
+    let (grace, body) = (config.shutdown_grace(), config.max_request_body_bytes());
+    let handshake_timeout = config.tls_handshake_timeout();
+    // Sockets hold a permit until served, but that ends as a WebSocket is upgraded, so the
+    // sessions are held to the same limit by the server's own guard.
+    let max_connections = config.max_connections();
+    let connections = ConnectionGuard::new(max_connections as usize);
+    let limiter = Arc::new(RateLimiter::new(config.rate_limits));
+    let authenticator = Arc::new(Authenticator::new(config.auth));
+    let metrics = manager.metrics();
+    let shutdown = manager.shutdown_handle();
+    let methods: Methods = manager.into_rpc().into();
+    let method_names = Arc::new(methods.method_names().collect::<HashSet<_>>());
+    let in_flight = shutdown.clone();
+    // Throttled and rejected calls are counted too, and guessing keys is throttled.
+    let rpc_middleware = RpcServiceBuilder::new()
+        .layer_fn(move |service| {
+            MetricsService::new(service, metrics.clone(), method_names.clone())
+        })
+        .layer_fn(TraceService::new)
+        .layer_fn(move |service| ShutdownService::new(service, &in_flight))
+        .layer_fn(move |service| RateLimitService::new(service, limiter.clone()))
+        .layer_fn(move |service| AuthService::new(service, authenticator.clone()));
+    let builder = Server::builder()
+        .set_config(
+            jsonrpsee::server::ServerConfig::builder()
+                .max_request_body_size(body)
+                .max_connections(max_connections)
+                .build(),
+        )
+        .set_rpc_middleware(rpc_middleware)
+        .to_service_builder();
+    let (stop_handle, server_handle) = stop_channel();
+    let acceptor = match tls {
+        Some(resolver) => {
+            let acceptor = resolver.acceptor();
+            let hangups = CertResolver::hangups()?;
+            tokio::spawn(resolver.watch(hangups, stop_handle.clone().shutdown()));
+            Some(acceptor)
+        }
+        None => None,
+    };
+
+    // The built-in accept loop does not tell the middleware who is calling, so this one tags
+    // each connection's requests with its peer, and each request with its API key.
+    tokio::spawn(async move {
+        loop {
+            let (socket, remote_addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(error) => {
+                        // Trying again right away spins while out of file descriptors.
+                        tracing::warn!("Failed to accept a connection: {error}");
+                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                        continue
+                    }
+                },
+                _ = stop_handle.clone().shutdown() => break,
+            };
+            let Some(permit) = connections.try_acquire() else {
+                tracing::warn!(
+                    "Closing the connection from {remote_addr}, {} are open already",
+                    connections.max_connections()
+                );
+                continue
+            };
+            let service = builder
+                .clone()
+                .set_http_middleware(
+                    tower::ServiceBuilder::new()
+                        .layer(AddExtensionLayer::new(ClientIp(remote_addr.ip())))
+                        .option_layer(cors.clone())
+                        .option_layer(host_filter.clone())
+                        .map_request(tag_api_key),
+                )
+                .build(methods.clone(), stop_handle.clone());
+            let (acceptor, stopped) = (acceptor.clone(), stop_handle.clone().shutdown());
+            tokio::spawn(async move {
+                let _permit = permit;
+                let Some(acceptor) = acceptor else {
+                    return serve_with_graceful_shutdown(socket, service, stopped).await
+                };
+                // Dropping the handshake closes the socket, so stalled clients give back their
+                // connection permit.
+                match tokio::time::timeout(handshake_timeout, acceptor.accept(socket)).await {
+                    Ok(Ok(stream)) => serve_with_graceful_shutdown(stream, service, stopped).await,
+                    Ok(Err(error)) => {
+                        tracing::debug!("TLS handshake with {remote_addr} failed: {error}");
+                        Ok(())
+                    }
+                    Err(_) => {
+                        tracing::debug!("TLS handshake with {remote_addr} timed out");
+                        Ok(())
+                    }
+                }
+            });
+        }
+        // Holding `stop_handle` until drained keeps `stopped` from returning early.
+        shutdown.shutdown(grace).await;
+        drop(stop_handle);
+    });
+
+    Ok((server_handle, local_addr))
+}
+
+/// Stop the server of `handle` once the process gets ctrl-c.
+pub async fn stop_on_ctrl_c(handle: ServerHandle) -> io::Result<()> {
+    tokio::signal::ctrl_c().await?;
+    tracing::info!("Got ctrl-c, shutting down");
+    // Already stopped is as good.
+    let _ = handle.stop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hash_api_key, ApiKey, ChainConfig, ChainManagerClient, RateLimit};
+    use jsonrpsee::{
+        http_client::{HeaderMap, HttpClient, HttpClientBuilder},
+        ws_client::WsClientBuilder,
+    };
+    use jsonrpsee_core::client::Error as ClientError;
+    use std::net::IpAddr;
+
+    const NO_AUTH: AuthConfig = AuthConfig { disabled: true, keys: Vec::new() };
+
+    fn client_with_key(address: SocketAddr, key: &str) -> HttpClient {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {key}").parse().unwrap());
+        HttpClientBuilder::default()
+            .set_headers(headers)
+            .build(format!("http://{address}"))
+            .expect("A client")
+    }
+
+    fn call_error<T: std::fmt::Debug>(result: Result<T, ClientError>) -> i32 {
+        match result {
+            Err(ClientError::Call(error)) => error.code(),
+            other => panic!("Expected a call error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() -> Result<(), Box<dyn std::error::Error>> {
+        let auth = AuthConfig {
+            disabled: false,
+            keys: vec![
+                ApiKey { sha256: hash_api_key("valid"), methods: None, admin: false },
+                ApiKey {
+                    sha256: hash_api_key("restricted"),
+                    methods: Some(["finalisedHeader".to_string()].into()),
+                    admin: false,
+                },
+            ],
+        };
+        let manager = ChainManagerImpl::new(Vec::new());
+        let config = ServerConfig { auth, ..Default::default() };
+        let (handle, address) = start_server("127.0.0.1:0", manager, config).await?;
+        let anonymous = HttpClientBuilder::default().build(format!("http://{address}"))?;
+        assert_eq!(call_error(anonymous.list_chains().await), -4015);
+        assert_eq!(call_error(client_with_key(address, "wrong").list_chains().await), -4015);
+        let restricted = client_with_key(address, "restricted");
+        assert_eq!(call_error(restricted.list_chains().await), -4016);
+        let valid = client_with_key(address, "valid");
+        assert_eq!(valid.list_chains().await?, Vec::new());
+        let chain =
+            ChainConfig { chain_id: 1, rpc_url: "http://127.0.0.1:1".into(), ..Default::default() };
+        assert_eq!(call_error(valid.admin_add_chain(chain, Some(false)).await), -4016);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_auth_only_on_loopback() {
+        // Every other test serves 127.0.0.1 with auth disabled.
+        let config = ServerConfig { auth: NO_AUTH, ..Default::default() };
+        let error =
+            start_server("0.0.0.0:0", ChainManagerImpl::new(Vec::new()), config).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("auth.disabled"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_client_ip() -> Result<(), Box<dyn std::error::Error>> {
+        let rate_limits = RateLimits {
+            default: Some(RateLimit { requests_per_second: 1.0, burst: 5 }),
+            ..Default::default()
+        };
+        let manager = ChainManagerImpl::new(Vec::new());
+        let config = ServerConfig { rate_limits, auth: NO_AUTH, ..Default::default() };
+        let (handle, address) = start_server("127.0.0.1:0", manager, config).await?;
+        let client = HttpClientBuilder::default().build(format!("http://{address}"))?;
+
+        for _ in 0..5 {
+            client.list_chains().await?;
+        }
+        let Err(ClientError::Call(error)) = client.list_chains().await else {
+            panic!("Expected the sixth request in a row to be throttled")
+        };
+        assert_eq!(error.code(), -4011);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get())?;
+        assert!(data["retry_after_ms"].as_u64().unwrap() > 0, "{data}");
+
+        // Loopback answers for all of 127/8, so a second address stands in for a second client.
+        let other =
+            reqwest::Client::builder().local_address(IpAddr::from([127, 0, 0, 2])).build()?;
+        let response = other
+            .post(format!("http://{address}"))
+            .header("content-type", "application/json")
+            .body(r#"{"jsonrpc":"2.0","id":1,"method":"listChains","params":[]}"#)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+        assert_eq!(response["result"], serde_json::json!([]), "{response}");
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cors_body_limit_and_hosts() -> Result<(), Box<dyn std::error::Error>> {
+        let config = ServerConfig {
+            auth: NO_AUTH,
+            cors: CorsConfig {
+                allowed_origins: vec!["https://status.example.com".into()],
+                permissive: false,
+            },
+            max_request_body_bytes: Some(1024),
+            allowed_hosts: vec!["127.0.0.1:*".into()],
+            ..Default::default()
+        };
+        let (handle, address) =
+            start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config).await?;
+        let client = reqwest::Client::new();
+        let call = |origin: &str| {
+            client
+                .post(format!("http://{address}"))
+                .header("origin", origin)
+                .header("content-type", "application/json")
+                .body(r#"{"jsonrpc":"2.0","id":1,"method":"listChains","params":[]}"#)
+                .send()
+        };
+        let allow_origin = |response: &reqwest::Response| {
+            response.headers().get("access-control-allow-origin").cloned()
+        };
+
+        let allowed = call("https://status.example.com").await?;
+        assert_eq!(allowed.status(), 200);
+        assert_eq!(allow_origin(&allowed).unwrap(), "https://status.example.com");
+        assert_eq!(allow_origin(&call("https://evil.example.com").await?), None);
+
+        let preflight = client
+            .request(Method::OPTIONS, format!("http://{address}"))
+            .header("origin", "https://status.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "authorization, content-type")
+            .send()
+            .await?;
+        assert_eq!(preflight.status(), 200);
+        assert_eq!(allow_origin(&preflight).unwrap(), "https://status.example.com");
+        let methods = preflight.headers()["access-control-allow-methods"].to_str()?;
+        assert!(methods.contains("POST"), "{methods}");
+        let headers = preflight.headers()["access-control-allow-headers"].to_str()?;
+        assert!(headers.contains("authorization"), "{headers}");
+
+        let oversized = client
+            .post(format!("http://{address}"))
+            .header("content-type", "application/json")
+            .body(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"{}","params":[]}}"#,
+                "a".repeat(1024)
+            ))
+            .send()
+            .await?;
+        assert_eq!(oversized.status(), 413);
+
+        let other_host = client
+            .post(format!("http://{address}"))
+            .header("host", "rpc.example.com")
+            .header("content-type", "application/json")
+            .body(r#"{"jsonrpc":"2.0","id":1,"method":"listChains","params":[]}"#)
+            .send()
+            .await?;
+        assert_eq!(other_host.status(), 403);
+
+        handle.stop()?;
+        handle.stopped().await;
+
+        let config = ServerConfig {
+            cors: CorsConfig { allowed_origins: vec!["*".into()], permissive: false },
+            ..Default::default()
+        };
+        let error = start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_connections() -> Result<(), Box<dyn std::error::Error>> {
+        let config = ServerConfig { auth: NO_AUTH, max_connections: Some(1), ..Default::default() };
+        let (handle, address) =
+            start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config).await?;
+        let connect = || WsClientBuilder::default().build(format!("ws://{address}"));
+        // The permit goes back once the server sees the connection close.
+        let connect_once_freed = || async {
+            for _ in 0..50 {
+                if let Ok(client) = connect().await {
+                    return Ok(client)
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            connect().await
+        };
+
+        // A socket that never sends a request holds its permit.
+        let idle = tokio::net::TcpStream::connect(address).await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(connect().await.is_err());
+        drop(idle);
+
+        let session = connect_once_freed().await?;
+        session.list_chains().await?;
+        assert!(connect().await.is_err());
+        drop(session);
+        connect_once_freed().await?.list_chains().await?;
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+}
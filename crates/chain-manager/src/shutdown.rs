@@ -0,0 +1,170 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy::providers::Provider;
+use dashmap::DashMap;
+use jsonrpsee::{
+    core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT},
+    server::MethodResponse,
+    types::{ErrorObjectOwned, Request},
+};
+use tokio::sync::Notify;
+
+use crate::{ChainManagerError, Metrics};
+
+/// How long a shutdown waits for calls in flight, unless a server sets `shutdown_grace_ms`.
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// Calls being served, and whether new ones are still taken.
+#[derive(Debug, Default)]
+pub(crate) struct InFlight {
+    closed: AtomicBool,
+    calls: AtomicUsize,
+    idle: Notify,
+}
+
+/// One call being served, until dropped.
+struct InFlightGuard(Arc<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.0.calls.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+impl InFlight {
+    fn enter(self: &Arc<Self>) -> Option<InFlightGuard> {
+        // Counted before checking, so a shutdown either sees the call or the call sees it.
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard(self.clone());
+        (!self.closed.load(Ordering::SeqCst)).then_some(guard)
+    }
+
+    async fn idle(&self) {
+        loop {
+            let mut idle = pin!(self.idle.notified());
+            idle.as_mut().enable();
+            if self.calls.load(Ordering::SeqCst) == 0 {
+                return
+            }
+            idle.await;
+        }
+    }
+}
+
+/// Shuts a [`ChainManagerImpl`](crate::ChainManagerImpl) down once it has been moved into a
+/// server.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    pub(crate) in_flight: Arc<InFlight>,
+    pub(crate) providers: Arc<DashMap<(u64, usize), Arc<dyn Provider>>>,
+    pub(crate) metrics: Arc<Metrics>,
+}
+
+impl std::fmt::Debug for ShutdownHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownHandle").field("in_flight", &self.in_flight).finish_non_exhaustive()
+    }
+}
+
+impl ShutdownHandle {
+    /// Reject new calls with `ShuttingDown`, wait up to `grace` for those in flight and drop the
+    /// providers. Returns whether every call finished in time.
+    pub async fn shutdown(&self, grace: Duration) -> bool {
+        self.in_flight.closed.store(true, Ordering::SeqCst);
+        let drained = tokio::time::timeout(grace, self.in_flight.idle()).await.is_ok();
+        if !drained {
+            tracing::warn!(
+                "{} calls still in flight after {grace:?}, shutting down anyway",
+                self.in_flight.calls.load(Ordering::SeqCst)
+            );
+        }
+        self.providers.clear();
+        self.metrics.set_providers(0);
+        drained
+    }
+}
+
+/// RPC middleware counting the calls in flight for [`ShutdownHandle::shutdown`] and rejecting
+/// them once it has been called.
+#[derive(Clone, Debug)]
+pub struct ShutdownService<S> {
+    service: S,
+    in_flight: Arc<InFlight>,
+}
+
+impl<S> ShutdownService<S> {
+    pub fn new(service: S, handle: &ShutdownHandle) -> Self {
+        Self { service, in_flight: handle.in_flight.clone() }
+    }
+}
+
+fn shutting_down() -> ErrorObjectOwned {
+    ChainManagerError::ShuttingDown { reason: "The server is shutting down".into() }.into()
+}
+
+impl<S> RpcServiceT for ShutdownService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, NotificationResponse = MethodResponse>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let guard = self.in_flight.enter();
+        let service = self.service.clone();
+        async move {
+            let Some(_guard) = guard else {
+                return MethodResponse::error(request.id, shutting_down())
+            };
+            service.call(request).await
+        }
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let guard = self.in_flight.enter();
+        if guard.is_none() {
+            for entry in batch.iter_mut() {
+                let Ok(BatchEntry::Call(request)) = entry else { continue };
+                *entry = Err(BatchEntryErr::new(request.id.clone(), shutting_down()));
+            }
+        }
+        let service = self.service.clone();
+        async move {
+            let _guard = guard;
+            service.batch(batch).await
+        }
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        let guard = self.in_flight.enter();
+        let service = self.service.clone();
+        async move {
+            let Some(_guard) = guard else { return MethodResponse::notification() };
+            service.notification(notification).await
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::{
+    consensus::Header,
+    providers::Provider,
+    rpc::types::BlockNumberOrTag,
+    transports::{RpcError, TransportResult},
+};
+use futures::StreamExt;
+use jsonrpsee::{core::SubscriptionResult, DisconnectError, SubscriptionSink};
+use serde::{Deserialize, Serialize};
+
+/// How often HTTP upstreams, which cannot push new heads, are polled for them.
+pub const NEW_HEADS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often `subscribeFinalised` polls, unless set with
+/// [`ChainManagerImpl::with_finalised_poll_interval`](crate::ChainManagerImpl::with_finalised_poll_interval).
+pub const DEFAULT_FINALISED_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// How a chain's finalised block is found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum Finality {
+    /// The upstream answers for the `finalized` tag.
+    FinalizedTag,
+    /// The upstream does not know the `finalized` tag, so the block `confirmations` below the
+    /// latest stands in for it.
+    ConfirmationDepth { confirmations: u64 },
+}
+
+/// One `subscribeFinalised` notification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalisedHeader {
+    pub header: Header,
+    /// How finality is decided for the chain, on the first notification only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Finality>,
+}
+
+impl Finality {
+    /// Use the `finalized` tag when `provider` answers for it, else `confirmations` below the
+    /// latest block. Only a node that answers, with no block or by rejecting the tag, says it
+    /// does not know the tag; failing to reach it is returned instead.
+    pub(crate) async fn detect(
+        provider: &dyn Provider,
+        confirmations: u64,
+    ) -> TransportResult<Self> {
+        match provider.get_block_by_number(BlockNumberOrTag::Finalized).await {
+            Ok(Some(_)) => Ok(Self::FinalizedTag),
+            Ok(None) | Err(RpcError::ErrorResp(_)) => Ok(Self::ConfirmationDepth { confirmations }),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// The header `provider` currently considers final under this rule.
+    pub(crate) async fn header(
+        self,
+        provider: &dyn Provider,
+    ) -> Result<Option<Header>, alloy::transports::TransportError> {
+        let at = match self {
+            Self::FinalizedTag => BlockNumberOrTag::Finalized,
+            Self::ConfirmationDepth { confirmations } => BlockNumberOrTag::Number(
+                provider.get_block_number().await?.saturating_sub(confirmations),
+            ),
+        };
+        Ok(provider.get_block_by_number(at).await?.map(|block| block.header.into()))
+    }
+}
+
+/// Send every new head of `provider` to `sink` until the client goes away. Upstreams that
+/// support `eth_subscribe` push their heads, the others are polled every
+/// [`NEW_HEADS_POLL_INTERVAL`].
+pub(crate) async fn forward_new_heads(
+    sink: SubscriptionSink,
+    provider: Arc<dyn Provider>,
+) -> SubscriptionResult {
+    let Ok(subscription) = provider.subscribe_blocks().await else {
+        return poll_new_heads(sink, provider).await
+    };
+    let mut heads = subscription.into_stream();
+    loop {
+        tokio::select! {
+            _ = sink.closed() => return Ok(()),
+            head = heads.next() => {
+                let Some(head) = head else { return Err("The upstream subscription ended".into()) };
+                if send(&sink, &Header::from(head)).await.is_err() {
+                    return Ok(())
+                }
+            }
+        }
+    }
+}
+
+async fn poll_new_heads(sink: SubscriptionSink, provider: Arc<dyn Provider>) -> SubscriptionResult {
+    let mut next = provider.get_block_number().await? + 1;
+    let mut interval = tokio::time::interval(NEW_HEADS_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = sink.closed() => return Ok(()),
+            _ = interval.tick() => {}
+        }
+        let latest = provider.get_block_number().await?;
+        for number in next..=latest {
+            let block = provider
+                .get_block_by_number(BlockNumberOrTag::Number(number))
+                .await?
+                .ok_or_else(|| format!("The node does not have block {number} it announced"))?;
+            if send(&sink, &Header::from(block.header)).await.is_err() {
+                return Ok(())
+            }
+        }
+        next = next.max(latest + 1);
+    }
+}
+
+/// Send `provider`'s finalised header under `finality` to `sink` each time it changes, polling
+/// every `interval`, until the client goes away.
+pub(crate) async fn forward_finalised(
+    sink: SubscriptionSink,
+    provider: Arc<dyn Provider>,
+    finality: Finality,
+    interval: Duration,
+) -> SubscriptionResult {
+    let mut metadata = Some(finality);
+    let mut last_hash = None;
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = sink.closed() => return Ok(()),
+            _ = interval.tick() => {}
+        }
+        let Some(header) = finality.header(provider.as_ref()).await? else { continue };
+        let hash = header.hash_slow();
+        if last_hash == Some(hash) {
+            continue
+        }
+        last_hash = Some(hash);
+        let notification = FinalisedHeader { header, metadata: metadata.take() };
+        if send(&sink, &notification).await.is_err() {
+            return Ok(())
+        }
+    }
+}
+
+/// Send one notification, failing only when the client has disconnected.
+pub(crate) async fn send(
+    sink: &SubscriptionSink,
+    item: &impl Serialize,
+) -> Result<(), DisconnectError> {
+    let message = serde_json::value::to_raw_value(item).expect("Headers serialize to JSON");
+    sink.send(message).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{block_json, flaky_upstream, mock_upstream};
+    use alloy::providers::ProviderBuilder;
+
+    #[tokio::test]
+    async fn test_detect_falls_back_only_on_an_answer() {
+        let detect = |url: String| async move {
+            let provider = ProviderBuilder::new().connect_http(url.parse().unwrap());
+            Finality::detect(&provider, 64).await
+        };
+        let (tagged, _) = flaky_upstream(0, block_json(7)).await;
+        assert_eq!(detect(tagged).await.unwrap(), Finality::FinalizedTag);
+
+        let depth = Finality::ConfirmationDepth { confirmations: 64 };
+        let (null, _) = flaky_upstream(0, serde_json::Value::Null).await;
+        assert_eq!(detect(null).await.unwrap(), depth);
+        let (rejecting, _) =
+            mock_upstream(0, Duration::ZERO, Err((-32602, "unknown block tag"))).await;
+        assert_eq!(detect(rejecting).await.unwrap(), depth);
+
+        let (down, _) = flaky_upstream(usize::MAX, serde_json::Value::Null).await;
+        assert!(detect(down).await.is_err(), "a 502 says nothing about the tag");
+    }
+
+    #[test]
+    fn test_metadata_only_on_first_notification() {
+        let first = FinalisedHeader {
+            header: Header::default(),
+            metadata: Some(Finality::ConfirmationDepth { confirmations: 64 }),
+        };
+        let encoded = serde_json::to_value(&first).unwrap();
+        assert_eq!(
+            encoded["metadata"],
+            serde_json::json!({ "source": "confirmationDepth", "confirmations": 64 })
+        );
+        assert_eq!(serde_json::from_value::<FinalisedHeader>(encoded).unwrap(), first);
+
+        let later = FinalisedHeader { metadata: None, ..first };
+        assert!(serde_json::to_value(&later).unwrap().get("metadata").is_none());
+    }
+}
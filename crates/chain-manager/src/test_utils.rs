@@ -0,0 +1,170 @@
+//! Upstreams and servers for tests, on ports the OS picks so tests can run in parallel. Other
+//! crates get them with the `test-utils` feature.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy::{
+    consensus::Header,
+    node_bindings::{Anvil, AnvilInstance},
+    rpc::types::{Block, Transaction},
+};
+use jsonrpsee::{
+    http_client::{HttpClient, HttpClientBuilder},
+    server::{ServerBuilder, ServerHandle},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::{ChainConfig, ChainManagerServer};
+
+/// `count` anvil nodes with chain ids 1 to `count`, each listening on a free port.
+pub fn create_anvil_instances(count: u64) -> Vec<AnvilInstance> {
+    (1..=count)
+        .map(|chain_id| {
+            Anvil::new().chain_id(chain_id).try_spawn().unwrap_or_else(|error| {
+                panic!("Failed to spawn anvil for chain {chain_id}: {error}")
+            })
+        })
+        .collect()
+}
+
+/// A config for every one of `anvils`, over HTTP.
+pub fn create_configs(anvils: &[AnvilInstance]) -> Vec<ChainConfig> {
+    anvils
+        .iter()
+        .map(|anvil| ChainConfig {
+            rpc_url: anvil.endpoint(),
+            chain_id: anvil.chain_id(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Serve `manager` on a free port, returning the address it listens on.
+pub async fn start_test_server(
+    manager: impl ChainManagerServer,
+) -> Result<(ServerHandle, SocketAddr), Box<dyn std::error::Error>> {
+    let server = ServerBuilder::default().build("127.0.0.1:0").await?;
+    let address = server.local_addr()?;
+    Ok((server.start(manager.into_rpc()), address))
+}
+
+/// Serve `manager` on a free port, with an HTTP client for it.
+pub async fn create_start_server(
+    manager: impl ChainManagerServer,
+) -> Result<(ServerHandle, HttpClient), Box<dyn std::error::Error>> {
+    let (handle, address) = start_test_server(manager).await?;
+    let client = HttpClientBuilder::default().build(format!("http://{address}"))?;
+    Ok((handle, client))
+}
+
+/// An empty block `number`, as the node returns it, with the hash of its header.
+pub fn block_json(number: u64) -> serde_json::Value {
+    let header = alloy::rpc::types::Header::new(Header { number, ..Default::default() });
+    serde_json::to_value(Block::<Transaction>::empty(header)).expect("A block")
+}
+
+/// A JSON-RPC upstream answering 502 to its first `failures` requests and `result` to the
+/// rest, with the number of requests it has seen.
+pub async fn flaky_upstream(
+    failures: usize,
+    result: serde_json::Value,
+) -> (String, Arc<AtomicUsize>) {
+    mock_upstream(failures, Duration::ZERO, Ok(result)).await
+}
+
+/// An upstream answering every request with the result or, for `Err((code, message))`, the
+/// JSON-RPC error in `answer`, `delay` after the request arrives. The first `failures`
+/// requests get a 502 instead. Returns its URL and the requests it has seen.
+pub async fn mock_upstream(
+    failures: usize,
+    delay: Duration,
+    answer: Result<serde_json::Value, (i64, &'static str)>,
+) -> (String, Arc<AtomicUsize>) {
+    let (url, requests, _) = tracked_upstream(failures, delay, answer).await;
+    (url, requests)
+}
+
+/// [`mock_upstream`], also counting the most requests it was answering at once.
+pub async fn tracked_upstream(
+    failures: usize,
+    delay: Duration,
+    answer: Result<serde_json::Value, (i64, &'static str)>,
+) -> (String, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind upstream");
+    let url = format!("http://{}", listener.local_addr().expect("Bound address"));
+    let requests = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (seen, most) = (requests.clone(), peak.clone());
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let (seen, answer) = (seen.clone(), answer.clone());
+            let (most, in_flight) = (most.clone(), in_flight.clone());
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                let body = loop {
+                    let read = stream.read(&mut buffer).await.unwrap_or_default();
+                    if read == 0 {
+                        return
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else { continue };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|length| length.trim().parse::<usize>().unwrap_or(0))
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break body.to_string()
+                    }
+                };
+                let answering = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(answering, Ordering::SeqCst);
+                let response = if seen.fetch_add(1, Ordering::SeqCst) < failures {
+                    "HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                        .to_string()
+                } else {
+                    let request: serde_json::Value =
+                        serde_json::from_str(&body).expect("JSON-RPC request");
+                    let body = match &answer {
+                        Ok(result) => serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "result": result,
+                        }),
+                        Err((code, message)) => serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "error": { "code": code, "message": message },
+                        }),
+                    }
+                    .to_string();
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                tokio::time::sleep(delay).await;
+                let _ = stream.write_all(response.as_bytes()).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    (url, requests, peak)
+}
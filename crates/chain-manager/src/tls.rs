@@ -0,0 +1,295 @@
+use std::{
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::pin,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use thiserror::Error;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio_rustls::TlsAcceptor;
+
+/// How often the certificate and key files are checked for changes.
+pub const CERT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a client gets to finish the TLS handshake before its connection is dropped, unless a
+/// server sets `tls_handshake_timeout_ms`.
+pub const DEFAULT_TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("`tls_cert_path` and `tls_key_path` have to be set together")]
+    Incomplete,
+    #[error("Could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("{} holds no PEM certificate", path.display())]
+    NoCertificate { path: PathBuf },
+    #[error("{} holds no PEM private key", path.display())]
+    NoPrivateKey { path: PathBuf },
+    #[error("{} is not the key of {}: {source}", key_path.display(), cert_path.display())]
+    InvalidKey { cert_path: PathBuf, key_path: PathBuf, source: rustls::Error },
+}
+
+/// The crypto of both ends, picked explicitly since more than one provider is compiled in.
+pub fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+/// Read the PEM certificate chain at `cert_path` and the PEM private key at `key_path`, checking
+/// that they belong together.
+pub fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, TlsError> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|source| TlsError::Read { path: path.to_path_buf(), source })
+    };
+    let pem = read(cert_path)?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| TlsError::Read { path: cert_path.to_path_buf(), source })?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificate { path: cert_path.to_path_buf() })
+    }
+    let pem = read(key_path)?;
+    let key = rustls_pemfile::private_key(&mut pem.as_slice())
+        .map_err(|source| TlsError::Read { path: key_path.to_path_buf(), source })?
+        .ok_or_else(|| TlsError::NoPrivateKey { path: key_path.to_path_buf() })?;
+    CertifiedKey::from_der(certs, key, &crypto_provider()).map_err(|source| TlsError::InvalidKey {
+        cert_path: cert_path.to_path_buf(),
+        key_path: key_path.to_path_buf(),
+        source,
+    })
+}
+
+/// The server's certificate, swapped for the one in its files by [`CertResolver::reload`].
+#[derive(Debug)]
+pub struct CertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertResolver {
+    pub fn new(cert_path: &Path, key_path: &Path) -> Result<Self, TlsError> {
+        let current = load_certified_key(cert_path, key_path)?;
+        Ok(Self {
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            current: RwLock::new(Arc::new(current)),
+        })
+    }
+
+    /// Load the files again. Connections keep the current certificate if they no longer hold a
+    /// valid pair.
+    pub fn reload(&self) -> Result<(), TlsError> {
+        let reloaded = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self.current.write().expect("certificate lock") = Arc::new(reloaded);
+        Ok(())
+    }
+
+    /// An acceptor terminating TLS with the current certificate.
+    pub fn acceptor(self: &Arc<Self>) -> TlsAcceptor {
+        let mut config = rustls::ServerConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()
+            .expect("The provider supports the default protocol versions")
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        TlsAcceptor::from(Arc::new(config))
+    }
+
+    /// Listen for SIGHUP, which [`CertResolver::watch`] reloads on.
+    pub fn hangups() -> io::Result<Signal> {
+        signal(SignalKind::hangup())
+    }
+
+    /// Reload on every one of `hangups` and whenever the files change, until `stopped`
+    /// completes. A reload that fails is logged and the current certificate kept.
+    pub async fn watch(self: Arc<Self>, mut hangups: Signal, stopped: impl Future<Output = ()>) {
+        let mut stopped = pin!(stopped);
+        let mut poll = tokio::time::interval(CERT_POLL_INTERVAL);
+        let mut seen = self.modified();
+        loop {
+            tokio::select! {
+                _ = &mut stopped => return,
+                Some(()) = hangups.recv() => {
+                    tracing::info!("Got SIGHUP, reloading the TLS certificate");
+                }
+                _ = poll.tick() => {
+                    let modified = self.modified();
+                    if modified == seen {
+                        continue
+                    }
+                    seen = modified;
+                }
+            }
+            match self.reload() {
+                Ok(()) => {
+                    tracing::info!("Reloaded the TLS certificate {}", self.cert_path.display())
+                }
+                Err(error) => tracing::warn!("Keeping the current TLS certificate: {error}"),
+            }
+        }
+    }
+
+    fn modified(&self) -> [Option<SystemTime>; 2] {
+        [&self.cert_path, &self.key_path]
+            .map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().expect("certificate lock").clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{start_server, AuthConfig, ChainManagerClient, ChainManagerImpl, ServerConfig};
+    use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+    use rustls::{pki_types::CertificateDer, ClientConfig, RootCertStore};
+    use std::net::SocketAddr;
+    use tokio::io::AsyncReadExt;
+
+    /// A self-signed certificate for `localhost` and its key, as PEM.
+    fn self_signed() -> (CertificateDer<'static>, String, String) {
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec!["localhost".into()]).expect("A certificate");
+        (cert.der().clone(), cert.pem(), key_pair.serialize_pem())
+    }
+
+    fn client_trusting(cert: CertificateDer<'static>, address: SocketAddr) -> HttpClient {
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).expect("A root certificate");
+        let config = ClientConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()
+            .expect("The provider supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        HttpClientBuilder::default()
+            .with_custom_cert_store(config)
+            .build(format!("https://localhost:{}", address.port()))
+            .expect("A client")
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_and_reloads_the_certificate(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let (cert_path, key_path) = (dir.path().join("cert.pem"), dir.path().join("key.pem"));
+        let (first, cert_pem, key_pem) = self_signed();
+        fs::write(&cert_path, cert_pem)?;
+        fs::write(&key_path, key_pem)?;
+        let config = ServerConfig {
+            auth: AuthConfig { disabled: true, keys: Vec::new() },
+            tls_cert_path: Some(cert_path.clone()),
+            tls_key_path: Some(key_path.clone()),
+            ..Default::default()
+        };
+        let (handle, address) =
+            start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config).await?;
+
+        assert!(client_trusting(first.clone(), address).list_chains().await?.is_empty());
+        let plain = HttpClientBuilder::default().build(format!("http://{address}"))?;
+        assert!(plain.list_chains().await.is_err());
+
+        let (second, cert_pem, key_pem) = self_signed();
+        fs::write(&cert_path, cert_pem)?;
+        fs::write(&key_path, key_pem)?;
+        let renewed = client_trusting(second, address);
+        let mut served = false;
+        for _ in 0..50 {
+            tokio::time::sleep(CERT_POLL_INTERVAL / 10).await;
+            if renewed.list_chains().await.is_ok() {
+                served = true;
+                break
+            }
+        }
+        assert!(served, "The new certificate was not picked up");
+        assert!(client_trusting(first, address).list_chains().await.is_err());
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshakes_are_dropped() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let (cert_path, key_path) = (dir.path().join("cert.pem"), dir.path().join("key.pem"));
+        let (_, cert_pem, key_pem) = self_signed();
+        fs::write(&cert_path, cert_pem)?;
+        fs::write(&key_path, key_pem)?;
+        let config = ServerConfig {
+            auth: AuthConfig { disabled: true, keys: Vec::new() },
+            tls_cert_path: Some(cert_path),
+            tls_key_path: Some(key_path),
+            tls_handshake_timeout_ms: Some(100),
+            ..Default::default()
+        };
+        let (handle, address) =
+            start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config).await?;
+
+        // The client never says hello, so the server hangs up.
+        let mut stalled = tokio::net::TcpStream::connect(address).await?;
+        let read = tokio::time::timeout(Duration::from_secs(5), stalled.read(&mut [0; 1])).await?;
+        assert_eq!(read?, 0);
+
+        handle.stop()?;
+        handle.stopped().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_misconfigured_certificates_fail_at_startup(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let (cert_path, key_path) = (dir.path().join("cert.pem"), dir.path().join("key.pem"));
+        let (_, cert_pem, _) = self_signed();
+        let (_, _, other_key_pem) = self_signed();
+        fs::write(&cert_path, &cert_pem)?;
+        fs::write(&key_path, other_key_pem)?;
+        assert!(matches!(
+            load_certified_key(&cert_path, &key_path),
+            Err(TlsError::InvalidKey { .. })
+        ));
+        assert!(matches!(
+            load_certified_key(&key_path, &key_path),
+            Err(TlsError::NoCertificate { .. })
+        ));
+        assert!(matches!(
+            load_certified_key(&cert_path, &cert_path),
+            Err(TlsError::NoPrivateKey { .. })
+        ));
+        assert!(matches!(
+            load_certified_key(&dir.path().join("missing.pem"), &key_path),
+            Err(TlsError::Read { .. })
+        ));
+
+        let config = ServerConfig {
+            tls_cert_path: Some(cert_path.clone()),
+            tls_key_path: Some(key_path),
+            ..Default::default()
+        };
+        let error = start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("is not the key of"), "{error}");
+
+        let config = ServerConfig { tls_cert_path: Some(cert_path), ..Default::default() };
+        let error = start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), TlsError::Incomplete.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    future::Future,
+    hash::{BuildHasher, RandomState},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock,
+    },
+};
+
+use jsonrpsee::{
+    core::middleware::{Batch, Notification, RpcServiceT},
+    server::MethodResponse,
+    types::{Extensions, Request},
+};
+use tracing::{field, Instrument, Span};
+
+use crate::{chain_id_param, ApiKeyHash, ClientIp};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id [`TraceService`] gave the request being served, if there is one.
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// A counter hashed with a key picked at startup, so ids do not repeat within a run and are
+/// unlikely to repeat across runs.
+fn new_request_id() -> String {
+    static KEY: LazyLock<RandomState> = LazyLock::new(RandomState::new);
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!("{:016x}", KEY.hash_one(COUNTER.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Who sent a request, as span fields: `client_ip` and, if it came with one, `api_key`.
+fn client(extensions: &Extensions) -> (Option<impl field::Value>, Option<String>) {
+    let client_ip = extensions.get::<ClientIp>().map(|ClientIp(ip)| field::display(*ip));
+    (client_ip, extensions.get::<ApiKeyHash>().map(ApiKeyHash::id))
+}
+
+/// The span of one request, with what it takes to find its logs again.
+fn request_span(
+    request_id: &str,
+    method: &str,
+    chain_id: Option<u64>,
+    extensions: &Extensions,
+) -> Span {
+    let (client_ip, api_key) = client(extensions);
+    tracing::info_span!("request", request_id, method, chain_id, client_ip, api_key)
+}
+
+/// RPC middleware giving every request an id and a span, so its logs can be told apart from
+/// those of other requests and found again by the id, which errors carry in their data.
+#[derive(Clone, Debug)]
+pub struct TraceService<S> {
+    service: S,
+}
+
+impl<S> TraceService<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+}
+
+impl<S> RpcServiceT for TraceService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, NotificationResponse = MethodResponse>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let id = new_request_id();
+        let span =
+            request_span(&id, request.method_name(), chain_id_param(&request), &request.extensions);
+        let service = self.service.clone();
+        let response = async move {
+            let response = service.call(request).await;
+            if let Some(code) = response.as_error_code() {
+                tracing::warn!(code, "Request failed");
+            }
+            response
+        };
+        REQUEST_ID.scope(id, response).instrument(span)
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let id = new_request_id();
+        let (client_ip, api_key) = client(batch.extensions());
+        let span = tracing::info_span!("batch", request_id = id, client_ip, api_key);
+        let service = self.service.clone();
+        REQUEST_ID.scope(id, async move { service.batch(batch).await }).instrument(span)
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        let id = new_request_id();
+        let span = request_span(&id, notification.method_name(), None, &notification.extensions);
+        let service = self.service.clone();
+        REQUEST_ID
+            .scope(id, async move { service.notification(notification).await })
+            .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        hash_api_key, start_server, ApiKey, AuthConfig, ChainManagerClient, ChainManagerImpl,
+        ServerConfig,
+    };
+    use alloy::eips::BlockNumberOrTag;
+    use jsonrpsee::http_client::{HeaderMap, HttpClientBuilder};
+    use jsonrpsee_core::client::Error as ClientError;
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+    type SpanFields = HashMap<String, String>;
+
+    /// The name and fields of every span opened.
+    #[derive(Clone, Default)]
+    struct CapturedSpans(Arc<Mutex<Vec<(&'static str, SpanFields)>>>);
+
+    #[derive(Default)]
+    struct Fields(SpanFields);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().into(), value.into());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name().into(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CapturedSpans {
+        fn on_new_span(&self, attributes: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+            let mut fields = Fields::default();
+            attributes.record(&mut fields);
+            self.0.lock().unwrap().push((attributes.metadata().name(), fields.0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_span_and_id() -> Result<(), Box<dyn std::error::Error>> {
+        let spans = CapturedSpans::default();
+        let _subscriber = tracing_subscriber::registry().with(spans.clone()).set_default();
+
+        let auth = AuthConfig {
+            disabled: false,
+            keys: vec![ApiKey { sha256: hash_api_key("valid"), methods: None, admin: false }],
+        };
+        let config = ServerConfig { auth, ..Default::default() };
+        let (handle, address) =
+            start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config).await?;
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer valid".parse()?);
+        let client =
+            HttpClientBuilder::default().set_headers(headers).build(format!("http://{address}"))?;
+
+        let Err(ClientError::Call(error)) =
+            client.finalised_header(999, BlockNumberOrTag::Finalized).await
+        else {
+            panic!("Expected an unknown chain to fail")
+        };
+        assert_eq!(error.code(), -4004);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get())?;
+        assert_eq!(data["chain_id"], 999);
+        let request_id = data["request_id"].as_str().expect("A request id").to_string();
+        handle.stop()?;
+        handle.stopped().await;
+
+        let spans = spans.0.lock().unwrap();
+        let (_, fields) = spans
+            .iter()
+            .find(|(name, fields)| *name == "request" && fields["request_id"] == request_id)
+            .expect("The span of the failed request");
+        assert_eq!(fields["method"], "finalisedHeader");
+        assert_eq!(fields["chain_id"], "999");
+        assert_eq!(fields["client_ip"], "127.0.0.1");
+        assert_eq!(fields["api_key"], ApiKeyHash(hash_api_key("valid")).id());
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+//! Uses the client the way a downstream crate would, against a server started in-process.
+
+use alloy::eips::BlockNumberOrTag;
+use chain_manager::{
+    start_server, AuthConfig, ChainManagerError, ChainManagerHandle, ChainManagerImpl, ClientError,
+    ClientOptions, ServerConfig,
+};
+
+#[tokio::test]
+async fn test_handle_from_another_crate() -> Result<(), Box<dyn std::error::Error>> {
+    let config = ServerConfig {
+        auth: AuthConfig { disabled: true, ..Default::default() },
+        ..Default::default()
+    };
+    let (server, address) =
+        start_server("127.0.0.1:0", ChainManagerImpl::new(Vec::new()), config).await?;
+
+    for url in [format!("http://{address}"), format!("ws://{address}")] {
+        let handle = ChainManagerHandle::connect(&url, ClientOptions::default()).await?;
+        assert!(handle.list_chains().await?.is_empty());
+        let error = handle.finalised_header(1, BlockNumberOrTag::Finalized).await.unwrap_err();
+        let ClientError::Server {
+            error: ChainManagerError::ChainIdNotFound { chain_id: 1, .. },
+            request_id: Some(_),
+            ..
+        } = error
+        else {
+            panic!("Expected an unknown chain to fail, got {error:?}")
+        };
+    }
+
+    server.stop()?;
+    server.stopped().await;
+    Ok(())
+}